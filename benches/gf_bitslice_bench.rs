@@ -3,6 +3,7 @@ use quicfuscate::fec::gf_tables::{
     gf_mul,
     gf_mul_table,
     init_gf_tables,
+    kernel_support,
     
     #[cfg(target_arch = "x86_64")]
     gf_mul_bitsliced_avx2,
@@ -30,9 +31,11 @@ fn gf_bitslice_bench(c: &mut Criterion) {
         });
     });
 
+    let hw = kernel_support();
+
     #[cfg(target_arch = "x86_64")]
     {
-        if std::is_x86_feature_detected!("sse2") && std::is_x86_feature_detected!("pclmulqdq") {
+        if hw.sse2 && hw.clmul {
             group.bench_function(BenchmarkId::new("sse2", 0), |bencher| {
                 bencher.iter(|| {
                     let mut acc = 0u8;
@@ -43,7 +46,7 @@ fn gf_bitslice_bench(c: &mut Criterion) {
                 });
             });
         }
-        if std::is_x86_feature_detected!("avx2") && std::is_x86_feature_detected!("pclmulqdq") {
+        if hw.avx2 && hw.clmul {
             group.bench_function(BenchmarkId::new("avx2", 0), |bencher| {
                 bencher.iter(|| {
                     let mut acc = 0u8;
@@ -54,10 +57,7 @@ fn gf_bitslice_bench(c: &mut Criterion) {
                 });
             });
         }
-        if std::is_x86_feature_detected!("avx512f")
-            && std::is_x86_feature_detected!("avx512vbmi")
-            && std::is_x86_feature_detected!("pclmulqdq")
-        {
+        if hw.avx512 && hw.clmul {
             group.bench_function(BenchmarkId::new("avx512", 0), |bencher| {
                 bencher.iter(|| {
                     let mut acc = 0u8;
@@ -74,7 +74,7 @@ fn gf_bitslice_bench(c: &mut Criterion) {
 
     #[cfg(target_arch = "aarch64")]
     {
-        if std::arch::is_aarch64_feature_detected!("pmull") {
+        if hw.neon && hw.clmul {
             group.bench_function(BenchmarkId::new("neon", 0), |bencher| {
                 bencher.iter(|| {
                     let mut acc = 0u8;
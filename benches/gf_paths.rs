@@ -0,0 +1,114 @@
+//! Compares every GF(2^8) kernel path that runs on this CPU. Before timing
+//! anything the paths are checked against the scalar tables, so a SIMD
+//! kernel that disagrees fails the run instead of producing a number.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use quicfuscate::fec::gf_tables::{
+    gf_mul, gf_mul_add_region_with, gf_mul_slice, hw_path, init_gf_tables, with_hw_path, HwPath,
+};
+
+const LEN: usize = 4096;
+const PATHS: [HwPath; 5] = [
+    HwPath::Scalar,
+    HwPath::Sse2,
+    HwPath::Avx2,
+    HwPath::Avx512,
+    HwPath::Neon,
+];
+
+fn supported_paths() -> Vec<HwPath> {
+    PATHS.into_iter().filter(|p| p.is_supported()).collect()
+}
+
+fn assert_paths_agree(a: &[u8], b: &[u8]) {
+    let mut expected = vec![0u8; a.len()];
+    with_hw_path(HwPath::Scalar, || gf_mul_slice(a, b, &mut expected));
+    for path in supported_paths() {
+        let mut out = vec![0u8; a.len()];
+        with_hw_path(path, || gf_mul_slice(a, b, &mut out));
+        assert_eq!(
+            out,
+            expected,
+            "gf_mul_slice on {} disagrees with scalar",
+            path.as_str()
+        );
+
+        let per_byte: Vec<u8> = with_hw_path(path, || {
+            a.iter().zip(b).map(|(&x, &y)| gf_mul(x, y)).collect()
+        });
+        assert_eq!(
+            per_byte,
+            expected,
+            "gf_mul on {} disagrees with scalar",
+            path.as_str()
+        );
+
+        let mut region = vec![0u8; a.len()];
+        let mut region_ref = vec![0u8; a.len()];
+        gf_mul_add_region_with(HwPath::Scalar, 0x57, a, &mut region_ref);
+        gf_mul_add_region_with(path, 0x57, a, &mut region);
+        assert_eq!(
+            region,
+            region_ref,
+            "region kernel on {} disagrees with scalar",
+            path.as_str()
+        );
+    }
+}
+
+fn bench_gf_paths(c: &mut Criterion) {
+    init_gf_tables();
+    let a: Vec<u8> = (0..LEN).map(|i| (i * 7 + 3) as u8).collect();
+    let b: Vec<u8> = (0..LEN).map(|i| (255 - i) as u8).collect();
+    assert_paths_agree(&a, &b);
+
+    let mut group = c.benchmark_group("gf_paths");
+    group.throughput(Throughput::Bytes(LEN as u64));
+    let mut out = vec![0u8; LEN];
+    for path in supported_paths() {
+        group.bench_function(BenchmarkId::new("mul_slice", path.as_str()), |bencher| {
+            with_hw_path(path, || {
+                bencher.iter(|| gf_mul_slice(black_box(&a), black_box(&b), black_box(&mut out)))
+            })
+        });
+        group.bench_function(
+            BenchmarkId::new("mul_add_region", path.as_str()),
+            |bencher| {
+                bencher.iter(|| {
+                    gf_mul_add_region_with(
+                        path,
+                        black_box(0x57),
+                        black_box(&a),
+                        black_box(&mut out),
+                    )
+                })
+            },
+        );
+        group.bench_function(BenchmarkId::new("mul_per_byte", path.as_str()), |bencher| {
+            with_hw_path(path, || {
+                bencher.iter(|| {
+                    let mut acc = 0u8;
+                    for i in 0..LEN {
+                        acc ^= gf_mul(black_box(a[i]), black_box(b[i]));
+                    }
+                    acc
+                })
+            })
+        });
+    }
+    // Whatever runtime detection (or a forced path) picks.
+    let label = format!("dispatch-{}", hw_path().as_str());
+    group.bench_function(BenchmarkId::new("mul_per_byte", label), |bencher| {
+        bencher.iter(|| {
+            let mut acc = 0u8;
+            for i in 0..LEN {
+                acc ^= gf_mul(black_box(a[i]), black_box(b[i]));
+            }
+            acc
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_gf_paths);
+criterion_main!(benches);
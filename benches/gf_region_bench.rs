@@ -0,0 +1,22 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use quicfuscate::fec::gf_tables::{gf_mul_add_region_with, init_gf_tables, HwPath};
+
+fn bench_gf_region(c: &mut Criterion) {
+    init_gf_tables();
+    let src: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+    let mut dst = vec![0u8; src.len()];
+
+    for path in [HwPath::Scalar, HwPath::Sse2, HwPath::Avx2] {
+        if !path.is_supported() {
+            continue;
+        }
+        c.bench_function(&format!("gf_mul_add_region_{}", path.as_str()), |bencher| {
+            bencher.iter(|| {
+                gf_mul_add_region_with(path, black_box(0x57), black_box(&src), black_box(&mut dst));
+            });
+        });
+    }
+}
+
+criterion_group!(benches, bench_gf_region);
+criterion_main!(benches);
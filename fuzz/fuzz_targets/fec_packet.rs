@@ -0,0 +1,43 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quicfuscate::fec::Packet;
+use quicfuscate::optimize::{OptimizationManager, OptimizeConfig};
+use std::sync::OnceLock;
+
+fn manager() -> &'static OptimizationManager {
+    static OPT: OnceLock<OptimizationManager> = OnceLock::new();
+    OPT.get_or_init(|| {
+        OptimizationManager::from_cfg(OptimizeConfig {
+            pool_capacity: 16,
+            block_size: 2048,
+            enable_xdp: false,
+            strict_pool_capacity: false,
+        })
+    })
+}
+
+// Both parsers must reject malformed frames with an error and agree with
+// each other on frames that fit a pool block.
+fuzz_target!(|data: &[u8]| {
+    let opt = manager();
+    let from_raw = Packet::from_raw(0, data, opt);
+
+    let mut block = opt.alloc_block();
+    if data.len() > block.len() {
+        opt.free_block(block);
+        return;
+    }
+    block[..data.len()].copy_from_slice(data);
+    let from_block = Packet::from_block(0, block, data.len(), opt);
+
+    match (from_raw, from_block) {
+        (Ok(a), Ok(b)) => {
+            assert_eq!(a.payload(), b.payload());
+            assert_eq!(a.coeff_len, b.coeff_len);
+            assert_eq!(a.is_systematic, b.is_systematic);
+        }
+        (Err(_), Err(_)) => {}
+        (a, b) => panic!("parsers disagree: {:?} vs {:?}", a.is_ok(), b.is_ok()),
+    }
+});
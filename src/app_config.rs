@@ -1,8 +1,55 @@
-use crate::fec::FecConfig;
-use crate::optimize::OptimizeConfig;
-use crate::stealth::StealthConfig;
+use crate::crypto::{CipherSuite, CipherSuiteSelector, CryptoManager};
+use crate::fec::{AdaptiveFec, FecConfig, FecMode};
+use crate::optimize::{MemoryPool, OptimizationManager, OptimizeConfig};
+use crate::stealth::{
+    BrowserProfile, OsProfile, StealthConfig, StealthManager, TlsClientHelloSpoofer,
+};
 use serde::Deserialize;
+use std::fmt;
 use std::path::Path;
+use std::sync::Arc;
+
+/// A single problem found while loading or validating a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// Dotted path of the offending field or section, e.g. `stealth.doh_provider`.
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    pub(crate) fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Every issue found in a configuration, reported together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ConfigError {
+    /// Returns `true` if an issue was reported for `field`.
+    pub fn has_field(&self, field: &str) -> bool {
+        self.issues.iter().any(|i| i.field == field)
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration:")?;
+        for issue in &self.issues {
+            write!(f, "\n  - {}: {}", issue.field, issue.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
 
 /// Unified configuration structure parsed from a TOML file.
 #[derive(Clone)]
@@ -14,11 +61,39 @@ pub struct AppConfig {
 
 impl AppConfig {
     /// Load configuration from a TOML string.
+    ///
+    /// Missing sections fall back to their defaults, but a section that is
+    /// present and cannot be parsed is reported as a [`ConfigError`]. Values
+    /// that parse as TOML but are not understood, such as an unknown profile
+    /// name, get one issue each; type errors are reported for the section.
     pub fn from_toml(s: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let table: toml::Table = toml::from_str(s)?;
+        let mut issues = Vec::new();
+
+        let fec = if table.contains_key("adaptive_fec") {
+            FecConfig::from_toml(s).unwrap_or_else(|e| {
+                collect_issues(&mut issues, "adaptive_fec", e);
+                FecConfig::default()
+            })
+        } else {
+            FecConfig::default()
+        };
+        let stealth = StealthConfig::from_toml(s).unwrap_or_else(|e| {
+            collect_issues(&mut issues, "stealth", e);
+            StealthConfig::default()
+        });
+        let optimize = OptimizeConfig::from_toml(s).unwrap_or_else(|e| {
+            collect_issues(&mut issues, "optimize", e);
+            OptimizeConfig::default()
+        });
+
+        if !issues.is_empty() {
+            return Err(Box::new(ConfigError { issues }));
+        }
         Ok(Self {
-            fec: FecConfig::from_toml(s).unwrap_or_default(),
-            stealth: StealthConfig::from_toml(s).unwrap_or_default(),
-            optimize: OptimizeConfig::from_toml(s).unwrap_or_default(),
+            fec,
+            stealth,
+            optimize,
         })
     }
 
@@ -28,11 +103,183 @@ impl AppConfig {
         Self::from_toml(&contents)
     }
 
-    /// Validate all sub-configurations.
-    pub fn validate(&self) -> Result<(), String> {
-        self.fec.validate()?;
-        self.stealth.validate()?;
-        self.optimize.validate()?;
-        Ok(())
+    /// Validate all sub-configurations, collecting every problem found.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut issues = self.fec.issues();
+        issues.extend(self.stealth.issues());
+        issues.extend(self.optimize.issues());
+
+        let st = &self.stealth;
+        if st.enable_doh && !st.doh_provider.is_empty() {
+            match url::Url::parse(&st.doh_provider) {
+                Ok(u) if u.scheme() == "https" && u.host_str().is_some() => {}
+                Ok(_) => issues.push(ConfigIssue::new(
+                    "stealth.doh_provider",
+                    format!("'{}' must be an https:// URL", st.doh_provider),
+                )),
+                Err(e) => issues.push(ConfigIssue::new(
+                    "stealth.doh_provider",
+                    format!("'{}' is not a valid URL: {}", st.doh_provider, e),
+                )),
+            }
+        }
+        if !st.enable_domain_fronting && !st.fronting_domains.is_empty() {
+            issues.push(ConfigIssue::new(
+                "stealth.fronting_domains",
+                "set while enable_domain_fronting is false",
+            ));
+        }
+        if st.use_qpack_headers && !st.enable_http3_masquerading {
+            issues.push(ConfigIssue::new(
+                "stealth.use_qpack_headers",
+                "requires enable_http3_masquerading",
+            ));
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError { issues })
+        }
+    }
+}
+
+/// Adds the issues of a section that failed to load. Sections report
+/// [`ConfigError`]s with one issue per field; any other error, such as a
+/// type mismatch, is attributed to the section as a whole.
+fn collect_issues(issues: &mut Vec<ConfigIssue>, section: &str, err: Box<dyn std::error::Error>) {
+    match err.downcast::<ConfigError>() {
+        Ok(e) => issues.extend(e.issues),
+        Err(e) => issues.push(ConfigIssue::new(section, e.to_string())),
+    }
+}
+
+/// What a connection built from an [`AppConfig`] would look like, produced
+/// by [`AppConfig::dry_run`].
+#[derive(Debug, Clone)]
+pub struct DryRunPlan {
+    pub browser_profile: BrowserProfile,
+    pub os_profile: OsProfile,
+    pub cipher_suite: CipherSuite,
+    pub fec_mode: FecMode,
+    pub fec_window: usize,
+    pub fec_systematic: bool,
+    pub pool_capacity: usize,
+    pub pool_block_size: usize,
+    pub xdp: bool,
+    /// `None` when DoH is disabled.
+    pub doh_provider: Option<String>,
+    pub fronting_domains: Vec<String>,
+    pub xor_obfuscation: bool,
+    pub http3_masquerading: bool,
+}
+
+impl fmt::Display for DryRunPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let on_off = |b: bool| if b { "on" } else { "off" };
+        writeln!(
+            f,
+            "profile:        {:?}@{:?}",
+            self.browser_profile, self.os_profile
+        )?;
+        writeln!(f, "cipher suite:   {:?}", self.cipher_suite)?;
+        writeln!(
+            f,
+            "fec:            {:?}, window {}, systematic {}",
+            self.fec_mode,
+            self.fec_window,
+            on_off(self.fec_systematic)
+        )?;
+        writeln!(
+            f,
+            "memory pool:    {} x {} bytes, xdp {}",
+            self.pool_capacity,
+            self.pool_block_size,
+            on_off(self.xdp)
+        )?;
+        match &self.doh_provider {
+            Some(url) => writeln!(f, "doh:            {}", url)?,
+            None => writeln!(f, "doh:            off")?,
+        }
+        if self.fronting_domains.is_empty() {
+            writeln!(f, "fronting:       off")?;
+        } else {
+            writeln!(f, "fronting:       {}", self.fronting_domains.join(", "))?;
+        }
+        writeln!(f, "xor:            {}", on_off(self.xor_obfuscation))?;
+        write!(f, "http3 masquerade: {}", on_off(self.http3_masquerading))
+    }
+}
+
+impl AppConfig {
+    /// Runs every check a connection would run at startup and builds the
+    /// stealth and FEC components, without opening a socket.
+    ///
+    /// On top of [`validate`](Self::validate) this checks that a ClientHello
+    /// capture exists for the browser/OS pair and that the selected cipher
+    /// passes [`CipherSuiteSelector::self_test`].
+    pub fn dry_run(&self) -> Result<DryRunPlan, ConfigError> {
+        let mut issues = match self.validate() {
+            Ok(()) => Vec::new(),
+            Err(e) => e.issues,
+        };
+
+        let st = &self.stealth;
+        let pair = (st.browser_profile, st.os_profile);
+        if !TlsClientHelloSpoofer::available_profiles().contains(&pair) {
+            issues.push(ConfigIssue::new(
+                "stealth.os_profile",
+                format!(
+                    "no ClientHello capture for {:?}@{:?}",
+                    st.browser_profile, st.os_profile
+                ),
+            ));
+        }
+
+        let selector = CipherSuiteSelector::new();
+        if let Err(e) = selector.self_test() {
+            issues.push(ConfigIssue::new("crypto", e.to_string()));
+        }
+
+        if !issues.is_empty() {
+            return Err(ConfigError { issues });
+        }
+
+        let opt = &self.optimize;
+        let _stealth = StealthManager::new(
+            st.clone(),
+            Arc::new(CryptoManager::new()),
+            Arc::new(OptimizationManager::from_cfg(opt.clone())),
+        );
+        let fec = AdaptiveFec::new(
+            self.fec.clone(),
+            Arc::new(MemoryPool::new(opt.pool_capacity, opt.block_size)),
+        );
+        let fec_mode = fec.current_mode();
+
+        Ok(DryRunPlan {
+            browser_profile: st.browser_profile,
+            os_profile: st.os_profile,
+            cipher_suite: selector.selected_suite(),
+            fec_mode,
+            fec_window: self
+                .fec
+                .window_sizes
+                .get(&fec_mode)
+                .copied()
+                .unwrap_or_default(),
+            fec_systematic: self.fec.systematic,
+            pool_capacity: opt.pool_capacity,
+            pool_block_size: opt.block_size,
+            xdp: opt.enable_xdp,
+            doh_provider: st.enable_doh.then(|| st.doh_provider.clone()),
+            fronting_domains: if st.enable_domain_fronting {
+                st.fronting_domains.clone()
+            } else {
+                Vec::new()
+            },
+            xor_obfuscation: st.enable_xor_obfuscation,
+            http3_masquerading: st.enable_http3_masquerading,
+        })
     }
 }
@@ -0,0 +1,222 @@
+// Copyright (c) 2024, The QuicFuscate Project Authors.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above
+//       copyright notice, this list of conditions and the following disclaimer
+//       in the documentation and/or other materials provided with the
+//       distribution.
+//
+//     * Neither the name of the copyright holder nor the names of its
+//       contributors may be used to endorse or promote products derived from
+//       this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # Certificate Verification
+//!
+//! Validates a server's presented certificate chain against a set of trust
+//! anchors loaded from a PEM file or supplied as an in-memory
+//! [`RootCertStore`]. Optionally the leaf certificate can be pinned to the
+//! SHA-256 hash of its SubjectPublicKeyInfo.
+
+use rustls::client::danger::ServerCertVerifier;
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, RootCertStore};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::error::CertVerifyError;
+
+/// Where the trust anchors for verification come from.
+#[derive(Clone)]
+pub enum CaSource {
+    /// A PEM file containing one or more CA certificates.
+    File(PathBuf),
+    /// A pre-populated root store.
+    Store(Arc<RootCertStore>),
+}
+
+impl CaSource {
+    fn load(&self) -> Result<Arc<RootCertStore>, CertVerifyError> {
+        match self {
+            CaSource::Store(store) => Ok(Arc::clone(store)),
+            CaSource::File(path) => load_ca_file(path).map(Arc::new),
+        }
+    }
+}
+
+/// Loads the trust anchors of a PEM file. A file that cannot be read, is
+/// empty, or holds no `CERTIFICATE` block yields [`CertVerifyError::CaLoad`]
+/// naming the path and the reason.
+pub fn load_ca_file(path: &Path) -> Result<RootCertStore, CertVerifyError> {
+    let ca_err = |reason: String| CertVerifyError::CaLoad(format!("{}: {reason}", path.display()));
+    let pem = std::fs::read(path).map_err(|e| ca_err(e.to_string()))?;
+    if pem.iter().all(u8::is_ascii_whitespace) {
+        return Err(ca_err("file is empty".into()));
+    }
+    let certs = parse_pem_certs(&pem).map_err(|e| ca_err(e.to_string()))?;
+    if certs.is_empty() {
+        return Err(ca_err("no PEM certificates found".into()));
+    }
+    let mut store = RootCertStore::empty();
+    for der in certs {
+        store.add(der).map_err(|e| ca_err(e.to_string()))?;
+    }
+    Ok(store)
+}
+
+/// Options controlling [`verify_certificate`].
+#[derive(Clone)]
+pub struct VerifyOptions {
+    pub ca: CaSource,
+    /// DNS name or IP address the leaf certificate must be valid for.
+    pub server_name: String,
+    /// Expected SHA-256 hash of the leaf's SubjectPublicKeyInfo.
+    pub spki_pin: Option<[u8; 32]>,
+    /// Verification time, defaults to the current time.
+    pub now: Option<UnixTime>,
+}
+
+impl VerifyOptions {
+    pub fn new(ca: CaSource, server_name: impl Into<String>) -> Self {
+        Self {
+            ca,
+            server_name: server_name.into(),
+            spki_pin: None,
+            now: None,
+        }
+    }
+
+    pub fn with_spki_pin(mut self, pin: [u8; 32]) -> Self {
+        self.spki_pin = Some(pin);
+        self
+    }
+}
+
+/// Verifies the DER encoded `chain` (leaf first) presented by a server and
+/// returns it as owned certificates on success.
+pub fn verify_certificate(
+    chain: &[&[u8]],
+    opts: &VerifyOptions,
+) -> Result<Vec<CertificateDer<'static>>, CertVerifyError> {
+    let chain: Vec<CertificateDer<'static>> = chain
+        .iter()
+        .map(|der| CertificateDer::from(der.to_vec()))
+        .collect();
+    let (leaf, intermediates) = chain.split_first().ok_or(CertVerifyError::EmptyChain)?;
+
+    let verifier = WebPkiServerVerifier::builder(opts.ca.load()?)
+        .build()
+        .map_err(|e| CertVerifyError::CaLoad(e.to_string()))?;
+    let name = ServerName::try_from(opts.server_name.clone())
+        .map_err(|_| CertVerifyError::InvalidServerName(opts.server_name.clone()))?;
+    let now = opts.now.unwrap_or_else(UnixTime::now);
+
+    verifier
+        .verify_server_cert(leaf, intermediates, &name, &[], now)
+        .map_err(|e| match e {
+            rustls::Error::InvalidCertificate(CertificateError::Expired) => {
+                CertVerifyError::Expired
+            }
+            other => CertVerifyError::Invalid(other),
+        })?;
+
+    if let Some(pin) = opts.spki_pin {
+        if spki_sha256(leaf)? != pin {
+            return Err(CertVerifyError::PinMismatch);
+        }
+    }
+    Ok(chain)
+}
+
+/// Returns the SHA-256 hash of the certificate's DER encoded
+/// SubjectPublicKeyInfo, the value used for SPKI pinning.
+pub fn spki_sha256(cert: &[u8]) -> Result<[u8; 32], CertVerifyError> {
+    let spki = subject_public_key_info(cert).ok_or(CertVerifyError::Malformed)?;
+    Ok(Sha256::digest(spki).into())
+}
+
+/// Decodes all `CERTIFICATE` blocks of a PEM document.
+pub fn parse_pem_certs(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>, CertVerifyError> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let text = std::str::from_utf8(pem).map_err(|_| CertVerifyError::Malformed)?;
+    let mut certs = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(BEGIN) {
+        let body = &rest[start + BEGIN.len()..];
+        let end = body.find(END).ok_or(CertVerifyError::Malformed)?;
+        let b64: String = body[..end].split_whitespace().collect();
+        let der = base64::decode(b64).map_err(|_| CertVerifyError::Malformed)?;
+        certs.push(CertificateDer::from(der));
+        rest = &body[end + END.len()..];
+    }
+    Ok(certs)
+}
+
+/// Splits one DER TLV off `buf`, returning `(tag, whole element, content, rest)`.
+fn der_next(buf: &[u8]) -> Option<(u8, &[u8], &[u8], &[u8])> {
+    let tag = *buf.first()?;
+    let first = *buf.get(1)?;
+    let (len, header) = if first & 0x80 == 0 {
+        (first as usize, 2)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let bytes = buf.get(2..2 + n)?;
+        (
+            bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize),
+            2 + n,
+        )
+    };
+    let end = header.checked_add(len)?;
+    let element = buf.get(..end)?;
+    Some((tag, element, &element[header..], &buf[end..]))
+}
+
+/// Locates the SubjectPublicKeyInfo inside a DER encoded X.509 certificate.
+fn subject_public_key_info(cert: &[u8]) -> Option<&[u8]> {
+    const SEQUENCE: u8 = 0x30;
+    const VERSION: u8 = 0xa0;
+
+    let (tag, _, cert, _) = der_next(cert)?;
+    if tag != SEQUENCE {
+        return None;
+    }
+    let (tag, _, tbs, _) = der_next(cert)?;
+    if tag != SEQUENCE {
+        return None;
+    }
+    let mut rest = tbs;
+    if *rest.first()? == VERSION {
+        rest = der_next(rest)?.3;
+    }
+    // serialNumber, signature, issuer, validity, subject
+    for _ in 0..5 {
+        rest = der_next(rest)?.3;
+    }
+    let (tag, spki, _, _) = der_next(rest)?;
+    (tag == SEQUENCE).then_some(spki)
+}
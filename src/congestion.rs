@@ -0,0 +1,389 @@
+// Copyright (c) 2024, The QuicFuscate Project Authors.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above
+//       copyright notice, this list of conditions and the following disclaimer
+//       in the documentation and/or other materials provided with the
+//       distribution.
+//
+//     * Neither the name of the copyright holder nor the names of its
+//       contributors may be used to endorse or promote products derived from
+//       this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # Congestion Control
+//!
+//! Window models for the congestion control algorithms a connection can
+//! switch between at runtime. quiche fixes its controller when the
+//! connection is created, so these models track the window the selected
+//! algorithm would use and drive the crate's own rate decisions (pacing,
+//! FEC redundancy) from it.
+
+use std::time::{Duration, Instant};
+
+/// Default maximum segment size used to scale the windows.
+pub const DEFAULT_MSS: usize = 1350;
+/// Initial window in segments (RFC 9002, section 7.2).
+const INITIAL_WINDOW_PACKETS: usize = 10;
+/// Minimum window in segments.
+const MINIMUM_WINDOW_PACKETS: usize = 2;
+
+/// Selectable congestion control algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CcAlgorithm {
+    Bbr,
+    Cubic,
+    Reno,
+}
+
+impl CcAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CcAlgorithm::Bbr => "bbr",
+            CcAlgorithm::Cubic => "cubic",
+            CcAlgorithm::Reno => "reno",
+        }
+    }
+
+    /// The matching quiche algorithm, for configuring new connections.
+    /// BBR maps to quiche's BBRv2.
+    pub fn to_quiche(self) -> quiche::CongestionControlAlgorithm {
+        match self {
+            CcAlgorithm::Bbr => quiche::CongestionControlAlgorithm::BBRv2,
+            CcAlgorithm::Cubic => quiche::CongestionControlAlgorithm::CUBIC,
+            CcAlgorithm::Reno => quiche::CongestionControlAlgorithm::Reno,
+        }
+    }
+
+    /// Creates a fresh controller for this algorithm.
+    pub fn controller(self, mss: usize) -> Box<dyn CongestionController> {
+        match self {
+            CcAlgorithm::Bbr => Box::new(Bbr::new(mss)),
+            CcAlgorithm::Cubic => Box::new(Cubic::new(mss)),
+            CcAlgorithm::Reno => Box::new(Reno::new(mss)),
+        }
+    }
+}
+
+impl std::str::FromStr for CcAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bbr" => Ok(CcAlgorithm::Bbr),
+            "cubic" => Ok(CcAlgorithm::Cubic),
+            "reno" => Ok(CcAlgorithm::Reno),
+            other => Err(format!("unknown congestion control algorithm '{other}'")),
+        }
+    }
+}
+
+/// Common interface of all congestion controllers.
+pub trait CongestionController: Send {
+    fn algorithm(&self) -> CcAlgorithm;
+
+    /// Called for every newly acknowledged packet of `bytes` bytes.
+    fn on_packet_acknowledged(&mut self, bytes: usize, rtt: Duration, now: Instant);
+
+    /// Called for every packet of `bytes` bytes declared lost.
+    fn on_packet_lost(&mut self, bytes: usize, now: Instant);
+
+    /// Current congestion window in bytes.
+    fn congestion_window(&self) -> usize;
+
+    /// Seeds the RTT estimate for paths with a known high latency. Has no
+    /// effect once a real RTT sample has been taken.
+    fn set_initial_rtt(&mut self, rtt: Duration);
+
+    /// Current RTT estimate, `None` before any sample or seed.
+    fn rtt(&self) -> Option<Duration>;
+
+    /// Pacing rate in bytes per second: one congestion window per RTT.
+    fn pacing_rate(&self) -> Option<u64> {
+        self.rtt()
+            .filter(|r| !r.is_zero())
+            .map(|r| (self.congestion_window() as f64 / r.as_secs_f64()) as u64)
+    }
+}
+
+/// RTT estimate that may be seeded before the first sample arrives.
+#[derive(Debug, Default, Clone, Copy)]
+struct RttEstimate {
+    value: Option<Duration>,
+    sampled: bool,
+}
+
+impl RttEstimate {
+    fn seed(&mut self, rtt: Duration) {
+        if !self.sampled && !rtt.is_zero() {
+            self.value = Some(rtt);
+        }
+    }
+
+    /// Tracks the most recent sample.
+    fn on_sample(&mut self, rtt: Duration) {
+        if !rtt.is_zero() {
+            self.value = Some(rtt);
+            self.sampled = true;
+        }
+    }
+
+    /// Tracks the smallest sample. A seed is replaced by the first sample
+    /// even if the sample is larger.
+    fn on_min_sample(&mut self, rtt: Duration) {
+        if rtt.is_zero() {
+            return;
+        }
+        self.value = Some(match self.value {
+            Some(min) if self.sampled => min.min(rtt),
+            _ => rtt,
+        });
+        self.sampled = true;
+    }
+
+    fn get(&self) -> Option<Duration> {
+        self.value
+    }
+}
+
+// --- Reno ---
+
+/// NewReno as described in RFC 9002, section 7.3.
+pub struct Reno {
+    mss: usize,
+    cwnd: usize,
+    ssthresh: usize,
+    acked: usize,
+    rtt: RttEstimate,
+}
+
+impl Reno {
+    pub fn new(mss: usize) -> Self {
+        Self {
+            mss,
+            cwnd: INITIAL_WINDOW_PACKETS * mss,
+            ssthresh: usize::MAX,
+            acked: 0,
+            rtt: RttEstimate::default(),
+        }
+    }
+}
+
+impl CongestionController for Reno {
+    fn algorithm(&self) -> CcAlgorithm {
+        CcAlgorithm::Reno
+    }
+
+    fn on_packet_acknowledged(&mut self, bytes: usize, rtt: Duration, _now: Instant) {
+        self.rtt.on_sample(rtt);
+        if self.cwnd < self.ssthresh {
+            self.cwnd += bytes;
+            return;
+        }
+        // Congestion avoidance: one MSS per window of acknowledged data.
+        self.acked += bytes;
+        if self.acked >= self.cwnd {
+            self.acked -= self.cwnd;
+            self.cwnd += self.mss;
+        }
+    }
+
+    fn on_packet_lost(&mut self, _bytes: usize, _now: Instant) {
+        self.cwnd = (self.cwnd / 2).max(MINIMUM_WINDOW_PACKETS * self.mss);
+        self.ssthresh = self.cwnd;
+        self.acked = 0;
+    }
+
+    fn congestion_window(&self) -> usize {
+        self.cwnd
+    }
+
+    fn set_initial_rtt(&mut self, rtt: Duration) {
+        self.rtt.seed(rtt);
+    }
+
+    fn rtt(&self) -> Option<Duration> {
+        self.rtt.get()
+    }
+}
+
+// --- CUBIC ---
+
+/// CUBIC as described in RFC 9438.
+pub struct Cubic {
+    mss: usize,
+    cwnd: usize,
+    ssthresh: usize,
+    w_max: f64,
+    k: f64,
+    epoch_start: Option<Instant>,
+    rtt: RttEstimate,
+}
+
+impl Cubic {
+    const C: f64 = 0.4;
+    const BETA: f64 = 0.7;
+
+    pub fn new(mss: usize) -> Self {
+        Self {
+            mss,
+            cwnd: INITIAL_WINDOW_PACKETS * mss,
+            ssthresh: usize::MAX,
+            w_max: 0.0,
+            k: 0.0,
+            epoch_start: None,
+            rtt: RttEstimate::default(),
+        }
+    }
+}
+
+impl CongestionController for Cubic {
+    fn algorithm(&self) -> CcAlgorithm {
+        CcAlgorithm::Cubic
+    }
+
+    fn on_packet_acknowledged(&mut self, bytes: usize, rtt: Duration, now: Instant) {
+        self.rtt.on_sample(rtt);
+        if self.cwnd < self.ssthresh {
+            self.cwnd += bytes;
+            return;
+        }
+        let start = *self.epoch_start.get_or_insert(now);
+        let t = (now.saturating_duration_since(start) + rtt).as_secs_f64();
+        // W_cubic(t) = C * (t - K)^3 + W_max, in segments.
+        let target = (Self::C * (t - self.k).powi(3) + self.w_max) * self.mss as f64;
+        let cwnd = self.cwnd as f64;
+        if target > cwnd {
+            let inc = (target - cwnd) / cwnd * bytes as f64;
+            self.cwnd += (inc as usize).min(bytes);
+        }
+    }
+
+    fn on_packet_lost(&mut self, _bytes: usize, _now: Instant) {
+        self.w_max = self.cwnd as f64 / self.mss as f64;
+        self.cwnd =
+            ((self.cwnd as f64 * Self::BETA) as usize).max(MINIMUM_WINDOW_PACKETS * self.mss);
+        self.ssthresh = self.cwnd;
+        self.k = (self.w_max * (1.0 - Self::BETA) / Self::C).cbrt();
+        self.epoch_start = None;
+    }
+
+    fn congestion_window(&self) -> usize {
+        self.cwnd
+    }
+
+    fn set_initial_rtt(&mut self, rtt: Duration) {
+        self.rtt.seed(rtt);
+    }
+
+    fn rtt(&self) -> Option<Duration> {
+        self.rtt.get()
+    }
+}
+
+// --- BBR ---
+
+/// Simplified BBR model: the window follows twice the estimated
+/// bandwidth-delay product and, unlike the loss-based controllers,
+/// individual losses do not shrink it.
+pub struct Bbr {
+    mss: usize,
+    cwnd: usize,
+    min_rtt: RttEstimate,
+    max_bw: f64,
+    round_start: Option<Instant>,
+    round_delivered: usize,
+    lost: usize,
+}
+
+impl Bbr {
+    const CWND_GAIN: f64 = 2.0;
+
+    pub fn new(mss: usize) -> Self {
+        Self {
+            mss,
+            cwnd: INITIAL_WINDOW_PACKETS * mss,
+            min_rtt: RttEstimate::default(),
+            max_bw: 0.0,
+            round_start: None,
+            round_delivered: 0,
+            lost: 0,
+        }
+    }
+
+    /// Estimated bottleneck bandwidth in bytes per second.
+    pub fn bandwidth(&self) -> f64 {
+        self.max_bw
+    }
+
+    /// Total bytes reported lost so far.
+    pub fn lost_bytes(&self) -> usize {
+        self.lost
+    }
+}
+
+impl CongestionController for Bbr {
+    fn algorithm(&self) -> CcAlgorithm {
+        CcAlgorithm::Bbr
+    }
+
+    fn on_packet_acknowledged(&mut self, bytes: usize, rtt: Duration, now: Instant) {
+        self.min_rtt.on_min_sample(rtt);
+        let start = *self.round_start.get_or_insert(now);
+        self.round_delivered += bytes;
+
+        let min_rtt = match self.min_rtt.get() {
+            Some(r) => r,
+            None => return,
+        };
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed >= min_rtt {
+            let sample = self.round_delivered as f64 / elapsed.as_secs_f64();
+            self.max_bw = self.max_bw.max(sample);
+            self.round_start = Some(now);
+            self.round_delivered = 0;
+        }
+
+        if self.max_bw > 0.0 {
+            let bdp = self.max_bw * min_rtt.as_secs_f64();
+            self.cwnd = ((bdp * Self::CWND_GAIN) as usize).max(4 * self.mss);
+        } else {
+            // Startup: grow like slow start until the first bandwidth sample.
+            self.cwnd += bytes;
+        }
+    }
+
+    fn on_packet_lost(&mut self, bytes: usize, _now: Instant) {
+        self.lost += bytes;
+    }
+
+    fn congestion_window(&self) -> usize {
+        self.cwnd
+    }
+
+    fn set_initial_rtt(&mut self, rtt: Duration) {
+        self.min_rtt.seed(rtt);
+    }
+
+    fn rtt(&self) -> Option<Duration> {
+        self.min_rtt.get()
+    }
+}
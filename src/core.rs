@@ -35,16 +35,28 @@
 //! orchestrates the crypto, FEC, and stealth modules to manage a full
 //! QUIC connection lifecycle.
 
+use crate::cert_verify::{verify_certificate, VerifyOptions};
+use crate::congestion::{CcAlgorithm, CongestionController, DEFAULT_MSS};
 use crate::crypto::{CipherSuiteSelector, CryptoManager};
-use crate::fec::{AdaptiveFec, FecConfig, Packet as FecPacket, PidConfig};
-use crate::optimize::{MemoryPool, OptimizationManager, OptimizeConfig};
-use crate::stealth::{StealthConfig, StealthManager};
+use crate::datagram::{DatagramEngine, Direction};
+use crate::error::CertVerifyError;
+use crate::fec::{
+    negotiate, AdaptiveFec, FecAgreement, FecConfig, FecOffer, Packet as FecPacket, PidConfig,
+};
+use crate::optimize::{
+    MemoryPool, MtuConfig, MtuStatus, OptimizationManager, OptimizeConfig, PathMtuManager,
+};
+use crate::stealth::{ObfuscationRotation, StealthConfig, StealthManager};
 use crate::telemetry;
 use crate::xdp_socket::XdpSocket;
+use crate::zero_rtt::ZeroRttEngine;
 use log::{debug, error, info, warn};
-use std::collections::VecDeque;
-use std::net::SocketAddr;
+use rand::{rngs::OsRng, RngCore};
+use rustls::pki_types::CertificateDer;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Represents a single QuicFuscate connection and manages its state.
 pub struct QuicFuscateConnection {
@@ -56,6 +68,7 @@ pub struct QuicFuscateConnection {
     // Core Modules
     crypto_selector: CipherSuiteSelector,
     fec: AdaptiveFec,
+    cc: Box<dyn CongestionController>,
 
     // Stealth & Optimization Modules
     stealth_manager: Arc<StealthManager>,
@@ -69,18 +82,754 @@ pub struct QuicFuscateConnection {
     outgoing_fec_packets: VecDeque<FecPacket>,
     xdp_socket: Option<XdpSocket>,
     h3_conn: Option<quiche::h3::Connection>,
+    h3_streams: HashMap<u64, Http3Stream>,
+    // Requests waiting for the peer to allow another bidirectional stream.
+    h3_deferred: VecDeque<String>,
+    datagrams: DatagramEngine,
+    zero_rtt: ZeroRttEngine,
     last_telemetry: std::time::Instant,
+    // quiche counters already reported to `cc`.
+    cc_acked_bytes: u64,
+    cc_lost_bytes: u64,
+    // RTT seed re-applied when the congestion controller is switched.
+    initial_rtt: Option<Duration>,
+    fec_transport: FecTransportPolicy,
+    // Framed repairs quiche has not yet taken on the repair stream.
+    fec_repair_tx: Vec<u8>,
+    // Bytes of the peer's repair stream not yet forming a whole frame.
+    fec_repair_rx: Vec<u8>,
+    fec_stream_repairs: u64,
+    // Sequence number of the peer-issued CID used for sending.
+    dcid_seq: u64,
+    // Set between `migrate_connection` and the path validation result.
+    migration_pending: bool,
+    // Whether the XOR key has been switched over after the handshake.
+    xor_key_coordinated: bool,
+    // Local FEC configuration the negotiated parameters are applied to.
+    fec_config: FecConfig,
+    fec_offer_sent: bool,
+    // Framed offer bytes quiche has not accepted yet.
+    fec_offer_out: Vec<u8>,
+    fec_offer_buf: Vec<u8>,
+    fec_agreement: Option<FecAgreement>,
+    retransmits: RetransmitTracker,
+    io_observer: Option<IoObserver>,
+    // Mirrors quiche's path MTU discovery for the active path.
+    mtu: PathMtuManager,
+    // Path and send time of the last packet quiche built.
+    send_info: quiche::SendInfo,
+    span: tracing::Span,
+    padding: PaddingPolicy,
 }
 
+/// Creates the span grouping all log events of the connection with `peer`
+/// that uses `scid` as its source connection ID.
+pub fn connection_span(peer: SocketAddr, scid: &[u8]) -> tracing::Span {
+    let scid: String = scid.iter().map(|b| format!("{:02x}", b)).collect();
+    tracing::info_span!("connection", peer = %peer, scid = %scid)
+}
+
+/// Callback seeing every datagram a connection sends or receives, see
+/// [`QuicFuscateConnection::set_io_observer`].
+pub type IoObserver = Box<dyn Fn(Direction, &[u8]) + Send + Sync>;
+
 /// Tracks performance and reliability metrics for a connection.
-#[derive(Default, Debug)]
+///
+/// RTT values are in milliseconds, window and in-flight sizes in bytes and
+/// the delivery rate in bytes per second.
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct ConnectionStats {
     pub rtt: f32,
+    pub min_rtt: f32,
     pub loss_rate: f32,
     pub packets_sent: u64,
     pub packets_lost: u64,
+    pub cwnd: u64,
+    pub bytes_in_flight: u64,
+    pub delivery_rate: u64,
+}
+
+impl ConnectionStats {
+    /// Builds the stats from quiche's connection wide counters and the
+    /// statistics of the active path, if there is one. RTT, window and
+    /// delivery rate are per path in quiche and stay zero without one.
+    pub fn from_quiche(stats: &quiche::Stats, path: Option<&quiche::PathStats>) -> Self {
+        let loss_rate = if stats.sent > 0 {
+            stats.lost as f32 / stats.sent as f32
+        } else {
+            0.0
+        };
+        let bytes_in_flight = stats
+            .sent_bytes
+            .saturating_sub(stats.acked_bytes)
+            .saturating_sub(stats.lost_bytes);
+        let mut out = Self {
+            rtt: 0.0,
+            min_rtt: 0.0,
+            loss_rate,
+            packets_sent: stats.sent as u64,
+            packets_lost: stats.lost as u64,
+            cwnd: 0,
+            bytes_in_flight,
+            delivery_rate: 0,
+        };
+        if let Some(path) = path {
+            out.rtt = path.rtt.as_secs_f32() * 1000.0;
+            out.min_rtt = path.min_rtt.unwrap_or(path.rtt).as_secs_f32() * 1000.0;
+            out.cwnd = path.cwnd as u64;
+            out.delivery_rate = path.delivery_rate;
+        }
+        out
+    }
+}
+
+/// Headers and body received on a single HTTP/3 stream.
+#[derive(Default, Debug)]
+pub struct Http3Stream {
+    /// Path of the request sent on the stream, `None` for streams the peer
+    /// opened.
+    pub path: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub finished: bool,
+}
+
+/// Drain state of a server used for graceful shutdown.
+///
+/// While draining, packets from unknown peers are refused and existing
+/// connections keep being serviced until they close or the timeout expires.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ServerDrain {
+    deadline: Option<Instant>,
 }
 
+impl ServerDrain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enters drain mode. Calling this again does not extend the deadline.
+    pub fn start(&mut self, timeout: Duration, now: Instant) {
+        self.deadline.get_or_insert(now + timeout);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    /// Returns whether a datagram from a peer should be processed.
+    /// `known` tells whether the peer already has a connection.
+    pub fn admits(&self, known: bool) -> bool {
+        known || !self.is_draining()
+    }
+
+    /// Returns the connection a datagram from `from` belongs to, creating it
+    /// with `accept` for a new peer. New peers are refused with `None` while
+    /// draining.
+    pub fn admit<'a>(
+        &self,
+        clients: &'a mut HashMap<SocketAddr, QuicFuscateConnection>,
+        from: SocketAddr,
+        accept: impl FnOnce() -> QuicFuscateConnection,
+    ) -> Option<&'a mut QuicFuscateConnection> {
+        if !self.admits(clients.contains_key(&from)) {
+            return None;
+        }
+        Some(clients.entry(from).or_insert_with(accept))
+    }
+
+    /// Returns `true` once the drain timeout has passed.
+    pub fn expired(&self, now: Instant) -> bool {
+        self.deadline.map_or(false, |d| now >= d)
+    }
+
+    /// Returns `true` if the server loop should stop: either the drain timed
+    /// out or no connections remain.
+    pub fn finished(&self, now: Instant, open_connections: usize) -> bool {
+        self.is_draining() && (open_connections == 0 || self.expired(now))
+    }
+}
+
+/// Source packets FEC could not recover, waiting for the send loop to
+/// request them again.
+///
+/// Ids are kept once, oldest first. When `capacity` is reached the oldest
+/// requests are dropped, since the data they refer to is the least likely
+/// to still be useful.
+#[derive(Debug, Clone)]
+pub struct RetransmitTracker {
+    pending: VecDeque<u64>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl Default for RetransmitTracker {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl RetransmitTracker {
+    /// Requests kept by [`RetransmitTracker::default`].
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    /// Creates a tracker holding at most `capacity` ids, at least one.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            capacity: capacity.max(1),
+            dropped: 0,
+        }
+    }
+
+    /// Records missing ids as reported by
+    /// [`AdaptiveFec::take_missing_sources`]. Returns how many were new.
+    pub fn record(&mut self, ids: impl IntoIterator<Item = u64>) -> usize {
+        let mut added = 0;
+        for id in ids {
+            if self.pending.contains(&id) {
+                continue;
+            }
+            if self.pending.len() == self.capacity {
+                self.pending.pop_front();
+                self.dropped += 1;
+            }
+            self.pending.push_back(id);
+            added += 1;
+        }
+        added
+    }
+
+    /// Ids waiting to be requested again, oldest first.
+    pub fn pending_retransmits(&self) -> Vec<u64> {
+        self.pending.iter().copied().collect()
+    }
+
+    /// Removes `ids` once the send loop has requested them.
+    pub fn mark_serviced(&mut self, ids: &[u64]) {
+        self.pending.retain(|id| !ids.contains(id));
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Requests discarded because the tracker was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+/// Selects how FEC repair packets are carried when they are handed to QUIC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FecTransportPolicy {
+    /// Always use DATAGRAM frames; oversized repairs follow the datagram
+    /// engine's [`OversizePolicy`](crate::datagram::OversizePolicy).
+    DatagramOnly,
+    /// Always write repairs to the dedicated repair stream.
+    StreamOnly,
+    /// Use DATAGRAM frames when the repair fits, the repair stream otherwise.
+    #[default]
+    Auto,
+}
+
+/// Path chosen for a single repair packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FecTransport {
+    Datagram,
+    Stream,
+}
+
+impl FecTransportPolicy {
+    /// Picks the transport for a repair of `len` bytes. `max_datagram` is
+    /// the largest DATAGRAM payload currently writable, `None` if the peer
+    /// did not negotiate the extension.
+    pub fn route(self, len: usize, max_datagram: Option<usize>) -> FecTransport {
+        match self {
+            FecTransportPolicy::DatagramOnly => FecTransport::Datagram,
+            FecTransportPolicy::StreamOnly => FecTransport::Stream,
+            FecTransportPolicy::Auto => match max_datagram {
+                Some(max) if len <= max => FecTransport::Datagram,
+                _ => FecTransport::Stream,
+            },
+        }
+    }
+}
+
+/// Delay between connection attempts to successive candidates
+/// (RFC 8305, section 5 recommends 250 ms).
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Orders candidate addresses for happy-eyeballs racing: families alternate,
+/// starting with IPv6, and the relative order within a family is kept.
+pub fn interleave_addrs(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<SocketAddr>, Vec<SocketAddr>) =
+        addrs.iter().copied().partition(|a| a.is_ipv6());
+    let mut out = Vec::with_capacity(addrs.len());
+    let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => break,
+            (a, b) => out.extend(a.into_iter().chain(b)),
+        }
+    }
+    out
+}
+
+/// Parses a peer address given as `host:port`, `[v6]:port`, or a bare IP
+/// literal (bracketed or not) or host name, in which case `default_port` is
+/// used. Host names go through the system resolver and the first answer
+/// wins.
+pub fn parse_peer_addr(s: &str, default_port: u16) -> std::io::Result<SocketAddr> {
+    Ok(parse_peer_addrs(s, default_port)?[0])
+}
+
+/// Like [`parse_peer_addr`], returning every address a host name resolves
+/// to, as candidates for [`connect_racing`]. Never returns an empty list.
+pub fn parse_peer_addrs(s: &str, default_port: u16) -> std::io::Result<Vec<SocketAddr>> {
+    let s = s.trim();
+    if let Ok(addr) = s.parse::<SocketAddr>() {
+        return Ok(vec![addr]);
+    }
+    let literal = s
+        .strip_prefix('[')
+        .and_then(|r| r.strip_suffix(']'))
+        .unwrap_or(s);
+    if let Ok(ip) = literal.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, default_port)]);
+    }
+    let has_port = s
+        .rsplit_once(':')
+        .map_or(false, |(_, port)| port.parse::<u16>().is_ok());
+    let host = if has_port {
+        s.to_string()
+    } else {
+        format!("{}:{}", s, default_port)
+    };
+    let mut addrs: Vec<SocketAddr> = Vec::new();
+    for addr in host.to_socket_addrs()? {
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+    if addrs.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no address found for {}", s),
+        ));
+    }
+    Ok(addrs)
+}
+
+/// Local address to bind for talking to `peer`. An unspecified `local` of
+/// the other address family is replaced by the unspecified address of the
+/// peer's family, keeping the port, so the default `0.0.0.0:0` also works
+/// for IPv6 servers.
+pub fn bind_addr_for(local: SocketAddr, peer: SocketAddr) -> SocketAddr {
+    if local.ip().is_unspecified() && local.is_ipv4() != peer.is_ipv4() {
+        let ip = match peer.ip() {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        SocketAddr::new(ip, local.port())
+    } else {
+        local
+    }
+}
+
+/// Binds a UDP socket. The IPv6 wildcard `[::]` is bound dual-stack, so one
+/// socket serves IPv6 peers and IPv4 peers, which then show up v4-mapped.
+pub fn bind_udp(addr: SocketAddr) -> std::io::Result<std::net::UdpSocket> {
+    let socket = socket2::Socket::new(
+        socket2::Domain::for_address(addr),
+        socket2::Type::DGRAM,
+        Some(socket2::Protocol::UDP),
+    )?;
+    if let SocketAddr::V6(v6) = addr {
+        if v6.ip().is_unspecified() {
+            socket.set_only_v6(false)?;
+        }
+    }
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
+
+/// Address under which `peer` is seen on a socket bound to `local`.
+///
+/// IPv4 peers of a dual-stack (`[::]`) or v4-mapped IPv6 socket are
+/// v4-mapped, v4-mapped peers of an IPv4 socket are unmapped. Returns
+/// `None` for an IPv6 peer that an IPv4 socket cannot reach, or an IPv4
+/// peer of an IPv6-only socket.
+pub fn path_peer_addr(local: SocketAddr, peer: SocketAddr) -> Option<SocketAddr> {
+    match (local.ip(), peer.ip()) {
+        (IpAddr::V4(_), IpAddr::V4(_)) => Some(peer),
+        (IpAddr::V4(_), IpAddr::V6(v6)) => v6
+            .to_ipv4_mapped()
+            .map(|v4| SocketAddr::new(IpAddr::V4(v4), peer.port())),
+        (IpAddr::V6(l), IpAddr::V4(v4)) => (l.is_unspecified() || l.to_ipv4_mapped().is_some())
+            .then(|| SocketAddr::new(IpAddr::V6(v4.to_ipv6_mapped()), peer.port())),
+        (IpAddr::V6(_), IpAddr::V6(_)) => Some(peer),
+    }
+}
+
+/// Races handshakes to several candidates (RFC 8305). Attempts start
+/// `stagger` apart in [`interleave_addrs`] order; the first to succeed wins
+/// and all others are cancelled. If every attempt fails the last error is
+/// returned.
+///
+/// `connect` performs one complete handshake, e.g. creating a
+/// [`QuicFuscateConnection`] and driving it until established.
+pub async fn connect_racing<F, Fut, C, E>(
+    addrs: &[SocketAddr],
+    stagger: Duration,
+    connect: F,
+) -> Result<(SocketAddr, C), E>
+where
+    F: Fn(SocketAddr) -> Fut,
+    Fut: std::future::Future<Output = Result<C, E>> + Send + 'static,
+    C: Send + 'static,
+    E: From<std::io::Error> + Send + 'static,
+{
+    let mut attempts = tokio::task::JoinSet::new();
+    for (i, addr) in interleave_addrs(addrs).into_iter().enumerate() {
+        let attempt = connect(addr);
+        let delay = stagger * i as u32;
+        attempts.spawn(async move {
+            tokio::time::sleep(delay).await;
+            debug!("Happy eyeballs: attempting {}", addr);
+            (addr, attempt.await)
+        });
+    }
+
+    let mut last_err = None;
+    while let Some(joined) = attempts.join_next().await {
+        match joined {
+            Ok((addr, Ok(conn))) => {
+                info!("Happy eyeballs: {} won the race", addr);
+                // Dropping the set aborts the remaining attempts.
+                return Ok((addr, conn));
+            }
+            Ok((addr, Err(e))) => {
+                warn!("Happy eyeballs: attempt to {} failed", addr);
+                last_err = Some(e);
+            }
+            Err(e) => last_err = Some(std::io::Error::new(std::io::ErrorKind::Other, e).into()),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "no candidate addresses").into()
+    }))
+}
+
+/// Exponential backoff between client reconnection attempts.
+///
+/// The delay before retry `n` (1-based) is `base_delay * 2^(n-1)`, capped at
+/// `max_delay`, then scaled by a random factor in `[1 - jitter, 1 + jitter]`
+/// so that many clients dropped at once do not reconnect in lockstep.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Retries after the first attempt; `0` disables reconnecting.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Relative jitter in `[0, 1]`.
+    pub jitter: f64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry `retry` (1-based) without jitter.
+    pub fn base_delay_for(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// Delay before retry `retry` (1-based) with jitter applied.
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        let delay = self.base_delay_for(retry);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        use rand::Rng;
+        delay.mul_f64(rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter))
+    }
+}
+
+/// Runs `attempt` until it succeeds or `policy.max_retries` retries have
+/// failed, waiting [`ReconnectPolicy::delay_for`] between attempts. The last
+/// error is returned when every attempt fails.
+///
+/// `attempt` receives the 0-based attempt number and should redo everything
+/// a fresh connection needs (address resolution, profile selection,
+/// handshake), so a retry picks up a rotated profile or a moved server.
+/// `sleep` performs the wait, normally [`tokio::time::sleep`].
+pub async fn reconnect_with_backoff<T, E, A, AFut, S, SFut>(
+    policy: &ReconnectPolicy,
+    mut attempt: A,
+    mut sleep: S,
+) -> Result<T, E>
+where
+    A: FnMut(u32) -> AFut,
+    AFut: std::future::Future<Output = Result<T, E>>,
+    S: FnMut(Duration) -> SFut,
+    SFut: std::future::Future<Output = ()>,
+    E: std::fmt::Display,
+{
+    let mut n = 0;
+    loop {
+        match attempt(n).await {
+            Ok(v) => return Ok(v),
+            Err(e) if n >= policy.max_retries => return Err(e),
+            Err(e) => {
+                n += 1;
+                let delay = policy.delay_for(n);
+                warn!(
+                    "Connection attempt {} failed: {}; retrying in {:?}",
+                    n, e, delay
+                );
+                sleep(delay).await;
+            }
+        }
+    }
+}
+
+/// How outgoing datagrams are padded so their sizes do not reveal the
+/// length of what they carry.
+///
+/// Padding is appended after the packets, so it only applies to datagrams
+/// whose last packet is a long-header one (Initial, 0-RTT, Handshake), see
+/// [`CoalescedDatagramBuilder::pad_to`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PaddingPolicy {
+    /// Send datagrams at their natural size.
+    #[default]
+    None,
+    /// Pad every datagram to the path MTU.
+    PadToMtu,
+    /// Pad up to the smallest listed size that fits, e.g. the sizes a
+    /// browser is seen sending. Larger datagrams are left as they are.
+    PadToBucket(Vec<usize>),
+}
+
+impl PaddingPolicy {
+    /// Size a datagram of `len` bytes is padded to, never above `mtu` and
+    /// never below `len`.
+    pub fn target_len(&self, len: usize, mtu: usize) -> usize {
+        let target = match self {
+            PaddingPolicy::None => len,
+            PaddingPolicy::PadToMtu => mtu,
+            PaddingPolicy::PadToBucket(sizes) => sizes
+                .iter()
+                .copied()
+                .filter(|&s| s >= len)
+                .min()
+                .unwrap_or(len),
+        };
+        target.min(mtu).max(len)
+    }
+}
+
+/// Packs several QUIC packets into one UDP payload, the way browsers
+/// coalesce Initial, Handshake and 1-RTT packets.
+///
+/// Only long-header packets carry a length, so a packet without one (a
+/// short-header, Retry or Version Negotiation packet) has to be the last in
+/// the datagram and closes the builder.
+pub struct CoalescedDatagramBuilder<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    packets: usize,
+    closed: bool,
+}
+
+impl<'a> CoalescedDatagramBuilder<'a> {
+    /// Builds into `buf`, never past `mtu` bytes.
+    pub fn new(buf: &'a mut [u8], mtu: usize) -> Self {
+        let cap = mtu.min(buf.len());
+        Self {
+            buf: &mut buf[..cap],
+            len: 0,
+            packets: 0,
+            closed: false,
+        }
+    }
+
+    /// Bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of packets in the datagram.
+    pub fn packet_count(&self) -> usize {
+        self.packets
+    }
+
+    /// Space left before the MTU is reached.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.len
+    }
+
+    /// Returns `true` if another packet may still be appended.
+    pub fn can_extend(&self) -> bool {
+        !self.closed && self.remaining() > 0
+    }
+
+    /// Appends `packet` if it fits and the datagram is still open. Returns
+    /// `false` if it has to go into the next datagram instead.
+    pub fn try_push(&mut self, packet: &[u8]) -> bool {
+        if packet.is_empty() || !self.can_extend() || packet.len() > self.remaining() {
+            return false;
+        }
+        self.buf[self.len..self.len + packet.len()].copy_from_slice(packet);
+        self.commit(packet.len());
+        true
+    }
+
+    /// Lets `write` fill the remaining space directly, e.g. with
+    /// `quiche::Connection::send`, and appends what it reports as written.
+    /// Nothing is called once the datagram is closed or full.
+    pub fn fill_with<E>(
+        &mut self,
+        write: impl FnOnce(&mut [u8]) -> Result<usize, E>,
+    ) -> Result<usize, E> {
+        if !self.can_extend() {
+            return Ok(0);
+        }
+        let written = write(&mut self.buf[self.len..])?;
+        if written > 0 {
+            self.commit(written);
+        }
+        Ok(written)
+    }
+
+    /// Appends zero bytes until the datagram is `target` bytes long, capped
+    /// at the MTU, and returns how many were added. The bytes follow the
+    /// last packet as an unparseable remainder that receivers discard, so
+    /// nothing is added to an empty datagram or after a packet without a
+    /// length field.
+    pub fn pad_to(&mut self, target: usize) -> usize {
+        if self.is_empty() || !self.can_extend() {
+            return 0;
+        }
+        let end = target.min(self.buf.len());
+        if end <= self.len {
+            return 0;
+        }
+        self.buf[self.len..end].fill(0);
+        let added = end - self.len;
+        self.len = end;
+        self.closed = true;
+        added
+    }
+
+    /// The coalesced datagram.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Accounts for `n` new bytes at the end, which may hold several packets.
+    fn commit(&mut self, n: usize) {
+        let mut off = self.len;
+        self.len += n;
+        while off < self.len {
+            self.packets += 1;
+            match long_header_packet_len(&self.buf[off..self.len]) {
+                Some(len) => off += len,
+                None => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Length of the long-header packet at the start of `buf`, `None` if the
+/// packet carries no length field or is truncated.
+fn long_header_packet_len(buf: &[u8]) -> Option<usize> {
+    fn varint(buf: &[u8]) -> Option<(u64, usize)> {
+        let first = *buf.first()?;
+        let len = 1usize << (first >> 6);
+        let bytes = buf.get(..len)?;
+        let v = bytes[1..]
+            .iter()
+            .fold((first & 0x3f) as u64, |acc, b| (acc << 8) | *b as u64);
+        Some((v, len))
+    }
+
+    let first = *buf.first()?;
+    let version = u32::from_be_bytes(buf.get(1..5)?.try_into().ok()?);
+    if first & 0x80 == 0 || version == 0 {
+        return None;
+    }
+    let packet_type = (first >> 4) & 0x03;
+    if packet_type == 0x03 {
+        // Retry
+        return None;
+    }
+    let mut off = 5;
+    for _ in 0..2 {
+        let cid_len = *buf.get(off)? as usize;
+        off += 1 + cid_len;
+    }
+    if packet_type == 0x00 {
+        let (token_len, n) = varint(buf.get(off..)?)?;
+        off += n + token_len as usize;
+    }
+    let (length, n) = varint(buf.get(off..)?)?;
+    let total = off + n + length as usize;
+    (total <= buf.len()).then_some(total)
+}
+
+/// Index of the unidirectional stream carrying repairs that do not fit into
+/// a DATAGRAM frame. Chosen above the streams HTTP/3 opens for control and
+/// QPACK so both can share a connection.
+const FEC_REPAIR_STREAM_INDEX: u64 = 16;
+/// Most repair stream bytes held back while quiche lacks stream credit.
+/// Repairs beyond it are dropped; they are only useful while fresh.
+const FEC_REPAIR_STREAM_BACKLOG: usize = 64 * 1024;
+/// Unidirectional stream carrying each endpoint's [`FecOffer`].
+const FEC_NEGOTIATION_STREAM_INDEX: u64 = 15;
+/// Reserved HTTP/3 stream type prefixed to the offer so an HTTP/3 peer
+/// ignores the stream instead of mistaking it for a push stream.
+const FEC_NEGOTIATION_STREAM_TYPE: u8 = 0x21;
+
 impl QuicFuscateConnection {
     /// Creates a new client connection.
     pub fn new_client(
@@ -93,8 +842,8 @@ impl QuicFuscateConnection {
         opt_cfg: OptimizeConfig,
         use_utls: bool,
     ) -> Result<Self, String> {
-        // --- Explicitly set BBRv2 Congestion Control as per PLAN.txt ---
-        config.set_cc_algorithm(quiche::CongestionControlAlgorithm::BBRv2);
+        // quiche fixes its controller when the connection is created.
+        config.set_cc_algorithm(opt_cfg.congestion_control.to_quiche());
         // --- Enable MTU Discovery ---
         config.enable_mtu_probing();
 
@@ -112,8 +861,18 @@ impl QuicFuscateConnection {
             Some(CipherSuiteSelector::new().tls_cipher()),
         );
 
+        stealth_manager
+            .initialize()
+            .map_err(|e| format!("Stealth initialization failed: {}", e))?;
+
         let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
 
+        let remote_addr = path_peer_addr(local_addr, remote_addr).ok_or_else(|| {
+            format!(
+                "Server address {} is not reachable from local address {}",
+                remote_addr, local_addr
+            )
+        })?;
         let (sni, host_header) = stealth_manager.get_connection_headers(server_name);
 
         let conn = quiche::connect(Some(&sni), &scid, local_addr, remote_addr, &mut config)
@@ -129,6 +888,7 @@ impl QuicFuscateConnection {
             optimization_manager,
             xdp_socket,
             fec_config,
+            opt_cfg.congestion_control,
         ))
     }
 
@@ -142,7 +902,7 @@ impl QuicFuscateConnection {
         mut fec_config: FecConfig,
         opt_cfg: OptimizeConfig,
     ) -> Result<Self, String> {
-        config.set_cc_algorithm(quiche::CongestionControlAlgorithm::BBRv2);
+        config.set_cc_algorithm(opt_cfg.congestion_control.to_quiche());
         config.enable_mtu_probing();
 
         let crypto_manager = Arc::new(CryptoManager::new());
@@ -153,6 +913,10 @@ impl QuicFuscateConnection {
             optimization_manager.clone(),
         ));
 
+        stealth_manager
+            .initialize()
+            .map_err(|e| format!("Stealth initialization failed: {}", e))?;
+
         let conn = quiche::accept(scid, odcid, local_addr, remote_addr, &mut config)
             .map_err(|e| format!("Failed to accept QUIC connection: {}", e))?;
 
@@ -167,6 +931,7 @@ impl QuicFuscateConnection {
             optimization_manager,
             xdp_socket,
             fec_config,
+            opt_cfg.congestion_control,
         ))
     }
 
@@ -179,14 +944,17 @@ impl QuicFuscateConnection {
         optimization_manager: Arc<OptimizationManager>,
         xdp_socket: Option<XdpSocket>,
         fec_config: FecConfig,
+        cc_algorithm: CcAlgorithm,
     ) -> Self {
+        let span = connection_span(peer_addr, conn.source_id().as_ref());
         Self {
             conn,
             peer_addr,
             local_addr,
             host_header,
             crypto_selector: CipherSuiteSelector::new(),
-            fec: AdaptiveFec::new(fec_config, optimization_manager.memory_pool()),
+            fec: AdaptiveFec::new(fec_config.clone(), optimization_manager.memory_pool()),
+            cc: cc_algorithm.controller(DEFAULT_MSS),
             stealth_manager,
             optimization_manager,
             stats: ConnectionStats::default(),
@@ -194,13 +962,59 @@ impl QuicFuscateConnection {
             outgoing_fec_packets: VecDeque::new(),
             xdp_socket,
             h3_conn: None,
+            h3_streams: HashMap::new(),
+            h3_deferred: VecDeque::new(),
+            datagrams: DatagramEngine::new(),
+            zero_rtt: ZeroRttEngine::new(),
             last_telemetry: std::time::Instant::now(),
+            cc_acked_bytes: 0,
+            cc_lost_bytes: 0,
+            initial_rtt: None,
+            fec_transport: FecTransportPolicy::default(),
+            fec_repair_tx: Vec::new(),
+            fec_repair_rx: Vec::new(),
+            fec_stream_repairs: 0,
+            dcid_seq: 0,
+            padding: PaddingPolicy::default(),
+            migration_pending: false,
+            xor_key_coordinated: false,
+            fec_config,
+            fec_offer_sent: false,
+            fec_offer_out: Vec::new(),
+            fec_offer_buf: Vec::new(),
+            fec_agreement: None,
+            retransmits: RetransmitTracker::default(),
+            io_observer: None,
+            mtu: PathMtuManager::new(MtuConfig::for_peer(&peer_addr)),
+            send_info: quiche::SendInfo {
+                from: local_addr,
+                to: peer_addr,
+                at: Instant::now(),
+            },
+            span,
         }
     }
 
+    /// Span that log events about this connection should be recorded in.
+    pub fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+
     /// Processes an incoming raw buffer, parsing it into an FEC packet and handling recovery.
     /// This now avoids any serialization overhead.
     pub fn recv(&mut self, data: &[u8]) -> Result<usize, crate::error::ConnectionError> {
+        self.recv_on_path(data, self.peer_addr, self.local_addr)
+    }
+
+    /// Like [`recv`](Self::recv), for a datagram that arrived on the path
+    /// `from -> to` rather than the active one, e.g. a path being validated
+    /// during migration.
+    pub fn recv_on_path(
+        &mut self,
+        data: &[u8],
+        from: SocketAddr,
+        to: SocketAddr,
+    ) -> Result<usize, crate::error::ConnectionError> {
         let mut block = self.optimization_manager.alloc_block();
         let len = if let Some(ref xdp) = self.xdp_socket {
             match xdp.recv(&mut block) {
@@ -215,6 +1029,9 @@ impl QuicFuscateConnection {
             block[..copy_len].copy_from_slice(&data[..copy_len]);
             copy_len
         };
+        if let Some(cb) = &self.io_observer {
+            cb(Direction::Inbound, &block[..len]);
+        }
 
         let fec_packet = FecPacket::from_block(
             self.packet_id_counter,
@@ -226,58 +1043,234 @@ impl QuicFuscateConnection {
         let recovered_packets = self.fec.on_receive(fec_packet).map_err(|e| {
             crate::error::ConnectionError::Fec(format!("FEC decoding failed: {}", e))
         })?;
+        self.retransmits.record(self.fec.take_missing_sources());
+        self.process_recovered(recovered_packets, from, to);
+        self.recv_fec_repair_stream(from, to);
 
-        for mut packet in recovered_packets {
-            if let Some(ref mut data) = packet.data {
-                // Deobfuscate payload if enabled
-                self.stealth_manager.process_incoming_packet(data);
+        self.datagrams.poll_from(&mut self.conn);
 
-                // Process the reconstructed QUIC packet
-                let recv_info = quiche::RecvInfo {
-                    from: self.peer_addr,
-                    to: self.local_addr,
-                };
-                if let Err(e) = self.conn.recv(data, recv_info) {
+        if !self.xor_key_coordinated && self.conn.is_established() {
+            self.coordinate_xor_key();
+        }
+        if (self.fec_agreement.is_none() || !self.fec_offer_sent) && self.conn.is_established() {
+            self.negotiate_fec();
+        }
+
+        Ok(len)
+    }
+
+    /// Deobfuscates the packets handed out by the FEC decoder and passes
+    /// them to quiche.
+    fn process_recovered(&mut self, packets: Vec<FecPacket>, from: SocketAddr, to: SocketAddr) {
+        for mut packet in packets {
+            if let Some(ref mut data) = packet.data {
+                // Deobfuscate and process the reconstructed QUIC packet
+                let recv_info = quiche::RecvInfo { from, to };
+                if let Err(e) = self.recv_deobfuscated(data, recv_info) {
                     // Log error, but continue processing other recovered packets
                     error!("quiche::recv failed after FEC recovery: {}", e);
                 }
             }
         }
+    }
 
-        Ok(len)
+    /// Exchanges [`FecOffer`]s with the peer on the negotiation stream and
+    /// restarts the FEC controller with the agreed parameters once the
+    /// peer's offer is complete. See [`negotiate`].
+    fn negotiate_fec(&mut self) {
+        let server = u64::from(self.conn.is_server());
+        if !self.fec_offer_sent {
+            let stream_id = (FEC_NEGOTIATION_STREAM_INDEX << 2) | 0x2 | server;
+            if self.fec_offer_out.is_empty() {
+                // Built once; retries only send what flow control held
+                // back.
+                self.fec_offer_out.push(FEC_NEGOTIATION_STREAM_TYPE);
+                self.fec_offer_out
+                    .extend_from_slice(&FecOffer::from_config(&self.fec_config).encode());
+            }
+            match self.conn.stream_send(stream_id, &self.fec_offer_out, true) {
+                Ok(n) => {
+                    self.fec_offer_out.drain(..n);
+                    self.fec_offer_sent = self.fec_offer_out.is_empty();
+                }
+                Err(e) => debug!("FEC offer not sent yet: {}", e),
+            }
+        }
+        if self.fec_agreement.is_some() {
+            return;
+        }
+
+        let peer_stream = (FEC_NEGOTIATION_STREAM_INDEX << 2) | 0x2 | (server ^ 1);
+        let mut buf = [0u8; 1 + FecOffer::LEN];
+        while self.fec_offer_buf.len() < buf.len() {
+            let want = buf.len() - self.fec_offer_buf.len();
+            match self.conn.stream_recv(peer_stream, &mut buf[..want]) {
+                Ok((n, _)) => self.fec_offer_buf.extend_from_slice(&buf[..n]),
+                Err(_) => break,
+            }
+        }
+        if self.fec_offer_buf.len() < buf.len() {
+            return;
+        }
+
+        let local = FecOffer::from_config(&self.fec_config);
+        let agreement = match FecOffer::decode(&self.fec_offer_buf[1..]) {
+            Ok(remote) => negotiate(&local, &remote),
+            Err(e) => {
+                warn!("Malformed FEC offer from peer, disabling FEC: {}", e);
+                FecAgreement::Disabled("malformed offer")
+            }
+        };
+        info!("FEC negotiated: {:?}", agreement);
+        agreement.apply(&mut self.fec_config);
+        self.fec = AdaptiveFec::new(
+            self.fec_config.clone(),
+            self.optimization_manager.memory_pool(),
+        );
+        self.fec_agreement = Some(agreement);
+    }
+
+    /// Largest UDP payload confirmed to reach the peer on the active path.
+    pub fn current_mtu(&self) -> u16 {
+        self.mtu.current_mtu()
+    }
+
+    /// State of the path MTU search, advanced by
+    /// [`update_state`](Self::update_state).
+    pub fn mtu_status(&self) -> MtuStatus {
+        self.mtu.status()
+    }
+
+    /// FEC parameters agreed with the peer, `None` until both offers have
+    /// been exchanged after the handshake.
+    pub fn fec_agreement(&self) -> Option<FecAgreement> {
+        self.fec_agreement
+    }
+
+    /// Removes the XOR layer of one reconstructed QUIC packet and passes it
+    /// to quiche.
+    fn recv_deobfuscated(
+        &mut self,
+        data: &mut [u8],
+        recv_info: quiche::RecvInfo,
+    ) -> quiche::Result<usize> {
+        self.stealth_manager.process_incoming_packet(data);
+        self.conn.recv(data, recv_info)
+    }
+
+    /// Settles the XOR key once the handshake completes, see
+    /// [`StealthManager::coordinate_xor_key`]. quiche does not expose the
+    /// TLS exporter, so there is no session secret to derive a key from and
+    /// the static key stays in use.
+    fn coordinate_xor_key(&mut self) {
+        self.xor_key_coordinated = true;
+        let source = self.stealth_manager.coordinate_xor_key(None);
+        debug!("XOR key source after handshake: {:?}", source);
     }
 
     /// Prepares QUIC packets for sending, wraps them in FEC, and buffers them.
     /// This has been completely refactored to eliminate serialization and copies.
     pub fn send(&mut self, buf: &mut [u8]) -> Result<usize, crate::error::ConnectionError> {
+        self.send_with_info(buf).map(|(len, _)| len)
+    }
+
+    /// Like [`send`](Self::send), also returning the path the datagram has
+    /// to be sent on, as `quiche::Connection::send` does. Packets for
+    /// different paths are never coalesced, and FEC repairs go out on the
+    /// path of the last packet quiche built.
+    pub fn send_with_info(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<(usize, quiche::SendInfo), crate::error::ConnectionError> {
         // If there are buffered FEC packets, send one directly.
-        if let Some(mut packet) = self.outgoing_fec_packets.pop_front() {
-            let len = if let Some(ref xdp) = self.xdp_socket {
-                xdp.send(&[&packet.data.as_ref().unwrap()[..packet.len]])
-                    .map_err(|e| crate::error::ConnectionError::Fec(e.to_string()))?;
-                packet.len
-            } else {
-                packet.to_raw(buf)?
-            };
-            if let Some(data) = packet.data.take() {
-                self.optimization_manager.free_block(data);
-            }
-            return Ok(len);
+        if let Some(packet) = self.next_fec_packet() {
+            return self.transmit(packet, buf).map(|len| (len, self.send_info));
         }
 
-        // Otherwise, generate a new QUIC packet using a pooled buffer.
+        // Repairs left over from a partial write go first on the stream.
+        if let Err(e) = self.flush_fec_repair_stream() {
+            warn!("FEC repair stream write failed: {}", e);
+        }
+        // Decoys due by now join the DATAGRAM queue once 1-RTT data can
+        // carry them.
+        if self.conn.is_established() {
+            self.stealth_manager
+                .send_cover_traffic(&mut self.datagrams, Instant::now());
+        }
+        // Hand queued DATAGRAM payloads to quiche before building the packet,
+        // as fast as the stealth pacer allows.
+        self.flush_paced_datagrams();
+        // Early data goes out as soon as quiche accepts it.
+        self.zero_rtt.flush_to(&mut self.conn);
+
+        // Otherwise, generate new QUIC packets using a pooled buffer,
+        // coalescing as many as fit into one datagram.
         let mut send_buffer = self.optimization_manager.alloc_block();
-        let (write, _send_info) = match self.conn.send(&mut send_buffer) {
-            Ok(v) => v,
+        let mtu = self.padding_mtu();
+        let no_spin = self.stealth_manager.spin_bit_disabled();
+        let built = {
+            let conn = &mut self.conn;
+            let padding = &self.padding;
+            let mut send_packet = |out: &mut [u8], path: Option<(SocketAddr, SocketAddr)>| {
+                let sent = match path {
+                    None => conn.send(out),
+                    Some((from, to)) => conn.send_on_path(out, Some(from), Some(to)),
+                };
+                sent.map(|(n, info)| {
+                    if no_spin && crate::stealth::clear_spin_bit(&mut out[..n]) {
+                        warn!("spin bit set on an outgoing packet while disabled");
+                    }
+                    (n, info)
+                })
+            };
+            let mut datagram = CoalescedDatagramBuilder::new(&mut send_buffer[..], mtu);
+            let mut first = None;
+            datagram
+                .fill_with(|out| {
+                    send_packet(out, None).map(|(n, info)| {
+                        first = Some(info);
+                        n
+                    })
+                })
+                .map(|_| {
+                    // Only packets for the first packet's path may follow it.
+                    if let Some(info) = first {
+                        let path = Some((info.from, info.to));
+                        while datagram.can_extend() {
+                            match datagram.fill_with(|out| send_packet(out, path).map(|(n, _)| n)) {
+                                Ok(n) if n > 0 => {}
+                                _ => break,
+                            }
+                        }
+                    }
+                    datagram.pad_to(padding.target_len(datagram.len(), mtu));
+                    (datagram.len(), first)
+                })
+        };
+        let write = match built {
+            Ok((len, info)) => {
+                if let Some(info) = info {
+                    self.send_info = info;
+                }
+                len
+            }
             Err(e) => {
                 self.optimization_manager.free_block(send_buffer);
+                if e == quiche::Error::Done {
+                    if let Some(sent) = self.send_flushed_fec(buf) {
+                        return sent;
+                    }
+                }
                 return Err(crate::error::ConnectionError::Quiche(e));
             }
         };
 
         if write == 0 {
             self.optimization_manager.free_block(send_buffer);
-            return Ok(0);
+            if let Some(sent) = self.send_flushed_fec(buf) {
+                return sent;
+            }
+            return Ok((0, self.send_info));
         }
 
         // The buffer may be larger than the written data; the length is tracked separately.
@@ -303,21 +1296,91 @@ impl QuicFuscateConnection {
         self.fec.on_send(fec_packet, &mut self.outgoing_fec_packets);
 
         // Pop the first packet from the buffer to send it now.
-        if let Some(mut packet) = self.outgoing_fec_packets.pop_front() {
-            let len = if let Some(ref xdp) = self.xdp_socket {
-                xdp.send(&[&packet.data.as_ref().unwrap()[..packet.len]])
-                    .map_err(|e| crate::error::ConnectionError::Fec(e.to_string()))?;
-                packet.len
-            } else {
-                packet.to_raw(buf)?
-            };
+        let len = match self.next_fec_packet() {
+            Some(packet) => self.transmit(packet, buf)?,
+            None => 0,
+        };
+        Ok((len, self.send_info))
+    }
+
+    /// Once quiche has nothing more to send, encodes a partially filled
+    /// coded block, see [`AdaptiveFec::flush`], and sends its first packet.
+    /// Returns `None` if there was nothing to flush.
+    fn send_flushed_fec(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Option<Result<(usize, quiche::SendInfo), crate::error::ConnectionError>> {
+        if self.fec.flush(&mut self.outgoing_fec_packets) == 0 {
+            return None;
+        }
+        let packet = self.next_fec_packet()?;
+        Some(self.transmit(packet, buf).map(|len| (len, self.send_info)))
+    }
+
+    /// Pops the next FEC packet to send as a datagram. Once the handshake
+    /// is done, repairs the transport policy keeps off datagrams of the
+    /// path MTU are written to the repair stream instead, see
+    /// [`send_fec_repair`](Self::send_fec_repair).
+    fn next_fec_packet(&mut self) -> Option<FecPacket> {
+        while let Some(mut packet) = self.outgoing_fec_packets.pop_front() {
+            if packet.is_systematic || !self.conn.is_established() {
+                return Some(packet);
+            }
+            let len = packet.len + packet.coeff_len + 3;
+            if self.fec_transport.route(len, Some(self.padding_mtu())) == FecTransport::Datagram {
+                return Some(packet);
+            }
+            if let Err(e) = self.send_fec_repair(&packet) {
+                warn!("FEC repair not sent: {}", e);
+            }
             if let Some(data) = packet.data.take() {
                 self.optimization_manager.free_block(data);
             }
-            Ok(len)
+        }
+        None
+    }
+
+    /// Writes `packet` to XDP or into `buf` and reports it to the observer.
+    fn transmit(
+        &mut self,
+        mut packet: FecPacket,
+        buf: &mut [u8],
+    ) -> Result<usize, crate::error::ConnectionError> {
+        let len = if let Some(ref xdp) = self.xdp_socket {
+            let data = &packet.data.as_ref().unwrap()[..packet.len];
+            xdp.send(&[data])
+                .map_err(|e| crate::error::ConnectionError::Fec(e.to_string()))?;
+            if let Some(cb) = &self.io_observer {
+                cb(Direction::Outbound, data);
+            }
+            packet.len
         } else {
-            Ok(0)
+            let len = packet.to_raw(buf)?;
+            if let Some(cb) = &self.io_observer {
+                cb(Direction::Outbound, &buf[..len]);
+            }
+            len
+        };
+        if let Some(data) = packet.data.take() {
+            self.optimization_manager.free_block(data);
         }
+        Ok(len)
+    }
+
+    /// Installs a callback invoked with every datagram handed out by
+    /// [`send`](Self::send) and every datagram passed to
+    /// [`recv`](Self::recv), as raw wire bytes before FEC and obfuscation
+    /// are undone. Replaces any previous observer.
+    pub fn set_io_observer<F>(&mut self, cb: F)
+    where
+        F: Fn(Direction, &[u8]) + Send + Sync + 'static,
+    {
+        self.io_observer = Some(Box::new(cb));
+    }
+
+    /// Removes the observer installed by [`set_io_observer`](Self::set_io_observer).
+    pub fn clear_io_observer(&mut self) {
+        self.io_observer = None;
     }
 
     /// Handles connection migration to a new network path.
@@ -326,13 +1389,37 @@ impl QuicFuscateConnection {
     /// The underlying QUIC connection will attempt to validate the new path
     /// and switch over once validation succeeds. Any error is returned so the
     /// caller can react accordingly.
+    ///
+    /// An IPv4 peer is reachable from a dual-stack IPv6 socket and is
+    /// addressed v4-mapped; an IPv6 peer cannot be reached from an IPv4
+    /// socket and yields [`quiche::Error::InvalidState`]. Use
+    /// [`migrate_path`](Self::migrate_path) to switch families with a newly
+    /// bound socket.
     pub fn migrate_connection(&mut self, new_peer: SocketAddr) -> Result<u64, quiche::Error> {
-        // Initiate path migration using quiche's API. The local address remains
-        // unchanged, but a new peer address is supplied. quiche handles sending
+        self.migrate_path(self.local_addr, new_peer)
+    }
+
+    /// Migrates to the path `new_local -> new_peer`, e.g. from an IPv4 to an
+    /// IPv6 socket. `new_local` must be the address of a socket the caller
+    /// already bound; see [`migrate_connection`](Self::migrate_connection)
+    /// for how address families are matched.
+    pub fn migrate_path(
+        &mut self,
+        new_local: SocketAddr,
+        new_peer: SocketAddr,
+    ) -> Result<u64, quiche::Error> {
+        let new_peer = match path_peer_addr(new_local, new_peer) {
+            Some(peer) => peer,
+            None => {
+                warn!("Cannot migrate to {new_peer}: not reachable from {new_local}");
+                return Err(quiche::Error::InvalidState);
+            }
+        };
+        // Initiate path migration using quiche's API. quiche handles sending
         // the probing packets required for validation.
         self.xdp_socket = self
             .optimization_manager
-            .create_xdp_socket(self.local_addr, new_peer);
+            .create_xdp_socket(new_local, new_peer);
         if let Some(ref xdp) = self.xdp_socket {
             let _ = xdp.update_remote(new_peer);
             telemetry!(telemetry::XDP_ACTIVE.set(1));
@@ -340,19 +1427,353 @@ impl QuicFuscateConnection {
             telemetry!(telemetry::XDP_ACTIVE.set(0));
         }
 
-        let res = self.conn.migrate(self.local_addr, new_peer);
+        let res = self.conn.migrate(new_local, new_peer);
         if res.is_ok() {
+            self.migration_pending = true;
             telemetry!(telemetry::PATH_MIGRATIONS.inc());
         }
         res
     }
 
+    /// Issues a fresh random source connection ID of
+    /// [`quiche::MAX_CONN_ID_LEN`] bytes and retires the oldest one if the
+    /// peer's limit is reached, so the peer moves to the new ID.
+    ///
+    /// If the peer has provided a spare connection ID, the ID used for our
+    /// own sends is retired as well and subsequent packets carry the next
+    /// one. This is skipped while a migration awaits validation, since the
+    /// new path needs a spare ID of its own.
+    pub fn rotate_connection_id(&mut self) -> Result<quiche::ConnectionId<'static>, quiche::Error> {
+        let mut raw = vec![0u8; quiche::MAX_CONN_ID_LEN];
+        OsRng.fill_bytes(&mut raw);
+        let mut token = [0u8; 16];
+        OsRng.fill_bytes(&mut token);
+        let scid = quiche::ConnectionId::from_vec(raw);
+        self.conn
+            .new_scid(&scid, u128::from_be_bytes(token), true)?;
+
+        if !self.migration_pending && self.conn.available_dcids() > 0 {
+            match self.conn.retire_destination_cid(self.dcid_seq) {
+                Ok(()) => self.dcid_seq += 1,
+                Err(e) => warn!("Failed to retire destination CID {}: {}", self.dcid_seq, e),
+            }
+        }
+        Ok(scid)
+    }
+
+    /// Connection ID the peer currently uses to address us.
+    pub fn connection_id(&self) -> quiche::ConnectionId<'static> {
+        self.conn.source_id().into_owned()
+    }
+
+    /// Connection ID carried by the packets we send.
+    pub fn peer_connection_id(&self) -> quiche::ConnectionId<'static> {
+        self.conn.destination_id().into_owned()
+    }
+
     /// Returns the Host header that should be used for HTTP requests when domain
     /// fronting is active.
     pub fn host_header(&self) -> &str {
         &self.host_header
     }
 
+    /// Returns the pacing rate of the active path in bytes per second: the
+    /// congestion window quiche reports per smoothed RTT. Until quiche has
+    /// taken an RTT sample, the selected controller's window per RTT is used.
+    pub fn pacing_rate(&self) -> Option<u64> {
+        self.conn
+            .path_stats()
+            .next()
+            .filter(|p| p.min_rtt.is_some() && !p.rtt.is_zero())
+            .map(|p| (p.cwnd as f64 / p.rtt.as_secs_f64()) as u64)
+            .or_else(|| self.cc.pacing_rate())
+    }
+
+    /// Returns the error code and reason phrase of the CONNECTION_CLOSE frame
+    /// once either side has closed the connection. A close sent by the peer
+    /// takes precedence over a locally initiated one. Both application and
+    /// transport close frames are reported.
+    pub fn close_reason(&self) -> Option<(u64, String)> {
+        self.conn
+            .peer_error()
+            .or_else(|| self.conn.local_error())
+            .map(|e| {
+                (
+                    e.error_code,
+                    String::from_utf8_lossy(&e.reason).into_owned(),
+                )
+            })
+    }
+
+    /// Switches the congestion control model, replacing the current one with
+    /// a fresh model for `algo`.
+    ///
+    /// Only the crate's own window model changes, which drives pacing and
+    /// the stealth rate hint. quiche keeps the controller the connection was
+    /// built with, chosen by [`OptimizeConfig::congestion_control`].
+    pub fn set_congestion_control(&mut self, algo: CcAlgorithm) {
+        if self.cc.algorithm() != algo {
+            info!(
+                "Switching congestion control {} -> {}",
+                self.cc.algorithm().as_str(),
+                algo.as_str()
+            );
+            self.cc = algo.controller(DEFAULT_MSS);
+            if let Some(rtt) = self.initial_rtt {
+                self.cc.set_initial_rtt(rtt);
+            }
+        }
+    }
+
+    /// Seeds the congestion controller's RTT estimate, and with it the
+    /// initial pacing rate, for paths with a known high latency such as
+    /// satellite links. The seed is dropped at the first real RTT sample.
+    pub fn set_initial_rtt(&mut self, rtt: Duration) {
+        self.initial_rtt = Some(rtt);
+        self.cc.set_initial_rtt(rtt);
+    }
+
+    /// Returns the statistics gathered by the last
+    /// [`update_state`](Self::update_state) call.
+    pub fn stats(&self) -> &ConnectionStats {
+        &self.stats
+    }
+
+    /// Returns the congestion controller's current RTT estimate.
+    pub fn rtt_estimate(&self) -> Option<Duration> {
+        self.cc.rtt()
+    }
+
+    /// Returns the active congestion control algorithm.
+    pub fn congestion_control(&self) -> CcAlgorithm {
+        self.cc.algorithm()
+    }
+
+    /// Returns the active controller's congestion window in bytes.
+    pub fn congestion_window(&self) -> usize {
+        self.cc.congestion_window()
+    }
+
+    pub fn on_packet_acknowledged(&mut self, bytes: usize, rtt: Duration, now: Instant) {
+        self.cc.on_packet_acknowledged(bytes, rtt, now);
+    }
+
+    pub fn on_packet_lost(&mut self, bytes: usize, now: Instant) {
+        self.cc.on_packet_lost(bytes, now);
+    }
+
+    /// Verifies the certificate chain presented by the peer during the
+    /// handshake, e.g. to enforce an SPKI pin on top of quiche's own checks.
+    pub fn verify_peer_chain(
+        &self,
+        opts: &VerifyOptions,
+    ) -> Result<Vec<CertificateDer<'static>>, CertVerifyError> {
+        let chain = self
+            .conn
+            .peer_cert_chain()
+            .ok_or(CertVerifyError::EmptyChain)?;
+        verify_certificate(&chain, opts)
+    }
+
+    /// Moves queued DATAGRAM payloads into quiche while the stealth layer's
+    /// pacer has budget for them, so shaped output stays within the pacing
+    /// rate or the one pinned by [`StealthManager::set_send_rate_hint`].
+    /// Payloads held back stay queued for the next send.
+    fn flush_paced_datagrams(&mut self) -> usize {
+        if let Some(max) = self.conn.dgram_max_writable_len() {
+            self.datagrams
+                .set_max_size(max.min(u16::MAX as usize) as u16);
+        }
+        let conn = &mut self.conn;
+        let stealth = &self.stealth_manager;
+        self.datagrams.flush_with(|payload| {
+            // Only spend pacing budget on payloads quiche can take.
+            if conn.is_dgram_send_queue_full() || !stealth.try_release_datagram(payload.len()) {
+                return Err(quiche::Error::Done);
+            }
+            conn.dgram_send(payload)
+        })
+    }
+
+    /// Hands all queued DATAGRAM payloads to quiche and shuts down the
+    /// stealth layer. The QUIC connection itself is left open.
+    pub fn shutdown(&mut self) {
+        let flushed = self.datagrams.flush_to(&mut self.conn);
+        debug!("Flushed {} datagrams on shutdown", flushed);
+        self.stealth_manager.shutdown();
+    }
+
+    /// Returns the DATAGRAM queue of this connection.
+    pub fn datagrams(&mut self) -> &mut DatagramEngine {
+        &mut self.datagrams
+    }
+
+    /// Returns the 0-RTT early data queue of this connection.
+    pub fn zero_rtt(&mut self) -> &mut ZeroRttEngine {
+        &mut self.zero_rtt
+    }
+
+    /// Sets how outgoing datagrams are padded to hide their real length.
+    pub fn set_padding_policy(&mut self, policy: PaddingPolicy) {
+        self.padding = policy;
+    }
+
+    pub fn padding_policy(&self) -> &PaddingPolicy {
+        &self.padding
+    }
+
+    /// Rotates the stealth layer's obfuscation parameters, see
+    /// [`StealthManager::rotate_obfuscation`], and pads datagrams to the
+    /// newly drawn buckets.
+    pub fn rotate_obfuscation(&mut self, rng: &mut impl RngCore) -> ObfuscationRotation {
+        let rotation = self.stealth_manager.rotate_obfuscation(rng);
+        self.padding = PaddingPolicy::PadToBucket(rotation.padding_buckets.clone());
+        rotation
+    }
+
+    /// Holds the latency spin bit at `0` on every outgoing 1-RTT packet
+    /// for privacy, regardless of the stealth layer's spin bit scheduler.
+    /// The peer's spin bit is never read, so incoming packets need no
+    /// treatment.
+    pub fn set_spin_bit_disabled(&mut self, disabled: bool) {
+        self.stealth_manager.set_spin_bit_disabled(disabled);
+    }
+
+    /// Largest datagram the send path builds, and thus pads to: the payload
+    /// size quiche's path MTU discovery has validated.
+    fn padding_mtu(&self) -> usize {
+        self.conn.max_send_udp_payload_size()
+    }
+
+    /// Selects how [`send_fec_repair`](Self::send_fec_repair) carries repairs.
+    pub fn set_fec_transport_policy(&mut self, policy: FecTransportPolicy) {
+        self.fec_transport = policy;
+    }
+
+    pub fn fec_transport_policy(&self) -> FecTransportPolicy {
+        self.fec_transport
+    }
+
+    /// Repairs received on the peer's repair stream so far.
+    pub fn fec_stream_repairs(&self) -> u64 {
+        self.fec_stream_repairs
+    }
+
+    /// Sends a serialized repair packet over DATAGRAM or the repair stream,
+    /// as decided by the transport policy and the negotiated datagram size.
+    /// Stream writes are prefixed with a 2-byte length so the receiver can
+    /// split the byte stream back into packets.
+    pub fn send_fec_repair(
+        &mut self,
+        packet: &FecPacket,
+    ) -> Result<FecTransport, crate::error::ConnectionError> {
+        let mut raw = vec![0u8; packet.len + packet.coeff_len + 3];
+        let len = packet.to_raw(&mut raw)?;
+        raw.truncate(len);
+
+        let route = self.route_fec_repair(len);
+        match route {
+            FecTransport::Datagram => self.datagrams.send(&raw)?,
+            FecTransport::Stream => {
+                if self.fec_repair_tx.len() + len + 2 > FEC_REPAIR_STREAM_BACKLOG {
+                    debug!("FEC repair stream backlog full, dropping repair");
+                    return Ok(route);
+                }
+                self.fec_repair_tx
+                    .extend_from_slice(&(len as u16).to_be_bytes());
+                self.fec_repair_tx.extend_from_slice(&raw);
+                self.flush_fec_repair_stream()?;
+            }
+        }
+        debug!("FEC repair of {} bytes sent via {:?}", len, route);
+        Ok(route)
+    }
+
+    fn route_fec_repair(&self, len: usize) -> FecTransport {
+        self.fec_transport
+            .route(len, self.conn.dgram_max_writable_len())
+    }
+
+    /// Writes as much of the buffered repair stream as quiche accepts. The
+    /// rest stays buffered so length prefixes and repairs are never cut.
+    fn flush_fec_repair_stream(&mut self) -> Result<(), quiche::Error> {
+        if self.fec_repair_tx.is_empty() {
+            return Ok(());
+        }
+        let stream_id = (FEC_REPAIR_STREAM_INDEX << 2) | 0x2 | u64::from(self.conn.is_server());
+        match self.conn.stream_send(stream_id, &self.fec_repair_tx, false) {
+            Ok(n) => {
+                self.fec_repair_tx.drain(..n);
+                Ok(())
+            }
+            Err(quiche::Error::Done) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Feeds the repairs the peer sent on its repair stream to the FEC
+    /// decoder and processes the packets they recover.
+    fn recv_fec_repair_stream(&mut self, from: SocketAddr, to: SocketAddr) {
+        let stream_id = (FEC_REPAIR_STREAM_INDEX << 2) | 0x2 | u64::from(!self.conn.is_server());
+        let mut buf = [0u8; 4096];
+        while let Ok((n, _)) = self.conn.stream_recv(stream_id, &mut buf) {
+            self.fec_repair_rx.extend_from_slice(&buf[..n]);
+        }
+
+        let mut offset = 0;
+        while let Some(len_bytes) = self.fec_repair_rx.get(offset..offset + 2) {
+            let len = u16::from_be_bytes([len_bytes[0], len_bytes[1]]) as usize;
+            let Some(raw) = self.fec_repair_rx.get(offset + 2..offset + 2 + len) else {
+                break;
+            };
+            offset += 2 + len;
+            let packet = match FecPacket::from_raw(
+                self.packet_id_counter,
+                raw,
+                &self.optimization_manager,
+            ) {
+                Ok(p) => p,
+                Err(e) => {
+                    warn!("Malformed FEC repair on stream: {}", e);
+                    continue;
+                }
+            };
+            self.fec_stream_repairs += 1;
+            match self.fec.on_receive(packet) {
+                Ok(recovered) => self.process_recovered(recovered, from, to),
+                Err(e) => warn!("FEC decoding of stream repair failed: {}", e),
+            }
+        }
+        self.fec_repair_rx.drain(..offset);
+    }
+
+    /// Queues a batch of DATAGRAM payloads for the next `send` calls.
+    ///
+    /// Ordering is preserved and queuing stops at the first payload exceeding
+    /// the negotiated size. Returns the number of payloads accepted. Fails with
+    /// `InvalidState` while the handshake has not completed.
+    pub fn send_batch(
+        &mut self,
+        datagrams: &[&[u8]],
+    ) -> Result<usize, crate::error::ConnectionError> {
+        if !self.conn.is_established() {
+            return Err(quiche::Error::InvalidState.into());
+        }
+        Ok(self.datagrams.send_batch(datagrams)?)
+    }
+
+    /// Source packets lost beyond FEC recovery that should be requested
+    /// again, oldest first. See [`RetransmitTracker`].
+    pub fn pending_retransmits(&self) -> Vec<u64> {
+        self.retransmits.pending_retransmits()
+    }
+
+    /// Clears `ids` from [`pending_retransmits`](Self::pending_retransmits)
+    /// after they have been requested.
+    pub fn mark_retransmits_serviced(&mut self, ids: &[u64]) {
+        self.retransmits.mark_serviced(ids);
+    }
+
     /// Returns the stealth manager for dynamic profile updates.
     pub fn stealth_manager(&self) -> Arc<StealthManager> {
         self.stealth_manager.clone()
@@ -365,6 +1786,10 @@ impl QuicFuscateConnection {
             let mut h3_cfg = quiche::h3::Config::new()?;
             h3_cfg.set_qpack_max_table_capacity(64 * 1024);
             h3_cfg.set_qpack_blocked_streams(16);
+            // When masquerading, announce the SETTINGS of the claimed browser.
+            if let Some(settings) = self.stealth_manager.http3_settings() {
+                settings.apply(&mut h3_cfg);
+            }
 
             let h3 = quiche::h3::Connection::with_transport(&mut self.conn, &h3_cfg)?;
             self.h3_conn = Some(h3);
@@ -374,6 +1799,64 @@ impl QuicFuscateConnection {
 
     /// Sends a masqueraded HTTP/3 GET request using the stealth manager.
     pub fn send_http3_request(&mut self, path: &str) -> Result<(), crate::error::ConnectionError> {
+        self.send_http3_get(path).map(|_| ())
+    }
+
+    /// Sends one GET request per path, each on its own stream, and returns
+    /// the ids of the streams opened now, in request order.
+    ///
+    /// Requests beyond the bidirectional streams the peer currently allows
+    /// (`initial_max_streams_bidi`) are queued behind earlier deferred ones
+    /// and sent by [`poll_http3`](Self::poll_http3) as the peer grants more
+    /// streams; [`Http3Stream::path`] tells their streams apart. Responses
+    /// are collected by `poll_http3` and returned by
+    /// [`take_completed_http3_streams`](Self::take_completed_http3_streams).
+    pub fn send_http3_requests(
+        &mut self,
+        paths: &[&str],
+    ) -> Result<Vec<u64>, crate::error::ConnectionError> {
+        self.h3_deferred
+            .extend(paths.iter().map(|path| path.to_string()));
+        let opened = self.send_deferred_http3_requests()?;
+        if !self.h3_deferred.is_empty() {
+            debug!(
+                "Peer stream limit reached, {} request(s) queued",
+                self.h3_deferred.len()
+            );
+        }
+        Ok(opened)
+    }
+
+    /// Number of requests waiting for stream credit from the peer.
+    pub fn pending_http3_requests(&self) -> usize {
+        self.h3_deferred.len()
+    }
+
+    /// Opens streams for queued requests while the peer allows them.
+    fn send_deferred_http3_requests(&mut self) -> Result<Vec<u64>, crate::error::ConnectionError> {
+        let mut opened = Vec::new();
+        while self.conn.peer_streams_left_bidi() > 0 {
+            let Some(path) = self.h3_deferred.pop_front() else {
+                break;
+            };
+            match self.send_http3_get(&path) {
+                Ok(stream_id) => opened.push(stream_id),
+                Err(e) => {
+                    self.h3_deferred.push_front(path);
+                    if matches!(
+                        e,
+                        crate::error::ConnectionError::H3(quiche::h3::Error::StreamBlocked)
+                    ) {
+                        break;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(opened)
+    }
+
+    fn send_http3_get(&mut self, path: &str) -> Result<u64, crate::error::ConnectionError> {
         self.init_http3()?;
         let host = self.host_header.clone();
         let headers = self
@@ -388,38 +1871,99 @@ impl QuicFuscateConnection {
                 ]
             });
 
-        if let Some(ref mut h3) = self.h3_conn {
-            let start = std::time::Instant::now();
-            h3.send_request(&mut self.conn, &headers, true)?;
-            info!("HTTP/3 request sent in {} ms", start.elapsed().as_millis());
+        let h3 = self.h3_conn.as_mut().expect("HTTP/3 initialized above");
+        let start = std::time::Instant::now();
+        let stream_id = h3.send_request(&mut self.conn, &headers, true)?;
+        info!(
+            "HTTP/3 request for {} sent on stream {} in {} ms",
+            path,
+            stream_id,
+            start.elapsed().as_millis()
+        );
+        self.h3_streams.insert(
+            stream_id,
+            Http3Stream {
+                path: Some(path.to_string()),
+                ..Http3Stream::default()
+            },
+        );
+        Ok(stream_id)
+    }
+
+    /// Sends a response with the given status and body on `stream_id`.
+    pub fn send_http3_response(
+        &mut self,
+        stream_id: u64,
+        status: u16,
+        body: &[u8],
+    ) -> Result<(), crate::error::ConnectionError> {
+        self.init_http3()?;
+        let status = status.to_string();
+        let mut headers = vec![
+            quiche::h3::Header::new(b":status", status.as_bytes()),
+            quiche::h3::Header::new(b"content-length", body.len().to_string().as_bytes()),
+        ];
+        headers.extend(self.stealth_manager.origin_response_headers());
+        if let Some(alt_svc) = self.stealth_manager.alt_svc_header() {
+            headers.push(quiche::h3::Header::new(b"alt-svc", alt_svc.as_bytes()));
+        }
+        let h3 = self.h3_conn.as_mut().expect("HTTP/3 initialized above");
+        h3.send_response(&mut self.conn, stream_id, &headers, body.is_empty())?;
+        if !body.is_empty() {
+            h3.send_body(&mut self.conn, stream_id, body, true)?;
         }
         Ok(())
     }
 
-    /// Polls HTTP/3 events and prints received data.
+    /// Removes and returns all streams that have finished, keyed by stream
+    /// id. On a client these are responses, whose [`Http3Stream::path`] names
+    /// the request they answer; on a server the completed requests.
+    pub fn take_completed_http3_streams(&mut self) -> HashMap<u64, Http3Stream> {
+        let done: Vec<u64> = self
+            .h3_streams
+            .iter()
+            .filter(|(_, s)| s.finished)
+            .map(|(id, _)| *id)
+            .collect();
+        done.into_iter()
+            .filter_map(|id| self.h3_streams.remove(&id).map(|s| (id, s)))
+            .collect()
+    }
+
+    /// Returns the state of an HTTP/3 stream seen by this connection.
+    pub fn http3_stream(&self, stream_id: u64) -> Option<&Http3Stream> {
+        self.h3_streams.get(&stream_id)
+    }
+
+    /// Polls HTTP/3 events, logging and collecting headers and bodies per
+    /// stream.
     pub fn poll_http3(&mut self) -> Result<(), crate::error::ConnectionError> {
         if let Some(ref mut h3) = self.h3_conn {
             let start = std::time::Instant::now();
             loop {
                 match h3.poll(&mut self.conn) {
                     Ok((stream_id, quiche::h3::Event::Headers { list, .. })) => {
+                        let stream = self.h3_streams.entry(stream_id).or_default();
                         for h in list {
-                            debug!(
-                                "{}: {}",
-                                String::from_utf8_lossy(h.name()),
-                                String::from_utf8_lossy(h.value())
-                            );
+                            let name = String::from_utf8_lossy(h.name()).into_owned();
+                            let value = String::from_utf8_lossy(h.value()).into_owned();
+                            debug!("{}: {}", name, value);
+                            stream.headers.push((name, value));
                         }
                     }
                     Ok((stream_id, quiche::h3::Event::Data)) => {
+                        let stream = self.h3_streams.entry(stream_id).or_default();
                         let mut buf = [0; 4096];
                         while let Ok(read) = h3.recv_body(&mut self.conn, stream_id, &mut buf) {
                             let data = &buf[..read];
                             debug!("Received {} bytes on stream {}", read, stream_id);
                             debug!("{}", String::from_utf8_lossy(data));
+                            stream.body.extend_from_slice(data);
                         }
                     }
-                    Ok((_id, quiche::h3::Event::Finished)) => {}
+                    Ok((stream_id, quiche::h3::Event::Finished)) => {
+                        self.h3_streams.entry(stream_id).or_default().finished = true;
+                    }
                     Err(quiche::h3::Error::Done) => break,
                     Err(e) => return Err(e.into()),
                 }
@@ -428,24 +1972,50 @@ impl QuicFuscateConnection {
                 "HTTP/3 events processed in {} ms",
                 start.elapsed().as_millis()
             );
+            // Finished streams may have earned credit for queued requests.
+            if !self.h3_deferred.is_empty() {
+                self.send_deferred_http3_requests()?;
+            }
         }
         Ok(())
     }
 
     /// Update internal state, e.g., FEC mode based on statistics.
     pub fn update_state(&mut self) {
-        // Update stats (in a real app, this comes from the quiche connection)
         let stats = self.conn.stats();
-        self.stats.packets_sent = stats.sent;
-        self.stats.packets_lost = stats.lost;
-        if stats.sent > 0 {
-            self.stats.loss_rate = stats.lost as f32 / stats.sent as f32;
+        let path = self.conn.path_stats().find(|p| p.active);
+        self.stats = ConnectionStats::from_quiche(&stats, path.as_ref());
+        let rtt = path.as_ref().map(|p| p.rtt).unwrap_or_default();
+
+        // Feed newly acknowledged and lost bytes to the congestion model.
+        let now = Instant::now();
+        let acked = stats.acked_bytes.saturating_sub(self.cc_acked_bytes);
+        let lost = stats.lost_bytes.saturating_sub(self.cc_lost_bytes);
+        self.cc_acked_bytes = stats.acked_bytes;
+        self.cc_lost_bytes = stats.lost_bytes;
+        if acked > 0 {
+            self.on_packet_acknowledged(acked as usize, rtt, now);
+        }
+        if lost > 0 {
+            self.on_packet_lost(lost as usize, now);
+        }
+
+        // Report stats to the adaptive FEC controller. A failed negotiation
+        // keeps FEC in zero mode for the rest of the connection.
+        if !matches!(self.fec_agreement, Some(FecAgreement::Disabled(_))) {
+            self.fec
+                .report_loss(stats.lost as usize, stats.sent as usize);
         }
-        self.stats.rtt = stats.rtt.as_millis() as f32;
 
-        // Report stats to the adaptive FEC controller.
-        self.fec
-            .report_loss(stats.lost as usize, stats.sent as usize);
+        // Bound shaped stealth output by the current pacing rate.
+        if let Some(rate) = self.pacing_rate() {
+            self.stealth_manager.update_send_rate(rate);
+        }
+        self.stealth_manager.on_rtt_sample(rtt);
+
+        // Let the MTU search follow the size quiche has confirmed.
+        let confirmed = self.conn.max_send_udp_payload_size().min(u16::MAX as usize) as u16;
+        self.mtu.update(confirmed);
 
         if self.last_telemetry.elapsed() >= std::time::Duration::from_secs(1) {
             telemetry!(telemetry::update_memory_usage());
@@ -461,8 +2031,14 @@ impl QuicFuscateConnection {
                 }
                 quiche::PathEvent::Validated(local, peer) => {
                     info!("Path validated: {local}->{peer}");
+                    if self.migration_pending {
+                        // The new path took the next peer-issued CID.
+                        self.migration_pending = false;
+                        self.dcid_seq += 1;
+                    }
                     self.peer_addr = peer;
                     self.local_addr = local;
+                    self.mtu = PathMtuManager::new(MtuConfig::for_peer(&peer));
                     if let Some(ref mut xdp) = self.xdp_socket {
                         if let Err(e) = xdp.reconfigure(local, peer) {
                             warn!("XDP reconfigure failed: {e}");
@@ -476,6 +2052,7 @@ impl QuicFuscateConnection {
                 }
                 quiche::PathEvent::FailedValidation(local, peer) => {
                     warn!("Path validation failed: {local}->{peer}");
+                    self.migration_pending = false;
                 }
                 quiche::PathEvent::Closed(local, peer) => {
                     info!("Path closed: {local}->{peer}");
@@ -487,6 +2064,7 @@ impl QuicFuscateConnection {
                     info!("Peer migrated: {local}->{peer}");
                     self.peer_addr = peer;
                     self.local_addr = local;
+                    self.mtu = PathMtuManager::new(MtuConfig::for_peer(&peer));
                     if let Some(ref mut xdp) = self.xdp_socket {
                         if let Err(e) = xdp.reconfigure(local, peer) {
                             warn!("XDP reconfigure failed: {e}");
@@ -36,6 +36,8 @@
 //! features a runtime selector to choose the most performant cipher suite
 //! based on detected CPU capabilities.
 
+use crate::error::CryptoError;
+use crate::telemetry;
 use crate::{cpu_features, CpuFeature};
 use aead::{AeadInPlace, KeyInit, Nonce, Tag};
 use aegis::compat::rustcrypto_traits_06::{
@@ -46,6 +48,9 @@ use aegis::compat::rustcrypto_traits_06::{
 use log::info;
 use morus::Morus;
 use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use subtle::ConstantTimeEq;
 
 /// Enumerates the available cipher suites.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -55,10 +60,105 @@ pub enum CipherSuite {
     Aegis256,
     Morus1280_128,
     Morus1280_256,
-    /// Pure software fallback without SIMD
+    /// Plaintext stub, only usable with the `allow_insecure_stub` feature
     SoftwareFallback,
 }
 
+impl CipherSuite {
+    /// Every suite, in order of preference.
+    pub const ALL: [CipherSuite; 6] = [
+        CipherSuite::Aegis256,
+        CipherSuite::Aegis128X,
+        CipherSuite::Aegis128L,
+        CipherSuite::Morus1280_256,
+        CipherSuite::Morus1280_128,
+        CipherSuite::SoftwareFallback,
+    ];
+
+    /// Key length in bytes expected by the suite.
+    pub fn key_len(self) -> usize {
+        match self {
+            CipherSuite::Aegis256 | CipherSuite::Morus1280_256 => 32,
+            _ => 16,
+        }
+    }
+
+    /// Nonce length in bytes expected by the suite.
+    pub fn nonce_len(self) -> usize {
+        match self {
+            CipherSuite::Aegis256 => 32,
+            _ => 16,
+        }
+    }
+}
+
+/// CPU capabilities relevant for cipher suite selection, as reported by
+/// [`detected_features`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DetectedFeatures {
+    pub aesni: bool,
+    pub vaes: bool,
+    pub pclmulqdq: bool,
+    pub avx2: bool,
+    pub avx512f: bool,
+    pub sse2: bool,
+    pub neon: bool,
+}
+
+impl DetectedFeatures {
+    /// Returns `true` if AES instructions are available, which is what the
+    /// AEGIS suites need to run in hardware.
+    pub fn hardware_aes(&self) -> bool {
+        self.aesni || self.vaes
+    }
+
+    /// Packs the flags into a bitmask in field declaration order.
+    pub fn mask(&self) -> u64 {
+        [
+            self.aesni,
+            self.vaes,
+            self.pclmulqdq,
+            self.avx2,
+            self.avx512f,
+            self.sse2,
+            self.neon,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |m, (i, on)| if *on { m | (1 << i) } else { m })
+    }
+}
+
+impl fmt::Display for DetectedFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        writeln!(f, "aesni:     {}", yes_no(self.aesni))?;
+        writeln!(f, "vaes:      {}", yes_no(self.vaes))?;
+        writeln!(f, "pclmulqdq: {}", yes_no(self.pclmulqdq))?;
+        writeln!(f, "avx2:      {}", yes_no(self.avx2))?;
+        writeln!(f, "avx512f:   {}", yes_no(self.avx512f))?;
+        writeln!(f, "sse2:      {}", yes_no(self.sse2))?;
+        write!(f, "neon:      {}", yes_no(self.neon))
+    }
+}
+
+/// Returns the CPU features the cipher suite selection is based on.
+/// The result is also exported as the `crypto_feature_mask` gauge.
+pub fn detected_features() -> DetectedFeatures {
+    let detector = cpu_features();
+    let features = DetectedFeatures {
+        aesni: detector.has_feature(CpuFeature::AESNI),
+        vaes: detector.has_feature(CpuFeature::VAES),
+        pclmulqdq: detector.has_feature(CpuFeature::PCLMULQDQ),
+        avx2: detector.has_feature(CpuFeature::AVX2),
+        avx512f: detector.has_feature(CpuFeature::AVX512F),
+        sse2: detector.has_feature(CpuFeature::SSE2),
+        neon: detector.has_feature(CpuFeature::NEON),
+    };
+    telemetry!(telemetry::CRYPTO_FEATURE_MASK.set(features.mask() as i64));
+    features
+}
+
 /// Trait implemented by each cipher providing encryption and decryption.
 trait CipherImpl {
     fn encrypt(
@@ -67,7 +167,7 @@ trait CipherImpl {
         nonce: &[u8],
         ad: &[u8],
         plaintext: &[u8],
-    ) -> Result<Vec<u8>, &'static str>;
+    ) -> Result<Vec<u8>, CryptoError>;
 
     fn decrypt(
         &self,
@@ -75,7 +175,7 @@ trait CipherImpl {
         nonce: &[u8],
         ad: &[u8],
         ciphertext: &[u8],
-    ) -> Result<Vec<u8>, &'static str>;
+    ) -> Result<Vec<u8>, CryptoError>;
 }
 
 struct Aegis128XImpl;
@@ -87,14 +187,14 @@ impl CipherImpl for Aegis128XImpl {
         nonce: &[u8],
         ad: &[u8],
         plaintext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
-        let cipher = Aegis128XAead::<16>::new_from_slice(key)
-            .map_err(|_| "Invalid key length for Aegis128X")?;
+    ) -> Result<Vec<u8>, CryptoError> {
+        let cipher =
+            Aegis128XAead::<16>::new_from_slice(key).map_err(|_| CryptoError::InvalidKeyLength)?;
         let mut buffer = plaintext.to_vec();
         let nonce = Nonce::<Aegis128XAead<16>>::from_slice(nonce);
         let tag: Tag<Aegis128XAead<16>> = cipher
             .encrypt_in_place_detached(nonce, ad, &mut buffer)
-            .map_err(|_| "Encryption failed")?;
+            .map_err(|_| CryptoError::EncryptionFailed)?;
         buffer.extend_from_slice(tag.as_slice());
         Ok(buffer)
     }
@@ -105,12 +205,12 @@ impl CipherImpl for Aegis128XImpl {
         nonce: &[u8],
         ad: &[u8],
         ciphertext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
+    ) -> Result<Vec<u8>, CryptoError> {
         if ciphertext.len() < 16 {
-            return Err("Ciphertext too short for Aegis128X");
+            return Err(CryptoError::CiphertextTooShort);
         }
-        let cipher = Aegis128XAead::<16>::new_from_slice(key)
-            .map_err(|_| "Invalid key length for Aegis128X")?;
+        let cipher =
+            Aegis128XAead::<16>::new_from_slice(key).map_err(|_| CryptoError::InvalidKeyLength)?;
         let (msg, tag_slice) = ciphertext.split_at(ciphertext.len() - 16);
         let mut buffer = msg.to_vec();
         cipher
@@ -120,7 +220,7 @@ impl CipherImpl for Aegis128XImpl {
                 &mut buffer,
                 Tag::<Aegis128XAead<16>>::from_slice(tag_slice),
             )
-            .map_err(|_| "Decryption failed")?;
+            .map_err(|_| CryptoError::TagMismatch)?;
         Ok(buffer)
     }
 }
@@ -134,13 +234,14 @@ impl CipherImpl for Aegis128LImpl {
         nonce: &[u8],
         ad: &[u8],
         plaintext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
-        let cipher = Aegis128LAead::<16>::new_from_slice(key).map_err(|_| "Invalid key length")?;
+    ) -> Result<Vec<u8>, CryptoError> {
+        let cipher =
+            Aegis128LAead::<16>::new_from_slice(key).map_err(|_| CryptoError::InvalidKeyLength)?;
         let mut buffer = plaintext.to_vec();
         let nonce = Nonce::<Aegis128LAead<16>>::from_slice(nonce);
         let tag: Tag<Aegis128LAead<16>> = cipher
             .encrypt_in_place_detached(nonce, ad, &mut buffer)
-            .map_err(|_| "Encryption failed")?;
+            .map_err(|_| CryptoError::EncryptionFailed)?;
         buffer.extend_from_slice(tag.as_slice());
         Ok(buffer)
     }
@@ -151,11 +252,12 @@ impl CipherImpl for Aegis128LImpl {
         nonce: &[u8],
         ad: &[u8],
         ciphertext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
+    ) -> Result<Vec<u8>, CryptoError> {
         if ciphertext.len() < 16 {
-            return Err("Ciphertext too short");
+            return Err(CryptoError::CiphertextTooShort);
         }
-        let cipher = Aegis128LAead::<16>::new_from_slice(key).map_err(|_| "Invalid key length")?;
+        let cipher =
+            Aegis128LAead::<16>::new_from_slice(key).map_err(|_| CryptoError::InvalidKeyLength)?;
         let (msg, tag_slice) = ciphertext.split_at(ciphertext.len() - 16);
         let mut buffer = msg.to_vec();
         cipher
@@ -165,7 +267,7 @@ impl CipherImpl for Aegis128LImpl {
                 &mut buffer,
                 Tag::<Aegis128LAead<16>>::from_slice(tag_slice),
             )
-            .map_err(|_| "Decryption failed")?;
+            .map_err(|_| CryptoError::TagMismatch)?;
         Ok(buffer)
     }
 }
@@ -179,26 +281,26 @@ impl CipherImpl for Aegis256Impl {
         nonce: &[u8],
         ad: &[u8],
         plaintext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
+    ) -> Result<Vec<u8>, CryptoError> {
         let detector = cpu_features();
         if detector.has_feature(CpuFeature::VAES) && detector.has_feature(CpuFeature::AVX512F) {
             let cipher = Aegis256X4Aead::<16>::new_from_slice(key)
-                .map_err(|_| "Invalid key length for Aegis256")?;
+                .map_err(|_| CryptoError::InvalidKeyLength)?;
             let mut buffer = plaintext.to_vec();
             let nonce = Nonce::<Aegis256X4Aead<16>>::from_slice(nonce);
             let tag: Tag<Aegis256X4Aead<16>> = cipher
                 .encrypt_in_place_detached(nonce, ad, &mut buffer)
-                .map_err(|_| "Encryption failed")?;
+                .map_err(|_| CryptoError::EncryptionFailed)?;
             buffer.extend_from_slice(tag.as_slice());
             Ok(buffer)
         } else {
             let cipher = Aegis256XAead::<16>::new_from_slice(key)
-                .map_err(|_| "Invalid key length for Aegis256")?;
+                .map_err(|_| CryptoError::InvalidKeyLength)?;
             let mut buffer = plaintext.to_vec();
             let nonce = Nonce::<Aegis256XAead<16>>::from_slice(nonce);
             let tag: Tag<Aegis256XAead<16>> = cipher
                 .encrypt_in_place_detached(nonce, ad, &mut buffer)
-                .map_err(|_| "Encryption failed")?;
+                .map_err(|_| CryptoError::EncryptionFailed)?;
             buffer.extend_from_slice(tag.as_slice());
             Ok(buffer)
         }
@@ -210,14 +312,14 @@ impl CipherImpl for Aegis256Impl {
         nonce: &[u8],
         ad: &[u8],
         ciphertext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
+    ) -> Result<Vec<u8>, CryptoError> {
         if ciphertext.len() < 16 {
-            return Err("Ciphertext too short for Aegis256");
+            return Err(CryptoError::CiphertextTooShort);
         }
         let detector = cpu_features();
         if detector.has_feature(CpuFeature::VAES) && detector.has_feature(CpuFeature::AVX512F) {
             let cipher = Aegis256X4Aead::<16>::new_from_slice(key)
-                .map_err(|_| "Invalid key length for Aegis256")?;
+                .map_err(|_| CryptoError::InvalidKeyLength)?;
             let (msg, tag_slice) = ciphertext.split_at(ciphertext.len() - 16);
             let mut buffer = msg.to_vec();
             cipher
@@ -227,11 +329,11 @@ impl CipherImpl for Aegis256Impl {
                     &mut buffer,
                     Tag::<Aegis256X4Aead<16>>::from_slice(tag_slice),
                 )
-                .map_err(|_| "Decryption failed")?;
+                .map_err(|_| CryptoError::TagMismatch)?;
             Ok(buffer)
         } else {
             let cipher = Aegis256XAead::<16>::new_from_slice(key)
-                .map_err(|_| "Invalid key length for Aegis256")?;
+                .map_err(|_| CryptoError::InvalidKeyLength)?;
             let (msg, tag_slice) = ciphertext.split_at(ciphertext.len() - 16);
             let mut buffer = msg.to_vec();
             cipher
@@ -241,7 +343,7 @@ impl CipherImpl for Aegis256Impl {
                     &mut buffer,
                     Tag::<Aegis256XAead<16>>::from_slice(tag_slice),
                 )
-                .map_err(|_| "Decryption failed")?;
+                .map_err(|_| CryptoError::TagMismatch)?;
             Ok(buffer)
         }
     }
@@ -256,13 +358,11 @@ impl CipherImpl for Morus256Impl {
         nonce: &[u8],
         ad: &[u8],
         plaintext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
-        let key_array: &[u8; 32] = key
-            .try_into()
-            .map_err(|_| "Invalid key length for Morus256")?;
+    ) -> Result<Vec<u8>, CryptoError> {
+        let key_array: &[u8; 32] = key.try_into().map_err(|_| CryptoError::InvalidKeyLength)?;
         let nonce_array: &[u8; 16] = nonce
             .try_into()
-            .map_err(|_| "Invalid nonce length for Morus256")?;
+            .map_err(|_| CryptoError::InvalidNonceLength)?;
         // Reuse Morus-1280-128 implementation using first half of key
         let mut cipher = Morus::new(&key_array[..16].try_into().unwrap(), nonce_array);
         let (mut ciphertext, tag) = cipher.encrypt(plaintext, ad);
@@ -276,22 +376,20 @@ impl CipherImpl for Morus256Impl {
         nonce: &[u8],
         ad: &[u8],
         ciphertext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
+    ) -> Result<Vec<u8>, CryptoError> {
         if ciphertext.len() < 16 {
-            return Err("Ciphertext too short for Morus256");
+            return Err(CryptoError::CiphertextTooShort);
         }
-        let key_array: &[u8; 32] = key
-            .try_into()
-            .map_err(|_| "Invalid key length for Morus256")?;
+        let key_array: &[u8; 32] = key.try_into().map_err(|_| CryptoError::InvalidKeyLength)?;
         let nonce_array: &[u8; 16] = nonce
             .try_into()
-            .map_err(|_| "Invalid nonce length for Morus256")?;
+            .map_err(|_| CryptoError::InvalidNonceLength)?;
         let mut cipher = Morus::new(&key_array[..16].try_into().unwrap(), nonce_array);
         let (msg, tag_slice) = ciphertext.split_at(ciphertext.len() - 16);
         let tag: &[u8; 16] = tag_slice.try_into().unwrap();
         cipher
             .decrypt(msg, tag, ad)
-            .map_err(|_| "Decryption failed")
+            .map_err(|_| CryptoError::TagMismatch)
     }
 }
 
@@ -304,11 +402,11 @@ impl CipherImpl for MorusImpl {
         nonce: &[u8],
         ad: &[u8],
         plaintext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
-        let key_array: &[u8; 16] = key.try_into().map_err(|_| "Invalid key length for Morus")?;
+    ) -> Result<Vec<u8>, CryptoError> {
+        let key_array: &[u8; 16] = key.try_into().map_err(|_| CryptoError::InvalidKeyLength)?;
         let nonce_array: &[u8; 16] = nonce
             .try_into()
-            .map_err(|_| "Invalid nonce length for Morus")?;
+            .map_err(|_| CryptoError::InvalidNonceLength)?;
         let mut cipher = Morus::new(key_array, nonce_array);
         let (mut ciphertext, tag) = cipher.encrypt(plaintext, ad);
         ciphertext.extend_from_slice(&tag);
@@ -321,45 +419,98 @@ impl CipherImpl for MorusImpl {
         nonce: &[u8],
         ad: &[u8],
         ciphertext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
+    ) -> Result<Vec<u8>, CryptoError> {
         if ciphertext.len() < 16 {
-            return Err("Ciphertext too short for Morus");
+            return Err(CryptoError::CiphertextTooShort);
         }
-        let key_array: &[u8; 16] = key.try_into().map_err(|_| "Invalid key length for Morus")?;
+        let key_array: &[u8; 16] = key.try_into().map_err(|_| CryptoError::InvalidKeyLength)?;
         let nonce_array: &[u8; 16] = nonce
             .try_into()
-            .map_err(|_| "Invalid nonce length for Morus")?;
+            .map_err(|_| CryptoError::InvalidNonceLength)?;
         let mut cipher = Morus::new(key_array, nonce_array);
         let (msg, tag_slice) = ciphertext.split_at(ciphertext.len() - 16);
         let tag: &[u8; 16] = tag_slice.try_into().unwrap();
         cipher
             .decrypt(msg, tag, ad)
-            .map_err(|_| "Decryption failed")
+            .map_err(|_| CryptoError::TagMismatch)
     }
 }
 
 /// Minimal software fallback that performs no encryption.
+///
+/// This stub offers no confidentiality whatsoever. It only operates when the
+/// `allow_insecure_stub` feature is enabled and returns
+/// [`CryptoError::InsecureStub`] otherwise. A keyed SHA-256 tag is appended so
+/// that tampering is still detected.
 struct SoftwareFallbackImpl;
 
+impl SoftwareFallbackImpl {
+    fn tag(key: &[u8], nonce: &[u8], ad: &[u8], msg: &[u8]) -> [u8; TAG_LEN] {
+        let digest = Sha256::new()
+            .chain_update(key)
+            .chain_update(nonce)
+            .chain_update((ad.len() as u64).to_be_bytes())
+            .chain_update(ad)
+            .chain_update(msg)
+            .finalize();
+        let mut tag = [0u8; TAG_LEN];
+        tag.copy_from_slice(&digest[..TAG_LEN]);
+        tag
+    }
+}
+
 impl CipherImpl for SoftwareFallbackImpl {
     fn encrypt(
         &self,
-        _key: &[u8],
-        _nonce: &[u8],
-        _ad: &[u8],
+        key: &[u8],
+        nonce: &[u8],
+        ad: &[u8],
         plaintext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
-        Ok(plaintext.to_vec())
+    ) -> Result<Vec<u8>, CryptoError> {
+        if !cfg!(feature = "allow_insecure_stub") {
+            return Err(CryptoError::InsecureStub);
+        }
+        let mut out = plaintext.to_vec();
+        out.extend_from_slice(&Self::tag(key, nonce, ad, plaintext));
+        Ok(out)
     }
 
     fn decrypt(
         &self,
-        _key: &[u8],
-        _nonce: &[u8],
-        _ad: &[u8],
+        key: &[u8],
+        nonce: &[u8],
+        ad: &[u8],
         ciphertext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
-        Ok(ciphertext.to_vec())
+    ) -> Result<Vec<u8>, CryptoError> {
+        if !cfg!(feature = "allow_insecure_stub") {
+            return Err(CryptoError::InsecureStub);
+        }
+        if ciphertext.len() < TAG_LEN {
+            return Err(CryptoError::CiphertextTooShort);
+        }
+        let (msg, tag) = ciphertext.split_at(ciphertext.len() - TAG_LEN);
+        verify_tag(&Self::tag(key, nonce, ad, msg), tag)?;
+        Ok(msg.to_vec())
+    }
+}
+
+/// Length of the authentication tag appended by every cipher suite.
+pub const TAG_LEN: usize = 16;
+
+/// Compares two byte slices with [`subtle::ConstantTimeEq`], which does not
+/// stop at the first differing byte. Slices of different lengths are never
+/// equal.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && bool::from(a.ct_eq(b))
+}
+
+/// Verifies an authentication tag, returning [`CryptoError::TagMismatch`] on
+/// any difference.
+fn verify_tag(expected: &[u8], received: &[u8]) -> Result<(), CryptoError> {
+    if constant_time_eq(expected, received) {
+        Ok(())
+    } else {
+        Err(CryptoError::TagMismatch)
     }
 }
 
@@ -372,17 +523,19 @@ pub struct CipherSuiteSelector {
 impl CipherSuiteSelector {
     /// Creates a new `CipherSuiteSelector` and determines the best available cipher.
     pub fn new() -> Self {
-        let detector = cpu_features();
+        let features = detected_features();
 
-        let selected_suite = if detector.has_feature(CpuFeature::VAES) {
+        let selected_suite = if features.vaes {
             CipherSuite::Aegis256
-        } else if detector.has_feature(CpuFeature::AESNI) {
+        } else if features.aesni {
             if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
                 CipherSuite::Aegis128X
             } else {
                 CipherSuite::Aegis128L
             }
-        } else if detector.has_any(&[CpuFeature::NEON, CpuFeature::SSE2]) {
+        } else if features.neon || features.sse2 || !cfg!(feature = "allow_insecure_stub") {
+            // MORUS is implemented in portable Rust, so it is preferred over
+            // the plaintext stub unless that has been explicitly allowed.
             CipherSuite::Morus1280_256
         } else {
             CipherSuite::SoftwareFallback
@@ -427,6 +580,14 @@ impl CipherSuiteSelector {
         self.selected_suite
     }
 
+    /// Returns `true` if the selected suite runs on AES hardware instructions.
+    pub fn is_hardware_accelerated(&self) -> bool {
+        matches!(
+            self.selected_suite,
+            CipherSuite::Aegis128X | CipherSuite::Aegis128L | CipherSuite::Aegis256
+        )
+    }
+
     /// Encrypts data using the automatically selected cipher suite.
     pub fn encrypt(
         &self,
@@ -434,20 +595,42 @@ impl CipherSuiteSelector {
         nonce: &[u8],
         ad: &[u8],
         plaintext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
+    ) -> Result<Vec<u8>, CryptoError> {
         self.cipher.encrypt(key, nonce, ad, plaintext)
     }
 
     /// Decrypts data using the automatically selected cipher suite.
+    ///
+    /// Any authentication failure is reported uniformly as
+    /// [`CryptoError::TagMismatch`]. The AEGIS and MORUS backends verify
+    /// tags inside their crates, so no timing guarantee is made for them.
     pub fn decrypt(
         &self,
         key: &[u8],
         nonce: &[u8],
         ad: &[u8],
         ciphertext: &[u8],
-    ) -> Result<Vec<u8>, &'static str> {
+    ) -> Result<Vec<u8>, CryptoError> {
         self.cipher.decrypt(key, nonce, ad, ciphertext)
     }
+
+    /// Encrypts and decrypts a fixed message with the selected suite and
+    /// checks that a corrupted ciphertext is rejected.
+    pub fn self_test(&self) -> Result<(), CryptoError> {
+        let suite = self.selected_suite;
+        let key = vec![0x42; suite.key_len()];
+        let nonce = vec![0x24; suite.nonce_len()];
+        let msg = b"quicfuscate self-test";
+        let mut ct = self.encrypt(&key, &nonce, b"ad", msg)?;
+        if self.decrypt(&key, &nonce, b"ad", &ct)? != msg {
+            return Err(CryptoError::SelfTestFailed("round trip mismatch"));
+        }
+        ct[0] ^= 1;
+        match self.decrypt(&key, &nonce, b"ad", &ct) {
+            Err(CryptoError::TagMismatch) => Ok(()),
+            _ => Err(CryptoError::SelfTestFailed("tampered ciphertext accepted")),
+        }
+    }
 }
 
 impl Default for CipherSuiteSelector {
@@ -455,6 +638,40 @@ impl Default for CipherSuiteSelector {
         Self::new()
     }
 }
+
+/// Lists every suite with whether it runs on AES hardware on this host and
+/// which one [`CipherSuiteSelector::new`] picks, as printed by
+/// `--list-ciphers`.
+pub fn cipher_listing() -> String {
+    let features = detected_features();
+    let selected = CipherSuiteSelector::new().selected_suite();
+    let mut out = String::from("Available cipher suites:");
+    for suite in CipherSuite::ALL {
+        let accelerated = features.hardware_aes()
+            && CipherSuiteSelector::with_suite(suite).is_hardware_accelerated();
+        out.push_str(&format!(
+            "\n  {:<18} hardware accelerated: {:<3}{}",
+            format!("{:?}", suite),
+            if accelerated { "yes" } else { "no" },
+            if suite == selected {
+                "  (selected)"
+            } else {
+                ""
+            }
+        ));
+    }
+    out
+}
+
+/// Runs [`CipherSuiteSelector::self_test`] for every suite. The software
+/// stub fails with [`CryptoError::InsecureStub`] unless the
+/// `allow_insecure_stub` feature is enabled.
+pub fn self_test_all() -> Vec<(CipherSuite, Result<(), CryptoError>)> {
+    CipherSuite::ALL
+        .into_iter()
+        .map(|suite| (suite, CipherSuiteSelector::with_suite(suite).self_test()))
+        .collect()
+}
 /// Manages cryptographic keys and provides secure random data.
 /// This manager ensures that all cryptographic operations are backed by
 /// secure, session-specific materials.
@@ -0,0 +1,459 @@
+// Copyright (c) 2024, The QuicFuscate Project Authors.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above
+//       copyright notice, this list of conditions and the following disclaimer
+//       in the documentation and/or other materials provided with the
+//       distribution.
+//
+//     * Neither the name of the copyright holder nor the names of its
+//       contributors may be used to endorse or promote products derived from
+//       this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # Datagram Engine
+//!
+//! Buffers QUIC DATAGRAM frames (RFC 9221) between the application and the
+//! underlying `quiche` connection. Outgoing payloads are queued until the
+//! connection has room for them and incoming payloads are drained from the
+//! connection into a local queue.
+//!
+//! For debugging obfuscation mismatches the engine can record every datagram
+//! it sees to a capture file and later replay the inbound half of such a
+//! recording through [`DatagramEngine::recv`].
+//!
+//! An optional de-duplication filter numbers every outgoing datagram and
+//! drops replayed ones on receipt, see [`DatagramEngine::set_dedup_window`].
+
+use log::{debug, warn};
+use std::collections::{BTreeSet, VecDeque};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Magic bytes at the start of every capture file.
+const CAPTURE_MAGIC: &[u8; 4] = b"QFDG";
+/// Capture format version.
+const CAPTURE_VERSION: u8 = 1;
+
+/// Size of the sequence number header added while de-duplication is on.
+pub const DEDUP_HEADER_LEN: usize = 8;
+
+/// Direction of a captured datagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Outbound = 0,
+    Inbound = 1,
+}
+
+/// A single datagram read back from a capture file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedDatagram {
+    pub direction: Direction,
+    /// Microseconds since the UNIX epoch.
+    pub timestamp_us: u64,
+    pub payload: Vec<u8>,
+}
+
+/// How [`DatagramEngine::send`] treats payloads above the negotiated limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversizePolicy {
+    /// Reject the payload with `quiche::Error::BufferTooShort`.
+    #[default]
+    Reject,
+    /// Split the payload into several datagrams of at most the maximum size.
+    /// Reassembly is left to the application.
+    Split,
+}
+
+/// Sliding-window replay filter over datagram sequence numbers.
+struct DedupFilter {
+    window: u64,
+    highest: Option<u64>,
+    // Sequence numbers seen within `window` of `highest`.
+    seen: BTreeSet<u64>,
+}
+
+impl DedupFilter {
+    fn new(window: usize) -> Self {
+        Self {
+            window: window as u64,
+            highest: None,
+            seen: BTreeSet::new(),
+        }
+    }
+
+    /// Records `seq` and returns `true` if it has not been seen before.
+    /// Numbers older than the window are treated as replays.
+    fn accept(&mut self, seq: u64) -> bool {
+        if let Some(high) = self.highest {
+            if seq.saturating_add(self.window) <= high || self.seen.contains(&seq) {
+                return false;
+            }
+        }
+        self.seen.insert(seq);
+        let high = self.highest.map_or(seq, |h| h.max(seq));
+        self.highest = Some(high);
+        if let Some(floor) = (high + 1).checked_sub(self.window) {
+            self.seen = self.seen.split_off(&floor);
+        }
+        true
+    }
+}
+
+/// Queues DATAGRAM payloads for a single connection.
+pub struct DatagramEngine {
+    outgoing: VecDeque<Vec<u8>>,
+    incoming: VecDeque<Vec<u8>>,
+    capture: Option<BufWriter<File>>,
+    max_size: Option<u16>,
+    oversize_policy: OversizePolicy,
+    dedup: Option<DedupFilter>,
+    tx_seq: u64,
+    duplicates: u64,
+    oversized: u64,
+}
+
+impl DatagramEngine {
+    pub fn new() -> Self {
+        Self {
+            outgoing: VecDeque::new(),
+            incoming: VecDeque::new(),
+            capture: None,
+            max_size: None,
+            oversize_policy: OversizePolicy::Reject,
+            dedup: None,
+            tx_seq: 0,
+            duplicates: 0,
+            oversized: 0,
+        }
+    }
+
+    /// Enables the replay filter with a window of `size` sequence numbers,
+    /// or disables it for `size == 0`.
+    ///
+    /// While enabled every sent datagram is prefixed with a
+    /// [`DEDUP_HEADER_LEN`] byte sequence number, and [`recv`](Self::recv)
+    /// drops datagrams whose number was already seen or lies more than
+    /// `size` behind the newest one. Both peers must use the same setting.
+    pub fn set_dedup_window(&mut self, size: usize) {
+        self.dedup = (size > 0).then(|| DedupFilter::new(size));
+    }
+
+    /// Replay window in sequence numbers, `None` while the filter is off.
+    pub fn dedup_window(&self) -> Option<usize> {
+        self.dedup.as_ref().map(|d| d.window as usize)
+    }
+
+    /// Number of received datagrams dropped by the replay filter.
+    pub fn duplicates_dropped(&self) -> u64 {
+        self.duplicates
+    }
+
+    fn header_len(&self) -> usize {
+        if self.dedup.is_some() {
+            DEDUP_HEADER_LEN
+        } else {
+            0
+        }
+    }
+
+    /// Sets the largest payload the peer accepts, usually derived from its
+    /// `max_datagram_frame_size` transport parameter.
+    pub fn set_max_size(&mut self, max: u16) {
+        self.max_size = Some(max);
+    }
+
+    /// Returns the configured maximum payload size, if known.
+    pub fn max_size(&self) -> Option<u16> {
+        self.max_size
+    }
+
+    /// Selects how payloads above the maximum size are handled.
+    pub fn set_oversize_policy(&mut self, policy: OversizePolicy) {
+        self.oversize_policy = policy;
+    }
+
+    /// Queues a payload for transmission.
+    ///
+    /// Payloads larger than [`max_size`](Self::max_size) are rejected with
+    /// `quiche::Error::BufferTooShort` or split, depending on the
+    /// [`OversizePolicy`].
+    pub fn send(&mut self, payload: &[u8]) -> Result<(), quiche::Error> {
+        let header = self.header_len();
+        match self.max_size.map(|m| usize::from(m).saturating_sub(header)) {
+            Some(max) if payload.len() > max => match self.oversize_policy {
+                OversizePolicy::Reject => Err(quiche::Error::BufferTooShort),
+                OversizePolicy::Split if max == 0 => Err(quiche::Error::BufferTooShort),
+                OversizePolicy::Split => {
+                    for chunk in payload.chunks(max) {
+                        self.enqueue(chunk);
+                    }
+                    Ok(())
+                }
+            },
+            _ => {
+                self.enqueue(payload);
+                Ok(())
+            }
+        }
+    }
+
+    /// Queues several payloads in order, applying the same size checks as
+    /// [`send`](Self::send) to each. Queuing stops at the first payload that
+    /// is rejected so ordering is never broken. Returns the number of
+    /// payloads accepted, or the error if not even the first one fit.
+    pub fn send_batch(&mut self, datagrams: &[&[u8]]) -> Result<usize, quiche::Error> {
+        for (i, payload) in datagrams.iter().enumerate() {
+            if let Err(e) = self.send(payload) {
+                return if i == 0 { Err(e) } else { Ok(i) };
+            }
+        }
+        Ok(datagrams.len())
+    }
+
+    fn enqueue(&mut self, payload: &[u8]) {
+        let datagram = if self.dedup.is_some() {
+            let mut framed = Vec::with_capacity(DEDUP_HEADER_LEN + payload.len());
+            framed.extend_from_slice(&self.tx_seq.to_be_bytes());
+            framed.extend_from_slice(payload);
+            self.tx_seq += 1;
+            framed
+        } else {
+            payload.to_vec()
+        };
+        self.record(Direction::Outbound, &datagram);
+        self.outgoing.push_back(datagram);
+    }
+
+    /// Hands a received payload to the engine. Returns `false` if the
+    /// replay filter dropped it.
+    pub fn recv(&mut self, payload: &[u8]) -> bool {
+        self.record(Direction::Inbound, payload);
+        let Some(filter) = self.dedup.as_mut() else {
+            self.incoming.push_back(payload.to_vec());
+            return true;
+        };
+        let fresh = payload.len() >= DEDUP_HEADER_LEN && {
+            let mut seq = [0u8; DEDUP_HEADER_LEN];
+            seq.copy_from_slice(&payload[..DEDUP_HEADER_LEN]);
+            filter.accept(u64::from_be_bytes(seq))
+        };
+        if !fresh {
+            self.duplicates += 1;
+            debug!("Dropping replayed or malformed datagram");
+            return false;
+        }
+        self.incoming
+            .push_back(payload[DEDUP_HEADER_LEN..].to_vec());
+        true
+    }
+
+    /// Removes the next queued outgoing payload.
+    pub fn pop_outgoing(&mut self) -> Option<Vec<u8>> {
+        self.outgoing.pop_front()
+    }
+
+    /// Removes the next received payload.
+    pub fn pop_incoming(&mut self) -> Option<Vec<u8>> {
+        self.incoming.pop_front()
+    }
+
+    /// Number of payloads waiting to be sent.
+    pub fn pending_outgoing(&self) -> usize {
+        self.outgoing.len()
+    }
+
+    /// Number of received payloads not yet consumed.
+    pub fn pending_incoming(&self) -> usize {
+        self.incoming.len()
+    }
+
+    /// Moves queued payloads into the QUIC connection until it refuses more.
+    /// Returns the number of datagrams handed to `quiche`.
+    pub fn flush_to(&mut self, conn: &mut quiche::Connection) -> usize {
+        if let Some(max) = conn.dgram_max_writable_len() {
+            self.set_max_size(max.min(u16::MAX as usize) as u16);
+        }
+        self.flush_with(|payload| conn.dgram_send(payload))
+    }
+
+    /// Hands queued payloads to `send` in order until it refuses one.
+    ///
+    /// `quiche::Error::Done` (queue full) and other errors leave the payload
+    /// queued for the next flush. A payload rejected with
+    /// `quiche::Error::BufferTooShort` no longer fits the path, for example
+    /// after the writable size shrank, and would block the queue forever;
+    /// it is dropped and counted in [`oversized_dropped`](Self::oversized_dropped).
+    pub fn flush_with<F>(&mut self, mut send: F) -> usize
+    where
+        F: FnMut(&[u8]) -> Result<(), quiche::Error>,
+    {
+        let mut sent = 0;
+        while let Some(payload) = self.outgoing.front() {
+            match send(payload) {
+                Ok(()) => {
+                    self.outgoing.pop_front();
+                    sent += 1;
+                }
+                Err(quiche::Error::Done) => break,
+                Err(quiche::Error::BufferTooShort) => {
+                    warn!(
+                        "Dropping queued datagram of {} bytes above the writable size",
+                        payload.len()
+                    );
+                    self.outgoing.pop_front();
+                    self.oversized += 1;
+                }
+                Err(e) => {
+                    debug!("dgram_send failed: {}", e);
+                    break;
+                }
+            }
+        }
+        sent
+    }
+
+    /// Number of queued datagrams dropped because they no longer fit.
+    pub fn oversized_dropped(&self) -> u64 {
+        self.oversized
+    }
+
+    /// Drains all DATAGRAM frames received by the QUIC connection.
+    /// Returns the number of datagrams read.
+    pub fn poll_from(&mut self, conn: &mut quiche::Connection) -> usize {
+        let mut buf = [0u8; 65535];
+        let mut read = 0;
+        while let Ok(len) = conn.dgram_recv(&mut buf) {
+            self.recv(&buf[..len]);
+            read += 1;
+        }
+        read
+    }
+
+    /// Starts recording every sent and received datagram to `path`,
+    /// truncating any existing file.
+    pub fn set_capture(&mut self, path: &Path) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        w.write_all(CAPTURE_MAGIC)?;
+        w.write_all(&[CAPTURE_VERSION])?;
+        self.capture = Some(w);
+        Ok(())
+    }
+
+    /// Stops recording and flushes the capture file.
+    pub fn stop_capture(&mut self) -> io::Result<()> {
+        match self.capture.take() {
+            Some(mut w) => w.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Feeds all inbound datagrams recorded in `path` back through
+    /// [`recv`](Self::recv). Replayed datagrams are not recorded again.
+    /// Returns the number of datagrams replayed.
+    pub fn replay(&mut self, path: &Path) -> io::Result<usize> {
+        let records = read_capture(path)?;
+        let capture = self.capture.take();
+        let mut count = 0;
+        for rec in records {
+            if rec.direction == Direction::Inbound {
+                self.recv(&rec.payload);
+                count += 1;
+            }
+        }
+        self.capture = capture;
+        Ok(count)
+    }
+
+    fn record(&mut self, direction: Direction, payload: &[u8]) {
+        let Some(w) = self.capture.as_mut() else {
+            return;
+        };
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+        let res = w
+            .write_all(&[direction as u8])
+            .and_then(|_| w.write_all(&ts.to_be_bytes()))
+            .and_then(|_| w.write_all(&(payload.len() as u32).to_be_bytes()))
+            .and_then(|_| w.write_all(payload))
+            .and_then(|_| w.flush());
+        if let Err(e) = res {
+            warn!("Datagram capture failed, disabling: {}", e);
+            self.capture = None;
+        }
+    }
+}
+
+impl Default for DatagramEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads every record from a capture file written by
+/// [`DatagramEngine::set_capture`].
+pub fn read_capture(path: &Path) -> io::Result<Vec<CapturedDatagram>> {
+    let mut r = BufReader::new(File::open(path)?);
+    let mut header = [0u8; 5];
+    r.read_exact(&mut header)?;
+    if &header[..4] != CAPTURE_MAGIC || header[4] != CAPTURE_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a datagram capture file",
+        ));
+    }
+
+    let mut out = Vec::new();
+    loop {
+        let mut dir = [0u8; 1];
+        match r.read_exact(&mut dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        let direction = match dir[0] {
+            0 => Direction::Outbound,
+            1 => Direction::Inbound,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid capture direction",
+                ))
+            }
+        };
+        let mut ts = [0u8; 8];
+        r.read_exact(&mut ts)?;
+        let mut len = [0u8; 4];
+        r.read_exact(&mut len)?;
+        let mut payload = vec![0u8; u32::from_be_bytes(len) as usize];
+        r.read_exact(&mut payload)?;
+        out.push(CapturedDatagram {
+            direction,
+            timestamp_us: u64::from_be_bytes(ts),
+            payload,
+        });
+    }
+    Ok(out)
+}
@@ -10,6 +10,70 @@ pub enum ConnectionError {
     Fec(String),
 }
 
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum CryptoError {
+    #[error("invalid key length")]
+    InvalidKeyLength,
+    #[error("invalid nonce length")]
+    InvalidNonceLength,
+    #[error("ciphertext too short")]
+    CiphertextTooShort,
+    #[error("encryption failed")]
+    EncryptionFailed,
+    #[error("authentication tag mismatch")]
+    TagMismatch,
+    #[error("insecure stub cipher disabled; enable the `allow_insecure_stub` feature")]
+    InsecureStub,
+    #[error("cipher self-test failed: {0}")]
+    SelfTestFailed(&'static str),
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum StealthError {
+    #[error("invalid configuration: {0}")]
+    Config(String),
+    #[error("invalid fingerprint profile: {0}")]
+    InvalidProfile(String),
+    #[error("invalid DoH provider '{url}': {reason}")]
+    InvalidDohProvider { url: String, reason: String },
+    #[error("XOR key too short: {len} bytes, need at least {min}")]
+    XorKeyTooShort { len: usize, min: usize },
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    #[error("DoH resolution disabled")]
+    Disabled,
+    #[error("DoH resolution timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("DoH resolution failed: {0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Error)]
+pub enum CertVerifyError {
+    #[error("empty certificate chain")]
+    EmptyChain,
+    #[error("failed to load CA certificates: {0}")]
+    CaLoad(String),
+    #[error("invalid server name '{0}'")]
+    InvalidServerName(String),
+    #[error("malformed certificate")]
+    Malformed,
+    #[error("certificate expired")]
+    Expired,
+    #[error("certificate verification failed: {0}")]
+    Invalid(rustls::Error),
+    #[error("SPKI pin mismatch")]
+    PinMismatch,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum PoolError {
+    #[error("memory pool exhausted ({capacity} blocks in use)")]
+    Exhausted { capacity: usize },
+}
+
 impl From<&'static str> for ConnectionError {
     fn from(s: &'static str) -> Self {
         ConnectionError::Fec(s.to_string())
@@ -3,6 +3,7 @@
 // establishing a real TLS session.
 
 use crate::stealth::FingerprintProfile;
+use sha2::{Digest, Sha256};
 
 /// Owned variant of [`ServerHelloParams`] for storing in fingerprint profiles.
 #[derive(Debug, Clone)]
@@ -96,6 +97,46 @@ impl FakeTls {
         Self::record(0x01, &payload)
     }
 
+    /// Builds a ClientHello whose extensions appear in `extension_order`.
+    /// ALPN carries `alpn`; server name, groups, point formats, signature
+    /// algorithms and supported versions get fixed common values and every
+    /// other extension an empty body.
+    pub fn client_hello_ordered(
+        cipher_suites: &[u16],
+        extension_order: &[u16],
+        alpn: &[String],
+    ) -> Vec<u8> {
+        let mut extensions = Vec::new();
+        for &ty in extension_order {
+            let body: Vec<u8> = match ty {
+                EXT_SERVER_NAME => vec![0x00, 0x03, 0x00, 0x00, 0x00],
+                EXT_SUPPORTED_GROUPS => vec![0x00, 0x06, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18],
+                EXT_EC_POINT_FORMATS => vec![0x01, 0x00],
+                EXT_SIGNATURE_ALGORITHMS => vec![0x00, 0x06, 0x04, 0x03, 0x08, 0x04, 0x04, 0x01],
+                EXT_ALPN => {
+                    let mut list = Vec::new();
+                    for proto in alpn {
+                        list.push(proto.len() as u8);
+                        list.extend_from_slice(proto.as_bytes());
+                    }
+                    let mut body = (list.len() as u16).to_be_bytes().to_vec();
+                    body.extend_from_slice(&list);
+                    body
+                }
+                EXT_SUPPORTED_VERSIONS => vec![0x02, 0x03, 0x04],
+                _ => Vec::new(),
+            };
+            extensions.extend_from_slice(&ty.to_be_bytes());
+            extensions.extend_from_slice(&(body.len() as u16).to_be_bytes());
+            extensions.extend_from_slice(&body);
+        }
+        Self::client_hello_custom(ClientHelloParams {
+            tls_version: 0x0303,
+            cipher_suites,
+            extensions: &extensions,
+        })
+    }
+
     /// Builds a minimal ServerHello record using the provided parameters.
     pub fn server_hello_custom(params: ServerHelloParams) -> Vec<u8> {
         let mut payload = Vec::new();
@@ -190,3 +231,309 @@ impl FakeTls {
         }
     }
 }
+
+/// Fields of a ClientHello relevant for JA3/JA4 fingerprinting.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientHelloInfo {
+    /// `true` if the message was wrapped in a TLS record (TCP framing),
+    /// `false` for a bare handshake message as carried in QUIC CRYPTO frames.
+    pub record_framed: bool,
+    pub legacy_version: u16,
+    pub cipher_suites: Vec<u16>,
+    /// Extension types in wire order.
+    pub extensions: Vec<u16>,
+    pub supported_groups: Vec<u16>,
+    pub ec_point_formats: Vec<u8>,
+    pub signature_algorithms: Vec<u16>,
+    pub supported_versions: Vec<u16>,
+    pub alpn: Vec<String>,
+    pub has_sni: bool,
+    /// Byte range of the server name within the parsed input.
+    pub sni_range: Option<std::ops::Range<usize>>,
+}
+
+const EXT_SERVER_NAME: u16 = 0x0000;
+const EXT_SUPPORTED_GROUPS: u16 = 0x000a;
+const EXT_EC_POINT_FORMATS: u16 = 0x000b;
+const EXT_SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const EXT_ALPN: u16 = 0x0010;
+const EXT_SUPPORTED_VERSIONS: u16 = 0x002b;
+
+/// GREASE values (RFC 8701) are ignored by both fingerprint formats.
+fn is_grease(v: u16) -> bool {
+    v & 0x0f0f == 0x0a0a && v >> 8 == v & 0xff
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u16_list(data: &[u8]) -> Vec<u16> {
+        data.chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect()
+    }
+}
+
+impl ClientHelloInfo {
+    /// Parses a ClientHello, either as a full TLS record or as a bare
+    /// handshake message. Returns `None` for anything else.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut info = ClientHelloInfo::default();
+        let mut r = Reader(data);
+        if data.first() == Some(&0x16) {
+            info.record_framed = true;
+            r.take(3)?;
+            let len = r.u16()? as usize;
+            r = Reader(r.take(len)?);
+        }
+        if r.u8()? != 0x01 {
+            return None;
+        }
+        let len = r.take(3)?;
+        let len = u32::from_be_bytes([0, len[0], len[1], len[2]]) as usize;
+        let mut r = Reader(r.take(len)?);
+
+        info.legacy_version = r.u16()?;
+        r.take(32)?; // random
+        let sid = r.u8()? as usize;
+        r.take(sid)?;
+        let cs = r.u16()? as usize;
+        info.cipher_suites = Reader::u16_list(r.take(cs)?);
+        let comp = r.u8()? as usize;
+        r.take(comp)?;
+        if r.0.is_empty() {
+            return Some(info);
+        }
+
+        let ext_len = r.u16()? as usize;
+        let mut exts = Reader(r.take(ext_len)?);
+        while !exts.0.is_empty() {
+            let ty = exts.u16()?;
+            let len = exts.u16()? as usize;
+            let body = exts.take(len)?;
+            info.extensions.push(ty);
+            let mut b = Reader(body);
+            match ty {
+                EXT_SERVER_NAME => {
+                    info.has_sni = true;
+                    // server_name_list length, name_type, HostName
+                    b.u16()?;
+                    b.u8()?;
+                    let n = b.u16()? as usize;
+                    let name = b.take(n)?;
+                    let start = name.as_ptr() as usize - data.as_ptr() as usize;
+                    info.sni_range = Some(start..start + n);
+                }
+                EXT_SUPPORTED_GROUPS => {
+                    let n = b.u16()? as usize;
+                    info.supported_groups = Reader::u16_list(b.take(n)?);
+                }
+                EXT_EC_POINT_FORMATS => {
+                    let n = b.u8()? as usize;
+                    info.ec_point_formats = b.take(n)?.to_vec();
+                }
+                EXT_SIGNATURE_ALGORITHMS => {
+                    let n = b.u16()? as usize;
+                    info.signature_algorithms = Reader::u16_list(b.take(n)?);
+                }
+                EXT_ALPN => {
+                    let n = b.u16()? as usize;
+                    let mut list = Reader(b.take(n)?);
+                    while !list.0.is_empty() {
+                        let l = list.u8()? as usize;
+                        info.alpn
+                            .push(String::from_utf8_lossy(list.take(l)?).into_owned());
+                    }
+                }
+                EXT_SUPPORTED_VERSIONS => {
+                    let n = b.u8()? as usize;
+                    info.supported_versions = Reader::u16_list(b.take(n)?);
+                }
+                _ => {}
+            }
+        }
+        Some(info)
+    }
+
+    /// Returns the JA3 string
+    /// `SSLVersion,Ciphers,Extensions,EllipticCurves,EllipticCurvePointFormats`.
+    ///
+    /// The string is returned unhashed; the commonly published JA3 value is
+    /// its MD5 digest.
+    pub fn ja3(&self) -> String {
+        fn join<T: ToString>(items: impl Iterator<Item = T>) -> String {
+            items.map(|v| v.to_string()).collect::<Vec<_>>().join("-")
+        }
+        let no_grease = |v: &&u16| !is_grease(**v);
+        format!(
+            "{},{},{},{},{}",
+            self.legacy_version,
+            join(self.cipher_suites.iter().filter(no_grease)),
+            join(self.extensions.iter().filter(no_grease)),
+            join(self.supported_groups.iter().filter(no_grease)),
+            join(self.ec_point_formats.iter()),
+        )
+    }
+
+    /// Returns the JA4 fingerprint (`a_b_c`) of this ClientHello.
+    pub fn ja4(&self) -> String {
+        fn hex_list(items: &[u16]) -> String {
+            items
+                .iter()
+                .map(|v| format!("{v:04x}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+        fn truncated_sha256(input: &str) -> String {
+            if input.is_empty() {
+                return "0".repeat(12);
+            }
+            let digest = Sha256::digest(input.as_bytes());
+            digest[..6].iter().map(|b| format!("{b:02x}")).collect()
+        }
+
+        let protocol = if self.record_framed { 't' } else { 'q' };
+        let version = self
+            .supported_versions
+            .iter()
+            .copied()
+            .filter(|v| !is_grease(*v))
+            .max()
+            .unwrap_or(self.legacy_version);
+        let version = match version {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            0x0300 => "s3",
+            _ => "00",
+        };
+        let sni = if self.has_sni { 'd' } else { 'i' };
+        let mut ciphers: Vec<u16> = self
+            .cipher_suites
+            .iter()
+            .copied()
+            .filter(|v| !is_grease(*v))
+            .collect();
+        let extensions: Vec<u16> = self
+            .extensions
+            .iter()
+            .copied()
+            .filter(|v| !is_grease(*v))
+            .collect();
+        let alpn = match self.alpn.first().map(|a| a.as_bytes()) {
+            Some([first, .., last]) => format!("{}{}", *first as char, *last as char),
+            Some([only]) => format!("{}{}", *only as char, *only as char),
+            _ => "00".to_string(),
+        };
+        let a = format!(
+            "{protocol}{version}{sni}{:02}{:02}{alpn}",
+            ciphers.len().min(99),
+            extensions.len().min(99)
+        );
+
+        ciphers.sort_unstable();
+        let b = truncated_sha256(&hex_list(&ciphers));
+
+        let mut sorted_ext: Vec<u16> = extensions
+            .into_iter()
+            .filter(|v| *v != EXT_SERVER_NAME && *v != EXT_ALPN)
+            .collect();
+        sorted_ext.sort_unstable();
+        let mut c_input = hex_list(&sorted_ext);
+        if !self.signature_algorithms.is_empty() {
+            c_input.push('_');
+            c_input.push_str(&hex_list(&self.signature_algorithms));
+        }
+        let c = truncated_sha256(&c_input);
+
+        format!("{a}_{b}_{c}")
+    }
+}
+
+/// How a ClientHello is cut into segments before it is written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitStrategy {
+    /// Write the ClientHello in one piece.
+    #[default]
+    None,
+    /// Cut in the middle of the server name so that no single segment
+    /// carries the complete SNI. A record-framed hello is re-framed as two
+    /// TLS records, a bare handshake message becomes two CRYPTO chunks.
+    SegmentAtSni,
+}
+
+impl FakeTls {
+    /// Splits `client_hello` according to `strategy`. Input without a
+    /// parseable server name is returned as a single segment.
+    pub fn split_client_hello(client_hello: &[u8], strategy: SplitStrategy) -> Vec<Vec<u8>> {
+        let sni = match strategy {
+            SplitStrategy::None => None,
+            SplitStrategy::SegmentAtSni => ClientHelloInfo::parse(client_hello)
+                .and_then(|info| info.sni_range)
+                .filter(|r| r.len() >= 2),
+        };
+        let sni = match sni {
+            Some(r) => r,
+            None => return vec![client_hello.to_vec()],
+        };
+        let cut = sni.start + sni.len() / 2;
+        if client_hello[0] != 0x16 {
+            return vec![client_hello[..cut].to_vec(), client_hello[cut..].to_vec()];
+        }
+
+        // Handshake messages may be fragmented over several records, so each
+        // half gets its own record header. Anything after the first record is
+        // passed through behind the second half.
+        let len = u16::from_be_bytes([client_hello[3], client_hello[4]]) as usize;
+        let (body, rest) = client_hello[5..].split_at(len);
+        let record = |part: &[u8]| {
+            let mut out = client_hello[..3].to_vec();
+            out.extend_from_slice(&(part.len() as u16).to_be_bytes());
+            out.extend_from_slice(part);
+            out
+        };
+        let mut second = record(&body[cut - 5..]);
+        second.extend_from_slice(rest);
+        vec![record(&body[..cut - 5]), second]
+    }
+
+    /// Computes the JA3 string of a generated ClientHello, see
+    /// [`ClientHelloInfo::ja3`].
+    pub fn ja3(client_hello: &[u8]) -> Option<String> {
+        ClientHelloInfo::parse(client_hello).map(|info| info.ja3())
+    }
+
+    /// Computes the JA4 fingerprint of a generated ClientHello.
+    pub fn ja4(client_hello: &[u8]) -> Option<String> {
+        ClientHelloInfo::parse(client_hello).map(|info| info.ja4())
+    }
+
+    /// Checks that `client_hello` produces the `expected` JA4 fingerprint.
+    /// Intended for CI checks that a profile still matches its reference.
+    pub fn assert_ja4(client_hello: &[u8], expected: &str) -> Result<(), String> {
+        match Self::ja4(client_hello) {
+            Some(ref actual) if actual == expected => Ok(()),
+            Some(actual) => Err(format!("JA4 mismatch: expected {expected}, got {actual}")),
+            None => Err("not a ClientHello".to_string()),
+        }
+    }
+}
@@ -1,10 +1,12 @@
-use super::decoder::DecoderVariant;
+use super::decoder::{new_recovery_cache, DecoderVariant, SharedRecoveryCache};
 use super::encoder::{EncoderVariant, Packet, PidConfig};
-use super::gf_tables::init_gf_tables;
-use crate::optimize::MemoryPool;
+use super::gf_tables::{gf_mul_add_region_with, hw_path, init_gf_tables, with_hw_path, HwPath};
+use crate::app_config::ConfigIssue;
+use crate::optimize::{Clock, MemoryPool, SystemClock};
 use crate::telemetry;
+use log::{debug, info, warn};
 use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
 // --- Core Data Structures ---
 
@@ -98,6 +100,9 @@ impl LossEstimator {
     }
 }
 
+/// Default for [`FecConfig::min_dwell_time`].
+pub const DEFAULT_MIN_DWELL: Duration = Duration::from_millis(500);
+
 /// Manages the FEC mode using a PID controller for dynamic redundancy adjustment.
 pub struct ModeManager {
     current_mode: FecMode,
@@ -108,6 +113,11 @@ pub struct ModeManager {
     min_dwell_time: Duration,
     hysteresis: f32,
     current_window: usize,
+    max_window: Option<usize>,
+    oscillation: OscillationConfig,
+    recent_flips: VecDeque<Instant>,
+    frozen_until: Option<Instant>,
+    clock: Arc<dyn Clock>,
 }
 
 impl ModeManager {
@@ -115,10 +125,17 @@ impl ModeManager {
     const ALPHA_K: f32 = 0.5;
 
     fn initial_window(&self, mode: FecMode) -> usize {
-        self.window_sizes
+        let window = self
+            .window_sizes
             .get(&mode)
             .copied()
-            .unwrap_or_else(|| *FecConfig::default_windows().get(&mode).unwrap_or(&0))
+            .unwrap_or_else(|| *FecConfig::default_windows().get(&mode).unwrap_or(&0));
+        self.cap_window(window)
+    }
+
+    /// Applies [`FecConfig::max_window`], which wins over the mode's range.
+    fn cap_window(&self, window: usize) -> usize {
+        self.max_window.map_or(window, |max| window.min(max))
     }
 
     fn window_range(mode: FecMode) -> (usize, usize) {
@@ -156,6 +173,10 @@ impl ModeManager {
         hysteresis: f32,
         initial_mode: FecMode,
         window_sizes: HashMap<FecMode, usize>,
+        max_window: Option<usize>,
+        oscillation: OscillationConfig,
+        min_dwell_time: Duration,
+        clock: Arc<dyn Clock>,
     ) -> Self {
         let mut mode_thresholds = HashMap::new();
         mode_thresholds.insert(FecMode::Zero, 0.01);
@@ -174,13 +195,18 @@ impl ModeManager {
 
         Self {
             current_mode,
-            pid: PidController::new(pid_config),
+            pid: PidController::new(pid_config, Arc::clone(&clock)),
             mode_thresholds,
             window_sizes,
-            last_mode_change: Instant::now(),
-            min_dwell_time: Duration::from_millis(500),
+            last_mode_change: clock.now(),
+            min_dwell_time,
             hysteresis,
-            current_window,
+            current_window: max_window.map_or(current_window, |max| current_window.min(max)),
+            max_window,
+            oscillation,
+            recent_flips: VecDeque::new(),
+            frozen_until: None,
+            clock,
         }
     }
 
@@ -188,16 +214,28 @@ impl ModeManager {
     /// Returns the new mode, window and an optional previous (mode, window) if a
     /// cross-fade should start.
     fn update(&mut self, estimated_loss: f32) -> (FecMode, usize, Option<(FecMode, usize)>) {
-        // Emergency override for sudden loss spikes
+        // Emergency override for sudden loss spikes, even while the mode is
+        // frozen after oscillation.
         if estimated_loss > self.mode_thresholds[&FecMode::Strong] + self.hysteresis {
             let prev = (self.current_mode, self.current_window);
             self.current_mode = FecMode::Extreme;
             self.current_window = self.initial_window(self.current_mode);
-            self.last_mode_change = Instant::now();
+            self.last_mode_change = self.clock.now();
+            if prev.0 != self.current_mode {
+                self.record_flip(self.last_mode_change);
+            }
             return (self.current_mode, self.current_window, Some(prev));
         }
 
-        if self.last_mode_change.elapsed() < self.min_dwell_time {
+        if self.is_frozen() {
+            return (self.current_mode, self.current_window, None);
+        }
+
+        let dwell = self
+            .clock
+            .now()
+            .saturating_duration_since(self.last_mode_change);
+        if dwell < self.min_dwell_time {
             return (self.current_mode, self.current_window, None);
         }
 
@@ -222,8 +260,9 @@ impl ModeManager {
 
         if new_mode != self.current_mode {
             self.current_mode = new_mode;
-            self.last_mode_change = Instant::now();
+            self.last_mode_change = self.clock.now();
             self.current_window = self.initial_window(new_mode);
+            self.record_flip(self.last_mode_change);
         }
 
         // Dynamic window update according to PLAN
@@ -231,7 +270,7 @@ impl ModeManager {
         let alpha = 1.0 + Self::ALPHA_K * (estimated_loss - target_loss_for_mode);
         let range = Self::window_range(self.current_mode);
         let mut new_window = ((self.current_window as f32) * alpha).round() as usize;
-        new_window = new_window.clamp(range.0, range.1);
+        new_window = self.cap_window(new_window.clamp(range.0, range.1));
         self.current_window = new_window;
 
         if prev_mode != self.current_mode || prev_window != self.current_window {
@@ -259,6 +298,49 @@ impl ModeManager {
         (self.current_mode, self.current_window, None)
     }
 
+    /// Returns `true` while the mode is pinned after oscillation was
+    /// detected, see [`OscillationConfig`].
+    fn is_frozen(&mut self) -> bool {
+        match self.frozen_until {
+            Some(until) if self.clock.now() < until => true,
+            Some(_) => {
+                info!("FEC mode unfrozen, resuming adaptation");
+                self.frozen_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Counts a mode change and freezes the mode once too many happened
+    /// within the detection window.
+    fn record_flip(&mut self, now: Instant) {
+        let cfg = self.oscillation;
+        if cfg.max_flips == 0 {
+            return;
+        }
+        while let Some(&oldest) = self.recent_flips.front() {
+            if now.duration_since(oldest) > cfg.window {
+                self.recent_flips.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.recent_flips.push_back(now);
+        if self.recent_flips.len() >= cfg.max_flips {
+            warn!(
+                "FEC mode oscillating ({} changes within {:?}), holding {:?} for {:?}",
+                self.recent_flips.len(),
+                cfg.window,
+                self.current_mode,
+                cfg.cooldown
+            );
+            telemetry!(telemetry::FEC_OSCILLATION_FREEZES.inc());
+            self.recent_flips.clear();
+            self.frozen_until = Some(now + cfg.cooldown);
+        }
+    }
+
     fn next_mode(&self, mode: FecMode) -> FecMode {
         match mode {
             FecMode::Zero => FecMode::Light,
@@ -280,6 +362,144 @@ impl ModeManager {
     }
 }
 
+/// Strategy used to size the number of repair packets per block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepairPolicy {
+    /// Fixed overhead ratio per mode, see [`ModeManager::params_for`].
+    #[default]
+    Fixed,
+    /// Repair count derived from the measured loss rate and block size.
+    Proportional,
+}
+
+impl RepairPolicy {
+    /// Returns the number of repair packets for a block of `window` source
+    /// packets in `mode` at the given loss rate.
+    pub fn repairs(self, mode: FecMode, window: usize, loss: f32) -> usize {
+        match self {
+            RepairPolicy::Fixed => {
+                let (k, n) = ModeManager::params_for(mode, window);
+                n.saturating_sub(k)
+            }
+            RepairPolicy::Proportional => optimal_repair_count(window, loss),
+        }
+    }
+}
+
+/// Computes how many repair packets are needed so that `k` source packets
+/// remain decodable under an independent loss rate `loss`.
+///
+/// The block size `n` is chosen such that the expected number of received
+/// packets minus two standard deviations still covers `k`, i.e.
+/// `n(1-p) - 2*sqrt(n*p*(1-p)) >= k`.
+pub fn optimal_repair_count(k: usize, loss: f32) -> usize {
+    if k == 0 {
+        return 0;
+    }
+    let p = loss.clamp(0.0, 0.9) as f64;
+    let q = 1.0 - p;
+    let s = (p * q).sqrt();
+    let x = (2.0 * s + (4.0 * s * s + 4.0 * q * k as f64).sqrt()) / (2.0 * q);
+    let n = (x * x).ceil() as usize;
+    n.saturating_sub(k)
+}
+
+/// Time spent coding with one [`FecMode`], see [`AdaptiveFec::latency`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FecLatency {
+    pub encode_time_ns: u64,
+    pub encode_calls: u64,
+    pub decode_time_ns: u64,
+    pub decode_calls: u64,
+}
+
+impl FecLatency {
+    /// Mean time per [`AdaptiveFec::on_send`] call, `None` before the first.
+    pub fn avg_encode(&self) -> Option<Duration> {
+        (self.encode_calls > 0)
+            .then(|| Duration::from_nanos(self.encode_time_ns / self.encode_calls))
+    }
+
+    /// Mean time per [`AdaptiveFec::on_receive`] call, `None` before the first.
+    pub fn avg_decode(&self) -> Option<Duration> {
+        (self.decode_calls > 0)
+            .then(|| Duration::from_nanos(self.decode_time_ns / self.decode_calls))
+    }
+}
+
+/// Events raised by [`AdaptiveFec`] on the receive path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FecEvent {
+    /// An incomplete block was dropped, because it exceeded the deadline or
+    /// because its decoder was retired at the end of a mode cross-fade.
+    /// `received` is the number of packets collected before expiry.
+    BlockExpired { age: Duration, received: usize },
+}
+
+/// Receive-side state of the block a decoder is collecting.
+struct PendingBlock {
+    // Source count of the block, fixed when its first packet arrives so a
+    // later mode switch does not change how the block is judged.
+    k: usize,
+    started: Instant,
+    received: usize,
+    // Source ids received, to tell which are missing if the block expires.
+    sources: Vec<u64>,
+}
+
+impl PendingBlock {
+    fn new(k: usize, started: Instant) -> Self {
+        Self {
+            k,
+            started,
+            received: 0,
+            sources: Vec::new(),
+        }
+    }
+
+    fn age(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.started)
+    }
+
+    /// Source ids of the block that were never received. Source packets are
+    /// numbered consecutively per block, so the block start follows from any
+    /// source id received in it.
+    fn missing_sources(&self) -> Vec<u64> {
+        let (Some(&id), true) = (self.sources.first(), self.k > 0) else {
+            return Vec::new();
+        };
+        let k = self.k as u64;
+        let base = id - id % k;
+        (base..base + k)
+            .filter(|id| !self.sources.contains(id))
+            .collect()
+    }
+}
+
+// --- Oscillation Detection ---
+
+/// Sensitivity of the mode oscillation detector. When the mode changes
+/// `max_flips` times within `window`, it is held for `cooldown` so noisy
+/// loss measurements do not keep rebuilding the encoder and decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OscillationConfig {
+    /// Mode changes within `window` that count as oscillation; `0`
+    /// disables the detector.
+    pub max_flips: usize,
+    pub window: Duration,
+    pub cooldown: Duration,
+}
+
+impl Default for OscillationConfig {
+    fn default() -> Self {
+        Self {
+            max_flips: 4,
+            window: Duration::from_secs(10),
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
 // --- PID Controller ---
 
 pub struct PidConfig {
@@ -293,20 +513,22 @@ struct PidController {
     integral: f32,
     previous_error: f32,
     last_time: Instant,
+    clock: Arc<dyn Clock>,
 }
 
 impl PidController {
-    fn new(config: PidConfig) -> Self {
+    fn new(config: PidConfig, clock: Arc<dyn Clock>) -> Self {
         Self {
             config,
             integral: 0.0,
             previous_error: 0.0,
-            last_time: Instant::now(),
+            last_time: clock.now(),
+            clock,
         }
     }
 
     fn update(&mut self, current_value: f32, setpoint: f32) -> f32 {
-        let now = Instant::now();
+        let now = self.clock.now();
         let dt = now.duration_since(self.last_time).as_secs_f32();
         self.last_time = now;
 
@@ -328,11 +550,33 @@ pub struct AdaptiveFec {
     mode_mgr: Arc<Mutex<ModeManager>>,
     encoder: EncoderVariant,
     decoder: DecoderVariant,
+    // Recovery inverses of this instance's decoders, see
+    // `Decoder::with_recovery_cache`.
+    recovery_cache: SharedRecoveryCache,
     transition_encoder: Option<EncoderVariant>,
     transition_decoder: Option<DecoderVariant>,
     transition_left: usize,
     mem_pool: Arc<MemoryPool>,
     config: FecConfig,
+    repair_policy: RepairPolicy,
+    deadline: Option<Duration>,
+    block: Option<PendingBlock>,
+    // Block of `transition_decoder` during a cross-fade.
+    transition_block: Option<PendingBlock>,
+    missing_sources: Vec<u64>,
+    events: VecDeque<FecEvent>,
+    // Set while the active mode is `Zero`; sends then bypass the encoder.
+    zero_mode: bool,
+    passthrough_packets: u64,
+    // Sources waiting for a full block in non-systematic mode.
+    coded_block: Vec<Packet>,
+    latency: HashMap<FecMode, FecLatency>,
+    // Kernel path used in the high-loss modes, set by `autotune`.
+    high_loss_path: Option<HwPath>,
+    // Send time of the newest source in reactive mode; repairs requested
+    // after the recovery deadline has passed are not sent.
+    last_source_sent: Option<Instant>,
+    clock: Arc<dyn Clock>,
 }
 
 #[derive(Clone)]
@@ -340,12 +584,40 @@ pub struct FecConfig {
     pub lambda: f32,
     pub burst_window: usize,
     pub hysteresis: f32,
+    /// Shortest time a mode is kept before the controller may leave it
+    /// again. Loss spikes beyond the `Strong` threshold switch to `Extreme`
+    /// regardless.
+    pub min_dwell_time: Duration,
     pub pid: PidConfig,
     pub initial_mode: FecMode,
     pub kalman_enabled: bool,
     pub kalman_q: f32,
     pub kalman_r: f32,
     pub window_sizes: HashMap<FecMode, usize>,
+    /// Send the source packets themselves alongside the repair packets.
+    /// When `false` only coded combinations leave the sender, one block of
+    /// `k` sources at a time, and the receiver has to decode every source.
+    pub systematic: bool,
+    /// Longest time an incomplete block may wait for missing packets before
+    /// it is dropped, see [`AdaptiveFec::set_deadline_policy`]. `None` keeps
+    /// blocks until they decode.
+    pub max_recovery_delay: Option<Duration>,
+    /// Benchmark the GF(2^8) kernel paths once at startup and use the
+    /// fastest in the high-loss modes, see [`AdaptiveFec::autotune`]. Off by
+    /// default, as the benchmark delays the first connection by a few
+    /// milliseconds.
+    pub autotune: bool,
+    /// Upper bound on the source window in every mode, below the mode's own
+    /// range if need be. Bounds memory and decoding latency on constrained
+    /// devices; `None` leaves the windows to the modes.
+    pub max_window: Option<usize>,
+    /// Freezes the mode for a while when it keeps flipping, see
+    /// [`OscillationConfig`].
+    pub oscillation: OscillationConfig,
+    /// Hold repairs back until the receiver reports loss, see
+    /// [`AdaptiveFec::report_receiver_loss`]. Only sources are sent on a
+    /// clean path. Requires `systematic`.
+    pub reactive: bool,
 }
 
 impl FecConfig {
@@ -372,11 +644,25 @@ impl FecConfig {
             lambda: Option<f32>,
             burst_window: Option<usize>,
             hysteresis: Option<f32>,
+            min_dwell_ms: Option<u64>,
             pid: Option<PidSection>,
             kalman_enabled: Option<bool>,
             kalman_q: Option<f32>,
             kalman_r: Option<f32>,
             modes: Option<Vec<ModeSection>>,
+            systematic: Option<bool>,
+            max_recovery_delay_ms: Option<u64>,
+            autotune: Option<bool>,
+            max_window: Option<usize>,
+            oscillation: Option<OscillationSection>,
+            reactive: Option<bool>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct OscillationSection {
+            max_flips: Option<usize>,
+            window_ms: Option<u64>,
+            cooldown_ms: Option<u64>,
         }
 
         #[derive(serde::Deserialize)]
@@ -399,6 +685,19 @@ impl FecConfig {
             ki: 0.5,
             kd: 0.1,
         });
+        let osc_default = OscillationConfig::default();
+        let oscillation = match af.oscillation {
+            Some(o) => OscillationConfig {
+                max_flips: o.max_flips.unwrap_or(osc_default.max_flips),
+                window: o
+                    .window_ms
+                    .map_or(osc_default.window, Duration::from_millis),
+                cooldown: o
+                    .cooldown_ms
+                    .map_or(osc_default.cooldown, Duration::from_millis),
+            },
+            None => osc_default,
+        };
         let mut windows = FecConfig::default_windows();
         if let Some(modes) = af.modes {
             for msec in modes {
@@ -411,6 +710,9 @@ impl FecConfig {
             lambda: af.lambda.unwrap_or(0.1),
             burst_window: af.burst_window.unwrap_or(20),
             hysteresis: af.hysteresis.unwrap_or(0.02),
+            min_dwell_time: af
+                .min_dwell_ms
+                .map_or(DEFAULT_MIN_DWELL, Duration::from_millis),
             pid: PidConfig {
                 kp: pid.kp,
                 ki: pid.ki,
@@ -421,6 +723,12 @@ impl FecConfig {
             kalman_q: af.kalman_q.unwrap_or(0.001),
             kalman_r: af.kalman_r.unwrap_or(0.01),
             window_sizes: windows,
+            systematic: af.systematic.unwrap_or(true),
+            max_recovery_delay: af.max_recovery_delay_ms.map(Duration::from_millis),
+            autotune: af.autotune.unwrap_or(false),
+            max_window: af.max_window,
+            oscillation,
+            reactive: af.reactive.unwrap_or(false),
         })
     }
 
@@ -436,6 +744,7 @@ impl Default for FecConfig {
             lambda: 0.1,
             burst_window: 20,
             hysteresis: 0.02,
+            min_dwell_time: DEFAULT_MIN_DWELL,
             pid: PidConfig {
                 kp: 1.2,
                 ki: 0.5,
@@ -446,41 +755,253 @@ impl Default for FecConfig {
             kalman_q: 0.001,
             kalman_r: 0.01,
             window_sizes: FecConfig::default_windows(),
+            systematic: true,
+            max_recovery_delay: None,
+            autotune: false,
+            max_window: None,
+            oscillation: OscillationConfig::default(),
+            reactive: false,
         }
     }
 }
 
 impl FecConfig {
-    /// Validate the configuration values.
+    /// Validate the configuration values, reporting every problem joined
+    /// by `"; "`.
     pub fn validate(&self) -> Result<(), String> {
-        if !(0.0..=1.0).contains(&self.lambda) {
-            return Err("lambda must be between 0 and 1".into());
+        let issues = self.issues();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            let messages: Vec<_> = issues.into_iter().map(|i| i.message).collect();
+            Err(messages.join("; "))
+        }
+    }
+
+    /// Every problem with the configuration values, one per field and keyed
+    /// by its path in the `[adaptive_fec]` TOML section. Empty if they are
+    /// valid.
+    pub fn issues(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        if !(self.lambda > 0.0 && self.lambda <= 1.0) {
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.lambda",
+                "lambda must be in (0, 1]",
+            ));
         }
         if self.burst_window == 0 {
-            return Err("burst_window must be > 0".into());
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.burst_window",
+                "burst_window must be > 0",
+            ));
         }
         if !(0.0..1.0).contains(&self.hysteresis) {
-            return Err("hysteresis must be between 0 and 1".into());
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.hysteresis",
+                "hysteresis must be between 0 and 1",
+            ));
         }
-        if self.kalman_enabled && (self.kalman_q <= 0.0 || self.kalman_r <= 0.0) {
-            return Err("kalman_q and kalman_r must be positive".into());
+        if self.kalman_enabled && self.kalman_q <= 0.0 {
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.kalman_q",
+                "kalman_q must be positive",
+            ));
         }
-        Ok(())
+        if self.kalman_enabled && self.kalman_r <= 0.0 {
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.kalman_r",
+                "kalman_r must be positive",
+            ));
+        }
+        if [self.pid.kp, self.pid.ki, self.pid.kd]
+            .iter()
+            .any(|g| !g.is_finite() || *g < 0.0)
+        {
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.pid",
+                "PID gains must be finite and non-negative",
+            ));
+        }
+        let mut empty: Vec<_> = self
+            .window_sizes
+            .iter()
+            .filter(|(mode, window)| **mode != FecMode::Zero && **window == 0)
+            .map(|(mode, _)| *mode)
+            .collect();
+        empty.sort();
+        for mode in empty {
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.modes",
+                format!("window for {:?} must be > 0", mode),
+            ));
+        }
+        if self.max_recovery_delay == Some(Duration::ZERO) {
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.max_recovery_delay_ms",
+                "max_recovery_delay must be > 0",
+            ));
+        }
+        if self.max_window == Some(0) {
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.max_window",
+                "max_window must be > 0",
+            ));
+        }
+        if self.oscillation.max_flips > 0 && self.oscillation.window.is_zero() {
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.oscillation.window_ms",
+                "oscillation window must be > 0",
+            ));
+        }
+        if self.reactive && !self.systematic {
+            issues.push(ConfigIssue::new(
+                "adaptive_fec.reactive",
+                "reactive repairs require systematic mode",
+            ));
+        }
+        issues
+    }
+}
+
+/// Builder for [`FecConfig`] whose setters reject out-of-range values
+/// immediately instead of deferring to [`FecConfig::validate`]. Both apply
+/// the rules of [`FecConfig::issues`].
+#[derive(Clone, Default)]
+pub struct FecConfigBuilder {
+    config: FecConfig,
+}
+
+impl FecConfigBuilder {
+    /// Starts from [`FecConfig::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `self` unless [`FecConfig::issues`] reports a problem with
+    /// `key` of the `[adaptive_fec]` section.
+    fn check(self, key: &str) -> Result<Self, String> {
+        let field = format!("adaptive_fec.{}", key);
+        match self.config.issues().into_iter().find(|i| i.field == field) {
+            Some(issue) => Err(issue.message),
+            None => Ok(self),
+        }
+    }
+
+    pub fn systematic(mut self, systematic: bool) -> Self {
+        self.config.systematic = systematic;
+        self
+    }
+
+    pub fn autotune(mut self, autotune: bool) -> Self {
+        self.config.autotune = autotune;
+        self
+    }
+
+    pub fn min_dwell_time(mut self, dwell: Duration) -> Self {
+        self.config.min_dwell_time = dwell;
+        self
+    }
+
+    pub fn reactive(mut self, reactive: bool) -> Self {
+        self.config.reactive = reactive;
+        self
+    }
+
+    pub fn max_recovery_delay(mut self, delay: Duration) -> Result<Self, String> {
+        self.config.max_recovery_delay = Some(delay);
+        self.check("max_recovery_delay_ms")
+    }
+
+    pub fn max_window(mut self, max_window: usize) -> Result<Self, String> {
+        self.config.max_window = Some(max_window);
+        self.check("max_window")
+    }
+
+    /// Sets the oscillation detector's sensitivity. `max_flips == 0`
+    /// disables it.
+    pub fn oscillation(mut self, oscillation: OscillationConfig) -> Result<Self, String> {
+        self.config.oscillation = oscillation;
+        self.check("oscillation.window_ms")
+    }
+
+    pub fn lambda(mut self, lambda: f32) -> Result<Self, String> {
+        self.config.lambda = lambda;
+        self.check("lambda")
+    }
+
+    pub fn burst_window(mut self, burst_window: usize) -> Result<Self, String> {
+        self.config.burst_window = burst_window;
+        self.check("burst_window")
+    }
+
+    pub fn hysteresis(mut self, hysteresis: f32) -> Result<Self, String> {
+        self.config.hysteresis = hysteresis;
+        self.check("hysteresis")
+    }
+
+    pub fn pid(mut self, kp: f32, ki: f32, kd: f32) -> Result<Self, String> {
+        self.config.pid = PidConfig { kp, ki, kd };
+        self.check("pid")
+    }
+
+    pub fn initial_mode(mut self, mode: FecMode) -> Self {
+        self.config.initial_mode = mode;
+        self
+    }
+
+    /// Enables the Kalman filter with the given process and measurement noise.
+    pub fn kalman(mut self, q: f32, r: f32) -> Result<Self, String> {
+        self.config.kalman_enabled = true;
+        self.config.kalman_q = q;
+        self.config.kalman_r = r;
+        self.check("kalman_q")?.check("kalman_r")
+    }
+
+    /// Sets the source window of a mode. Only [`FecMode::Zero`] may use an
+    /// empty window.
+    pub fn window_size(mut self, mode: FecMode, window: usize) -> Result<Self, String> {
+        self.config.window_sizes.insert(mode, window);
+        self.check("modes")
+    }
+
+    pub fn build(self) -> Result<FecConfig, String> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+impl FecConfig {
+    pub fn builder() -> FecConfigBuilder {
+        FecConfigBuilder::new()
     }
 }
 
 impl AdaptiveFec {
     pub fn new(config: FecConfig, mem_pool: Arc<MemoryPool>) -> Self {
+        Self::with_clock(config, mem_pool, Arc::new(SystemClock))
+    }
+
+    /// Like [`new`](Self::new), but the mode controller, the PID loop and
+    /// the receive and reactive-repair timestamps read time from `clock`,
+    /// e.g. a [`ManualClock`](crate::optimize::ManualClock) in tests.
+    pub fn with_clock(config: FecConfig, mem_pool: Arc<MemoryPool>, clock: Arc<dyn Clock>) -> Self {
         init_gf_tables();
         let mode_mgr = ModeManager::new(
             config.pid.clone(),
             config.hysteresis,
             config.initial_mode,
             config.window_sizes.clone(),
+            config.max_window,
+            config.oscillation,
+            config.min_dwell_time,
+            Arc::clone(&clock),
         );
         let (k, n) = ModeManager::params_for(mode_mgr.current_mode, mode_mgr.current_window);
+        let zero_mode = mode_mgr.current_mode == FecMode::Zero;
+        let deadline = config.max_recovery_delay;
+        let recovery_cache = new_recovery_cache();
 
-        let this = Self {
+        let mut this = Self {
             estimator: Arc::new(Mutex::new(LossEstimator::new(
                 config.lambda,
                 config.burst_window,
@@ -490,18 +1011,40 @@ impl AdaptiveFec {
             ))),
             mode_mgr: Arc::new(Mutex::new(mode_mgr)),
             encoder: EncoderVariant::new(mode_mgr.current_mode, k, n),
-            decoder: DecoderVariant::new(mode_mgr.current_mode, k, Arc::clone(&mem_pool)),
+            decoder: DecoderVariant::new(
+                mode_mgr.current_mode,
+                k,
+                Arc::clone(&mem_pool),
+                &recovery_cache,
+            ),
+            recovery_cache,
             transition_encoder: None,
             transition_decoder: None,
             transition_left: 0,
             mem_pool,
             config,
+            repair_policy: RepairPolicy::Fixed,
+            deadline,
+            block: None,
+            transition_block: None,
+            missing_sources: Vec::new(),
+            events: VecDeque::new(),
+            zero_mode,
+            passthrough_packets: 0,
+            coded_block: Vec::new(),
+            latency: HashMap::new(),
+            high_loss_path: None,
+            last_source_sent: None,
+            clock,
         };
         telemetry!(telemetry::FEC_WINDOW.set(mode_mgr.current_window as i64));
         telemetry!(telemetry::FEC_LAMBDA.set((config.lambda * 1000.0) as i64));
         telemetry!(telemetry::FEC_BURST_WINDOW.set(config.burst_window as i64));
         telemetry!(telemetry::FEC_HYSTERESIS.set((config.hysteresis * 1000.0) as i64));
         telemetry!(telemetry::FEC_KALMAN.set(if config.kalman_enabled { 1 } else { 0 }));
+        if this.config.autotune {
+            this.autotune();
+        }
         this
     }
 
@@ -510,13 +1053,268 @@ impl AdaptiveFec {
         mgr.current_mode
     }
 
+    /// Source window of the active mode, after [`FecConfig::max_window`].
+    pub fn current_window(&self) -> usize {
+        self.mode_mgr.lock().unwrap().current_window
+    }
+
     pub fn is_transitioning(&self) -> bool {
         self.transition_left > 0
     }
 
+    /// Returns `true` if outgoing packets currently bypass FEC entirely,
+    /// i.e. the mode is [`FecMode::Zero`] and no cross-fade is running.
+    pub fn is_passthrough(&self) -> bool {
+        self.zero_mode && self.transition_left == 0
+    }
+
+    /// Number of packets sent through the passthrough path.
+    pub fn passthrough_packets(&self) -> u64 {
+        self.passthrough_packets
+    }
+
+    /// Encode and decode time accumulated while `mode` was active. Lets
+    /// operators spot a mode whose coding cost eats the latency budget.
+    pub fn latency(&self, mode: FecMode) -> FecLatency {
+        self.latency.get(&mode).copied().unwrap_or_default()
+    }
+
+    /// Returns the GF(2^8) kernel path used for encoding and decoding.
+    pub fn hw_path(&self) -> &'static str {
+        super::gf_tables::hw_path().as_str()
+    }
+
+    /// Overrides the GF(2^8) kernel path for testing and debugging. The
+    /// override is process wide and applies to every FEC instance; forcing
+    /// [`HwPath::Scalar`] disables SIMD GF arithmetic.
+    pub fn force_hw_path(&self, path: HwPath) -> Result<(), &'static str> {
+        super::gf_tables::force_hw_path(path)
+    }
+
+    /// Picks the fastest GF(2^8) kernel path for the high-loss modes
+    /// ([`FecMode::Strong`] and [`FecMode::Extreme`]), where coding cost
+    /// dominates, instead of relying on CPU feature detection alone. The
+    /// benchmark runs once per process; later calls reuse its result.
+    pub fn autotune(&mut self) -> HwPath {
+        let path = *AUTOTUNED_PATH.get_or_init(fastest_hw_path);
+        debug!(
+            "FEC autotune selected {} for high-loss modes",
+            path.as_str()
+        );
+        self.high_loss_path = Some(path);
+        path
+    }
+
+    /// Repair packets generated per block in the current mode, 0 while FEC
+    /// is bypassed.
+    pub fn current_repair_count(&self) -> usize {
+        let (k, n) = self.encoder_params();
+        n.saturating_sub(k)
+    }
+
+    /// Repair packets per source packet in the current mode, e.g. `0.15`
+    /// for 15% overhead. 0 while FEC is bypassed.
+    pub fn redundancy_ratio(&self) -> f64 {
+        match self.encoder_params() {
+            (0, _) => 0.0,
+            (k, n) => n.saturating_sub(k) as f64 / k as f64,
+        }
+    }
+
+    fn encoder_params(&self) -> (usize, usize) {
+        if self.is_passthrough() {
+            return (0, 0);
+        }
+        match &self.encoder {
+            EncoderVariant::G8(e) => (e.k, e.n),
+            EncoderVariant::G16(e) => (e.k, e.n),
+        }
+    }
+
+    /// Path chosen by [`autotune`](Self::autotune), `None` if it has not run.
+    pub fn autotuned_path(&self) -> Option<HwPath> {
+        self.high_loss_path
+    }
+
+    /// Kernel path used for coding in the current mode.
+    pub fn kernel_path(&self) -> HwPath {
+        self.kernel_override().unwrap_or_else(hw_path)
+    }
+
+    /// Hands the kernel path of the current mode to the coders. Their rayon
+    /// workers run on other threads, where [`with_hw_path`] has no effect.
+    fn sync_kernel_path(&mut self) {
+        let path = self.kernel_path();
+        self.encoder.set_kernel_path(path);
+        self.decoder.set_kernel_path(path);
+        if let Some(enc) = self.transition_encoder.as_mut() {
+            enc.set_kernel_path(path);
+        }
+        if let Some(dec) = self.transition_decoder.as_mut() {
+            dec.set_kernel_path(path);
+        }
+    }
+
+    fn kernel_override(&self) -> Option<HwPath> {
+        match self.current_mode() {
+            FecMode::Strong | FecMode::Extreme => self.high_loss_path,
+            _ => None,
+        }
+    }
+
+    /// Selects how the repair count of new blocks is derived. Takes effect
+    /// with the next loss report.
+    pub fn set_repair_policy(&mut self, policy: RepairPolicy) {
+        self.repair_policy = policy;
+    }
+
+    pub fn repair_policy(&self) -> RepairPolicy {
+        self.repair_policy
+    }
+
+    /// Enables partial reliability: an incomplete block whose first packet
+    /// arrived more than `max_age` ago is dropped instead of waiting for the
+    /// missing packets. Each dropped block raises [`FecEvent::BlockExpired`].
+    pub fn set_deadline_policy(&mut self, max_age: Duration) {
+        self.deadline = Some(max_age);
+    }
+
+    /// Disables the block deadline again.
+    pub fn clear_deadline_policy(&mut self) {
+        self.deadline = None;
+    }
+
+    pub fn deadline_policy(&self) -> Option<Duration> {
+        self.deadline
+    }
+
+    /// Returns `(hits, misses)` of the recovery matrix cache shared by this
+    /// instance's decoders.
+    pub fn recovery_cache_stats(&self) -> (u64, u64) {
+        self.recovery_cache.lock().unwrap().stats()
+    }
+
+    /// Returns how long the current incomplete block has been pending.
+    pub fn pending_block_age(&self, now: Instant) -> Option<Duration> {
+        self.block.as_ref().map(|b| b.age(now))
+    }
+
+    /// Drops the current block, and the block of a decoder kept for a mode
+    /// cross-fade, if incomplete and older than the configured deadline.
+    /// Returns `true` if a block was dropped.
+    pub fn flush_expired(&mut self, now: Instant) -> bool {
+        let mut dropped = false;
+        if self.block_expired(&self.transition_block, now) {
+            let block = self.transition_block.take().unwrap();
+            self.transition_decoder = None;
+            self.expire_block(block, now);
+            dropped = true;
+        }
+        if self.block_expired(&self.block, now) && !self.decoder.is_decoded() {
+            let block = self.block.take().unwrap();
+            let (mode, window) = {
+                let mgr = self.mode_mgr.lock().unwrap();
+                (mgr.current_mode, mgr.current_window)
+            };
+            let (k, _) = ModeManager::params_for(mode, window);
+            self.decoder =
+                DecoderVariant::new(mode, k, Arc::clone(&self.mem_pool), &self.recovery_cache);
+            self.expire_block(block, now);
+            dropped = true;
+        }
+        dropped
+    }
+
+    fn block_expired(&self, block: &Option<PendingBlock>, now: Instant) -> bool {
+        match (block, self.deadline) {
+            (Some(block), Some(max_age)) => block.age(now) > max_age,
+            _ => false,
+        }
+    }
+
+    fn expire_block(&mut self, block: PendingBlock, now: Instant) {
+        let age = block.age(now);
+        debug!(
+            "FEC block expired after {:?} with {} packets",
+            age, block.received
+        );
+        self.events.push_back(FecEvent::BlockExpired {
+            age,
+            received: block.received,
+        });
+        self.missing_sources.extend(block.missing_sources());
+        telemetry!(telemetry::FEC_BLOCKS_EXPIRED.inc());
+        telemetry!(telemetry::FEC_DECODE_FAILURES.inc());
+    }
+
+    /// Returns and clears the ids of source packets that were lost in blocks
+    /// dropped by the deadline, i.e. could neither be received nor
+    /// recovered. Blocks without any received source packet are not
+    /// reported since their position is unknown.
+    pub fn take_missing_sources(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.missing_sources)
+    }
+
+    /// Pops the next pending receive-side event.
+    pub fn poll_event(&mut self) -> Option<FecEvent> {
+        self.events.pop_front()
+    }
+
+    /// Returns the instance to the state of a freshly created one so it can be
+    /// reused for another connection: the loss estimate, mode, coder windows,
+    /// pending events and counters are discarded. Buffered packets go back to
+    /// the memory pool. The repair and deadline policies and the clock are
+    /// kept.
+    pub fn reset(&mut self) {
+        let repair_policy = self.repair_policy;
+        let deadline = self.deadline;
+        *self = AdaptiveFec::with_clock(
+            self.config.clone(),
+            Arc::clone(&self.mem_pool),
+            Arc::clone(&self.clock),
+        );
+        self.repair_policy = repair_policy;
+        self.deadline = deadline;
+    }
+
+    fn block_params(&self, mode: FecMode, window: usize, loss: f32) -> (usize, usize) {
+        (
+            window,
+            window + self.repair_policy.repairs(mode, window, loss),
+        )
+    }
+
     /// Processes an outgoing packet, adding it to the FEC window and pushing
     /// resulting systematic and repair packets into the outgoing queue.
+    ///
+    /// In [`FecMode::Zero`] the packet is queued as is: no copy is made for
+    /// the encoder, no lock is taken and no repair packets are produced.
     pub fn on_send(&mut self, pkt: Packet, outgoing_queue: &mut VecDeque<Packet>) {
+        if self.is_passthrough() {
+            self.passthrough_packets += 1;
+            outgoing_queue.push_back(pkt);
+            return;
+        }
+        let mode = self.current_mode();
+        let start = Instant::now();
+        self.sync_kernel_path();
+        match self.kernel_override() {
+            Some(path) => with_hw_path(path, || self.encode_packet(pkt, outgoing_queue)),
+            None => self.encode_packet(pkt, outgoing_queue),
+        }
+        let ns = start.elapsed().as_nanos() as u64;
+        let stats = self.latency.entry(mode).or_default();
+        stats.encode_time_ns += ns;
+        stats.encode_calls += 1;
+        telemetry!(crate::telemetry::FEC_ENCODE_TIME_NS.inc_by(ns));
+    }
+
+    fn encode_packet(&mut self, pkt: Packet, outgoing_queue: &mut VecDeque<Packet>) {
+        if !self.config.systematic {
+            self.coded_block.push(pkt);
+            self.emit_coded_block(outgoing_queue);
+            return;
+        }
         if let Some(enc) = self.transition_encoder.as_mut() {
             enc.add_source_packet(pkt.clone_for_encoder(&self.mem_pool));
         }
@@ -526,19 +1324,80 @@ impl AdaptiveFec {
         outgoing_queue.push_back(pkt);
         telemetry!(crate::telemetry::ENCODED_PACKETS.inc());
 
-        if self.transition_left > ModeManager::CROSS_FADE_LEN / 2 {
-            if let Some(enc) = self.transition_encoder.as_mut() {
-                Self::emit_repairs(enc, &self.mem_pool, outgoing_queue);
+        if self.config.reactive {
+            // Repairs wait for `report_receiver_loss`.
+            self.last_source_sent = Some(self.clock.now());
+        } else {
+            if self.transition_left > ModeManager::CROSS_FADE_LEN / 2 {
+                if let Some(enc) = self.transition_encoder.as_mut() {
+                    Self::emit_repairs(enc, &self.mem_pool, outgoing_queue);
+                }
             }
+            Self::emit_repairs(&mut self.encoder, &self.mem_pool, outgoing_queue);
         }
 
-        Self::emit_repairs(&mut self.encoder, &self.mem_pool, outgoing_queue);
-
         if self.transition_left > 0 {
             self.transition_left -= 1;
             if self.transition_left == ModeManager::CROSS_FADE_LEN / 2 {
                 self.transition_encoder = None;
                 self.transition_decoder = None;
+                if let Some(block) = self.transition_block.take() {
+                    let now = self.clock.now();
+                    self.expire_block(block, now);
+                }
+            }
+        }
+    }
+
+    /// Encodes the sources buffered for a coded block even if fewer than `k`
+    /// were sent, filling the block up with all-zero sources the receiver
+    /// drops after decoding. Without it the tail of a flight would wait for
+    /// sources that never come. Does nothing in systematic mode, where each
+    /// source goes out as it is sent. Returns the number of packets queued.
+    pub fn flush(&mut self, outgoing_queue: &mut VecDeque<Packet>) -> usize {
+        if self.config.systematic || self.coded_block.is_empty() {
+            return 0;
+        }
+        let k = match &self.encoder {
+            EncoderVariant::G8(e) => e.k,
+            EncoderVariant::G16(e) => e.k,
+        };
+        let len = self.coded_block[0].len;
+        let mut id = self.coded_block[self.coded_block.len() - 1].id;
+        while self.coded_block.len() < k {
+            id += 1;
+            self.coded_block
+                .push(Packet::padding(id, len, &self.mem_pool));
+        }
+        let queued = outgoing_queue.len();
+        self.emit_coded_block(outgoing_queue);
+        outgoing_queue.len() - queued
+    }
+
+    /// Emits coded packets for the buffered block once `k` sources are
+    /// available. With GF(2^8) at most `256 - k` coded packets exist, so
+    /// large windows may get fewer than `n`.
+    fn emit_coded_block(&mut self, outgoing_queue: &mut VecDeque<Packet>) {
+        let (k, n) = match &self.encoder {
+            EncoderVariant::G8(e) => (e.k, e.n),
+            EncoderVariant::G16(e) => (e.k, e.n),
+        };
+        if k == 0 || self.coded_block.len() < k {
+            return;
+        }
+        let mode = self.current_mode();
+        let count = match self.encoder {
+            EncoderVariant::G8(_) => n.min(256 - k.min(256)),
+            EncoderVariant::G16(_) => n,
+        };
+        let mut encoder = EncoderVariant::new(mode, k, n);
+        for pkt in self.coded_block.drain(..k) {
+            encoder.add_source_packet(pkt);
+        }
+        for i in 0..count {
+            if let Some(coded) = encoder.generate_repair_packet(i, &self.mem_pool) {
+                outgoing_queue.push_back(coded);
+                telemetry!(crate::telemetry::ENCODED_PACKETS.inc());
             }
         }
     }
@@ -561,9 +1420,130 @@ impl AdaptiveFec {
         }
     }
 
+    /// Feedback entry for [`FecConfig::reactive`]: the receiver reported
+    /// `lost` missing packets. Up to `lost` repairs for the current source
+    /// window are queued, at most the active mode's repair count. Nothing is
+    /// sent outside reactive mode, before a full window was sent, or once the
+    /// newest source is older than [`FecConfig::max_recovery_delay`] and the
+    /// receiver has given up on the block. Returns the number of repairs
+    /// queued.
+    pub fn report_receiver_loss(
+        &mut self,
+        lost: usize,
+        now: Instant,
+        outgoing_queue: &mut VecDeque<Packet>,
+    ) -> usize {
+        if !self.config.reactive || lost == 0 || self.is_passthrough() {
+            return 0;
+        }
+        let Some(sent) = self.last_source_sent else {
+            return 0;
+        };
+        if let Some(max_delay) = self.config.max_recovery_delay {
+            if now.saturating_duration_since(sent) > max_delay {
+                debug!("loss report arrived after the recovery deadline");
+                return 0;
+            }
+        }
+        if !self.encoder.is_window_full() {
+            debug!("loss report arrived before a full source window was sent");
+            return 0;
+        }
+        let (k, n) = match &self.encoder {
+            EncoderVariant::G8(e) => (e.k, e.n),
+            EncoderVariant::G16(e) => (e.k, e.n),
+        };
+        let mut queued = 0;
+        for i in 0..lost.min(n.saturating_sub(k)) {
+            if let Some(repair) = self.encoder.generate_repair_packet(i, &self.mem_pool) {
+                outgoing_queue.push_back(repair);
+                telemetry!(crate::telemetry::ENCODED_PACKETS.inc());
+                queued += 1;
+            }
+        }
+        queued
+    }
+
+    /// Returns `true` if `packets` are enough to recover a block of
+    /// `source_count` source packets with the current mode's code, without
+    /// decoding anything.
+    ///
+    /// The Cauchy codes used in every mode are MDS, so any `source_count`
+    /// distinct packets suffice: source packets count once per block
+    /// position, repair packets once per id and only if they carry a full
+    /// coefficient row for the mode's field (one byte per source packet in
+    /// GF(2^8), two in GF(2^16)).
+    pub fn can_decode(&self, packets: &[Packet], source_count: usize) -> bool {
+        if source_count == 0 {
+            return true;
+        }
+        let symbol = if self.current_mode() == FecMode::Extreme {
+            2
+        } else {
+            1
+        };
+        let mut sources = vec![false; source_count];
+        let mut repairs = std::collections::HashSet::new();
+        let mut rank = 0;
+        for pkt in packets {
+            let fresh = if pkt.is_systematic {
+                let idx = (pkt.id % source_count as u64) as usize;
+                !std::mem::replace(&mut sources[idx], true)
+            } else {
+                pkt.coefficients.is_some()
+                    && pkt.coeff_len >= symbol * source_count
+                    && repairs.insert(pkt.id)
+            };
+            if fresh {
+                rank += 1;
+                if rank == source_count {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
     /// Processes an incoming packet, adding it to the decoder and attempting recovery.
     /// Returns a list of recovered packets if decoding is successful.
     pub fn on_receive(&mut self, pkt: Packet) -> Result<Vec<Packet>, &'static str> {
+        let now = self.clock.now();
+        self.on_receive_at(pkt, now)
+    }
+
+    /// Same as [`on_receive`](Self::on_receive) but with an explicit receive
+    /// timestamp, used to evaluate the block deadline.
+    pub fn on_receive_at(
+        &mut self,
+        pkt: Packet,
+        now: Instant,
+    ) -> Result<Vec<Packet>, &'static str> {
+        if self.is_passthrough() {
+            // No blocks exist in zero mode; repairs of a peer still coding
+            // are of no use without a decoder.
+            return Ok(if pkt.is_systematic {
+                vec![pkt]
+            } else {
+                Vec::new()
+            });
+        }
+        self.flush_expired(now);
+        let mode = self.current_mode();
+        let start = Instant::now();
+        self.sync_kernel_path();
+        let res = match self.kernel_override() {
+            Some(path) => with_hw_path(path, || self.decode_packet(pkt, now)),
+            None => self.decode_packet(pkt, now),
+        };
+        let ns = start.elapsed().as_nanos() as u64;
+        let stats = self.latency.entry(mode).or_default();
+        stats.decode_time_ns += ns;
+        stats.decode_calls += 1;
+        telemetry!(crate::telemetry::FEC_DECODE_TIME_NS.inc_by(ns));
+        res
+    }
+
+    fn decode_packet(&mut self, pkt: Packet, now: Instant) -> Result<Vec<Packet>, &'static str> {
         let mut recovered = Vec::new();
         let was_decoded = self.decoder.is_decoded();
         let pkt_clone = if self.transition_left > ModeManager::CROSS_FADE_LEN / 2 {
@@ -572,14 +1552,31 @@ impl AdaptiveFec {
             None
         };
 
+        let source_id = pkt.is_systematic.then_some(pkt.id);
         match self.decoder.add_packet(pkt) {
             Ok(is_now_decoded) => {
                 if !was_decoded && is_now_decoded {
                     recovered.extend(self.decoder.get_decoded_packets());
+                    if !self.config.systematic {
+                        // Sources the sender added in `flush`.
+                        recovered.retain(|p| p.payload().iter().any(|&b| b != 0));
+                    }
                     telemetry!(crate::telemetry::DECODED_PACKETS.inc_by(recovered.len() as u64));
+                    telemetry!(crate::telemetry::FEC_RECOVERED.inc());
                 }
+                let k = self.decoder.k();
+                if is_now_decoded || k == 0 {
+                    self.block = None;
+                } else {
+                    let block = self.block.get_or_insert_with(|| PendingBlock::new(k, now));
+                    block.received += 1;
+                    block.sources.extend(source_id);
+                }
+            }
+            Err(e) => {
+                telemetry!(crate::telemetry::FEC_DECODE_FAILURES.inc());
+                return Err(e);
             }
-            Err(e) => return Err(e),
         }
 
         if let (Some(trans_dec), Some(clone_pkt)) = (self.transition_decoder.as_mut(), pkt_clone) {
@@ -589,6 +1586,8 @@ impl AdaptiveFec {
                     if !was_dec && now {
                         recovered.extend(trans_dec.get_decoded_packets());
                         telemetry!(crate::telemetry::DECODED_PACKETS.inc_by(recovered.len() as u64));
+                        telemetry!(crate::telemetry::FEC_RECOVERED.inc());
+                        self.transition_block = None;
                     }
                 }
                 Err(e) => return Err(e),
@@ -608,7 +1607,9 @@ impl AdaptiveFec {
 
         let mut mode_mgr = self.mode_mgr.lock().unwrap();
         let (new_mode, new_window, prev) = mode_mgr.update(estimated_loss);
-        let (k, n) = ModeManager::params_for(new_mode, new_window);
+        drop(mode_mgr);
+        let (k, n) = self.block_params(new_mode, new_window, estimated_loss);
+        self.zero_mode = new_mode == FecMode::Zero;
 
         if let Some((old_mode, old_window)) = prev {
             let (ok, _) = ModeManager::params_for(old_mode, old_window);
@@ -620,14 +1621,168 @@ impl AdaptiveFec {
             ));
             self.transition_decoder = Some(std::mem::replace(
                 &mut self.decoder,
-                DecoderVariant::new(new_mode, k, Arc::clone(&self.mem_pool)),
+                DecoderVariant::new(
+                    new_mode,
+                    k,
+                    Arc::clone(&self.mem_pool),
+                    &self.recovery_cache,
+                ),
             ));
+            // The pending block stays with the decoder collecting it.
+            self.transition_block = self.block.take();
             self.transition_left = ModeManager::CROSS_FADE_LEN;
         } else {
             self.encoder = EncoderVariant::new(new_mode, k, n);
-            self.decoder = DecoderVariant::new(new_mode, k, Arc::clone(&self.mem_pool));
+            self.decoder = DecoderVariant::new(
+                new_mode,
+                k,
+                Arc::clone(&self.mem_pool),
+                &self.recovery_cache,
+            );
+        }
+    }
+}
+
+// --- Kernel Autotuning ---
+
+static AUTOTUNED_PATH: OnceLock<HwPath> = OnceLock::new();
+
+// Benchmark block: MTU sized packets, small enough to finish in a few
+// milliseconds even on the scalar path.
+const AUTOTUNE_K: usize = 16;
+const AUTOTUNE_REPAIRS: usize = 8;
+const AUTOTUNE_LEN: usize = 1200;
+const AUTOTUNE_ROUNDS: usize = 3;
+
+/// Times encoding `AUTOTUNE_REPAIRS` repairs and decoding a full block on
+/// every supported path and returns the fastest. Both are the same
+/// region multiply-adds the codec runs.
+fn fastest_hw_path() -> HwPath {
+    init_gf_tables();
+    let sources: Vec<Vec<u8>> = (0..AUTOTUNE_K)
+        .map(|i| (0..AUTOTUNE_LEN).map(|j| (i * 31 + j) as u8).collect())
+        .collect();
+    // One output row per repair, then one per decoded source.
+    let mut out = vec![vec![0u8; AUTOTUNE_LEN]; AUTOTUNE_REPAIRS + AUTOTUNE_K];
+    let candidates = [
+        HwPath::Scalar,
+        HwPath::Sse2,
+        HwPath::Avx2,
+        HwPath::Avx512,
+        HwPath::Neon,
+    ];
+    candidates
+        .into_iter()
+        .filter(|p| p.is_supported())
+        .map(|path| {
+            let start = Instant::now();
+            for round in 0..AUTOTUNE_ROUNDS {
+                for (r, dst) in out.iter_mut().enumerate() {
+                    for (j, src) in sources.iter().enumerate() {
+                        let c = (1 + r * AUTOTUNE_K + j + round) as u8 | 1;
+                        gf_mul_add_region_with(path, c, src, dst);
+                    }
+                }
+            }
+            (start.elapsed(), path)
+        })
+        .min_by_key(|(elapsed, _)| *elapsed)
+        .map(|(_, path)| path)
+        .unwrap_or(HwPath::Scalar)
+}
+
+// --- Interop Test Vectors ---
+
+/// Payload lengths covered by [`export_test_vectors`]. All are even so the
+/// GF(2^16) code of [`FecMode::Extreme`] can use them as well.
+pub const TEST_VECTOR_LENGTHS: [usize; 3] = [16, 64, 256];
+
+/// A canonical encoding of one block, used to check other implementations
+/// of the wire format against this one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVector {
+    pub seed: u64,
+    pub mode: FecMode,
+    pub k: usize,
+    pub n: usize,
+    /// Source payloads, source `i` is sent with packet id `i`.
+    pub sources: Vec<Vec<u8>>,
+    /// Expected [`Packet::to_raw`] output for packet ids `0..n`: the `k`
+    /// systematic packets followed by the `n - k` repair packets.
+    pub packets: Vec<Vec<u8>>,
+}
+
+/// SplitMix64, chosen for vector generation because it is trivial to port
+/// and, unlike `rand`'s generators, guaranteed to never change.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Produces test vectors for `mode` with its default window, one per entry
+/// of [`TEST_VECTOR_LENGTHS`]. Source bytes are the little-endian output of
+/// SplitMix64 seeded with `seed`, continuing across sources and vectors.
+/// [`FecMode::Zero`] has no block structure and yields no vectors.
+pub fn export_test_vectors(seed: u64, mode: FecMode) -> Vec<TestVector> {
+    init_gf_tables();
+    let window = *FecConfig::default_windows().get(&mode).unwrap_or(&0);
+    let (k, n) = ModeManager::params_for(mode, window);
+    if k == 0 {
+        return Vec::new();
+    }
+
+    let mut state = seed;
+    let mut vectors = Vec::with_capacity(TEST_VECTOR_LENGTHS.len());
+    for &len in TEST_VECTOR_LENGTHS.iter() {
+        let pool = Arc::new(MemoryPool::new(2 * n + 2, len.max(2 * k)));
+        let mut encoder = EncoderVariant::new(mode, k, n);
+        let mut sources = Vec::with_capacity(k);
+        let mut packets = Vec::with_capacity(n);
+        let mut raw = vec![0u8; len + 3 + 2 * k];
+
+        for id in 0..k as u64 {
+            let mut payload = Vec::with_capacity(len + 8);
+            while payload.len() < len {
+                payload.extend_from_slice(&splitmix64(&mut state).to_le_bytes());
+            }
+            payload.truncate(len);
+
+            let mut block = pool.alloc();
+            block[..len].copy_from_slice(&payload);
+            let pkt = Packet {
+                id,
+                data: Some(block),
+                len,
+                is_systematic: true,
+                coefficients: None,
+                coeff_len: 0,
+                mem_pool: Arc::clone(&pool),
+            };
+            let written = pkt.to_raw(&mut raw).expect("buffer sized for packet");
+            packets.push(raw[..written].to_vec());
+            encoder.add_source_packet(pkt);
+            sources.push(payload);
+        }
+        for i in 0..n - k {
+            if let Some(repair) = encoder.generate_repair_packet(i, &pool) {
+                let written = repair.to_raw(&mut raw).expect("buffer sized for packet");
+                packets.push(raw[..written].to_vec());
+            }
         }
+
+        vectors.push(TestVector {
+            seed,
+            mode,
+            k,
+            n,
+            sources,
+            packets,
+        });
     }
+    vectors
 }
 
 // [Die Tests wurden oben nicht verändert und bleiben wie im Input – ebenfalls konfliktfrei!]
@@ -656,6 +1811,136 @@ mod tests {
         }
     }
 
+    fn oscillating_mode_manager(oscillation: OscillationConfig) -> ModeManager {
+        let mut mgr = ModeManager::new(
+            PidConfig {
+                kp: 1.2,
+                ki: 0.5,
+                kd: 0.1,
+            },
+            0.02,
+            FecMode::Normal,
+            FecConfig::default_windows(),
+            None,
+            oscillation,
+            Duration::ZERO,
+            Arc::new(SystemClock),
+        );
+        mgr
+    }
+
+    #[test]
+    fn pid_output_matches_analytic_terms() {
+        use crate::optimize::ManualClock;
+
+        let clock = Arc::new(ManualClock::new());
+        let (kp, ki, kd) = (1.2, 0.5, 0.1);
+        let mut pid = PidController::new(PidConfig { kp, ki, kd }, clock.clone());
+        // No time has passed: no output and no state change.
+        assert_eq!(pid.update(0.3, 0.15), 0.0);
+
+        let setpoint = 0.15;
+        let (mut integral, mut prev) = (0.0f32, 0.0f32);
+        for (dt_ms, loss) in [(100u64, 0.30f32), (200, 0.25), (50, 0.10), (400, 0.05)] {
+            clock.advance(Duration::from_millis(dt_ms));
+            let dt = dt_ms as f32 / 1000.0;
+            let error = setpoint - loss;
+            integral += error * dt;
+            let expected = kp * error + ki * integral + kd * (error - prev) / dt;
+            prev = error;
+            let out = pid.update(loss, setpoint);
+            assert!((out - expected).abs() < 1e-4, "{} != {}", out, expected);
+        }
+    }
+
+    #[test]
+    fn dwell_time_follows_injected_clock() {
+        use crate::optimize::ManualClock;
+
+        let clock = Arc::new(ManualClock::new());
+        let mut mgr = ModeManager::new(
+            PidConfig {
+                kp: 1.2,
+                ki: 0.5,
+                kd: 0.1,
+            },
+            0.02,
+            FecMode::Normal,
+            FecConfig::default_windows(),
+            None,
+            OscillationConfig::default(),
+            Duration::from_millis(200),
+            clock.clone(),
+        );
+        assert_eq!(mgr.update(0.40).0, FecMode::Normal);
+        clock.advance(Duration::from_millis(199));
+        assert_eq!(mgr.update(0.40).0, FecMode::Normal);
+
+        clock.advance(Duration::from_millis(1));
+        let (mode, _, _) = mgr.update(0.40);
+        assert_ne!(mode, FecMode::Normal);
+        // The switch restarts the dwell period.
+        clock.advance(Duration::from_millis(150));
+        assert_eq!(mgr.update(0.0).0, mode);
+    }
+
+    /// Feeds a loss signal alternating between 20% and 40% and returns the
+    /// number of mode changes.
+    fn count_flips(mgr: &mut ModeManager, updates: usize) -> usize {
+        let mut flips = 0;
+        let mut mode = mgr.current_mode;
+        for i in 0..updates {
+            std::thread::sleep(Duration::from_millis(1));
+            let loss = if i % 2 == 0 { 0.2 } else { 0.4 };
+            let (new_mode, _, _) = mgr.update(loss);
+            if new_mode != mode {
+                flips += 1;
+                mode = new_mode;
+            }
+        }
+        flips
+    }
+
+    #[test]
+    fn oscillation_freezes_mode() {
+        let disabled = OscillationConfig {
+            max_flips: 0,
+            ..OscillationConfig::default()
+        };
+        let flips = count_flips(&mut oscillating_mode_manager(disabled), 20);
+        assert!(flips >= 10, "signal should oscillate, got {} flips", flips);
+
+        let detector = OscillationConfig {
+            max_flips: 3,
+            window: Duration::from_secs(10),
+            cooldown: Duration::from_secs(3600),
+        };
+        let mut mgr = oscillating_mode_manager(detector);
+        let flips = count_flips(&mut mgr, 20);
+        assert_eq!(flips, 3, "mode should stop changing once frozen");
+        assert!(mgr.frozen_until.is_some());
+    }
+
+    #[test]
+    fn loss_spike_overrides_frozen_mode() {
+        let detector = OscillationConfig {
+            max_flips: 3,
+            window: Duration::from_secs(10),
+            cooldown: Duration::from_secs(3600),
+        };
+        let mut mgr = oscillating_mode_manager(detector);
+        count_flips(&mut mgr, 20);
+        assert!(mgr.frozen_until.is_some());
+        let frozen = mgr.current_mode;
+        assert_ne!(frozen, FecMode::Extreme);
+
+        let (mode, _, prev) = mgr.update(0.9);
+        assert_eq!(mode, FecMode::Extreme);
+        assert_eq!(prev.map(|p| p.0), Some(frozen));
+        // Ordinary loss still leaves the mode alone during the freeze.
+        assert_eq!(mgr.update(0.0).0, FecMode::Extreme);
+    }
+
     #[test]
     fn gaussian_path_decodes() {
         init_gf_tables();
@@ -734,6 +2019,7 @@ mod tests {
             lambda: 0.01,
             burst_window: 50,
             hysteresis: 0.02,
+            min_dwell_time: DEFAULT_MIN_DWELL,
             pid: PidConfig {
                 kp: 1.0,
                 ki: 0.0,
@@ -744,6 +2030,12 @@ mod tests {
             kalman_q: 0.001,
             kalman_r: 0.01,
             window_sizes: FecConfig::default_windows(),
+            systematic: true,
+            max_recovery_delay: None,
+            autotune: false,
+            max_window: None,
+            oscillation: OscillationConfig::default(),
+            reactive: false,
         };
         let mut fec = AdaptiveFec::new(cfg, Arc::clone(&pool));
         fec.report_loss(18, 20);
@@ -758,6 +2050,7 @@ mod tests {
             lambda: 0.01,
             burst_window: 50,
             hysteresis: 0.02,
+            min_dwell_time: DEFAULT_MIN_DWELL,
             pid: PidConfig {
                 kp: 1.0,
                 ki: 0.0,
@@ -768,6 +2061,12 @@ mod tests {
             kalman_q: 0.001,
             kalman_r: 0.01,
             window_sizes: FecConfig::default_windows(),
+            systematic: true,
+            max_recovery_delay: None,
+            autotune: false,
+            max_window: None,
+            oscillation: OscillationConfig::default(),
+            reactive: false,
         };
         let mut fec = AdaptiveFec::new(cfg, Arc::clone(&pool));
         fec.report_loss(10, 20);
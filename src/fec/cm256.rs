@@ -0,0 +1,276 @@
+//! Block Reed-Solomon erasure code in the style of CM256.
+//!
+//! Unlike the sliding-window coder, a [`Cm256Scheme`] works on fixed blocks
+//! of `k` equally sized data shards and produces `m` parity shards; any `k`
+//! of the `k + m` shards restore the data. The `leopard` feature backs it
+//! with `leopard-codec`. Without the feature, or when the kernel path is
+//! [`HwPath::Scalar`], a Cauchy Reed-Solomon code over the GF(2^8) tables
+//! of this module is used instead.
+
+use super::gf_tables::{gf_inv, gf_mul, gf_mul_add_region_with, hw_path, HwPath};
+
+/// Largest number of data plus parity shards in one block.
+pub const CM256_MAX_SHARDS: usize = 256;
+
+/// Implementation behind a [`Cm256Scheme`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cm256Backend {
+    /// `leopard-codec`, available with the `leopard` feature. Shards must
+    /// be a multiple of 64 bytes long.
+    Leopard,
+    /// Cauchy Reed-Solomon on the GF(2^8) kernels.
+    Scalar,
+}
+
+impl Cm256Backend {
+    /// Backend for the given kernel path: leopard when it is compiled in and
+    /// SIMD kernels are in use, the scalar code otherwise.
+    pub fn for_path(path: HwPath) -> Self {
+        if Cm256Backend::Leopard.is_available() && path != HwPath::Scalar {
+            Cm256Backend::Leopard
+        } else {
+            Cm256Backend::Scalar
+        }
+    }
+
+    pub fn is_available(self) -> bool {
+        match self {
+            Cm256Backend::Leopard => cfg!(feature = "leopard"),
+            Cm256Backend::Scalar => true,
+        }
+    }
+}
+
+/// Systematic `(k, m)` block erasure code.
+#[derive(Debug, Clone)]
+pub struct Cm256Scheme {
+    data_shards: usize,
+    parity_shards: usize,
+    backend: Cm256Backend,
+}
+
+impl Cm256Scheme {
+    /// Creates a scheme with the backend chosen for the current [`hw_path`].
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self, String> {
+        Self::with_backend(
+            data_shards,
+            parity_shards,
+            Cm256Backend::for_path(hw_path()),
+        )
+    }
+
+    pub fn with_backend(
+        data_shards: usize,
+        parity_shards: usize,
+        backend: Cm256Backend,
+    ) -> Result<Self, String> {
+        if data_shards == 0 {
+            return Err("CM256 needs at least one data shard".to_string());
+        }
+        if data_shards + parity_shards > CM256_MAX_SHARDS {
+            return Err(format!(
+                "CM256 supports at most {} shards, got {}",
+                CM256_MAX_SHARDS,
+                data_shards + parity_shards
+            ));
+        }
+        if !backend.is_available() {
+            return Err(format!("CM256 backend {:?} is not compiled in", backend));
+        }
+        Ok(Self {
+            data_shards,
+            parity_shards,
+            backend,
+        })
+    }
+
+    pub fn backend(&self) -> Cm256Backend {
+        self.backend
+    }
+
+    pub fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    pub fn parity_shards(&self) -> usize {
+        self.parity_shards
+    }
+
+    /// Computes the parity shards of `data`, which must hold `k` shards of
+    /// the same length.
+    pub fn encode(&self, data: &[&[u8]]) -> Result<Vec<Vec<u8>>, String> {
+        if data.len() != self.data_shards {
+            return Err(format!(
+                "expected {} data shards, got {}",
+                self.data_shards,
+                data.len()
+            ));
+        }
+        let len = data[0].len();
+        if data.iter().any(|s| s.len() != len) {
+            return Err("data shards differ in length".to_string());
+        }
+        if self.parity_shards == 0 {
+            return Ok(Vec::new());
+        }
+        match self.backend {
+            Cm256Backend::Leopard => self.encode_leopard(data, len),
+            Cm256Backend::Scalar => Ok(self.encode_scalar(data, len)),
+        }
+    }
+
+    /// Restores the missing data shards in place. `shards` holds the `k`
+    /// data shards followed by the `m` parity shards, `None` marking an
+    /// erasure. Missing parity shards are not rebuilt.
+    pub fn decode(&self, shards: &mut [Option<Vec<u8>>]) -> Result<(), String> {
+        let total = self.data_shards + self.parity_shards;
+        if shards.len() != total {
+            return Err(format!("expected {} shards, got {}", total, shards.len()));
+        }
+        if shards[..self.data_shards].iter().all(Option::is_some) {
+            return Ok(());
+        }
+        let present = shards.iter().filter(|s| s.is_some()).count();
+        if present < self.data_shards {
+            return Err(format!(
+                "{} shards present, {} needed",
+                present, self.data_shards
+            ));
+        }
+        let len = shards.iter().flatten().next().map_or(0, Vec::len);
+        if shards.iter().flatten().any(|s| s.len() != len) {
+            return Err("shards differ in length".to_string());
+        }
+        match self.backend {
+            Cm256Backend::Leopard => self.decode_leopard(shards),
+            Cm256Backend::Scalar => self.decode_scalar(shards, len),
+        }
+    }
+
+    /// Coefficient of data shard `j` in parity shard `i`: the Cauchy matrix
+    /// `1 / (x_i + y_j)` with `x_i = k + i` and `y_j = j`, so every square
+    /// submatrix of the extended generator is invertible.
+    fn coefficient(&self, i: usize, j: usize) -> u8 {
+        gf_inv(((self.data_shards + i) ^ j) as u8)
+    }
+
+    /// Generator row of shard `index`: a unit row for data shards, the
+    /// Cauchy row for parity shards.
+    fn generator_row(&self, index: usize) -> Vec<u8> {
+        let k = self.data_shards;
+        if index < k {
+            let mut row = vec![0u8; k];
+            row[index] = 1;
+            row
+        } else {
+            (0..k).map(|j| self.coefficient(index - k, j)).collect()
+        }
+    }
+
+    fn encode_scalar(&self, data: &[&[u8]], len: usize) -> Vec<Vec<u8>> {
+        let path = hw_path();
+        (0..self.parity_shards)
+            .map(|i| {
+                let mut parity = vec![0u8; len];
+                for (j, shard) in data.iter().enumerate() {
+                    gf_mul_add_region_with(path, self.coefficient(i, j), shard, &mut parity);
+                }
+                parity
+            })
+            .collect()
+    }
+
+    fn decode_scalar(&self, shards: &mut [Option<Vec<u8>>], len: usize) -> Result<(), String> {
+        let k = self.data_shards;
+        let rows: Vec<usize> = (0..shards.len())
+            .filter(|&i| shards[i].is_some())
+            .take(k)
+            .collect();
+        let inverse = invert(rows.iter().map(|&r| self.generator_row(r)).collect())
+            .ok_or_else(|| "CM256 decode matrix is singular".to_string())?;
+        let path = hw_path();
+        for missing in 0..k {
+            if shards[missing].is_some() {
+                continue;
+            }
+            let mut out = vec![0u8; len];
+            for (&row, &coef) in rows.iter().zip(&inverse[missing]) {
+                if let Some(src) = &shards[row] {
+                    gf_mul_add_region_with(path, coef, src, &mut out);
+                }
+            }
+            shards[missing] = Some(out);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "leopard")]
+    fn encode_leopard(&self, data: &[&[u8]], len: usize) -> Result<Vec<Vec<u8>>, String> {
+        let mut shards: Vec<Vec<u8>> = data.iter().map(|s| s.to_vec()).collect();
+        shards.resize(self.data_shards + self.parity_shards, vec![0u8; len]);
+        leopard_codec::encode(&mut shards, self.data_shards)
+            .map_err(|e| format!("leopard encode failed: {}", e))?;
+        Ok(shards.split_off(self.data_shards))
+    }
+
+    #[cfg(not(feature = "leopard"))]
+    fn encode_leopard(&self, _data: &[&[u8]], _len: usize) -> Result<Vec<Vec<u8>>, String> {
+        Err("CM256 leopard backend is not compiled in".to_string())
+    }
+
+    #[cfg(feature = "leopard")]
+    fn decode_leopard(&self, shards: &mut [Option<Vec<u8>>]) -> Result<(), String> {
+        // leopard marks erasures with empty shards.
+        let mut work: Vec<Vec<u8>> = shards
+            .iter()
+            .map(|s| s.clone().unwrap_or_default())
+            .collect();
+        leopard_codec::reconstruct(&mut work, self.data_shards)
+            .map_err(|e| format!("leopard reconstruct failed: {}", e))?;
+        for (slot, shard) in shards.iter_mut().zip(work).take(self.data_shards) {
+            if slot.is_none() {
+                *slot = Some(shard);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "leopard"))]
+    fn decode_leopard(&self, _shards: &mut [Option<Vec<u8>>]) -> Result<(), String> {
+        Err("CM256 leopard backend is not compiled in".to_string())
+    }
+}
+
+/// Inverts a square matrix over GF(2^8) by Gauss-Jordan elimination.
+fn invert(mut m: Vec<Vec<u8>>) -> Option<Vec<Vec<u8>>> {
+    let n = m.len();
+    let mut inv: Vec<Vec<u8>> = (0..n)
+        .map(|i| {
+            let mut row = vec![0u8; n];
+            row[i] = 1;
+            row
+        })
+        .collect();
+    for col in 0..n {
+        let pivot = (col..n).find(|&r| m[r][col] != 0)?;
+        m.swap(col, pivot);
+        inv.swap(col, pivot);
+        let scale = gf_inv(m[col][col]);
+        for c in 0..n {
+            m[col][c] = gf_mul(m[col][c], scale);
+            inv[col][c] = gf_mul(inv[col][c], scale);
+        }
+        let (pivot_row, pivot_inv) = (m[col].clone(), inv[col].clone());
+        for r in 0..n {
+            let factor = m[r][col];
+            if r == col || factor == 0 {
+                continue;
+            }
+            for c in 0..n {
+                m[r][c] ^= gf_mul(factor, pivot_row[c]);
+                inv[r][c] ^= gf_mul(factor, pivot_inv[c]);
+            }
+        }
+    }
+    Some(inv)
+}
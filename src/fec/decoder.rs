@@ -1,12 +1,23 @@
 // --- Encoder & Decoder ---
 
 /// Generates repair packets from source packets using a Cauchy matrix for coefficients.
+///
+/// Coefficients depend only on `k` and the repair index, never on an RNG, so
+/// identical source windows always produce byte-identical repair packets.
 pub struct Encoder {
     k: usize, // Number of source packets
     n: usize, // Total packets (source + repair)
     source_window: VecDeque<Packet>,
+    // GF(2^8) kernel path, handed to the rayon workers explicitly.
+    path: super::gf_tables::HwPath,
 }
 
+/// GF(2^16) counterpart of [`Encoder`], used in [`FecMode::Extreme`].
+///
+/// Payloads are coded as big-endian 16-bit symbols. An odd trailing byte
+/// forms a symbol with a zero low half, so repair packets of odd-length
+/// sources are one byte longer and recovered packets come out padded to an
+/// even length.
 pub struct Encoder16 {
     k: usize,
     n: usize,
@@ -38,6 +49,7 @@ impl Encoder16 {
             return None;
         }
         let packet_len = self.source_window[0].len;
+        let padded_len = packet_len + packet_len % 2;
         let mut repair_data = mem_pool.alloc();
         repair_data.iter_mut().for_each(|b| *b = 0);
 
@@ -49,13 +61,15 @@ impl Encoder16 {
             }
             let data = &src.data[..packet_len];
             let mut j = 0;
-            while j + 1 < packet_len {
+            while j < packet_len {
                 if j + 64 < packet_len {
                     unsafe {
                         prefetch_data(data.as_ptr().add(j + 64));
                     }
                 }
-                let s = u16::from_be_bytes([data[j], data[j + 1]]);
+                // The last symbol of an odd-length payload is zero padded.
+                let lo = if j + 1 < packet_len { data[j + 1] } else { 0 };
+                let s = u16::from_be_bytes([data[j], lo]);
                 let r = u16::from_be_bytes([repair_data[j], repair_data[j + 1]]);
                 let v = gf16_mul_add(coeff, s, r);
                 let b = v.to_be_bytes();
@@ -73,7 +87,7 @@ impl Encoder16 {
         Some(Packet {
             id: self.source_window.back().unwrap().id + 1 + repair_packet_index as u64,
             data: repair_data,
-            len: packet_len,
+            len: padded_len,
             is_systematic: false,
             coefficients: Some(coeff_block),
             coeff_len: coeffs.len() * 2,
@@ -114,6 +128,23 @@ impl EncoderVariant {
             EncoderVariant::G16(e) => e.generate_repair_packet(idx, pool),
         }
     }
+
+    /// Returns `true` once the source window holds `k` packets, the point
+    /// from which repairs can be generated.
+    fn is_window_full(&self) -> bool {
+        match self {
+            EncoderVariant::G8(e) => e.source_window.len() >= e.k,
+            EncoderVariant::G16(e) => e.source_window.len() >= e.k,
+        }
+    }
+
+    /// Sets the GF(2^8) kernel path the encoder's workers use. The GF(2^16)
+    /// encoder has no kernel paths.
+    fn set_kernel_path(&mut self, path: super::gf_tables::HwPath) {
+        if let EncoderVariant::G8(e) = self {
+            e.path = path;
+        }
+    }
 }
 
 enum DecoderVariant {
@@ -122,11 +153,16 @@ enum DecoderVariant {
 }
 
 impl DecoderVariant {
-    fn new(mode: FecMode, k: usize, pool: Arc<MemoryPool>) -> Self {
+    fn new(
+        mode: FecMode,
+        k: usize,
+        pool: Arc<MemoryPool>,
+        cache: &SharedRecoveryCache,
+    ) -> Self {
         if mode == FecMode::Extreme {
             DecoderVariant::G16(Decoder16::new(k, pool))
         } else {
-            DecoderVariant::G8(Decoder::new(k, pool))
+            DecoderVariant::G8(Decoder::with_recovery_cache(k, pool, Arc::clone(cache)))
         }
     }
 
@@ -150,6 +186,22 @@ impl DecoderVariant {
             DecoderVariant::G16(d) => d.is_decoded,
         }
     }
+
+    /// Sets the GF(2^8) kernel path the decoder's workers use. The GF(2^16)
+    /// decoder has no kernel paths.
+    fn set_kernel_path(&mut self, path: super::gf_tables::HwPath) {
+        if let DecoderVariant::G8(d) = self {
+            d.set_kernel_path(path);
+        }
+    }
+
+    /// Source count of the block being decoded.
+    fn k(&self) -> usize {
+        match self {
+            DecoderVariant::G8(d) => d.k,
+            DecoderVariant::G16(d) => d.k,
+        }
+    }
 }
 
 impl Encoder {
@@ -158,6 +210,7 @@ impl Encoder {
             k,
             n,
             source_window: VecDeque::with_capacity(k),
+            path: super::gf_tables::hw_path(),
         }
     }
 
@@ -183,6 +236,7 @@ impl Encoder {
         repair_data.iter_mut().for_each(|b| *b = 0);
 
         let coeffs = self.generate_cauchy_coefficients(repair_packet_index);
+        let path = self.path;
 
         optimize::dispatch(|policy| {
             if policy.as_any().is::<optimize::Avx2>() || policy.as_any().is::<optimize::Neon>() {
@@ -205,13 +259,13 @@ impl Encoder {
                                     prefetch_data(source_data.as_ptr().add(j + 68));
                                 }
                             }
-                            repair_data[j] = gf_mul_add(coeff, source_data[j], repair_data[j]);
+                            repair_data[j] = gf_mul_add_with(path, coeff, source_data[j], repair_data[j]);
                             repair_data[j + 1] =
-                                gf_mul_add(coeff, source_data[j + 1], repair_data[j + 1]);
+                                gf_mul_add_with(path, coeff, source_data[j + 1], repair_data[j + 1]);
                             repair_data[j + 2] =
-                                gf_mul_add(coeff, source_data[j + 2], repair_data[j + 2]);
+                                gf_mul_add_with(path, coeff, source_data[j + 2], repair_data[j + 2]);
                             repair_data[j + 3] =
-                                gf_mul_add(coeff, source_data[j + 3], repair_data[j + 3]);
+                                gf_mul_add_with(path, coeff, source_data[j + 3], repair_data[j + 3]);
                             j += 4;
                         }
                         while j < packet_len {
@@ -220,7 +274,7 @@ impl Encoder {
                                     prefetch_data(source_data.as_ptr().add(j + 64));
                                 }
                             }
-                            repair_data[j] = gf_mul_add(coeff, source_data[j], repair_data[j]);
+                            repair_data[j] = gf_mul_add_with(path, coeff, source_data[j], repair_data[j]);
                             j += 1;
                         }
                     });
@@ -308,6 +362,9 @@ pub struct CsrMatrix {
     /// Payloads associated with each row (for repair packets).
     payloads: Vec<Option<AlignedBox<[u8]>>>,
     num_cols: usize,
+    /// GF(2^8) kernel path of the row operations, handed to the rayon
+    /// workers explicitly.
+    path: super::gf_tables::HwPath,
 }
 
 impl CsrMatrix {
@@ -318,6 +375,7 @@ impl CsrMatrix {
             row_ptr: vec![0],
             payloads: Vec::new(),
             num_cols,
+            path: super::gf_tables::hw_path(),
         }
     }
 
@@ -407,6 +465,7 @@ impl CsrMatrix {
     fn scale_row(&mut self, row: usize, factor: u8) {
         let row_start = self.row_ptr[row];
         let row_end = self.row_ptr[row + 1];
+        let path = self.path;
         optimize::dispatch(|policy| {
             if policy.as_any().is::<optimize::Avx2>() || policy.as_any().is::<optimize::Neon>() {
                 use rayon::prelude::*;
@@ -419,7 +478,7 @@ impl CsrMatrix {
                                 prefetch_data(self.values.as_ptr().add(row_start + idx + 32));
                             }
                         }
-                        *v = gf_mul(*v, factor);
+                        *v = gf_mul_with(path, *v, factor);
                     });
                 if let Some(ref mut payload) = self.payloads[row] {
                     payload.par_iter_mut().enumerate().for_each(|(i, b)| {
@@ -428,7 +487,7 @@ impl CsrMatrix {
                                 prefetch_data(payload.as_ptr().add(i + 32));
                             }
                         }
-                        *b = gf_mul(*b, factor);
+                        *b = gf_mul_with(path, *b, factor);
                     });
                 }
             } else {
@@ -455,6 +514,7 @@ impl CsrMatrix {
     }
 
     fn add_scaled_row(&mut self, target_row: usize, source_row: usize, factor: u8) {
+        let path = self.path;
         optimize::dispatch(|policy| {
             let mut dense = vec![0u8; self.num_cols];
             for (c, v) in self.row_entries(target_row) {
@@ -485,7 +545,7 @@ impl CsrMatrix {
                                 prefetch_data(src.as_ptr().add(i + 32));
                             }
                         }
-                        *t = gf_mul_add(factor, src[i], *t);
+                        *t = gf_mul_add_with(path, factor, src[i], *t);
                     });
                 } else {
                     let mut i = 0;
@@ -517,6 +577,94 @@ impl CsrMatrix {
     }
 }
 
+/// Upper bound on the number of recovery matrices kept by [`RecoveryMatrixCache`].
+pub const RECOVERY_CACHE_CAPACITY: usize = 64;
+
+/// Bounded LRU cache of inverted decoding matrices.
+///
+/// Under steady loss the same erasure pattern tends to repeat from one window
+/// to the next. The key encodes `k`, the bitmask of received source packets
+/// and the coefficient rows of the repair packets, so a hit yields exactly the
+/// inverse that elimination would have produced.
+pub struct RecoveryMatrixCache {
+    capacity: usize,
+    entries: std::collections::HashMap<Vec<u8>, Arc<Vec<Vec<u8>>>>,
+    order: std::collections::VecDeque<Vec<u8>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl RecoveryMatrixCache {
+    /// Creates an empty cache holding at most `capacity` matrices.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: std::collections::HashMap::new(),
+            order: std::collections::VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Looks up the inverse for `key`, marking it most recently used.
+    pub fn get(&mut self, key: &[u8]) -> Option<Arc<Vec<Vec<u8>>>> {
+        match self.entries.get(key) {
+            Some(inv) => {
+                let inv = Arc::clone(inv);
+                if let Some(pos) = self.order.iter().position(|k| k.as_slice() == key) {
+                    let k = self.order.remove(pos).unwrap();
+                    self.order.push_back(k);
+                }
+                self.hits += 1;
+                Some(inv)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Stores an inverse, evicting the least recently used entry when full.
+    pub fn insert(&mut self, key: Vec<u8>, inverse: Arc<Vec<Vec<u8>>>) {
+        if self.entries.insert(key.clone(), inverse).is_some() {
+            self.order.retain(|k| *k != key);
+        } else if self.entries.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.push_back(key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns `(hits, misses)` since the cache was created.
+    pub fn stats(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+}
+
+/// Recovery matrix cache shared by the decoders of one connection.
+pub type SharedRecoveryCache = Arc<std::sync::Mutex<RecoveryMatrixCache>>;
+
+/// Creates an empty [`SharedRecoveryCache`] of [`RECOVERY_CACHE_CAPACITY`].
+pub fn new_recovery_cache() -> SharedRecoveryCache {
+    Arc::new(std::sync::Mutex::new(RecoveryMatrixCache::new(
+        RECOVERY_CACHE_CAPACITY,
+    )))
+}
+
 /// Represents the chosen decoding algorithm based on window size.
 enum DecodingStrategy {
     GaussianElimination,
@@ -529,8 +677,14 @@ pub struct Decoder {
     mem_pool: Arc<MemoryPool>,
     decoding_matrix: CsrMatrix,
     systematic_packets: Vec<Option<Packet>>,
+    // Ids of the repairs added so far; a repeated repair would leave the
+    // matrix rank deficient with no room for an independent one.
+    repair_ids: std::collections::HashSet<u64>,
     is_decoded: bool,
     strategy: DecodingStrategy,
+    /// Set when the last recovery reused an inverse from the cache.
+    cache_hit: bool,
+    recovery_cache: SharedRecoveryCache,
 }
 
 pub struct Decoder16 {
@@ -538,6 +692,11 @@ pub struct Decoder16 {
     mem_pool: Arc<MemoryPool>,
     matrix: Vec<Vec<u16>>, // dense for simplicity
     payloads: Vec<Option<AlignedBox<[u8]>>>,
+    // Source indices and repair ids already added, so a duplicate cannot
+    // take the place of an independent row.
+    have_source: Vec<bool>,
+    repair_ids: std::collections::HashSet<u64>,
+    block_len: usize,
     is_decoded: bool,
 }
 
@@ -548,34 +707,50 @@ impl Decoder16 {
             mem_pool,
             matrix: Vec::new(),
             payloads: Vec::new(),
+            have_source: vec![false; k],
+            repair_ids: std::collections::HashSet::new(),
+            block_len: 0,
             is_decoded: false,
         }
     }
 
-    fn add_packet(&mut self, packet: Packet) -> Result<bool, &'static str> {
+    /// Adds a source or repair packet. Source payloads take part in the
+    /// elimination like repair payloads, so any `k` distinct packets decode
+    /// the block, including repairs alone.
+    fn add_packet(&mut self, mut packet: Packet) -> Result<bool, &'static str> {
         if self.is_decoded || self.matrix.len() >= self.k {
             return Ok(self.is_decoded);
         }
-        let coeffs = if packet.is_systematic {
-            let mut row = vec![0u16; self.k];
+        let row = if packet.is_systematic {
             let idx = (packet.id as usize) % self.k;
-            row[idx] = 1;
-            self.matrix.push(row);
-            self.payloads.push(None);
-            return Ok(false);
-        } else if let Some(c) = packet.coefficients {
-            let mut row = Vec::with_capacity(self.k);
-            for i in 0..self.k {
-                let hi = c[2 * i];
-                let lo = c[2 * i + 1];
-                row.push(u16::from_be_bytes([hi, lo]));
+            if std::mem::replace(&mut self.have_source[idx], true) {
+                return Ok(false);
             }
-            self.matrix.push(row);
-            self.payloads.push(Some(packet.data));
+            let mut row = vec![0u16; self.k];
+            row[idx] = 1;
             row
+        } else if let Some(c) = packet.coefficients.as_ref() {
+            if packet.coeff_len < 2 * self.k {
+                return Err("truncated coeffs");
+            }
+            if !self.repair_ids.insert(packet.id) {
+                return Ok(false);
+            }
+            (0..self.k)
+                .map(|i| u16::from_be_bytes([c[2 * i], c[2 * i + 1]]))
+                .collect()
         } else {
             return Err("missing coeffs");
         };
+        let mut data = packet.data.take().ok_or("missing payload")?;
+        if packet.is_systematic {
+            // Match the zero padding the encoder applies past the payload.
+            let len = packet.len.min(data.len());
+            data[len..].fill(0);
+        }
+        self.block_len = self.block_len.max(packet.len);
+        self.matrix.push(row);
+        self.payloads.push(Some(data));
         Ok(self.try_decode())
     }
 
@@ -585,52 +760,37 @@ impl Decoder16 {
         }
         let k = self.k;
         for i in 0..k {
-            // pivot search
-            let mut pivot = i;
-            while pivot < k && self.matrix[pivot][i] == 0 {
-                pivot += 1;
-            }
-            if pivot == k {
-                return false;
-            }
+            let pivot = match (i..k).find(|&r| self.matrix[r][i] != 0) {
+                Some(p) => p,
+                None => return false,
+            };
             self.matrix.swap(i, pivot);
             self.payloads.swap(i, pivot);
             let inv = gf16_inv(self.matrix[i][i]);
             for val in self.matrix[i].iter_mut() {
                 *val = gf16_mul(*val, inv);
             }
-            if let Some(ref mut p) = self.payloads[i] {
-                let mut j = 0;
-                while j + 1 < p.len() {
-                    let v = u16::from_be_bytes([p[j], p[j + 1]]);
-                    let v = gf16_mul(v, inv);
-                    let b = v.to_be_bytes();
-                    p[j] = b[0];
-                    p[j + 1] = b[1];
-                    j += 2;
-                }
+            if let Some(p) = self.payloads[i].as_mut() {
+                gf16_scale_region(inv, p);
             }
+            let pivot_row = self.matrix[i].clone();
             for r in 0..k {
-                if r != i && self.matrix[r][i] != 0 {
-                    let factor = self.matrix[r][i];
-                    for c in 0..k {
-                        let t = gf16_mul(factor, self.matrix[i][c]);
-                        self.matrix[r][c] ^= t;
-                    }
-                    if let (Some(ref src), Some(ref mut tgt)) =
-                        (&self.payloads[i], &mut self.payloads[r])
-                    {
-                        let mut j = 0;
-                        while j + 1 < src.len() {
-                            let s = u16::from_be_bytes([src[j], src[j + 1]]);
-                            let t = u16::from_be_bytes([tgt[j], tgt[j + 1]]);
-                            let val = gf16_mul_add(factor, s, t);
-                            let b = val.to_be_bytes();
-                            tgt[j] = b[0];
-                            tgt[j + 1] = b[1];
-                            j += 2;
-                        }
-                    }
+                let factor = self.matrix[r][i];
+                if r == i || factor == 0 {
+                    continue;
+                }
+                for (v, &p) in self.matrix[r].iter_mut().zip(&pivot_row) {
+                    *v ^= gf16_mul(factor, p);
+                }
+                let (src, tgt) = if i < r {
+                    let (lo, hi) = self.payloads.split_at_mut(r);
+                    (&lo[i], &mut hi[0])
+                } else {
+                    let (lo, hi) = self.payloads.split_at_mut(i);
+                    (&hi[0], &mut lo[r])
+                };
+                if let (Some(src), Some(tgt)) = (src.as_ref(), tgt.as_mut()) {
+                    gf16_mul_add_region(factor, src, tgt);
                 }
             }
         }
@@ -638,25 +798,67 @@ impl Decoder16 {
         true
     }
 
+    /// Returns the `k` source packets, ids relative to the block, once
+    /// decoded. Once a repair took part, lengths are those of the repair,
+    /// i.e. odd-length payloads carry one trailing zero byte.
     fn get_decoded_packets(&mut self) -> Vec<Packet> {
-        let mut out = Vec::new();
-        for (i, payload) in self.payloads.iter_mut().enumerate() {
-            if let Some(data) = payload.take() {
-                out.push(Packet {
+        let len = self.block_len;
+        let pool = &self.mem_pool;
+        self.payloads
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, payload)| {
+                payload.take().map(|data| Packet {
                     id: i as u64,
-                    data,
-                    len: data.len(),
+                    data: Some(data),
+                    len,
                     is_systematic: true,
                     coefficients: None,
-                });
-            }
-        }
-        out
+                    coeff_len: 0,
+                    mem_pool: Arc::clone(pool),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Multiplies every big-endian GF(2^16) symbol of `data` by `c`.
+fn gf16_scale_region(c: u16, data: &mut [u8]) {
+    for pair in data.chunks_exact_mut(2) {
+        let v = gf16_mul(u16::from_be_bytes([pair[0], pair[1]]), c);
+        pair.copy_from_slice(&v.to_be_bytes());
+    }
+}
+
+/// `dst += c * src` over big-endian GF(2^16) symbols.
+fn gf16_mul_add_region(c: u16, src: &[u8], dst: &mut [u8]) {
+    for (s, d) in src.chunks_exact(2).zip(dst.chunks_exact_mut(2)) {
+        let v = gf16_mul_add(
+            c,
+            u16::from_be_bytes([s[0], s[1]]),
+            u16::from_be_bytes([d[0], d[1]]),
+        );
+        d.copy_from_slice(&v.to_be_bytes());
     }
 }
 
 impl Decoder {
+    /// Sets the GF(2^8) kernel path used by the parallel row operations.
+    fn set_kernel_path(&mut self, path: super::gf_tables::HwPath) {
+        self.decoding_matrix.path = path;
+    }
+
     fn new(k: usize, mem_pool: Arc<MemoryPool>) -> Self {
+        Self::with_recovery_cache(k, mem_pool, new_recovery_cache())
+    }
+
+    /// Like `new`, looking up and storing inverses in `recovery_cache`, so
+    /// the decoders of successive blocks of one connection share it.
+    pub fn with_recovery_cache(
+        k: usize,
+        mem_pool: Arc<MemoryPool>,
+        recovery_cache: SharedRecoveryCache,
+    ) -> Self {
         // Select the decoding strategy based on the window size `k`.
         let strategy = if k > 256 {
             DecodingStrategy::Wiedemann
@@ -669,8 +871,11 @@ impl Decoder {
             mem_pool,
             decoding_matrix: CsrMatrix::new(k), // The matrix size is k x k for coefficients
             systematic_packets: vec![None; k],
+            repair_ids: std::collections::HashSet::new(),
             is_decoded: false,
             strategy,
+            cache_hit: false,
+            recovery_cache,
         }
     }
 
@@ -692,6 +897,9 @@ impl Decoder {
             self.decoding_matrix.append_row(&identity_row, None);
             Ok(self.try_decode())
         } else if let Some(coeffs) = packet.coefficients {
+            if !self.repair_ids.insert(packet.id) {
+                return Ok(self.is_decoded); // Duplicate packet
+            }
             self.decoding_matrix
                 .append_row(&coeffs[..packet.coeff_len], packet.data);
             Ok(self.try_decode())
@@ -711,11 +919,132 @@ impl Decoder {
 
         // --- High-performance decoding pipeline ---
         match self.strategy {
-            DecodingStrategy::GaussianElimination => self.gaussian_elimination(),
+            DecodingStrategy::GaussianElimination => {
+                self.cached_recovery() || self.gaussian_elimination()
+            }
             DecodingStrategy::Wiedemann => self.wiedemann_algorithm(),
         }
     }
 
+    /// Returns `true` if the block was recovered with a cached inverse.
+    pub fn used_cached_inverse(&self) -> bool {
+        self.cache_hit
+    }
+
+    /// Recovers missing source packets by multiplying the received payloads
+    /// with the inverse of the decoding matrix. Inverses are kept in the
+    /// decoder's [`RecoveryMatrixCache`] so repeated erasure patterns skip
+    /// elimination.
+    fn cached_recovery(&mut self) -> bool {
+        let start = std::time::Instant::now();
+        let k = self.k;
+        let rows: Vec<Vec<u8>> = (0..k)
+            .map(|r| {
+                let mut dense = vec![0u8; k];
+                for (c, v) in self.decoding_matrix.row_entries(r) {
+                    dense[c] = v;
+                }
+                dense
+            })
+            .collect();
+
+        // Canonical row order: source rows first, repair rows by coefficients.
+        // Arrival order then no longer affects the cache key.
+        let mut order: Vec<usize> = (0..k).collect();
+        order.sort_by(|&a, &b| {
+            let ka = (self.decoding_matrix.get_payload(a).is_some(), &rows[a]);
+            let kb = (self.decoding_matrix.get_payload(b).is_some(), &rows[b]);
+            ka.cmp(&kb)
+        });
+
+        let mut key = (k as u32).to_be_bytes().to_vec();
+        let mut mask = vec![0u8; (k + 7) / 8];
+        for (i, p) in self.systematic_packets.iter().enumerate() {
+            if p.is_some() {
+                mask[i / 8] |= 1 << (i % 8);
+            }
+        }
+        key.extend_from_slice(&mask);
+        for &r in &order {
+            if self.decoding_matrix.get_payload(r).is_some() {
+                key.extend_from_slice(&rows[r]);
+            }
+        }
+
+        let cached = self.recovery_cache.lock().unwrap().get(&key);
+        self.cache_hit = cached.is_some();
+        let inverse = match cached {
+            Some(inv) => inv,
+            None => {
+                let sorted: Vec<Vec<u8>> = order.iter().map(|&r| rows[r].clone()).collect();
+                let inv = match gauss_jordan_inverse(&sorted) {
+                    Some(inv) => Arc::new(inv),
+                    None => return false,
+                };
+                self.recovery_cache
+                    .lock()
+                    .unwrap()
+                    .insert(key, Arc::clone(&inv));
+                inv
+            }
+        };
+
+        let block_len = order
+            .iter()
+            .filter_map(|&r| self.decoding_matrix.get_payload(r).as_ref())
+            .map(|p| p.len())
+            .max()
+            .unwrap_or(0);
+        let inputs: Vec<&[u8]> = order
+            .iter()
+            .map(|&r| match self.decoding_matrix.get_payload(r) {
+                Some(p) => &p[..],
+                None => {
+                    let col = rows[r].iter().position(|&v| v != 0).unwrap_or(0);
+                    self.systematic_packets[col]
+                        .as_ref()
+                        .map(|p| p.payload())
+                        .unwrap_or(&[])
+                }
+            })
+            .collect();
+
+        let path = self.decoding_matrix.path;
+        let mut recovered = Vec::new();
+        for i in 0..k {
+            if self.systematic_packets[i].is_some() {
+                continue;
+            }
+            let mut packet_data = self.mem_pool.alloc();
+            packet_data[..block_len].fill(0);
+            for (j, input) in inputs.iter().enumerate() {
+                let n = input.len().min(block_len);
+                super::gf_tables::gf_mul_add_region_with(
+                    path,
+                    inverse[i][j],
+                    &input[..n],
+                    &mut packet_data[..n],
+                );
+            }
+            recovered.push((i, packet_data));
+        }
+        for (i, packet_data) in recovered {
+            self.systematic_packets[i] = Some(Packet {
+                id: i as u64,
+                data: Some(packet_data),
+                len: block_len,
+                is_systematic: true,
+                coefficients: None,
+                coeff_len: 0,
+                mem_pool: Arc::clone(&self.mem_pool),
+            });
+        }
+
+        self.is_decoded = true;
+        telemetry!(telemetry::DECODING_TIME_MS.set(start.elapsed().as_millis() as i64));
+        true
+    }
+
     /// Performs Sparse Gaussian elimination on the CSR matrix.
     fn gaussian_elimination(&mut self) -> bool {
         // This is a simplified sparse implementation. A truly high-performance version
@@ -974,6 +1303,70 @@ impl Decoder {
     }
 }
 
+/// Returns the index of the block of `k` sources that `packet` belongs to.
+///
+/// Source packets map to `id / k`. A repair packet carries the id after the
+/// newest source of its window plus its repair index, which is recovered from
+/// the first Cauchy coefficient `1 / (0 ^ (k + index))`. Repairs without
+/// coefficients, or whose window would start before id 0, have no block.
+pub fn block_of(k: usize, packet: &Packet) -> Option<u64> {
+    if k == 0 {
+        return None;
+    }
+    let k = k as u64;
+    if packet.is_systematic {
+        return Some(packet.id / k);
+    }
+    let first = *packet.coefficients.as_ref()?.first()?;
+    if first == 0 || packet.coeff_len == 0 {
+        return None;
+    }
+    let y = gf_inv(first) as u64;
+    let index = (y + 256 - k % 256) % 256;
+    let end = packet.id.checked_sub(index)?;
+    (end / k).checked_sub(1).filter(|_| end % k == 0)
+}
+
+/// Decodes a mix of source and repair packets from several blocks of `k`
+/// sources, e.g. a receive buffer spanning more than one block.
+///
+/// Packets are partitioned with [`block_of`] and every block gets its own
+/// [`Decoder`]. The result maps each block that could be completed to its
+/// `k` source packets, ids rebased to the block, in order. Blocks still
+/// missing packets, and packets that belong to no block, are dropped.
+pub fn decode_blocks(
+    k: usize,
+    packets: Vec<Packet>,
+    mem_pool: &Arc<MemoryPool>,
+) -> std::collections::BTreeMap<u64, Vec<Packet>> {
+    let mut blocks: std::collections::BTreeMap<u64, Decoder> = std::collections::BTreeMap::new();
+    for packet in packets {
+        let Some(block) = block_of(k, &packet) else {
+            continue;
+        };
+        let decoder = blocks
+            .entry(block)
+            .or_insert_with(|| Decoder::new(k, Arc::clone(mem_pool)));
+        if decoder.add_packet(packet).is_err() {
+            log::warn!("decode_blocks: dropping repair without coefficients");
+        }
+    }
+
+    blocks
+        .into_iter()
+        .filter(|(_, d)| d.is_decoded)
+        .map(|(block, mut d)| {
+            let base = block * k as u64;
+            let mut sources = d.get_decoded_packets();
+            for p in sources.iter_mut() {
+                p.id = base + p.id % k as u64;
+            }
+            sources.sort_by_key(|p| p.id);
+            (block, sources)
+        })
+        .collect()
+}
+
 /// Multiplies two dense matrices over GF(2^8).
 fn mat_mul(a: &[Vec<u8>], b: &[Vec<u8>]) -> Vec<Vec<u8>> {
     let rows = a.len();
@@ -993,6 +1386,37 @@ fn mat_mul(a: &[Vec<u8>], b: &[Vec<u8>]) -> Vec<Vec<u8>> {
     out
 }
 
+/// Inverts a dense square matrix over GF(2^8) with Gauss-Jordan elimination.
+fn gauss_jordan_inverse(m: &[Vec<u8>]) -> Option<Vec<Vec<u8>>> {
+    let n = m.len();
+    let mut a: Vec<Vec<u8>> = m.to_vec();
+    let mut inv = vec![vec![0u8; n]; n];
+    for (i, row) in inv.iter_mut().enumerate() {
+        row[i] = 1;
+    }
+    for col in 0..n {
+        let pivot = (col..n).find(|&r| a[r][col] != 0)?;
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+        let p_inv = gf_inv(a[col][col]);
+        for c in 0..n {
+            a[col][c] = gf_mul(a[col][c], p_inv);
+            inv[col][c] = gf_mul(inv[col][c], p_inv);
+        }
+        for r in 0..n {
+            let factor = a[r][col];
+            if r == col || factor == 0 {
+                continue;
+            }
+            for c in 0..n {
+                a[r][c] ^= gf_mul(factor, a[col][c]);
+                inv[r][c] ^= gf_mul(factor, inv[col][c]);
+            }
+        }
+    }
+    Some(inv)
+}
+
 /// Computes a dense matrix inverse using block recursive inversion.
 fn recursive_inverse(m: &[Vec<u8>]) -> Option<Vec<Vec<u8>>> {
     let n = m.len();
@@ -11,46 +11,106 @@ pub struct Packet {
     mem_pool: Arc<MemoryPool>,
 }
 
+/// Layout of a serialized packet as found by [`parse_frame`].
+struct FrameLayout {
+    is_systematic: bool,
+    coeffs: std::ops::Range<usize>,
+    payload_offset: usize,
+}
+
+/// Validates the frame header of `raw` without copying anything.
+///
+/// Frame format: <is_systematic_byte (1)> <coeff_len (2)> <coeffs (coeff_len)> <payload>,
+/// where the coefficient fields are only present for repair packets (type
+/// byte 0). Every offset is checked against `raw.len()` so malformed input
+/// yields an error, never a panic.
+fn parse_frame(raw: &[u8]) -> Result<FrameLayout, String> {
+    let (&kind, rest) = raw
+        .split_first()
+        .ok_or_else(|| "Raw data is empty".to_string())?;
+    let is_systematic = match kind {
+        1 => true,
+        0 => false,
+        k => return Err(format!("unknown packet type '{}'", k)),
+    };
+    let (coeffs, payload_offset) = if is_systematic {
+        (1..1, 1)
+    } else {
+        let len_bytes: [u8; 2] = rest
+            .get(..2)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| "Buffer too short for coefficient length".to_string())?;
+        let coeff_len = u16::from_be_bytes(len_bytes) as usize;
+        if coeff_len == 0 {
+            return Err("Repair packet without coefficients".to_string());
+        }
+        let end = 3 + coeff_len;
+        if raw.len() < end {
+            return Err(format!(
+                "Buffer too short for coefficients: {} of {} bytes",
+                raw.len() - 3,
+                coeff_len
+            ));
+        }
+        (3..end, end)
+    };
+    if raw.len() == payload_offset {
+        return Err("Packet has an empty payload".to_string());
+    }
+    Ok(FrameLayout {
+        is_systematic,
+        coeffs,
+        payload_offset,
+    })
+}
+
 impl Packet {
-    /// Deserializes a packet from a raw byte buffer.
-    /// This is a lightweight framing implementation.
-    /// Frame format: <is_systematic_byte (1)> <coeff_len (2)> <coeffs (coeff_len)> <payload>
+    /// Deserializes a packet from a raw byte buffer, see [`parse_frame`] for
+    /// the frame format. Truncated headers, coefficient lengths beyond the
+    /// buffer and empty payloads are rejected.
     pub fn from_raw(
         id: u64,
         raw_data: &[u8],
         opt_manager: &OptimizationManager,
     ) -> Result<Self, String> {
-        if raw_data.is_empty() {
-            error!("from_raw: input buffer empty");
-            return Err("Raw data is empty".to_string());
-        }
-
-        let is_systematic = raw_data[0] == 1;
-        let mut offset = 1;
+        let layout = parse_frame(raw_data).map_err(|e| {
+            error!("from_raw: {}", e);
+            e
+        })?;
+        let coeff_len = layout.coeffs.len();
 
-        let (coefficients, coeff_len, payload_offset) = if !is_systematic {
-            if raw_data.len() < 3 {
-                error!("from_raw: coefficient length missing");
-                return Err("Buffer too short for coefficient length".to_string());
+        let coefficients = if !layout.is_systematic {
+            let mut coeff_block = opt_manager.try_alloc_block().map_err(|e| {
+                error!("from_raw: {}", e);
+                e.to_string()
+            })?;
+            if coeff_block.len() < coeff_len {
+                opt_manager.free_block(coeff_block);
+                error!("from_raw: coefficients exceed pool block size");
+                return Err("Coefficients larger than pool block".to_string());
             }
-            let coeff_len =
-                u16::from_be_bytes(raw_data[offset..offset + 2].try_into().unwrap()) as usize;
-            offset += 2;
-
-            if raw_data.len() < offset + coeff_len {
-                error!("from_raw: coefficient data truncated");
-                return Err("Buffer too short for coefficients".to_string());
-            }
-            let mut coeff_block = opt_manager.alloc_block();
-            coeff_block[..coeff_len].copy_from_slice(&raw_data[offset..offset + coeff_len]);
-            (Some(coeff_block), coeff_len, offset + coeff_len)
+            coeff_block[..coeff_len].copy_from_slice(&raw_data[layout.coeffs]);
+            Some(coeff_block)
         } else {
-            (None, 0, offset)
+            None
         };
 
-        let payload = &raw_data[payload_offset..];
-        let mut data = opt_manager.alloc_block();
+        let payload = &raw_data[layout.payload_offset..];
+        let mut data = match opt_manager.try_alloc_block() {
+            Ok(d) => d,
+            Err(e) => {
+                if let Some(c) = coefficients {
+                    opt_manager.free_block(c);
+                }
+                error!("from_raw: {}", e);
+                return Err(e.to_string());
+            }
+        };
         if data.len() < payload.len() {
+            opt_manager.free_block(data);
+            if let Some(c) = coefficients {
+                opt_manager.free_block(c);
+            }
             error!("from_raw: pool buffer too small");
             return Err("Buffer from pool is too small".to_string());
         }
@@ -60,7 +120,7 @@ impl Packet {
             id,
             data: Some(data),
             len: payload.len(),
-            is_systematic,
+            is_systematic: layout.is_systematic,
             coefficients,
             coeff_len,
             mem_pool: opt_manager.memory_pool(),
@@ -68,7 +128,8 @@ impl Packet {
     }
 
     /// Creates a packet from a pooled memory block. The `len` parameter
-    /// specifies the amount of valid data in the block.
+    /// specifies the amount of valid data in the block. The block is
+    /// returned to the pool if the frame is malformed.
     pub fn from_block(
         id: u64,
         mut block: AlignedBox<[u8]>,
@@ -81,39 +142,45 @@ impl Packet {
             return Err("Invalid raw packet length".to_string());
         }
 
-        let is_systematic = block[0] == 1;
-        let mut offset = 1;
-
-        let (coefficients, coeff_len, payload_offset) = if !is_systematic {
-            if len < 3 {
+        let layout = match parse_frame(&block[..len]) {
+            Ok(l) => l,
+            Err(e) => {
                 opt_manager.free_block(block);
-                error!("from_block: coefficient length missing");
-                return Err("Buffer too short for coefficient length".to_string());
+                error!("from_block: {}", e);
+                return Err(e);
             }
-            let coeff_len = u16::from_be_bytes([block[offset], block[offset + 1]]) as usize;
-            offset += 2;
-            if len < offset + coeff_len {
+        };
+        let coeff_len = layout.coeffs.len();
+
+        let coefficients = if !layout.is_systematic {
+            let mut coeff_block = match opt_manager.try_alloc_block() {
+                Ok(c) => c,
+                Err(e) => {
+                    opt_manager.free_block(block);
+                    error!("from_block: {}", e);
+                    return Err(e.to_string());
+                }
+            };
+            if coeff_block.len() < coeff_len {
+                opt_manager.free_block(coeff_block);
                 opt_manager.free_block(block);
-                error!("from_block: coefficient data truncated");
-                return Err("Buffer too short for coefficients".to_string());
+                error!("from_block: coefficients exceed pool block size");
+                return Err("Coefficients larger than pool block".to_string());
             }
-            let mut coeff_block = opt_manager.alloc_block();
-            coeff_block[..coeff_len].copy_from_slice(&block[offset..offset + coeff_len]);
-            (Some(coeff_block), coeff_len, offset + coeff_len)
+            coeff_block[..coeff_len].copy_from_slice(&block[layout.coeffs]);
+            Some(coeff_block)
         } else {
-            (None, 0, offset)
+            None
         };
 
-        let payload_len = len - payload_offset;
-        if payload_offset > 0 {
-            block.copy_within(payload_offset..len, 0);
-        }
+        let payload_len = len - layout.payload_offset;
+        block.copy_within(layout.payload_offset..len, 0);
 
         Ok(Packet {
             id,
             data: Some(block),
             len: payload_len,
-            is_systematic,
+            is_systematic: layout.is_systematic,
             coefficients,
             coeff_len,
             mem_pool: opt_manager.memory_pool(),
@@ -151,6 +218,27 @@ impl Packet {
         Ok(offset)
     }
 
+    /// Returns the valid payload bytes of this packet.
+    pub fn payload(&self) -> &[u8] {
+        match self.data {
+            Some(ref data) => &data[..self.len],
+            None => &[],
+        }
+    }
+
+    /// Copies the payload into a caller provided buffer, avoiding a round
+    /// trip through the memory pool. On success the number of bytes written
+    /// is returned. If `out` is too small nothing is written and the required
+    /// size is returned as the error value.
+    pub fn write_payload(&self, out: &mut [u8]) -> Result<usize, usize> {
+        let payload = self.payload();
+        if out.len() < payload.len() {
+            return Err(payload.len());
+        }
+        out[..payload.len()].copy_from_slice(payload);
+        Ok(payload.len())
+    }
+
     /// Clones the packet structure and its data for use in the encoder window.
     /// This is a deep copy of the data into a new buffer from the memory pool.
     pub fn clone_for_encoder(&self, mem_pool: &Arc<MemoryPool>) -> Self {
@@ -174,6 +262,24 @@ impl Packet {
     }
 }
 
+impl Packet {
+    /// All-zero source packet of `len` bytes used to fill up a coded block
+    /// flushed before it was complete.
+    pub(crate) fn padding(id: u64, len: usize, mem_pool: &Arc<MemoryPool>) -> Self {
+        let mut data = mem_pool.alloc();
+        data[..len].fill(0);
+        Packet {
+            id,
+            data: Some(data),
+            len,
+            is_systematic: true,
+            coefficients: None,
+            coeff_len: 0,
+            mem_pool: Arc::clone(mem_pool),
+        }
+    }
+}
+
 impl Drop for Packet {
     fn drop(&mut self) {
         if let Some(data) = self.data.take() {
@@ -1,17 +1,49 @@
-use crate::optimize::{self, SimdPolicy};
+use crate::optimize::{self, CpuFeature, FeatureDetector, SimdPolicy};
 use rayon::prelude::*;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+/// SIMD capabilities available to the GF kernels.
+///
+/// This is a view on the shared [`FeatureDetector`]; callers must use it
+/// instead of probing the CPU themselves so that the FEC kernels, the crypto
+/// selector and the telemetry always agree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GfKernelSupport {
+    pub avx512: bool,
+    pub avx2: bool,
+    pub sse2: bool,
+    pub neon: bool,
+    /// Carry-less multiply (PCLMULQDQ on x86, PMULL on ARM), required by all
+    /// bitsliced kernels.
+    pub clmul: bool,
+}
+
+/// Returns the kernel capabilities reported by the shared feature detector.
+pub fn kernel_support() -> GfKernelSupport {
+    let detector = FeatureDetector::instance();
+    GfKernelSupport {
+        avx512: detector.has_feature(CpuFeature::AVX512F)
+            && detector.has_feature(CpuFeature::AVX512VBMI),
+        avx2: detector.has_feature(CpuFeature::AVX2),
+        sse2: detector.has_feature(CpuFeature::SSE2),
+        neon: detector.has_feature(CpuFeature::NEON),
+        clmul: detector.has_feature(CpuFeature::PCLMULQDQ),
+    }
+}
 
 #[inline(always)]
 pub(crate) unsafe fn prefetch_log(idx: usize) {
     #[cfg(target_arch = "x86_64")]
     {
         use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
-        _mm_prefetch(LOG_TABLE.as_ptr().add(idx) as *const i8, _MM_HINT_T0);
+        _mm_prefetch(log_table().as_ptr().add(idx) as *const i8, _MM_HINT_T0);
     }
     #[cfg(target_arch = "aarch64")]
     {
         use std::arch::aarch64::__prefetch;
-        __prefetch(LOG_TABLE.as_ptr().add(idx));
+        __prefetch(log_table().as_ptr().add(idx));
     }
 }
 
@@ -20,12 +52,12 @@ pub(crate) unsafe fn prefetch_exp(idx: usize) {
     #[cfg(target_arch = "x86_64")]
     {
         use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
-        _mm_prefetch(EXP_TABLE.as_ptr().add(idx) as *const i8, _MM_HINT_T0);
+        _mm_prefetch(exp_table().as_ptr().add(idx) as *const i8, _MM_HINT_T0);
     }
     #[cfg(target_arch = "aarch64")]
     {
         use std::arch::aarch64::__prefetch;
-        __prefetch(EXP_TABLE.as_ptr().add(idx));
+        __prefetch(exp_table().as_ptr().add(idx));
     }
 }
 
@@ -43,17 +75,16 @@ pub(crate) unsafe fn prefetch_data(ptr: *const u8) {
     }
 }
 
+/// Multiplies through the log/exp tables of the active table source, see
+/// [`init_gf_tables`].
 #[inline(always)]
-pub(crate) fn gf_mul_table(a: u8, b: u8) -> u8 {
+pub fn gf_mul_table(a: u8, b: u8) -> u8 {
     if a == 0 || b == 0 {
         return 0;
     }
-    unsafe {
-        let log_a = LOG_TABLE[a as usize] as u16;
-        let log_b = LOG_TABLE[b as usize] as u16;
-        let sum_log = log_a + log_b;
-        EXP_TABLE[sum_log as usize]
-    }
+    let log = log_table();
+    let sum_log = log[a as usize] as u16 + log[b as usize] as u16;
+    exp_table()[sum_log as usize]
 }
 
 #[inline(always)]
@@ -252,11 +283,11 @@ unsafe fn gf_mul_slice_neon(a: &[u8], b: &[u8], out: &mut [u8]) {
 /// Element-wise multiplication of two equally sized slices.
 ///
 /// The appropriate SIMD implementation is chosen at runtime via `optimize`.
-pub(crate) fn gf_mul_slice(a: &[u8], b: &[u8], out: &mut [u8]) {
+pub fn gf_mul_slice(a: &[u8], b: &[u8], out: &mut [u8]) {
     assert_eq!(a.len(), b.len());
     assert_eq!(out.len(), a.len());
 
-    optimize::dispatch_bitslice(|policy| match policy {
+    dispatch_gf(|policy| match policy {
         #[cfg(target_arch = "x86_64")]
         &optimize::Avx512 => unsafe { gf_mul_slice_avx512(a, b, out) },
         #[cfg(target_arch = "x86_64")]
@@ -272,6 +303,248 @@ pub(crate) fn gf_mul_slice(a: &[u8], b: &[u8], out: &mut [u8]) {
         }
     });
 }
+
+// Constant-times-region kernels ---------------------------------------------------
+
+/// Products of `c` with every low and every high nibble, so that
+/// `c * x == lo[x & 15] ^ hi[x >> 4]`.
+fn nibble_tables(c: u8) -> ([u8; 16], [u8; 16]) {
+    let mut lo = [0u8; 16];
+    let mut hi = [0u8; 16];
+    for i in 0..16u8 {
+        lo[i as usize] = gf_mul_shift(c, i);
+        hi[i as usize] = gf_mul_shift(c, i << 4);
+    }
+    (lo, hi)
+}
+
+fn gf_mul_add_region_scalar(c: u8, src: &[u8], dst: &mut [u8]) {
+    let (lo, hi) = nibble_tables(c);
+    for (d, &s) in dst.iter_mut().zip(src) {
+        *d ^= lo[(s & 0x0f) as usize] ^ hi[(s >> 4) as usize];
+    }
+}
+
+/// 32 bytes per step: both nibble tables live in a register and `vpshufb`
+/// looks up all lanes at once.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn gf_mul_add_region_avx2(c: u8, src: &[u8], dst: &mut [u8]) {
+    use std::arch::x86_64::*;
+
+    let (lo, hi) = nibble_tables(c);
+    let tlo = _mm256_broadcastsi128_si256(_mm_loadu_si128(lo.as_ptr() as *const __m128i));
+    let thi = _mm256_broadcastsi128_si256(_mm_loadu_si128(hi.as_ptr() as *const __m128i));
+    let mask = _mm256_set1_epi8(0x0f);
+    let mut i = 0;
+    while i + 32 <= src.len() {
+        let x = _mm256_loadu_si256(src.as_ptr().add(i) as *const __m256i);
+        let l = _mm256_and_si256(x, mask);
+        let h = _mm256_and_si256(_mm256_srli_epi64(x, 4), mask);
+        let p = _mm256_xor_si256(_mm256_shuffle_epi8(tlo, l), _mm256_shuffle_epi8(thi, h));
+        let d = dst.as_mut_ptr().add(i) as *mut __m256i;
+        _mm256_storeu_si256(d, _mm256_xor_si256(_mm256_loadu_si256(d), p));
+        i += 32;
+    }
+    gf_mul_add_region_scalar(c, &src[i..], &mut dst[i..]);
+}
+
+/// SSE2 has no byte shuffle, so the product is built shift-and-add style:
+/// 16 bytes are doubled in GF(2^8) once per bit of `c`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn gf_mul_add_region_sse2(c: u8, src: &[u8], dst: &mut [u8]) {
+    use std::arch::x86_64::*;
+
+    let poly = _mm_set1_epi8(IRREDUCIBLE_POLY as u8 as i8);
+    let zero = _mm_setzero_si128();
+    let mut i = 0;
+    while i + 16 <= src.len() {
+        let mut x = _mm_loadu_si128(src.as_ptr().add(i) as *const __m128i);
+        let mut acc = zero;
+        let mut bits = c;
+        while bits != 0 {
+            if bits & 1 != 0 {
+                acc = _mm_xor_si128(acc, x);
+            }
+            // Bytes with the top bit set compare as negative.
+            let carry = _mm_and_si128(_mm_cmplt_epi8(x, zero), poly);
+            x = _mm_xor_si128(_mm_add_epi8(x, x), carry);
+            bits >>= 1;
+        }
+        let d = dst.as_mut_ptr().add(i) as *mut __m128i;
+        _mm_storeu_si128(d, _mm_xor_si128(_mm_loadu_si128(d), acc));
+        i += 16;
+    }
+    gf_mul_add_region_scalar(c, &src[i..], &mut dst[i..]);
+}
+
+/// Computes `dst ^= c * src` over GF(2^8), the inner step of every matrix
+/// product in the codec, on the kernel path currently in use.
+pub fn gf_mul_add_region(c: u8, src: &[u8], dst: &mut [u8]) {
+    gf_mul_add_region_with(hw_path(), c, src, dst)
+}
+
+/// Same as [`gf_mul_add_region`] on an explicit path. AVX-512 uses the AVX2
+/// kernel; NEON and paths the CPU cannot run use the scalar one.
+pub fn gf_mul_add_region_with(path: HwPath, c: u8, src: &[u8], dst: &mut [u8]) {
+    assert_eq!(src.len(), dst.len());
+    if c == 0 {
+        return;
+    }
+    let hw = kernel_support();
+    match path {
+        #[cfg(target_arch = "x86_64")]
+        HwPath::Avx2 | HwPath::Avx512 if hw.avx2 => unsafe { gf_mul_add_region_avx2(c, src, dst) },
+        #[cfg(target_arch = "x86_64")]
+        HwPath::Sse2 if hw.sse2 => unsafe { gf_mul_add_region_sse2(c, src, dst) },
+        _ => gf_mul_add_region_scalar(c, src, dst),
+    }
+}
+
+/// Multiplies `matrix` (one row per output) with the `inputs` regions:
+/// `outputs[r] = sum_j matrix[r][j] * inputs[j]`. Outputs are overwritten.
+pub fn gf_matrix_multiply(matrix: &[Vec<u8>], inputs: &[&[u8]], outputs: &mut [Vec<u8>]) {
+    let path = hw_path();
+    for (row, out) in matrix.iter().zip(outputs.iter_mut()) {
+        out.fill(0);
+        for (&coef, input) in row.iter().zip(inputs) {
+            let n = input.len().min(out.len());
+            gf_mul_add_region_with(path, coef, &input[..n], &mut out[..n]);
+        }
+    }
+}
+
+/// GF(2^8) kernel family used by the FEC encoder and decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HwPath {
+    Scalar = 0,
+    Sse2 = 1,
+    Avx2 = 2,
+    Avx512 = 3,
+    Neon = 4,
+}
+
+const NO_OVERRIDE: u8 = u8::MAX;
+static FORCED_HW_PATH: AtomicU8 = AtomicU8::new(NO_OVERRIDE);
+
+thread_local! {
+    // Set by `with_hw_path` for the duration of a call on this thread.
+    static SCOPED_HW_PATH: Cell<u8> = const { Cell::new(NO_OVERRIDE) };
+}
+
+/// The scoped path of this thread, else the forced one, if any.
+fn override_path() -> Option<HwPath> {
+    HwPath::from_u8(SCOPED_HW_PATH.with(Cell::get))
+        .or_else(|| HwPath::from_u8(FORCED_HW_PATH.load(Ordering::Relaxed)))
+}
+
+impl HwPath {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HwPath::Scalar => "scalar",
+            HwPath::Sse2 => "sse2",
+            HwPath::Avx2 => "avx2",
+            HwPath::Avx512 => "avx512",
+            HwPath::Neon => "neon",
+        }
+    }
+
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(HwPath::Scalar),
+            1 => Some(HwPath::Sse2),
+            2 => Some(HwPath::Avx2),
+            3 => Some(HwPath::Avx512),
+            4 => Some(HwPath::Neon),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the kernels for this path can run on the current CPU.
+    pub fn is_supported(self) -> bool {
+        let hw = kernel_support();
+        match self {
+            HwPath::Scalar => true,
+            HwPath::Sse2 => cfg!(target_arch = "x86_64") && hw.sse2 && hw.clmul,
+            HwPath::Avx2 => cfg!(target_arch = "x86_64") && hw.avx2 && hw.clmul,
+            HwPath::Avx512 => cfg!(target_arch = "x86_64") && hw.avx512 && hw.clmul,
+            HwPath::Neon => cfg!(target_arch = "aarch64") && hw.neon && hw.clmul,
+        }
+    }
+}
+
+/// Returns the path selected by runtime detection, ignoring any override.
+/// Mirrors the order used by [`optimize::dispatch_bitslice`].
+pub fn detected_hw_path() -> HwPath {
+    [HwPath::Avx512, HwPath::Avx2, HwPath::Sse2, HwPath::Neon]
+        .into_iter()
+        .find(|p| p.is_supported())
+        .unwrap_or(HwPath::Scalar)
+}
+
+/// Returns the GF(2^8) kernel path currently in use.
+pub fn hw_path() -> HwPath {
+    override_path().unwrap_or_else(detected_hw_path)
+}
+
+/// Forces all FEC instances onto the given kernel path, e.g. `Scalar` for
+/// reproducible benchmarks or debugging. Forcing `Scalar` disables SIMD GF
+/// arithmetic entirely. Paths the CPU cannot run are refused.
+pub fn force_hw_path(path: HwPath) -> Result<(), &'static str> {
+    if !path.is_supported() {
+        return Err("hardware path not supported on this CPU");
+    }
+    FORCED_HW_PATH.store(path as u8, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Removes a previous [`force_hw_path`] override.
+pub fn clear_hw_path_override() {
+    FORCED_HW_PATH.store(NO_OVERRIDE, Ordering::Relaxed);
+}
+
+/// Runs `f` with `path` as the kernel path of the calling thread only,
+/// taking precedence over [`force_hw_path`]. Unsupported paths run `f` on
+/// the current path.
+pub fn with_hw_path<R>(path: HwPath, f: impl FnOnce() -> R) -> R {
+    if !path.is_supported() {
+        return f();
+    }
+    struct Restore(u8);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED_HW_PATH.with(|p| p.set(self.0));
+        }
+    }
+    let _restore = Restore(SCOPED_HW_PATH.with(|p| p.replace(path as u8)));
+    f()
+}
+
+/// Runs `f` with the policy of the forced path, or the detected one.
+fn dispatch_gf<F, R>(f: F) -> R
+where
+    F: Fn(&dyn SimdPolicy) -> R,
+{
+    dispatch_gf_on(override_path(), f)
+}
+
+/// Runs `f` with the policy of `path`, or of runtime detection for `None`.
+fn dispatch_gf_on<F, R>(path: Option<HwPath>, f: F) -> R
+where
+    F: Fn(&dyn SimdPolicy) -> R,
+{
+    match path {
+        Some(HwPath::Scalar) => f(&optimize::Scalar),
+        Some(HwPath::Sse2) => f(&optimize::Sse2),
+        Some(HwPath::Avx2) => f(&optimize::Avx2),
+        Some(HwPath::Avx512) => f(&optimize::Avx512),
+        Some(HwPath::Neon) => f(&optimize::Neon),
+        None => optimize::dispatch_bitslice(f),
+    }
+}
+
 // --- High-Performance Finite Field Arithmetic (GF(2^8)) ---
 
 /// A dispatching wrapper for Galois Field (GF(2^8)) multiplication.
@@ -280,9 +553,22 @@ pub(crate) fn gf_mul_slice(a: &[u8], b: &[u8], out: &mut [u8]) {
 /// performant implementation of GF(2^8)) multiplication available on the current CPU
 /// architecture, ranging from table-lookups to SIMD-accelerated versions (PCLMULQDQ, NEON).
 #[inline(always)]
-pub(crate) fn gf_mul(a: u8, b: u8) -> u8 {
+pub fn gf_mul(a: u8, b: u8) -> u8 {
+    gf_mul_on(override_path(), a, b)
+}
+
+/// [`gf_mul`] on an explicit kernel path. Work handed to other threads,
+/// such as rayon workers, uses this with the path chosen by the caller,
+/// since [`with_hw_path`] only applies to the calling thread.
+#[inline(always)]
+pub fn gf_mul_with(path: HwPath, a: u8, b: u8) -> u8 {
+    gf_mul_on(Some(path), a, b)
+}
+
+#[inline(always)]
+fn gf_mul_on(path: Option<HwPath>, a: u8, b: u8) -> u8 {
     let mut result = 0;
-    optimize::dispatch_bitslice(|policy| {
+    dispatch_gf_on(path, |policy| {
         result = match policy {
             #[cfg(target_arch = "x86_64")]
             &optimize::Avx512 => unsafe { gf_mul_avx512(a, b) },
@@ -305,7 +591,7 @@ pub(crate) fn gf_inv(a: u8) -> u8 {
     if a == 0 {
         panic!("Inverse of 0 is undefined in GF(2^8))");
     }
-    unsafe { EXP_TABLE[255 - LOG_TABLE[a as usize] as usize] }
+    exp_table()[255 - log_table()[a as usize] as usize]
 }
 
 #[inline(always)]
@@ -315,10 +601,10 @@ pub(crate) fn gf_inv_prefetch(a: u8) -> u8 {
     }
     unsafe {
         prefetch_log(a as usize);
-        let log_a = LOG_TABLE[a as usize];
+        let log_a = log_table()[a as usize];
         let exp_idx = 255 - log_a as usize;
         prefetch_exp(exp_idx);
-        EXP_TABLE[exp_idx]
+        exp_table()[exp_idx]
     }
 }
 
@@ -328,50 +614,82 @@ pub(crate) fn gf_mul_add(a: u8, b: u8, c: u8) -> u8 {
     gf_mul(a, b) ^ c
 }
 
+/// [`gf_mul_add`] on an explicit kernel path, see [`gf_mul_with`].
+#[inline(always)]
+pub(crate) fn gf_mul_add_with(path: HwPath, a: u8, b: u8, c: u8) -> u8 {
+    gf_mul_with(path, a, b) ^ c
+}
+
 // --- GF(2^16) Arithmetic for Extreme Mode ---
 
 const GF16_POLY: u32 = 0x1100b;
+const GF16_ORDER: usize = 1 << 16;
 
-#[inline(always)]
-pub(crate) fn gf16_mul(mut a: u16, mut b: u16) -> u16 {
-    let mut res: u16 = 0;
-    optimize::dispatch(|_policy| {
-        // SIMD accelerated implementations could be plugged in here. For now we
-        // use the classic shift-and-add approach which is efficient for the
-        // small field size.
-        while b != 0 {
-            if (b & 1) != 0 {
-                res ^= a;
-            }
-            b >>= 1;
-            a <<= 1;
-            if (a & 0x10000) != 0 {
-                a ^= GF16_POLY as u16;
+/// Log/exp tables of GF(2^16), 128 KiB each. `exp` is doubled like its
+/// GF(2^8) counterpart so the sum of two logs needs no modulo.
+struct Gf16Tables {
+    log: Box<[u16]>,
+    exp: Box<[u16]>,
+}
+
+/// Built on the first GF(2^16) operation, i.e. when Extreme mode first
+/// encodes or decodes, so other modes never pay for the memory.
+static GF16_TABLES: OnceLock<Gf16Tables> = OnceLock::new();
+
+fn gf16_tables() -> &'static Gf16Tables {
+    GF16_TABLES.get_or_init(|| {
+        let mut log = vec![0u16; GF16_ORDER].into_boxed_slice();
+        let mut exp = vec![0u16; 2 * (GF16_ORDER - 1)].into_boxed_slice();
+        let mut x: u32 = 1;
+        for i in 0..GF16_ORDER - 1 {
+            exp[i] = x as u16;
+            exp[i + GF16_ORDER - 1] = x as u16;
+            log[x as usize] = i as u16;
+            x <<= 1;
+            if x & 0x10000 != 0 {
+                x ^= GF16_POLY;
             }
         }
-    });
-    res
+        Gf16Tables { log, exp }
+    })
 }
 
-#[inline(always)]
-pub(crate) fn gf16_pow(mut x: u16, mut power: u32) -> u16 {
-    let mut result: u16 = 1;
-    while power > 0 {
-        if power & 1 != 0 {
-            result = gf16_mul(result, x);
+/// Shift-and-add multiplication in GF(2^16), the reference for the table
+/// based [`gf16_mul`].
+pub fn gf16_mul_bitserial(a: u16, mut b: u16) -> u16 {
+    let mut a = a as u32;
+    let mut res: u32 = 0;
+    while b != 0 {
+        if (b & 1) != 0 {
+            res ^= a;
+        }
+        b >>= 1;
+        a <<= 1;
+        if (a & 0x10000) != 0 {
+            a ^= GF16_POLY;
         }
-        x = gf16_mul(x, x);
-        power >>= 1;
     }
-    result
+    res as u16
+}
+
+/// Multiplies in GF(2^16) through the log/exp tables.
+#[inline(always)]
+pub fn gf16_mul(a: u16, b: u16) -> u16 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    let t = gf16_tables();
+    t.exp[t.log[a as usize] as usize + t.log[b as usize] as usize]
 }
 
+/// Multiplicative inverse in GF(2^16).
 #[inline(always)]
-pub(crate) fn gf16_inv(x: u16) -> u16 {
+pub fn gf16_inv(x: u16) -> u16 {
     if x == 0 {
         panic!("Inverse of 0")
     }
-    gf16_pow(x, 0x1_0000 - 2)
+    let t = gf16_tables();
+    t.exp[GF16_ORDER - 1 - t.log[x as usize] as usize]
 }
 
 #[inline(always)]
@@ -384,15 +702,87 @@ pub(crate) fn gf16_mul_add(a: u16, b: u16, c: u16) -> u16 {
 const GF_ORDER: usize = 256;
 const IRREDUCIBLE_POLY: u16 = 0x11D; // Standard AES polynomial: x^8 + x^4 + x^3 + x^2 + 1
 
+/// Log/exp tables of GF(2^8). `exp` is doubled so the sum of two logs can
+/// index it without a modulo.
+struct GfTables {
+    log: [u8; GF_ORDER],
+    exp: [u8; GF_ORDER * 2],
+}
+
+impl GfTables {
+    const fn compute() -> Self {
+        let mut log = [0u8; GF_ORDER];
+        let mut exp = [0u8; GF_ORDER * 2];
+        let mut x: u16 = 1;
+        let mut i = 0;
+        while i < 255 {
+            exp[i] = x as u8;
+            exp[i + 255] = x as u8;
+            log[x as usize] = i as u8;
+            x <<= 1;
+            if x >= 256 {
+                x ^= IRREDUCIBLE_POLY;
+            }
+            i += 1;
+        }
+        Self { log, exp }
+    }
+}
+
+/// Tables evaluated at compile time. They back the arithmetic when the
+/// `static-gf-tables` feature is enabled and serve as the reference for the
+/// runtime tables otherwise.
+static PRECOMPUTED: GfTables = GfTables::compute();
+
+#[cfg(feature = "static-gf-tables")]
+#[inline(always)]
+fn log_table() -> &'static [u8; GF_ORDER] {
+    &PRECOMPUTED.log
+}
+
+#[cfg(feature = "static-gf-tables")]
+#[inline(always)]
+fn exp_table() -> &'static [u8; GF_ORDER * 2] {
+    &PRECOMPUTED.exp
+}
+
+/// Multiplies through the compile-time tables, independent of the active
+/// table source.
+pub fn gf_mul_precomputed(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    let sum_log = PRECOMPUTED.log[a as usize] as u16 + PRECOMPUTED.log[b as usize] as u16;
+    PRECOMPUTED.exp[sum_log as usize]
+}
+
+#[cfg(not(feature = "static-gf-tables"))]
 static mut LOG_TABLE: [u8; GF_ORDER] = [0; GF_ORDER];
+#[cfg(not(feature = "static-gf-tables"))]
 static mut EXP_TABLE: [u8; GF_ORDER * 2] = [0; GF_ORDER * 2];
 
+#[cfg(not(feature = "static-gf-tables"))]
+#[inline(always)]
+fn log_table() -> &'static [u8; GF_ORDER] {
+    // Only written once inside `init_gf_tables`.
+    unsafe { &*std::ptr::addr_of!(LOG_TABLE) }
+}
+
+#[cfg(not(feature = "static-gf-tables"))]
+#[inline(always)]
+fn exp_table() -> &'static [u8; GF_ORDER * 2] {
+    unsafe { &*std::ptr::addr_of!(EXP_TABLE) }
+}
+
 /// Initializes the Galois Field log/exp tables for fast arithmetic.
-/// This is a fallback for when SIMD is not available.
+/// This is a fallback for when SIMD is not available. With the
+/// `static-gf-tables` feature the tables are built at compile time and this
+/// is a no-op.
 pub fn init_gf_tables() {
-    static GF_INIT: std::sync::Once = std::sync::Once::new();
-    GF_INIT.call_once(|| {
-        unsafe {
+    #[cfg(not(feature = "static-gf-tables"))]
+    {
+        static GF_INIT: std::sync::Once = std::sync::Once::new();
+        GF_INIT.call_once(|| unsafe {
             let mut x: u16 = 1;
             for i in 0..255 {
                 EXP_TABLE[i] = x as u8;
@@ -403,6 +793,6 @@ pub fn init_gf_tables() {
                     x ^= IRREDUCIBLE_POLY;
                 }
             }
-        }
-    });
+        });
+    }
 }
@@ -53,6 +53,12 @@ pub use adaptive::*;
 pub mod decoder;
 pub use decoder::*;
 pub use encoder::*;
+pub mod reorder;
+pub use reorder::*;
+pub mod negotiation;
+pub use negotiation::*;
+pub mod cm256;
+pub use cm256::*;
 pub struct KalmanFilter {
     estimate: f32,
     error_cov: f32,
@@ -194,6 +200,7 @@ mod tests {
             lambda: 0.01,
             burst_window: 50,
             hysteresis: 0.02,
+            min_dwell_time: DEFAULT_MIN_DWELL,
             pid: PidConfig {
                 kp: 1.0,
                 ki: 0.0,
@@ -204,6 +211,12 @@ mod tests {
             kalman_q: 0.001,
             kalman_r: 0.01,
             window_sizes: FecConfig::default_windows(),
+            systematic: true,
+            max_recovery_delay: None,
+            autotune: false,
+            max_window: None,
+            oscillation: OscillationConfig::default(),
+            reactive: false,
         };
         let mut fec = AdaptiveFec::new(cfg, Arc::clone(&pool));
         fec.report_loss(18, 20);
@@ -218,6 +231,7 @@ mod tests {
             lambda: 0.01,
             burst_window: 50,
             hysteresis: 0.02,
+            min_dwell_time: DEFAULT_MIN_DWELL,
             pid: PidConfig {
                 kp: 1.0,
                 ki: 0.0,
@@ -228,6 +242,12 @@ mod tests {
             kalman_q: 0.001,
             kalman_r: 0.01,
             window_sizes: FecConfig::default_windows(),
+            systematic: true,
+            max_recovery_delay: None,
+            autotune: false,
+            max_window: None,
+            oscillation: OscillationConfig::default(),
+            reactive: false,
         };
         let mut fec = AdaptiveFec::new(cfg, Arc::clone(&pool));
         fec.report_loss(10, 20);
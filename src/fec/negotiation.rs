@@ -0,0 +1,199 @@
+use super::adaptive::{FecConfig, FecMode};
+use log::warn;
+
+/// Version of the offer format below. Endpoints speaking different versions
+/// run without FEC.
+pub const FEC_NEGOTIATION_VERSION: u8 = 1;
+
+/// Erasure codes an endpoint can encode and decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FecAlgorithm {
+    /// Cauchy Reed-Solomon over GF(2^8), used up to [`FecMode::Strong`].
+    CauchyGf8,
+    /// Cauchy Reed-Solomon over GF(2^16), used by [`FecMode::Extreme`].
+    CauchyGf16,
+}
+
+impl FecAlgorithm {
+    /// Bit of the algorithm in [`FecOffer::algorithms`].
+    pub fn bit(self) -> u8 {
+        match self {
+            FecAlgorithm::CauchyGf8 => 0x01,
+            FecAlgorithm::CauchyGf16 => 0x02,
+        }
+    }
+
+    /// Algorithm the decoders use for `mode`.
+    pub fn for_mode(mode: FecMode) -> Self {
+        if mode == FecMode::Extreme {
+            FecAlgorithm::CauchyGf16
+        } else {
+            FecAlgorithm::CauchyGf8
+        }
+    }
+}
+
+/// FEC parameters one endpoint proposes to its peer.
+///
+/// Wire format, 8 bytes: version, algorithm bitmask, mode, flags (bit 0:
+/// systematic), largest source window as big-endian `u32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FecOffer {
+    pub version: u8,
+    /// Bitmask of [`FecAlgorithm::bit`] values.
+    pub algorithms: u8,
+    /// Mode the endpoint wants to start in.
+    pub mode: FecMode,
+    /// Largest source window the endpoint accepts.
+    pub window: u32,
+    pub systematic: bool,
+}
+
+impl FecOffer {
+    /// Encoded length of an offer.
+    pub const LEN: usize = 8;
+
+    /// Builds the offer for a local configuration. The window is the one of
+    /// the initial mode, bounded by `max_window`.
+    pub fn from_config(config: &FecConfig) -> Self {
+        let mut window = config
+            .window_sizes
+            .get(&config.initial_mode)
+            .copied()
+            .unwrap_or(0);
+        if let Some(max) = config.max_window {
+            window = window.min(max);
+        }
+        Self {
+            version: FEC_NEGOTIATION_VERSION,
+            algorithms: FecAlgorithm::CauchyGf8.bit() | FecAlgorithm::CauchyGf16.bit(),
+            mode: config.initial_mode,
+            window: window.min(u32::MAX as usize) as u32,
+            systematic: config.systematic,
+        }
+    }
+
+    pub fn supports(&self, algorithm: FecAlgorithm) -> bool {
+        self.algorithms & algorithm.bit() != 0
+    }
+
+    pub fn encode(&self) -> [u8; Self::LEN] {
+        let mut out = [0u8; Self::LEN];
+        out[0] = self.version;
+        out[1] = self.algorithms;
+        out[2] = self.mode as u8;
+        out[3] = u8::from(self.systematic);
+        out[4..8].copy_from_slice(&self.window.to_be_bytes());
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, String> {
+        if buf.len() < Self::LEN {
+            return Err(format!("FEC offer too short: {} bytes", buf.len()));
+        }
+        let mode = match buf[2] {
+            0 => FecMode::Zero,
+            1 => FecMode::Light,
+            2 => FecMode::Normal,
+            3 => FecMode::Medium,
+            4 => FecMode::Strong,
+            5 => FecMode::Extreme,
+            m => return Err(format!("unknown FEC mode '{}'", m)),
+        };
+        Ok(Self {
+            version: buf[0],
+            algorithms: buf[1],
+            mode,
+            window: u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            systematic: buf[3] & 0x01 != 0,
+        })
+    }
+}
+
+/// Outcome of [`negotiate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FecAgreement {
+    Enabled {
+        algorithm: FecAlgorithm,
+        mode: FecMode,
+        window: usize,
+        systematic: bool,
+    },
+    /// The offers cannot be reconciled; both endpoints run without FEC.
+    Disabled(&'static str),
+}
+
+impl FecAgreement {
+    pub fn is_enabled(&self) -> bool {
+        matches!(self, FecAgreement::Enabled { .. })
+    }
+
+    /// Rewrites `config` to the agreed parameters. The agreed window becomes
+    /// the `max_window` of every mode so the controller stays within what
+    /// both endpoints accept. A disabled agreement starts in
+    /// [`FecMode::Zero`]; keeping it there is up to the caller.
+    pub fn apply(&self, config: &mut FecConfig) {
+        match *self {
+            FecAgreement::Enabled {
+                mode,
+                window,
+                systematic,
+                ..
+            } => {
+                config.initial_mode = mode;
+                if window > 0 {
+                    config.max_window = Some(window);
+                }
+                config.systematic = systematic;
+                if !systematic {
+                    config.reactive = false;
+                }
+            }
+            FecAgreement::Disabled(_) => config.initial_mode = FecMode::Zero,
+        }
+    }
+}
+
+/// Reconciles the local offer with the peer's.
+///
+/// The result does not depend on which side is local, so both endpoints
+/// reach the same agreement from the two offers. The stronger mode wins,
+/// falling back to [`FecMode::Strong`] when GF(2^16) is not shared; the
+/// window is the smaller non-empty one; repairs are systematic only if both
+/// sides want it. Version mismatches and offers without a common algorithm
+/// disable FEC with a warning.
+pub fn negotiate(local: &FecOffer, remote: &FecOffer) -> FecAgreement {
+    let agreement = reconcile(local, remote);
+    if let FecAgreement::Disabled(reason) = agreement {
+        warn!("FEC negotiation failed, disabling FEC: {}", reason);
+    }
+    agreement
+}
+
+fn reconcile(local: &FecOffer, remote: &FecOffer) -> FecAgreement {
+    if local.version != remote.version {
+        return FecAgreement::Disabled("version mismatch");
+    }
+    let common = local.algorithms & remote.algorithms;
+    let mut mode = local.mode.max(remote.mode);
+    if mode == FecMode::Extreme && common & FecAlgorithm::CauchyGf16.bit() == 0 {
+        mode = FecMode::Strong;
+    }
+    let algorithm = FecAlgorithm::for_mode(mode);
+    if common & algorithm.bit() == 0 {
+        return FecAgreement::Disabled("no common FEC algorithm");
+    }
+    let window = match (local.window, remote.window) {
+        (0, w) | (w, 0) => w,
+        (a, b) => a.min(b),
+    } as usize;
+    if mode != FecMode::Zero && window == 0 {
+        return FecAgreement::Disabled("no usable source window");
+    }
+    FecAgreement::Enabled {
+        algorithm,
+        mode,
+        window,
+        systematic: local.systematic && remote.systematic,
+    }
+}
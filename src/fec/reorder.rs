@@ -0,0 +1,94 @@
+use super::encoder::Packet;
+use std::collections::BTreeMap;
+
+/// Default reorder window in packets, see [`ReorderBuffer::new`].
+pub const DEFAULT_REORDER_WINDOW: usize = 64;
+
+/// Sliding-window reorder buffer placed in front of the decoder.
+///
+/// Packets are keyed by their sequence number (`Packet::id`) and grouped into
+/// blocks of `block_size` consecutive numbers, the same grouping the decoders
+/// use for source indices. A block is released once it is complete, or once
+/// a packet arrives `window` or more sequence numbers past the start of the
+/// oldest pending block; the block is then handed on with its gaps so FEC can
+/// recover the rest. Blocks, and the packets within them, always come out in
+/// sequence order.
+pub struct ReorderBuffer {
+    block_size: usize,
+    window: usize,
+    next_block: u64,
+    pending: BTreeMap<u64, Packet>,
+    late: u64,
+}
+
+impl ReorderBuffer {
+    /// Creates a buffer for blocks of `block_size` packets. The window is
+    /// raised to at least one block.
+    pub fn new(block_size: usize, window: usize) -> Self {
+        let block_size = block_size.max(1);
+        Self {
+            block_size,
+            window: window.max(block_size),
+            next_block: 0,
+            pending: BTreeMap::new(),
+            late: 0,
+        }
+    }
+
+    /// Reorder window in packets.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Number of packets currently held back.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Packets dropped because their block had already been released, or
+    /// because the same sequence number was seen before.
+    pub fn late_packets(&self) -> u64 {
+        self.late
+    }
+
+    /// Buffers `pkt` and returns every block that became ready, oldest first.
+    pub fn push(&mut self, pkt: Packet) -> Vec<Vec<Packet>> {
+        if pkt.id < self.next_block * self.block_size as u64 || self.pending.contains_key(&pkt.id) {
+            self.late += 1;
+            return Vec::new();
+        }
+        self.pending.insert(pkt.id, pkt);
+        self.drain(false)
+    }
+
+    /// Releases all buffered packets regardless of completeness.
+    pub fn flush(&mut self) -> Vec<Vec<Packet>> {
+        self.drain(true)
+    }
+
+    fn drain(&mut self, force: bool) -> Vec<Vec<Packet>> {
+        let bs = self.block_size as u64;
+        let mut out = Vec::new();
+        while let Some(&first) = self.pending.keys().next() {
+            // Skip blocks that were lost entirely.
+            self.next_block = self.next_block.max(first / bs);
+            let start = self.next_block * bs;
+            let end = start + bs;
+            let complete = self.pending.range(start..end).count() == self.block_size;
+            let newest = *self.pending.keys().next_back().unwrap();
+            let overflow = newest - start >= self.window as u64;
+            if !(force || complete || overflow) {
+                break;
+            }
+            let rest = self.pending.split_off(&end);
+            let block = std::mem::replace(&mut self.pending, rest);
+            out.push(block.into_values().collect());
+            self.next_block += 1;
+        }
+        out
+    }
+}
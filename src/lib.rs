@@ -5,13 +5,19 @@
 // and stealth techniques, consolidated into a single crate.
 
 pub mod core;
+pub mod congestion;
 pub mod crypto;
+pub mod datagram;
 pub mod fec;
 pub mod optimize;
 pub mod app_config;
 pub mod stealth;
+pub mod stream;
+pub mod transport;
+pub mod zero_rtt;
 pub mod xdp_socket;
 pub mod tls_ffi;
+pub mod cert_verify;
 pub mod fake_tls;
 pub mod telemetry;
 pub mod error;
@@ -1,14 +1,19 @@
 use crate::app_config::AppConfig;
-use crate::core::QuicFuscateConnection;
+use crate::congestion::CcAlgorithm;
+use crate::core::{
+    bind_addr_for, bind_udp, connect_racing, parse_peer_addr, parse_peer_addrs,
+    reconnect_with_backoff, QuicFuscateConnection, ReconnectPolicy, ServerDrain,
+    CONNECTION_ATTEMPT_DELAY,
+};
 use crate::fec::{FecConfig, FecMode};
 use crate::optimize::OptimizeConfig;
 #[cfg(unix)]
 use crate::optimize::ZeroCopyBuffer;
-use crate::stealth::StealthConfig;
-use crate::stealth::{BrowserProfile, FingerprintProfile, OsProfile};
+use crate::stealth::{BrowserProfile, FingerprintProfile, OsProfile, RotationSchedule};
+use crate::stealth::{StealthConfig, XorPattern};
 use crate::telemetry;
 use clap::{Parser, Subcommand, ValueEnum};
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 use std::collections::HashMap;
 use std::net::{SocketAddr, ToSocketAddrs};
 #[cfg(unix)]
@@ -16,7 +21,7 @@ use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::signal;
 use tokio::time;
 
@@ -30,6 +35,21 @@ struct Cli {
     /// Enable telemetry metrics
     #[clap(long, global = true)]
     telemetry: bool,
+    /// Print the CPU features relevant for cipher selection and exit
+    #[clap(long, global = true)]
+    print_crypto_features: bool,
+    /// List the cipher suites, their hardware support and the selected one
+    /// and exit
+    #[clap(long, global = true)]
+    list_ciphers: bool,
+    /// Run the encryption self-test of every cipher suite and exit; fails if
+    /// a usable suite does not pass
+    #[clap(long, global = true)]
+    crypto_self_test: bool,
+    /// Validate the configuration, print what would be set up and exit
+    /// without opening a socket
+    #[clap(long, global = true)]
+    dry_run: bool,
     #[clap(subcommand)]
     command: Commands,
 }
@@ -66,6 +86,15 @@ enum Commands {
         #[clap(long, default_value_t = 0)]
         profile_interval: u64,
 
+        /// Random jitter in seconds applied to each profile switching interval
+        #[clap(long, default_value_t = 0)]
+        profile_jitter: u64,
+
+        /// Pick a random profile of --profile-seq for every request instead
+        /// of switching on a timer
+        #[clap(long, conflicts_with = "profile_interval")]
+        profile_per_request: bool,
+
         /// Initial FEC mode
         #[clap(long, value_enum, default_value = "zero")]
         fec_mode: FecMode,
@@ -94,6 +123,10 @@ enum Commands {
         #[clap(long, value_name = "PATH")]
         fec_config: Option<PathBuf>,
 
+        /// Abort on any configuration load or validation error
+        #[clap(long)]
+        strict_config: bool,
+
         /// Custom DNS-over-HTTPS provider URL
         #[clap(long, default_value = "https://cloudflare-dns.com/dns-query")]
         doh_provider: String,
@@ -116,6 +149,12 @@ enum Commands {
         /// Enable certificate validation when connecting to the server
         #[clap(long)]
         verify_peer: bool,
+        /// Reconnect attempts after a failed handshake or dropped connection
+        #[clap(long, default_value_t = 5)]
+        max_retries: u32,
+        /// Initial reconnect delay in milliseconds, doubled on every retry
+        #[clap(long, default_value_t = 500)]
+        retry_backoff: u64,
 
         /// Disable DNS over HTTPS
         #[clap(long)]
@@ -129,6 +168,14 @@ enum Commands {
         #[clap(long)]
         disable_xor: bool,
 
+        /// Pre-shared XOR obfuscation key, hex encoded (at least 16 bytes)
+        #[clap(long, value_name = "HEX")]
+        xor_key: Option<String>,
+
+        /// How the XOR key stream evolves between packets (repeating, rolling)
+        #[clap(long, value_enum)]
+        xor_pattern: Option<XorPattern>,
+
         /// Disable HTTP/3 masquerading
         #[clap(long)]
         disable_http3: bool,
@@ -163,6 +210,10 @@ enum Commands {
         #[clap(long, default_value_t = 0)]
         profile_interval: u64,
 
+        /// Random jitter in seconds applied to each profile switching interval
+        #[clap(long, default_value_t = 0)]
+        profile_jitter: u64,
+
         /// Initial FEC mode
         #[clap(long, value_enum, default_value = "zero")]
         fec_mode: FecMode,
@@ -183,6 +234,15 @@ enum Commands {
         #[clap(long, value_name = "PATH")]
         fec_config: Option<PathBuf>,
 
+        /// Abort on any configuration load or validation error
+        #[clap(long)]
+        strict_config: bool,
+
+        /// Seconds to keep servicing existing connections after Ctrl-C
+        /// while refusing new ones (0 closes immediately)
+        #[clap(long, default_value_t = 0)]
+        drain_timeout: u64,
+
         /// Custom DNS-over-HTTPS provider URL
         #[clap(long, default_value = "https://cloudflare-dns.com/dns-query")]
         doh_provider: String,
@@ -203,6 +263,14 @@ enum Commands {
         #[clap(long)]
         disable_xor: bool,
 
+        /// Pre-shared XOR obfuscation key, hex encoded (at least 16 bytes)
+        #[clap(long, value_name = "HEX")]
+        xor_key: Option<String>,
+
+        /// How the XOR key stream evolves between packets (repeating, rolling)
+        #[clap(long, value_enum)]
+        xor_pattern: Option<XorPattern>,
+
         /// Disable HTTP/3 masquerading
         #[clap(long)]
         disable_http3: bool,
@@ -216,9 +284,47 @@ async fn main() -> std::io::Result<()> {
         std::env::set_var("RUST_LOG", "info");
     }
     env_logger::init();
+    let health = Arc::new(telemetry::HealthState::new());
     if cli.telemetry {
         telemetry::TELEMETRY_ENABLED.store(true, Ordering::Relaxed);
-        crate::telemetry::serve("0.0.0.0:9898");
+        crate::telemetry::serve_with_health("0.0.0.0:9898", health.clone());
+    }
+    if cli.print_crypto_features {
+        let selector = crate::crypto::CipherSuiteSelector::new();
+        println!("{}", crate::crypto::detected_features());
+        println!(
+            "selected suite: {:?} (hardware accelerated: {})",
+            selector.selected_suite(),
+            selector.is_hardware_accelerated()
+        );
+        return Ok(());
+    }
+    if cli.list_ciphers {
+        println!("{}", crate::crypto::cipher_listing());
+        return Ok(());
+    }
+    if cli.crypto_self_test {
+        let mut failed = false;
+        for (suite, res) in crate::crypto::self_test_all() {
+            match res {
+                Ok(()) => println!("{:?}: pass", suite),
+                // The plaintext stub is disabled by design, not broken.
+                Err(crate::error::CryptoError::InsecureStub) => {
+                    println!("{:?}: skipped (insecure stub disabled)", suite)
+                }
+                Err(e) => {
+                    failed = true;
+                    println!("{:?}: FAIL ({})", suite, e);
+                }
+            }
+        }
+        if failed {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "cipher self-test failed",
+            ));
+        }
+        return Ok(());
     }
 
     match &cli.command {
@@ -230,8 +336,11 @@ async fn main() -> std::io::Result<()> {
             os,
             profile_seq,
             profile_interval,
+            profile_jitter,
+            profile_per_request,
             fec_mode,
             fec_config,
+            strict_config,
             doh_provider,
             front_domain,
             ca_file,
@@ -239,9 +348,13 @@ async fn main() -> std::io::Result<()> {
             debug_tls,
             list_fingerprints,
             verify_peer,
+            max_retries,
+            retry_backoff,
             disable_doh,
             disable_fronting,
             disable_xor,
+            xor_key,
+            xor_pattern,
             disable_http3,
         } => {
             let browser = *profile;
@@ -254,6 +367,8 @@ async fn main() -> std::io::Result<()> {
                 os_profile,
                 profile_seq,
                 *profile_interval,
+                *profile_jitter,
+                *profile_per_request,
                 *fec_mode,
                 *pool_capacity,
                 *pool_block,
@@ -261,6 +376,7 @@ async fn main() -> std::io::Result<()> {
                 *xdp_stats,
                 config,
                 fec_config,
+                *strict_config,
                 &doh_provider,
                 &front_domain,
                 &ca_file,
@@ -268,10 +384,15 @@ async fn main() -> std::io::Result<()> {
                 *debug_tls,
                 *list_fingerprints,
                 *verify_peer,
+                *max_retries,
+                *retry_backoff,
                 *disable_doh,
                 *disable_fronting,
                 *disable_xor,
+                xor_key,
+                *xor_pattern,
                 *disable_http3,
+                cli.dry_run,
             )
             .await?;
         }
@@ -283,15 +404,20 @@ async fn main() -> std::io::Result<()> {
             os,
             profile_seq,
             profile_interval,
+            profile_jitter,
             fec_mode,
             pool_capacity,
             pool_block,
             fec_config,
+            strict_config,
+            drain_timeout,
             doh_provider,
             front_domain,
             disable_doh,
             disable_fronting,
             disable_xor,
+            xor_key,
+            xor_pattern,
             disable_http3,
         } => {
             let browser = *profile;
@@ -304,6 +430,7 @@ async fn main() -> std::io::Result<()> {
                 os_profile,
                 profile_seq,
                 *profile_interval,
+                *profile_jitter,
                 *fec_mode,
                 *pool_capacity,
                 *pool_block,
@@ -311,12 +438,18 @@ async fn main() -> std::io::Result<()> {
                 *xdp_stats,
                 config,
                 fec_config,
+                *strict_config,
+                *drain_timeout,
                 &doh_provider,
                 &front_domain,
                 *disable_doh,
                 *disable_fronting,
                 *disable_xor,
+                xor_key,
+                *xor_pattern,
                 *disable_http3,
+                cli.dry_run,
+                health.clone(),
             )
             .await?;
         }
@@ -361,6 +494,41 @@ fn parse_profile_entry(entry: &str, default_os: OsProfile) -> Option<Fingerprint
     Some(fp)
 }
 
+/// Applies `--xor-key` and `--xor-pattern` on top of the loaded stealth
+/// configuration. A malformed or too short key is an error.
+fn apply_xor_options(
+    stealth: &mut StealthConfig,
+    xor_key: &Option<String>,
+    xor_pattern: Option<XorPattern>,
+) -> std::io::Result<()> {
+    if let Some(hex_key) = xor_key {
+        let key = crate::stealth::parse_xor_key(hex_key)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        stealth.xor_key = Some(key);
+    }
+    if let Some(pattern) = xor_pattern {
+        stealth.xor_pattern = pattern;
+    }
+    Ok(())
+}
+
+/// Prints the plan for `--dry-run`, or every problem found and an error.
+fn report_dry_run(app: AppConfig) -> std::io::Result<()> {
+    match app.dry_run() {
+        Ok(plan) => {
+            println!("{}", plan);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                e.to_string(),
+            ))
+        }
+    }
+}
+
 async fn run_client(
     remote_addr_str: &str,
     local_addr_str: &str,
@@ -369,6 +537,8 @@ async fn run_client(
     os: OsProfile,
     profile_seq: &Option<Vec<String>>,
     profile_interval: u64,
+    profile_jitter: u64,
+    profile_per_request: bool,
     fec_mode: FecMode,
     pool_capacity: usize,
     pool_block: usize,
@@ -376,6 +546,7 @@ async fn run_client(
     xdp_stats: bool,
     config: &Option<PathBuf>,
     fec_config: &Option<PathBuf>,
+    strict_config: bool,
     doh_provider: &str,
     front_domain: &Vec<String>,
     ca_file: &Option<PathBuf>,
@@ -383,10 +554,15 @@ async fn run_client(
     debug_tls: bool,
     list_fingerprints: bool,
     verify_peer: bool,
+    max_retries: u32,
+    retry_backoff: u64,
     disable_doh: bool,
     disable_fronting: bool,
     disable_xor: bool,
+    xor_key: &Option<String>,
+    xor_pattern: Option<XorPattern>,
     disable_http3: bool,
+    dry_run: bool,
 ) -> std::io::Result<()> {
     let config_path = config.clone();
     if list_fingerprints {
@@ -401,9 +577,7 @@ async fn run_client(
         return Ok(());
     }
 
-    let server_addr = remote_addr_str.to_socket_addrs()?.next().ok_or_else(|| {
-        std::io::Error::new(std::io::ErrorKind::NotFound, "Server address not found")
-    })?;
+    let server_addrs = parse_peer_addrs(remote_addr_str, 443)?;
 
     let local_addr = local_addr_str.to_socket_addrs()?.next().ok_or_else(|| {
         std::io::Error::new(
@@ -412,12 +586,6 @@ async fn run_client(
         )
     })?;
 
-    let socket = std::net::UdpSocket::bind(local_addr)?;
-    socket.connect(server_addr)?;
-    socket.set_nonblocking(true)?;
-
-    info!("Client connecting to {}", server_addr);
-
     if xdp_stats {
         tokio::spawn(async move {
             loop {
@@ -435,12 +603,25 @@ async fn run_client(
         match AppConfig::from_file(cfg) {
             Ok(c) => {
                 if let Err(e) = c.validate() {
+                    if strict_config {
+                        error!("{}", e);
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            e.to_string(),
+                        ));
+                    }
                     warn!("Config validation failed: {}", e);
                 }
                 (c.fec, c.stealth, c.optimize)
             }
             Err(e) => {
                 error!("Failed to load config {}: {}", cfg.display(), e);
+                if strict_config {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        e.to_string(),
+                    ));
+                }
                 (
                     FecConfig::default(),
                     StealthConfig::default(),
@@ -453,12 +634,22 @@ async fn run_client(
             match FecConfig::from_file(path) {
                 Ok(cfg) => {
                     if let Err(e) = cfg.validate() {
+                        if strict_config {
+                            error!("FEC config validation failed: {}", e);
+                            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+                        }
                         warn!("FEC config validation failed: {}", e);
                     }
                     cfg
                 }
                 Err(e) => {
                     error!("Failed to load FEC config {}: {}", path.display(), e);
+                    if strict_config {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            e.to_string(),
+                        ));
+                    }
                     FecConfig::default()
                 }
             }
@@ -469,27 +660,9 @@ async fn run_client(
     };
     fec_cfg.initial_mode = fec_mode;
 
-    let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
-    config
-        .set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
-        .unwrap();
-    config.set_max_idle_timeout(30000);
-    config.set_max_recv_udp_payload_size(1460);
-    config.set_max_send_udp_payload_size(1200);
-    config.set_initial_max_data(10_000_000);
-    config.set_initial_max_stream_data_bidi_local(1_000_000);
-    config.set_initial_max_stream_data_bidi_remote(1_000_000);
-    config.set_initial_max_streams_bidi(100);
-    config.set_initial_max_streams_uni(100);
-    config.verify_peer(verify_peer);
-    if debug_tls {
-        config.log_keys();
-    }
-    if let Some(path) = ca_file {
-        if let Err(e) = config.load_verify_locations_from_file(path.to_str().unwrap()) {
-            error!("Failed to load CA file {}: {}", path.display(), e);
-        }
-    }
+    // Checked up front so a bad CA file stops setup before any attempt;
+    // every racing attempt builds its own configuration.
+    client_quiche_config(ca_file, verify_peer, debug_tls)?;
 
     let url_parsed =
         url::Url::parse(url).unwrap_or_else(|_| url::Url::parse("https://example.com/").unwrap());
@@ -501,35 +674,44 @@ async fn run_client(
     stealth_config.enable_domain_fronting = !disable_fronting;
     stealth_config.fronting_domains = front_domain.clone();
     stealth_config.enable_xor_obfuscation = !disable_xor;
+    apply_xor_options(&mut stealth_config, xor_key, xor_pattern)?;
     stealth_config.enable_http3_masquerading = !disable_http3;
     telemetry!(telemetry::STEALTH_BROWSER_PROFILE.set(stealth_config.browser_profile as i64));
     telemetry!(telemetry::STEALTH_OS_PROFILE.set(stealth_config.os_profile as i64));
 
-    let host = url_parsed.host_str().unwrap_or("example.com");
+    // IPv6 literals come back bracketed, SNI and the certificate check want
+    // the bare address.
+    let host = url_parsed
+        .host_str()
+        .unwrap_or("example.com")
+        .trim_start_matches('[')
+        .trim_end_matches(']');
     let opt_params = if config_path.is_some() {
         OptimizeConfig {
             pool_capacity: opt_cfg.pool_capacity,
             block_size: opt_cfg.block_size,
             enable_xdp: opt_cfg.enable_xdp || xdp,
+            strict_pool_capacity: opt_cfg.strict_pool_capacity,
+            congestion_control: opt_cfg.congestion_control,
         }
     } else {
         OptimizeConfig {
             pool_capacity,
             block_size: pool_block,
             enable_xdp: xdp,
+            strict_pool_capacity: false,
+            congestion_control: CcAlgorithm::Bbr,
         }
     };
-    let mut conn = QuicFuscateConnection::new_client(
-        host,
-        local_addr,
-        server_addr,
-        config,
-        stealth_config.clone(),
-        fec_cfg,
-        opt_params,
-        !no_utls,
-    )
-    .expect("failed to create client connection");
+
+    if dry_run {
+        println!("client {} -> {} ({})", local_addr, server_addrs[0], host);
+        return report_dry_run(AppConfig {
+            fec: fec_cfg,
+            stealth: stealth_config,
+            optimize: opt_params,
+        });
+    }
 
     let profiles: Vec<FingerprintProfile> = match profile_seq {
         Some(seq) => seq
@@ -539,7 +721,7 @@ async fn run_client(
         None => vec![FingerprintProfile::new(profile, os)],
     };
 
-    if profile_interval > 0 && profiles.is_empty() {
+    if (profile_interval > 0 || profile_per_request) && profiles.is_empty() {
         error!("No valid profiles supplied with --profile-seq");
         return Err(std::io::Error::new(
             std::io::ErrorKind::InvalidInput,
@@ -547,93 +729,302 @@ async fn run_client(
         ));
     }
 
-    if profile_interval > 0 && profiles.len() > 1 {
-        let sm = conn.stealth_manager();
-        sm.start_profile_rotation(profiles, std::time::Duration::from_secs(profile_interval));
-    }
-
-    let mut buf = [0; 65535];
-    let mut out = [0; 65535];
-
-    // Send initial packet
-    if let Ok(len) = conn.send(&mut out) {
-        if len > 0 {
-            telemetry!(telemetry::BYTES_SENT.inc_by(len as u64));
-            #[cfg(unix)]
-            {
-                let zc = ZeroCopyBuffer::new(&[&out[..len]]);
-                zc.send(socket.as_raw_fd());
+    // Ctrl-C ends the current session and stops any further reconnects.
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        if signal::ctrl_c().await.is_ok() {
+            let _ = shutdown_tx.send(true);
+        }
+    });
+
+    let host = host.to_string();
+    let path = url_parsed.path().to_string();
+    let policy = ReconnectPolicy::new(max_retries, Duration::from_millis(retry_backoff));
+    reconnect_with_backoff(
+        &policy,
+        |attempt| {
+            // Resolve again and move on to the next profile of the sequence
+            // so a retry does not repeat the fingerprint that just failed.
+            let server_addrs = parse_peer_addrs(remote_addr_str, 443);
+            let ca_file = ca_file.clone();
+            let mut stealth_config = stealth_config.clone();
+            if !profiles.is_empty() {
+                let p = &profiles[attempt as usize % profiles.len()];
+                stealth_config.browser_profile = p.browser;
+                stealth_config.os_profile = p.os;
+            }
+            let session = ClientSession {
+                host: host.clone(),
+                path: path.clone(),
+                local_addr,
+                stealth_config,
+                fec_cfg: fec_cfg.clone(),
+                opt_params,
+                utls: !no_utls,
+                profiles: profiles.clone(),
+                rotation: (profiles.len() > 1)
+                    .then(|| {
+                        if profile_per_request {
+                            Some(RotationSchedule::PerRequest)
+                        } else {
+                            (profile_interval > 0).then(|| {
+                                RotationSchedule::jittered(
+                                    Duration::from_secs(profile_interval),
+                                    Duration::from_secs(profile_jitter),
+                                )
+                            })
+                        }
+                    })
+                    .flatten(),
+                shutdown: shutdown_rx.clone(),
+            };
+            async move {
+                if *session.shutdown.borrow() {
+                    return Ok(());
+                }
+                let server_addrs = server_addrs?;
+                let make_config = || client_quiche_config(&ca_file, verify_peer, debug_tls);
+                let mut shutdown = session.shutdown.clone();
+                let (socket, conn) = tokio::select! {
+                    _ = shutdown.changed() => return Ok(()),
+                    res = session.connect(&server_addrs, make_config) => res?,
+                };
+                session.run(socket, conn).await
             }
-            #[cfg(not(unix))]
-            {
-                socket.send(&out[..len])?;
+        },
+        tokio::time::sleep,
+    )
+    .await
+}
+
+/// Builds the quiche client configuration. The CA file is checked up front
+/// so a missing, empty or certificate-less file stops setup instead of
+/// silently leaving verification open.
+fn client_quiche_config(
+    ca_file: &Option<PathBuf>,
+    verify_peer: bool,
+    debug_tls: bool,
+) -> std::io::Result<quiche::Config> {
+    let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    config
+        .set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
+        .unwrap();
+    config.set_max_idle_timeout(30000);
+    config.set_max_recv_udp_payload_size(1460);
+    config.set_max_send_udp_payload_size(1200);
+    config.set_initial_max_data(10_000_000);
+    config.set_initial_max_stream_data_bidi_local(1_000_000);
+    config.set_initial_max_stream_data_bidi_remote(1_000_000);
+    config.set_initial_max_streams_bidi(100);
+    config.set_initial_max_streams_uni(100);
+    config.verify_peer(verify_peer);
+    if debug_tls {
+        config.log_keys();
+    }
+    if let Some(path) = ca_file {
+        let loaded = crate::cert_verify::load_ca_file(path)
+            .map_err(|e| e.to_string())
+            .and_then(|_| {
+                config
+                    .load_verify_locations_from_file(&path.to_string_lossy())
+                    .map_err(|e| format!("failed to load CA certificates: {}: {e}", path.display()))
+            });
+        if let Err(e) = loaded {
+            if verify_peer {
+                error!("{}", e);
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
             }
-            info!("Sent initial packet of size {}", len);
+            warn!("{} (ignored, peer verification is off)", e);
         }
     }
+    Ok(config)
+}
 
-    let mut request_sent = false;
-    let mut shutdown = signal::ctrl_c();
-    tokio::pin!(shutdown);
+/// Everything one client connection attempt needs, owned so that each
+/// attempt of the reconnect loop starts from a clean state.
+struct ClientSession {
+    host: String,
+    path: String,
+    local_addr: SocketAddr,
+    stealth_config: StealthConfig,
+    fec_cfg: FecConfig,
+    opt_params: OptimizeConfig,
+    utls: bool,
+    profiles: Vec<FingerprintProfile>,
+    rotation: Option<RotationSchedule>,
+    shutdown: tokio::sync::watch::Receiver<bool>,
+}
 
-    loop {
-        tokio::select! {
-            _ = &mut shutdown => {
-                info!("Shutdown signal received");
-                let _ = conn.conn.close(true, 0x0, b"ctrl_c");
-                break;
-            }
-            _ = async {
-                // Process incoming packets
-                match {
-                    #[cfg(unix)]
-                    {
-                        let mut slice = [&mut buf[..]];
-                        let mut zc = ZeroCopyBuffer::new_mut(&mut slice);
-                        let r = zc.recv(socket.as_raw_fd());
-                        if r >= 0 { Ok(r as usize) } else { Err(std::io::Error::last_os_error()) }
+impl ClientSession {
+    /// Races handshakes to all `server_addrs` (RFC 8305) and returns the
+    /// socket and connection of the first to complete; the other attempts
+    /// are dropped.
+    async fn connect(
+        &self,
+        server_addrs: &[SocketAddr],
+        make_config: impl Fn() -> std::io::Result<quiche::Config>,
+    ) -> std::io::Result<(std::net::UdpSocket, QuicFuscateConnection)> {
+        let (_, established) =
+            connect_racing(server_addrs, CONNECTION_ATTEMPT_DELAY, |server_addr| {
+                let config = make_config();
+                let host = self.host.clone();
+                let local_addr = self.local_addr;
+                let stealth_config = self.stealth_config.clone();
+                let fec_cfg = self.fec_cfg.clone();
+                let opt_params = self.opt_params;
+                let utls = self.utls;
+                async move {
+                    // Bind in the peer's family, a wildcard v4 bind cannot
+                    // reach a v6 server and vice versa.
+                    let socket = bind_udp(bind_addr_for(local_addr, server_addr))?;
+                    socket.connect(server_addr)?;
+                    socket.set_nonblocking(true)?;
+                    info!(
+                        "Client connecting to {} as {:?}@{:?}",
+                        server_addr, stealth_config.browser_profile, stealth_config.os_profile
+                    );
+                    let mut conn = QuicFuscateConnection::new_client(
+                        &host,
+                        socket.local_addr()?,
+                        server_addr,
+                        config?,
+                        stealth_config,
+                        fec_cfg,
+                        opt_params,
+                        utls,
+                    )
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                    handshake(&socket, &mut conn).await?;
+                    Ok::<_, std::io::Error>((socket, conn))
+                }
+            })
+            .await?;
+        Ok(established)
+    }
+
+    /// Drives one established connection. Returns `Ok` once the user asked
+    /// to shut down and an error when the connection drops, which makes the
+    /// caller reconnect.
+    async fn run(
+        self,
+        socket: std::net::UdpSocket,
+        mut conn: QuicFuscateConnection,
+    ) -> std::io::Result<()> {
+        let ClientSession {
+            path,
+            profiles,
+            rotation,
+            mut shutdown,
+            ..
+        } = self;
+
+        if let Some(schedule) = rotation {
+            conn.stealth_manager()
+                .start_profile_rotation(profiles, schedule);
+        }
+
+        let mut buf = [0; 65535];
+        let mut out = [0; 65535];
+
+        let mut request_sent = false;
+
+        loop {
+            telemetry!(telemetry::flush_local());
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    info!("Shutdown signal received");
+                    let _ = conn.conn.close(true, 0x0, b"ctrl_c");
+                    return Ok(());
+                }
+                res = async {
+                    // Process incoming packets
+                    match recv_datagram(&socket, &mut buf) {
+                        Ok(len) => {
+                            telemetry!(telemetry::inc_local(&telemetry::BYTES_RECEIVED, len as u64));
+                            let _ = conn.recv(&buf[..len]);
+                        }
+                        Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                        Err(e) => {
+                            error!("Failed to read from socket: {}", e);
+                            return Err(e);
+                        }
                     }
-                    #[cfg(not(unix))]
-                    {
-                        socket.recv(&mut buf)
+
+                    if conn.conn.is_established() && !request_sent {
+                        if let Err(e) = conn.send_http3_request(&path) {
+                            warn!("HTTP/3 request failed: {:?}", e);
+                        } else {
+                            request_sent = true;
+                        }
                     }
-                } {
-                    Ok(len) => {
-                        telemetry!(telemetry::BYTES_RECEIVED.inc_by(len as u64));
-                        let _ = conn.recv(&buf[..len]);
+
+                    if let Err(e) = conn.poll_http3() {
+                        warn!("HTTP/3 error: {:?}", e);
                     }
-                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
-                    Err(e) => {
-                        error!("Failed to read from socket: {}", e);
-                        return;
+
+                    loop {
+                        match conn.send(&mut out) {
+                            Ok(len) if len > 0 => {
+                                telemetry!(telemetry::inc_local(&telemetry::BYTES_SENT, len as u64));
+                                send_datagram(&socket, &out[..len])?;
+                            }
+                            Ok(_) => break,
+                            Err(crate::error::ConnectionError::Quiche(quiche::Error::Done)) => break,
+                            Err(e) => {
+                                error!("Send failed: {:?}", e);
+                                break;
+                            }
+                        }
                     }
-                }
 
-        if conn.conn.is_established() && !request_sent {
-            if let Err(e) = conn.send_http3_request(url_parsed.path()) {
-                warn!("HTTP/3 request failed: {:?}", e);
-            } else {
-                request_sent = true;
+                    conn.update_state();
+                    info!(
+                        "client stats: RTT {:.0} ms, Loss {:.2}%",
+                        conn.stats().rtt,
+                        conn.stats().loss_rate * 100.0
+                    );
+                    conn.conn.on_timeout();
+
+                    // Sleep to avoid busy-looping
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    Ok(())
+                } => res?,
             }
-        }
 
-        if let Err(e) = conn.poll_http3() {
-            warn!("HTTP/3 error: {:?}", e);
+            if conn.conn.is_closed() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionAborted,
+                    format!("connection dropped ({:?})", conn.conn.peer_error()),
+                ));
+            }
         }
+    }
+}
 
+/// Drives the handshake of `conn` over the connected `socket` until it
+/// completes, pacing the handshake datagrams like the claimed browser
+/// instead of writing them back to back.
+async fn handshake(
+    socket: &std::net::UdpSocket,
+    conn: &mut QuicFuscateConnection,
+) -> std::io::Result<()> {
+    let stealth = conn.stealth_manager();
+    let mut buf = vec![0; 65535];
+    let mut out = vec![0; 65535];
+    let mut initial = true;
+    loop {
         loop {
             match conn.send(&mut out) {
                 Ok(len) if len > 0 => {
-                    telemetry!(telemetry::BYTES_SENT.inc_by(len as u64));
-                    #[cfg(unix)]
-                    {
-                        let zc = ZeroCopyBuffer::new(&[&out[..len]]);
-                        zc.send(socket.as_raw_fd());
-                    }
-                    #[cfg(not(unix))]
-                    {
-                        socket.send(&out[..len])?;
+                    // The Initial goes out immediately, it only starts the
+                    // timing model.
+                    let delay = stealth.next_handshake_delay();
+                    if !initial && !delay.is_zero() {
+                        time::sleep(delay).await;
                     }
+                    initial = false;
+                    telemetry!(telemetry::inc_local(&telemetry::BYTES_SENT, len as u64));
+                    send_datagram(socket, &out[..len])?;
                 }
                 Ok(_) => break,
                 Err(crate::error::ConnectionError::Quiche(quiche::Error::Done)) => break,
@@ -644,21 +1035,62 @@ async fn run_client(
             }
         }
 
-                conn.update_state();
-                info!(
-                    "client stats: RTT {:.0} ms, Loss {:.2}%",
-                    conn.stats.rtt,
-                    conn.stats.loss_rate * 100.0
-                );
-                conn.conn.on_timeout();
+        if conn.conn.is_established() {
+            return Ok(());
+        }
+        if conn.conn.is_closed() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("handshake failed ({:?})", conn.conn.peer_error()),
+            ));
+        }
 
-                // Sleep to avoid busy-looping
-                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
-            } => {}
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        loop {
+            match recv_datagram(socket, &mut buf) {
+                Ok(len) => {
+                    telemetry!(telemetry::inc_local(&telemetry::BYTES_RECEIVED, len as u64));
+                    let _ = conn.recv(&buf[..len]);
+                }
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
         }
+        conn.conn.on_timeout();
     }
+}
 
-    Ok(())
+/// Writes one datagram to the connected `socket`.
+fn send_datagram(socket: &std::net::UdpSocket, data: &[u8]) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let zc = ZeroCopyBuffer::new(&[data]);
+        zc.send(socket.as_raw_fd());
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        socket.send(data).map(|_| ())
+    }
+}
+
+/// Reads one datagram from the connected, non-blocking `socket`.
+fn recv_datagram(socket: &std::net::UdpSocket, buf: &mut [u8]) -> std::io::Result<usize> {
+    #[cfg(unix)]
+    {
+        let mut slice = [buf];
+        let mut zc = ZeroCopyBuffer::new_mut(&mut slice);
+        let r = zc.recv(socket.as_raw_fd());
+        if r >= 0 {
+            Ok(r as usize)
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        socket.recv(buf)
+    }
 }
 
 async fn run_server(
@@ -669,6 +1101,7 @@ async fn run_server(
     os: OsProfile,
     profile_seq: &Option<Vec<String>>,
     profile_interval: u64,
+    profile_jitter: u64,
     fec_mode: FecMode,
     pool_capacity: usize,
     pool_block: usize,
@@ -676,17 +1109,20 @@ async fn run_server(
     xdp_stats: bool,
     config: &Option<PathBuf>,
     fec_config: &Option<PathBuf>,
+    strict_config: bool,
+    drain_timeout: u64,
     doh_provider: &str,
     front_domain: &Vec<String>,
     disable_doh: bool,
     disable_fronting: bool,
     disable_xor: bool,
+    xor_key: &Option<String>,
+    xor_pattern: Option<XorPattern>,
     disable_http3: bool,
+    dry_run: bool,
+    health: Arc<telemetry::HealthState>,
 ) -> std::io::Result<()> {
     let config_path = config.clone();
-    let socket = std::net::UdpSocket::bind(listen_addr)?;
-    socket.set_nonblocking(true)?;
-    info!("Server listening on {}", listen_addr);
 
     if xdp_stats {
         tokio::spawn(async move {
@@ -705,12 +1141,25 @@ async fn run_server(
         match AppConfig::from_file(cfg) {
             Ok(c) => {
                 if let Err(e) = c.validate() {
+                    if strict_config {
+                        error!("{}", e);
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            e.to_string(),
+                        ));
+                    }
                     warn!("Config validation failed: {}", e);
                 }
                 (c.fec, c.stealth, c.optimize)
             }
             Err(e) => {
                 error!("Failed to load config {}: {}", cfg.display(), e);
+                if strict_config {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        e.to_string(),
+                    ));
+                }
                 (
                     FecConfig::default(),
                     StealthConfig::default(),
@@ -723,12 +1172,22 @@ async fn run_server(
             match FecConfig::from_file(path) {
                 Ok(cfg) => {
                     if let Err(e) = cfg.validate() {
+                        if strict_config {
+                            error!("FEC config validation failed: {}", e);
+                            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+                        }
                         warn!("FEC config validation failed: {}", e);
                     }
                     cfg
                 }
                 Err(e) => {
                     error!("Failed to load FEC config {}: {}", path.display(), e);
+                    if strict_config {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            e.to_string(),
+                        ));
+                    }
                     FecConfig::default()
                 }
             }
@@ -772,6 +1231,7 @@ async fn run_server(
         sc.enable_domain_fronting = !disable_fronting;
         sc.fronting_domains = front_domain.clone();
         sc.enable_xor_obfuscation = !disable_xor;
+        apply_xor_options(&mut sc, xor_key, xor_pattern)?;
         sc.enable_http3_masquerading = !disable_http3;
         telemetry!(telemetry::STEALTH_BROWSER_PROFILE.set(sc.browser_profile as i64));
         telemetry!(telemetry::STEALTH_OS_PROFILE.set(sc.os_profile as i64));
@@ -781,15 +1241,33 @@ async fn run_server(
             pool_capacity: opt_cfg.pool_capacity,
             block_size: opt_cfg.block_size,
             enable_xdp: opt_cfg.enable_xdp || xdp,
+            strict_pool_capacity: opt_cfg.strict_pool_capacity,
+            congestion_control: opt_cfg.congestion_control,
         }
     } else {
         OptimizeConfig {
             pool_capacity,
             block_size: pool_block,
             enable_xdp: xdp,
+            strict_pool_capacity: false,
+            congestion_control: CcAlgorithm::Bbr,
         }
     };
 
+    if dry_run {
+        println!("server on {}", listen_addr);
+        return report_dry_run(AppConfig {
+            fec: fec_cfg,
+            stealth: stealth_config.lock().unwrap().clone(),
+            optimize: opt_params,
+        });
+    }
+
+    let socket = bind_udp(parse_peer_addr(listen_addr, 4433)?)?;
+    socket.set_nonblocking(true)?;
+    info!("Server listening on {}", listen_addr);
+    health.set_listening(true);
+
     let profiles: Vec<FingerprintProfile> = match profile_seq {
         Some(seq) => seq
             .iter()
@@ -808,10 +1286,14 @@ async fn run_server(
 
     if profile_interval > 0 && profiles.len() > 1 {
         let cfg = stealth_config.clone();
+        let schedule = RotationSchedule::jittered(
+            std::time::Duration::from_secs(profile_interval),
+            std::time::Duration::from_secs(profile_jitter),
+        );
         tokio::spawn(async move {
             let mut idx = 0usize;
-            loop {
-                tokio::time::sleep(std::time::Duration::from_secs(profile_interval)).await;
+            while let Some(interval) = schedule.next_interval() {
+                tokio::time::sleep(interval).await;
                 idx = (idx + 1) % profiles.len();
                 let mut guard = cfg.lock().unwrap();
                 guard.browser_profile = profiles[idx].browser;
@@ -822,11 +1304,30 @@ async fn run_server(
 
     let mut shutdown = signal::ctrl_c();
     tokio::pin!(shutdown);
+    let mut drain = ServerDrain::new();
 
     loop {
+        telemetry!(telemetry::flush_local());
+        if drain.finished(Instant::now(), clients.len()) {
+            info!("Drain finished, closing {} connection(s)", clients.len());
+            for conn in clients.values_mut() {
+                let _ = conn.conn.close(true, 0x0, b"drain_timeout");
+            }
+            break;
+        }
         tokio::select! {
-            _ = &mut shutdown => {
+            _ = &mut shutdown, if !drain.is_draining() => {
                 info!("Shutdown signal received");
+                if drain_timeout > 0 {
+                    info!(
+                        "Draining {} connection(s) for up to {}s",
+                        clients.len(),
+                        drain_timeout
+                    );
+                    drain.start(std::time::Duration::from_secs(drain_timeout), Instant::now());
+                    health.set_draining(true);
+                    continue;
+                }
                 for conn in clients.values_mut() {
                     let _ = conn.conn.close(true, 0x0, b"ctrl_c");
                 }
@@ -835,10 +1336,10 @@ async fn run_server(
             _ = async {
                 match socket.recv_from(&mut buf) {
             Ok((len, from)) => {
-                telemetry!(telemetry::BYTES_RECEIVED.inc_by(len as u64));
+                telemetry!(telemetry::inc_local(&telemetry::BYTES_RECEIVED, len as u64));
                 info!("Received {} bytes from {}", len, from);
-                let client_conn = clients.entry(from).or_insert_with(|| {
-                    info!("New client connected: {}", from);
+                let is_new = !clients.contains_key(&from);
+                let Some(client_conn) = drain.admit(&mut clients, from, || {
                     let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
                     let cfg = stealth_config.lock().unwrap().clone();
                     QuicFuscateConnection::new_server(
@@ -852,15 +1353,22 @@ async fn run_server(
                         opt_params,
                     )
                     .expect("failed to create server connection")
-                });
+                }) else {
+                    debug!("Refusing new peer {} while draining", from);
+                    continue;
+                };
+                let _span = client_conn.span().clone().entered();
+                if is_new {
+                    tracing::info!("New client connected: {}", from);
+                }
 
                 if let Err(e) = client_conn.recv(&buf[..len]) {
-                    error!("QUIC recv failed: {:?}", e);
+                    tracing::error!("QUIC recv failed: {:?}", e);
                     continue;
                 }
 
                 if let Err(e) = client_conn.poll_http3() {
-                    warn!("HTTP/3 error: {:?}", e);
+                    tracing::warn!("HTTP/3 error: {:?}", e);
                 }
             }
             Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -874,34 +1382,48 @@ async fn run_server(
 
         // Send packets for all clients
         for (addr, conn) in clients.iter_mut() {
+            let _span = conn.span().clone().entered();
             loop {
-                match conn.send(&mut out) {
-                    Ok(len) if len > 0 => {
-                        telemetry!(telemetry::BYTES_SENT.inc_by(len as u64));
-                        if let Err(e) = socket.send_to(&out[..len], addr) {
-                            error!("Failed to send packet to {}: {}", addr, e);
+                match conn.send_with_info(&mut out) {
+                    Ok((len, info)) if len > 0 => {
+                        telemetry!(telemetry::inc_local(&telemetry::BYTES_SENT, len as u64));
+                        if let Err(e) = socket.send_to(&out[..len], info.to) {
+                            tracing::error!("Failed to send packet to {}: {}", info.to, e);
                         }
                     }
                     Ok(_) => break,
                     Err(crate::error::ConnectionError::Quiche(quiche::Error::Done)) => break,
                     Err(e) => {
-                        error!("Send failed to {}: {:?}", addr, e);
+                        tracing::error!("Send failed to {}: {:?}", addr, e);
                         break;
                     }
                 }
             }
             conn.update_state();
-            info!(
+            tracing::info!(
                 "client {} stats: RTT {:.0} ms, Loss {:.2}%",
                 addr,
-                conn.stats.rtt,
-                conn.stats.loss_rate * 100.0
+                conn.stats().rtt,
+                conn.stats().loss_rate * 100.0
             );
             conn.conn.on_timeout();
         }
 
                 // Clean up closed connections
-                clients.retain(|_, conn| !conn.conn.is_closed());
+                clients.retain(|addr, conn| {
+                    if !conn.conn.is_closed() {
+                        return true;
+                    }
+                    let _span = conn.span().clone().entered();
+                    match conn.close_reason() {
+                        Some((code, reason)) => tracing::info!(
+                            "client {} closed: code {:#x}, reason {:?}",
+                            addr, code, reason
+                        ),
+                        None => tracing::info!("client {} closed", addr),
+                    }
+                    false
+                });
 
                 // Sleep to avoid busy-looping
                 tokio::time::sleep(std::time::Duration::from_millis(10)).await;
@@ -909,5 +1431,6 @@ async fn run_server(
         }
     }
 
+    health.set_listening(false);
     Ok(())
 }
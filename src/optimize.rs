@@ -35,6 +35,9 @@
 //! function dispatching to select the best hardware-accelerated implementation.
 //! It also includes foundational structures for zero-copy operations and memory pooling.
 
+use crate::app_config::{ConfigError, ConfigIssue};
+use crate::congestion::CcAlgorithm;
+use crate::error::PoolError;
 use crate::telemetry;
 use crate::xdp_socket::XdpSocket;
 use aligned_box::AlignedBox;
@@ -50,8 +53,9 @@ use std::io;
 use std::net::SocketAddr;
 #[cfg(unix)]
 use std::os::unix::io::RawFd;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, Once};
+use std::time::{Duration, Instant};
 #[cfg(windows)]
 use windows_sys::Win32::Networking::WinSock::{WSARecvMsg, WSASendMsg, WSABUF, WSAMSG};
 
@@ -130,6 +134,11 @@ pub struct OptimizeConfig {
     pub pool_capacity: usize,
     pub block_size: usize,
     pub enable_xdp: bool,
+    /// Pin the memory pool at `pool_capacity` blocks, see
+    /// [`MemoryPool::set_strict_capacity`].
+    pub strict_pool_capacity: bool,
+    /// Congestion controller quiche runs for new connections.
+    pub congestion_control: CcAlgorithm,
 }
 
 impl Default for OptimizeConfig {
@@ -138,6 +147,8 @@ impl Default for OptimizeConfig {
             pool_capacity: 1024,
             block_size: 4096,
             enable_xdp: false,
+            strict_pool_capacity: false,
+            congestion_control: CcAlgorithm::Bbr,
         }
     }
 }
@@ -153,17 +164,29 @@ impl OptimizeConfig {
             pool_capacity: Option<usize>,
             block_size: Option<usize>,
             enable_xdp: Option<bool>,
+            strict_pool_capacity: Option<bool>,
+            congestion_control: Option<String>,
         }
         let root: Root = toml::from_str(s)?;
         let sec = root.optimize.unwrap_or(Section {
             pool_capacity: None,
             block_size: None,
             enable_xdp: None,
+            strict_pool_capacity: None,
+            congestion_control: None,
         });
+        let congestion_control = match sec.congestion_control {
+            Some(name) => name.parse().map_err(|e: String| ConfigError {
+                issues: vec![ConfigIssue::new("optimize.congestion_control", e)],
+            })?,
+            None => CcAlgorithm::Bbr,
+        };
         Ok(Self {
             pool_capacity: sec.pool_capacity.unwrap_or(1024),
             block_size: sec.block_size.unwrap_or(4096),
             enable_xdp: sec.enable_xdp.unwrap_or(false),
+            strict_pool_capacity: sec.strict_pool_capacity.unwrap_or(false),
+            congestion_control,
         })
     }
 
@@ -173,13 +196,32 @@ impl OptimizeConfig {
     }
 
     pub fn validate(&self) -> Result<(), String> {
+        let issues = self.issues();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            let messages: Vec<_> = issues.into_iter().map(|i| i.message).collect();
+            Err(messages.join("; "))
+        }
+    }
+
+    /// Every problem with the configuration values, one per field and keyed
+    /// by its path in the `[optimize]` TOML section. Empty if they are valid.
+    pub fn issues(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
         if self.pool_capacity == 0 {
-            return Err("pool_capacity must be > 0".into());
+            issues.push(ConfigIssue::new(
+                "optimize.pool_capacity",
+                "pool_capacity must be > 0",
+            ));
         }
         if self.block_size == 0 {
-            return Err("block_size must be > 0".into());
+            issues.push(ConfigIssue::new(
+                "optimize.block_size",
+                "block_size must be > 0",
+            ));
         }
-        Ok(())
+        issues
     }
 }
 
@@ -421,6 +463,7 @@ pub struct MemoryPool {
     capacity: AtomicUsize,
     in_use: AtomicUsize,
     available: AtomicUsize,
+    strict: AtomicBool,
 }
 
 impl MemoryPool {
@@ -460,6 +503,7 @@ impl MemoryPool {
             capacity: AtomicUsize::new(capacity),
             in_use: AtomicUsize::new(0),
             available: AtomicUsize::new(capacity),
+            strict: AtomicBool::new(false),
         };
         pool.update_metrics();
         pool
@@ -518,6 +562,53 @@ impl MemoryPool {
         Self::alloc_numa_block(self.block_size, node)
     }
 
+    /// Number of blocks currently handed out.
+    pub fn in_use(&self) -> usize {
+        self.in_use.load(Ordering::Relaxed)
+    }
+
+    /// Pins the pool at its current capacity. While enabled, [`try_alloc`]
+    /// fails once every cached block is handed out instead of growing the
+    /// pool, so a leak or overload shows up as an error rather than as
+    /// unbounded memory growth. [`alloc`] is unaffected and always grows.
+    ///
+    /// [`try_alloc`]: Self::try_alloc
+    /// [`alloc`]: Self::alloc
+    pub fn set_strict_capacity(&self, strict: bool) {
+        self.strict.store(strict, Ordering::Relaxed);
+    }
+
+    pub fn is_strict_capacity(&self) -> bool {
+        self.strict.load(Ordering::Relaxed)
+    }
+
+    /// Like [`alloc`](Self::alloc), but returns [`PoolError::Exhausted`]
+    /// instead of growing the pool when strict capacity is enabled. Blocks
+    /// cached on other NUMA nodes are used before giving up.
+    pub fn try_alloc(&self) -> Result<AlignedBox<[u8]>, PoolError> {
+        if !self.is_strict_capacity() {
+            return Ok(self.alloc());
+        }
+        let node = numa::current_node();
+        for i in 0..self.num_nodes {
+            let queue = match self.pools.get((node + i) % self.num_nodes) {
+                Some(q) => q,
+                None => continue,
+            };
+            if let Some(b) = queue.pop() {
+                self.available.fetch_sub(1, Ordering::Relaxed);
+                self.in_use.fetch_add(1, Ordering::Relaxed);
+                self.update_metrics();
+                telemetry!(telemetry::update_memory_usage());
+                return Ok(b);
+            }
+        }
+        telemetry!(telemetry::FEC_OVERFLOWS.inc());
+        Err(PoolError::Exhausted {
+            capacity: self.capacity.load(Ordering::Relaxed),
+        })
+    }
+
     /// Returns a memory block to the pool.
     /// If the pool is full, the block is dropped.
     pub fn free(&self, mut block: AlignedBox<[u8]>) {
@@ -836,6 +927,416 @@ impl<'a> Drop for ZeroCopyBuffer<'a> {
         self.bufs.clear();
     }
 }
+// --- Stream Flow-Control Tuning ---
+
+struct StreamWindow {
+    window: u64,
+    delivered: u64,
+    epoch_start: Option<Instant>,
+    urgency: u8,
+}
+
+/// Tracks per-stream flow-control windows and optionally grows them with
+/// the measured throughput so that high-BDP paths are not window limited.
+pub struct QuicStreamOptimizer {
+    streams: HashMap<u64, StreamWindow>,
+    initial_window: u64,
+    max_window: u64,
+    autotune: bool,
+}
+
+impl QuicStreamOptimizer {
+    /// Default upper bound for auto-tuned windows (16 MiB).
+    pub const DEFAULT_MAX_WINDOW: u64 = 16 * 1024 * 1024;
+    /// Default stream urgency (RFC 9218), `0` is the most urgent.
+    pub const DEFAULT_URGENCY: u8 = 3;
+    /// Least urgent priority level.
+    pub const MAX_URGENCY: u8 = 7;
+    /// Chunk size used for the most urgent streams.
+    pub const MAX_CHUNK_SIZE: usize = 64 * 1024;
+    /// Smallest chunk handed out, one full-sized QUIC packet.
+    pub const MIN_CHUNK_SIZE: usize = 1200;
+
+    pub fn new(initial_window: u64) -> Self {
+        Self {
+            streams: HashMap::new(),
+            initial_window,
+            max_window: Self::DEFAULT_MAX_WINDOW.max(initial_window),
+            autotune: false,
+        }
+    }
+
+    /// Enables or disables window auto-tuning.
+    pub fn set_autotune(&mut self, enabled: bool) {
+        self.autotune = enabled;
+    }
+
+    /// Sets the largest window auto-tuning may grow a stream to.
+    pub fn set_max_window(&mut self, max: u64) {
+        self.max_window = max;
+        for s in self.streams.values_mut() {
+            s.window = s.window.min(max);
+        }
+    }
+
+    fn stream(&mut self, stream_id: u64) -> &mut StreamWindow {
+        let initial = self.initial_window;
+        self.streams.entry(stream_id).or_insert(StreamWindow {
+            window: initial,
+            delivered: 0,
+            epoch_start: None,
+            urgency: Self::DEFAULT_URGENCY,
+        })
+    }
+
+    /// Explicitly sets the flow-control window of a stream.
+    pub fn update_flow_control_window(&mut self, stream_id: u64, size: u64) {
+        self.stream(stream_id).window = size;
+    }
+
+    /// Returns the current window of a stream.
+    pub fn window(&self, stream_id: u64) -> u64 {
+        self.streams
+            .get(&stream_id)
+            .map_or(self.initial_window, |s| s.window)
+    }
+
+    /// Sets the urgency of a stream, clamped to `0..=MAX_URGENCY`.
+    pub fn set_priority(&mut self, stream_id: u64, urgency: u8) {
+        self.stream(stream_id).urgency = urgency.min(Self::MAX_URGENCY);
+    }
+
+    /// Returns the urgency of a stream.
+    pub fn priority(&self, stream_id: u64) -> u8 {
+        self.streams
+            .get(&stream_id)
+            .map_or(Self::DEFAULT_URGENCY, |s| s.urgency)
+    }
+
+    /// Returns the chunk size to use for writes on a stream. Urgent streams
+    /// get larger chunks, each urgency level halving the size, and no chunk
+    /// exceeds the stream's current window.
+    pub fn get_optimal_chunk_size(&self, stream_id: u64) -> usize {
+        let by_priority =
+            (Self::MAX_CHUNK_SIZE >> self.priority(stream_id)).max(Self::MIN_CHUNK_SIZE);
+        let window = usize::try_from(self.window(stream_id)).unwrap_or(usize::MAX);
+        by_priority.min(window).max(1)
+    }
+
+    /// Returns `true` if `len` bytes fit into the stream's window.
+    pub fn can_send_data(&self, stream_id: u64, len: u64) -> bool {
+        len <= self.window(stream_id)
+    }
+
+    /// Records `bytes` delivered on a stream. Once per `rtt` the window is
+    /// moved towards twice the measured bandwidth-delay product, at most
+    /// doubling per round trip. A round trip without any delivery counts as a
+    /// stall and halves the window down to the initial size.
+    pub fn on_data_delivered(&mut self, stream_id: u64, bytes: u64, rtt: Duration, now: Instant) {
+        let autotune = self.autotune;
+        let (initial, max) = (self.initial_window, self.max_window);
+        let s = self.stream(stream_id);
+        s.delivered += bytes;
+        let start = *s.epoch_start.get_or_insert(now);
+        if !autotune || now.saturating_duration_since(start) < rtt {
+            return;
+        }
+
+        if s.delivered == 0 {
+            s.window = (s.window / 2).max(initial);
+        } else {
+            let target = s.delivered.saturating_mul(2).min(max);
+            if target > s.window {
+                s.window = target.min(s.window.saturating_mul(2)).min(max);
+            }
+        }
+        s.delivered = 0;
+        s.epoch_start = Some(now);
+    }
+
+    /// Forgets all state for a closed stream.
+    pub fn remove_stream(&mut self, stream_id: u64) {
+        self.streams.remove(&stream_id);
+    }
+}
+
+// --- Time Source ---
+
+/// Source of the current time. Components that schedule timeouts take a
+/// clock so tests can advance time without sleeping.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when advanced explicitly.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward by `by`. Clones share the same time.
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+// --- Path MTU Discovery ---
+
+/// Settings for [`PathMtuManager`].
+#[derive(Debug, Clone, Copy)]
+pub struct MtuConfig {
+    /// Size assumed to always work (QUIC minimum).
+    pub base_mtu: u16,
+    /// Largest size to probe for.
+    pub max_mtu: u16,
+    /// A probe without acknowledgement after this long is considered lost.
+    pub probe_timeout_ms: u64,
+    /// Delay after a completed search before probing again.
+    pub reprobe_interval_ms: u64,
+}
+
+impl Default for MtuConfig {
+    fn default() -> Self {
+        Self {
+            base_mtu: 1200,
+            max_mtu: 1500,
+            probe_timeout_ms: 1000,
+            reprobe_interval_ms: 600_000,
+        }
+    }
+}
+
+impl MtuConfig {
+    /// Largest link MTU probed for by [`MtuConfig::for_peer`] (Ethernet).
+    pub const LINK_MTU: u16 = 1500;
+
+    /// IP and UDP header bytes in front of every datagram sent to `peer`:
+    /// 20 + 8 for IPv4, 40 + 8 for IPv6. v4-mapped peers count as IPv4.
+    pub fn udp_overhead(peer: &SocketAddr) -> u16 {
+        match peer {
+            SocketAddr::V6(v6) if v6.ip().to_ipv4_mapped().is_none() => 48,
+            _ => 28,
+        }
+    }
+
+    /// Defaults with `max_mtu` lowered to the largest UDP payload that fits
+    /// an Ethernet frame for `peer`'s address family. IPv6 headers are 20
+    /// bytes larger, so its ceiling is 1452 instead of 1472. The floor stays
+    /// at the QUIC minimum of 1200, which every IPv6 path (minimum link MTU
+    /// 1280) carries as well.
+    pub fn for_peer(peer: &SocketAddr) -> Self {
+        Self {
+            max_mtu: Self::LINK_MTU - Self::udp_overhead(peer),
+            ..Self::default()
+        }
+    }
+}
+
+/// Progress of a [`PathMtuManager`] search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MtuStatus {
+    /// No probe has been sent yet.
+    Idle,
+    /// A probe of the given size awaits acknowledgement.
+    Probing(u16),
+    /// The bounds have converged; the search restarts after
+    /// `reprobe_interval_ms`.
+    Complete,
+}
+
+/// Binary-search path MTU discovery driven by the caller's send loop.
+///
+/// Probes are sent one at a time; an acknowledged probe raises the lower
+/// bound, a timed out one lowers the upper bound. Once the bounds meet the
+/// search is complete and restarts after `reprobe_interval_ms`.
+pub struct PathMtuManager {
+    config: MtuConfig,
+    clock: Arc<dyn Clock>,
+    current: u16,
+    low: u16,
+    high: u16,
+    in_flight: Option<(u16, Instant)>,
+    completed_at: Option<Instant>,
+}
+
+impl PathMtuManager {
+    pub fn new(config: MtuConfig) -> Self {
+        Self::with_clock(config, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(config: MtuConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            config,
+            clock,
+            current: config.base_mtu,
+            low: config.base_mtu,
+            high: config.max_mtu,
+            in_flight: None,
+            completed_at: None,
+        }
+    }
+
+    /// Largest size confirmed to work on the path.
+    pub fn current_mtu(&self) -> u16 {
+        self.current
+    }
+
+    /// Size of the probe currently awaiting a response.
+    pub fn probe_in_flight(&self) -> Option<u16> {
+        self.in_flight.map(|(size, _)| size)
+    }
+
+    /// Returns `true` once the search bounds have converged.
+    pub fn is_search_complete(&self) -> bool {
+        self.completed_at.is_some()
+    }
+
+    pub fn status(&self) -> MtuStatus {
+        match (self.completed_at, self.in_flight) {
+            (Some(_), _) => MtuStatus::Complete,
+            (None, Some((size, _))) => MtuStatus::Probing(size),
+            (None, None) => MtuStatus::Idle,
+        }
+    }
+
+    /// Advances the search from the size the transport currently confirms
+    /// for the path, e.g. quiche's `max_send_udp_payload_size`.
+    ///
+    /// A confirmed size at or above the probe in flight acknowledges it,
+    /// otherwise the probe times out as in [`check_probe_timeouts`]. A
+    /// confirmed size below [`current_mtu`] means the path shrank and the
+    /// search starts over from there. The next probe is sent if one is due.
+    ///
+    /// [`check_probe_timeouts`]: Self::check_probe_timeouts
+    /// [`current_mtu`]: Self::current_mtu
+    pub fn update(&mut self, confirmed: u16) -> MtuStatus {
+        let confirmed = confirmed.clamp(self.config.base_mtu, self.config.max_mtu);
+        if confirmed < self.current {
+            self.current = confirmed;
+            self.low = confirmed;
+            self.high = self.config.max_mtu;
+            self.in_flight = None;
+            self.completed_at = None;
+        }
+        match self.in_flight {
+            Some((size, _)) if confirmed >= size => {
+                self.handle_probe_response(size);
+            }
+            _ => {
+                self.check_probe_timeouts();
+            }
+        }
+        if confirmed > self.current {
+            self.current = confirmed;
+            self.low = self.low.max(confirmed);
+            self.high = self.high.max(self.low);
+            if self.low >= self.high && self.completed_at.is_none() {
+                self.complete();
+            }
+        }
+        self.maybe_send_probe();
+        self.status()
+    }
+
+    /// Records that a probe of `size` bytes has been sent.
+    pub fn send_probe(&mut self, size: u16) {
+        self.in_flight = Some((size, self.clock.now()));
+    }
+
+    /// Returns the size of the next probe to send, if one is due, and marks
+    /// it as sent.
+    pub fn maybe_send_probe(&mut self) -> Option<u16> {
+        if self.in_flight.is_some() {
+            return None;
+        }
+        if let Some(done) = self.completed_at {
+            let interval = Duration::from_millis(self.config.reprobe_interval_ms);
+            if self.clock.now().saturating_duration_since(done) < interval {
+                return None;
+            }
+            // Restart the search above the confirmed size.
+            self.completed_at = None;
+            self.low = self.current;
+            self.high = self.config.max_mtu;
+        }
+        if self.low >= self.high {
+            self.complete();
+            return None;
+        }
+        let size = self.low + (self.high - self.low + 1) / 2;
+        self.send_probe(size);
+        Some(size)
+    }
+
+    /// Handles the acknowledgement of a probe. Returns `true` if it matched
+    /// the probe in flight.
+    pub fn handle_probe_response(&mut self, size: u16) -> bool {
+        match self.in_flight {
+            Some((probe, _)) if probe == size => {
+                self.in_flight = None;
+                self.current = size;
+                self.low = size;
+                if self.low >= self.high {
+                    self.complete();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Declares the probe in flight lost once `probe_timeout_ms` has elapsed.
+    /// Returns `true` if a probe timed out.
+    pub fn check_probe_timeouts(&mut self) -> bool {
+        let Some((size, sent)) = self.in_flight else {
+            return false;
+        };
+        let timeout = Duration::from_millis(self.config.probe_timeout_ms);
+        if self.clock.now().saturating_duration_since(sent) < timeout {
+            return false;
+        }
+        self.in_flight = None;
+        self.high = size - 1;
+        if self.low >= self.high {
+            self.complete();
+        }
+        true
+    }
+
+    fn complete(&mut self) {
+        self.completed_at = Some(self.clock.now());
+    }
+}
+
 // --- Placeholder for full integration ---
 
 pub struct OptimizationManager {
@@ -858,7 +1359,10 @@ impl OptimizationManager {
     }
 
     pub fn from_cfg(cfg: OptimizeConfig) -> Self {
-        Self::new_with_config(cfg.pool_capacity, cfg.block_size, cfg.enable_xdp)
+        let mgr = Self::new_with_config(cfg.pool_capacity, cfg.block_size, cfg.enable_xdp);
+        mgr.memory_pool
+            .set_strict_capacity(cfg.strict_pool_capacity);
+        mgr
     }
 
     pub fn new() -> Self {
@@ -869,6 +1373,10 @@ impl OptimizationManager {
         self.memory_pool.alloc()
     }
 
+    pub fn try_alloc_block(&self) -> Result<AlignedBox<[u8]>, PoolError> {
+        self.memory_pool.try_alloc()
+    }
+
     pub fn free_block(&self, block: AlignedBox<[u8]>) {
         self.memory_pool.free(block);
     }
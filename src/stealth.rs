@@ -39,18 +39,23 @@
 use base64;
 use clap::ValueEnum;
 use lazy_static::lazy_static;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use reqwest::Client;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr};
+use std::ops::Range;
 use std::path::Path;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 use url::Url;
 
+use crate::app_config::{ConfigError, ConfigIssue};
 use crate::crypto::CryptoManager; // Assumed for integration
+use crate::datagram::DatagramEngine;
+use crate::error::{ResolveError, StealthError};
 use crate::fake_tls::{self, ServerHelloParamsOwned};
 use crate::optimize::{self, OptimizationManager}; // Assumed for integration
 use crate::telemetry;
@@ -111,10 +116,250 @@ pub async fn resolve_doh(
     Err("No A record returned".into())
 }
 
+/// Resolves `domain` through the operating system resolver. Unlike
+/// [`resolve_doh`] the query is visible on the network.
+pub async fn resolve_system(domain: &str) -> Result<IpAddr, Box<dyn std::error::Error>> {
+    tokio::net::lookup_host((domain, 0))
+        .await?
+        .next()
+        .map(|addr| addr.ip())
+        .ok_or_else(|| "No address returned".into())
+}
+
+/// Where a resolved address came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveSource {
+    /// Answered by the DoH provider.
+    Doh,
+    /// Served from the DoH cache.
+    Cache,
+    /// Answered by the operating system resolver after DoH failed, so the
+    /// query was sent unencrypted.
+    System,
+}
+
+impl ResolveSource {
+    /// Returns `false` if the lookup was visible to the network.
+    pub fn is_private(self) -> bool {
+        self != ResolveSource::System
+    }
+}
+
+/// An address together with how it was obtained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolved {
+    pub ip: IpAddr,
+    pub source: ResolveSource,
+}
+
+/// Upper bound applied by [`StealthManager::resolve_domain`] to a single query.
+pub const DEFAULT_RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Drives a resolver future to completion or fails with
+/// [`ResolveError::Timeout`] once `timeout` elapses. Dropping the future on
+/// expiry cancels the outstanding request.
+pub async fn resolve_with_timeout<F>(fut: F, timeout: Duration) -> Result<IpAddr, ResolveError>
+where
+    F: std::future::Future<Output = Result<IpAddr, Box<dyn std::error::Error>>>,
+{
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(Ok(ip)) => Ok(ip),
+        Ok(Err(e)) => Err(ResolveError::Failed(e.to_string())),
+        Err(_) => Err(ResolveError::Timeout(timeout)),
+    }
+}
+
+/// Connection settings for the DNS-over-HTTPS client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DohConfig {
+    /// Keep the provider connection open between queries.
+    pub keep_alive: bool,
+    /// Idle time after which a pooled connection is dropped.
+    pub pool_idle_timeout: Duration,
+    /// Random delay applied before a query is sent on a cache miss, so the
+    /// lookup is not timed exactly with connection setup. An empty range
+    /// disables the delay.
+    pub query_jitter: Range<Duration>,
+    /// How long resolved addresses are cached.
+    pub cache_ttl: Duration,
+    /// Ask the operating system resolver when the DoH lookup fails. Such
+    /// answers are reported as [`ResolveSource::System`] and not cached.
+    pub system_fallback: bool,
+}
+
+impl Default for DohConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive: true,
+            pool_idle_timeout: Duration::from_secs(90),
+            query_jitter: Duration::ZERO..Duration::ZERO,
+            cache_ttl: Duration::from_secs(300),
+            system_fallback: false,
+        }
+    }
+}
+
+impl DohConfig {
+    /// Draws the delay to wait before issuing a query.
+    pub fn query_delay(&self) -> Duration {
+        if self.query_jitter.is_empty() {
+            return self.query_jitter.start;
+        }
+        use rand::Rng;
+        rand::thread_rng().gen_range(self.query_jitter.clone())
+    }
+}
+
+/// Caches resolved addresses for [`DohConfig::cache_ttl`].
+///
+/// Each entry expires up to 10% before its TTL, chosen at random, so that
+/// domains resolved together are refreshed at different times instead of
+/// producing a burst of queries.
+pub struct DohCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (IpAddr, Instant)>>,
+}
+
+impl DohCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached address for `domain` unless it expired at `now`.
+    pub fn get(&self, domain: &str, now: Instant) -> Option<IpAddr> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(domain) {
+            Some(&(ip, expires)) if now < expires => Some(ip),
+            Some(_) => {
+                entries.remove(domain);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Stores `ip` for `domain` and returns when the entry expires.
+    pub fn insert(&self, domain: &str, ip: IpAddr, now: Instant) -> Instant {
+        use rand::Rng;
+        let spread = self.ttl / 10;
+        let early = if spread.is_zero() {
+            Duration::ZERO
+        } else {
+            rand::thread_rng().gen_range(Duration::ZERO..=spread)
+        };
+        let expires = now + self.ttl - early;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(domain.to_string(), (ip, expires));
+        expires
+    }
+
+    /// Returns when the entry for `domain` expires, if cached.
+    pub fn expires_at(&self, domain: &str) -> Option<Instant> {
+        self.entries.lock().unwrap().get(domain).map(|e| e.1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// Keeps the connection to the DoH provider warm between lookups.
+///
+/// Opening a fresh TLS connection for every query adds latency and produces
+/// a distinctive connection churn. The pool hands out the same client while
+/// it has been used within `pool_idle_timeout` and replaces it otherwise.
+pub struct DohPool<C = Client> {
+    config: DohConfig,
+    connect: Box<dyn Fn(&DohConfig) -> C + Send + Sync>,
+    idle: Mutex<Option<(C, Instant)>>,
+    opened: AtomicUsize,
+}
+
+impl DohPool<Client> {
+    /// Creates a pool of `reqwest` clients configured from `config`.
+    pub fn new(config: DohConfig) -> Self {
+        Self::with_connector(config, |cfg| {
+            Client::builder()
+                .pool_idle_timeout(cfg.pool_idle_timeout)
+                .pool_max_idle_per_host(if cfg.keep_alive { 1 } else { 0 })
+                .build()
+                .unwrap_or_else(|_| Client::new())
+        })
+    }
+}
+
+impl<C: Clone> DohPool<C> {
+    /// Creates a pool that opens connections through `connect`.
+    pub fn with_connector<F>(config: DohConfig, connect: F) -> Self
+    where
+        F: Fn(&DohConfig) -> C + Send + Sync + 'static,
+    {
+        Self {
+            config,
+            connect: Box::new(connect),
+            idle: Mutex::new(None),
+            opened: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns a connection for a lookup at `now`, reusing the pooled one if
+    /// keep-alive is enabled and it has not been idle for too long.
+    pub fn checkout(&self, now: Instant) -> C {
+        let mut idle = self.idle.lock().unwrap();
+        if let Some((conn, last_used)) = idle.as_mut() {
+            if self.config.keep_alive
+                && now.saturating_duration_since(*last_used) < self.config.pool_idle_timeout
+            {
+                *last_used = now;
+                return conn.clone();
+            }
+            debug!("Evicting idle DoH connection");
+        }
+        let conn = (self.connect)(&self.config);
+        self.opened.fetch_add(1, Ordering::Relaxed);
+        *idle = if self.config.keep_alive {
+            Some((conn.clone(), now))
+        } else {
+            None
+        };
+        conn
+    }
+
+    /// Number of connections opened so far.
+    pub fn connections_opened(&self) -> usize {
+        self.opened.load(Ordering::Relaxed)
+    }
+
+    pub fn config(&self) -> &DohConfig {
+        &self.config
+    }
+
+    /// Drops the pooled connection, if any.
+    pub fn clear(&self) {
+        self.idle.lock().unwrap().take();
+    }
+}
+
 // --- 2. Browser/OS Fingerprinting ---
 
 /// Defines the target browser for fingerprint spoofing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum BrowserProfile {
     Chrome,
     Firefox,
@@ -142,8 +387,165 @@ impl std::str::FromStr for BrowserProfile {
     }
 }
 
+/// Built-in browser definitions, the same values [`FingerprintProfile::new`]
+/// compiles in.
+pub const BUILTIN_DEFINITIONS: &str = include_str!("../browser_profiles/definitions.toml");
+
+/// One `[[profile]]` entry of a browser definitions file.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct BrowserDefinition {
+    pub browser: BrowserProfile,
+    pub os: OsProfile,
+    pub user_agent: String,
+    #[serde(default = "default_accept_language")]
+    pub accept_language: String,
+    pub cipher_suites: Vec<u16>,
+    /// ClientHello extension types in wire order. Empty keeps the captured
+    /// ClientHello of the browser/OS pair.
+    #[serde(default)]
+    pub extension_order: Vec<u16>,
+    #[serde(default = "default_alpn")]
+    pub alpn: Vec<String>,
+    pub h3_settings: Option<Http3Settings>,
+}
+
+fn default_accept_language() -> String {
+    "en-US,en;q=0.9".to_string()
+}
+
+fn default_alpn() -> Vec<String> {
+    vec!["h3".to_string()]
+}
+
+#[derive(serde::Deserialize)]
+struct DefinitionsFile {
+    #[serde(default)]
+    profile: Vec<BrowserDefinition>,
+}
+
+impl BrowserDefinition {
+    /// Builds the runtime profile. Transport parameters are taken from the
+    /// compiled-in profile of the same browser/OS pair.
+    pub fn to_profile(&self) -> FingerprintProfile {
+        let mut profile = FingerprintProfile::new(self.browser, self.os);
+        profile.user_agent = self.user_agent.clone();
+        profile.accept_language = self.accept_language.clone();
+        profile.tls_cipher_suites = self.cipher_suites.clone();
+        profile.extension_order = self.extension_order.clone();
+        profile.alpn = self.alpn.clone();
+        profile.h3_settings = self.h3_settings.clone();
+        if !self.extension_order.is_empty() {
+            profile.client_hello = Some(fake_tls::FakeTls::client_hello_ordered(
+                &self.cipher_suites,
+                &self.extension_order,
+                &self.alpn,
+            ));
+        }
+        profile
+    }
+}
+
+impl BrowserProfile {
+    /// Loads runtime profiles from a browser definitions file, JSON if the
+    /// extension is `.json` and TOML otherwise. See
+    /// `browser_profiles/definitions.toml` for the format.
+    pub fn from_definitions(
+        path: &Path,
+    ) -> Result<Vec<FingerprintProfile>, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let file: DefinitionsFile = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&contents)?
+        } else {
+            toml::from_str(&contents)?
+        };
+        Ok(file
+            .profile
+            .iter()
+            .map(BrowserDefinition::to_profile)
+            .collect())
+    }
+
+    /// Parses [`BUILTIN_DEFINITIONS`].
+    pub fn builtin_definitions() -> Vec<BrowserDefinition> {
+        toml::from_str::<DefinitionsFile>(BUILTIN_DEFINITIONS)
+            .expect("built-in definitions are valid")
+            .profile
+    }
+
+    /// User-Agent string this browser sends on `os`, the single source for
+    /// [`FingerprintProfile::new`] and with it the `user-agent` header of
+    /// [`Http3Masquerade`]. The platform token always matches `os`.
+    ///
+    /// Every browser on iOS is WebKit with its own product token. Safari
+    /// only exists on Apple platforms; elsewhere the Chrome UA of that OS is
+    /// returned rather than a Safari UA with an impossible platform.
+    pub fn user_agent(self, os: OsProfile) -> String {
+        const CHROME: &str = "126.0.0.0";
+        const FIREFOX: &str = "127.0";
+        const SAFARI: &str = "17.5";
+
+        if os == OsProfile::IOS {
+            let platform = "iPhone; CPU iPhone OS 17_5 like Mac OS X";
+            let product = match self {
+                BrowserProfile::Chrome => "CriOS/126.0.6478.54 ".to_string(),
+                BrowserProfile::Firefox => "FxiOS/127.0 ".to_string(),
+                BrowserProfile::Edge => "EdgiOS/126.0.2592.56 ".to_string(),
+                BrowserProfile::Opera => "OPT/5.0.0 ".to_string(),
+                // Brave and Vivaldi do not identify themselves on iOS.
+                _ => format!("Version/{SAFARI} "),
+            };
+            return format!(
+                "Mozilla/5.0 ({platform}) AppleWebKit/605.1.15 (KHTML, like Gecko) {product}Mobile/15E148 Safari/604.1"
+            );
+        }
+
+        match self {
+            BrowserProfile::Firefox => {
+                let platform = match os {
+                    OsProfile::Windows => "Windows NT 10.0; Win64; x64",
+                    OsProfile::MacOS => "Macintosh; Intel Mac OS X 13_6",
+                    OsProfile::Linux => "X11; Ubuntu; Linux x86_64",
+                    _ => "Android 14; Mobile",
+                };
+                let gecko = if os == OsProfile::Android {
+                    FIREFOX
+                } else {
+                    "20100101"
+                };
+                format!("Mozilla/5.0 ({platform}; rv:{FIREFOX}) Gecko/{gecko} Firefox/{FIREFOX}")
+            }
+            BrowserProfile::Safari if os == OsProfile::MacOS => format!(
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/{SAFARI} Safari/605.1.15"
+            ),
+            _ => {
+                let platform = match os {
+                    OsProfile::Windows => "Windows NT 10.0; Win64; x64",
+                    OsProfile::MacOS => "Macintosh; Intel Mac OS X 13_6",
+                    OsProfile::Linux => "X11; Linux x86_64",
+                    _ => "Linux; Android 14; Pixel 8",
+                };
+                let mobile = if os == OsProfile::Android { "Mobile " } else { "" };
+                let suffix = match self {
+                    BrowserProfile::Opera => " OPR/112.0.0.0",
+                    BrowserProfile::Brave => " Brave/1.67.0",
+                    BrowserProfile::Edge if os == OsProfile::Android => " EdgA/126.0.0.0",
+                    BrowserProfile::Edge => " Edg/126.0.0.0",
+                    BrowserProfile::Vivaldi => " Vivaldi/6.7.999.31",
+                    _ => "",
+                };
+                format!(
+                    "Mozilla/5.0 ({platform}) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{CHROME} {mobile}Safari/537.36{suffix}"
+                )
+            }
+        }
+    }
+}
+
 /// Defines the target operating system for fingerprint spoofing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum OsProfile {
     Windows,
     MacOS,
@@ -184,6 +586,11 @@ pub struct FingerprintProfile {
     pub client_hello: Option<Vec<u8>>,
     pub server_hello: Option<ServerHelloParamsOwned>,
     pub certificate: Option<Vec<u8>>,
+    /// Extension order of a synthesized ClientHello, empty for captures.
+    pub extension_order: Vec<u16>,
+    pub alpn: Vec<String>,
+    /// SETTINGS override, `None` uses [`Http3Settings::for_browser`].
+    pub h3_settings: Option<Http3Settings>,
 }
 
 impl FingerprintProfile {
@@ -192,8 +599,13 @@ impl FingerprintProfile {
         let mut profile = match (browser, os) {
             // --- Windows Profiles ---
             (BrowserProfile::Chrome, OsProfile::Windows) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 10_000_000,
                 initial_max_stream_data_bidi_local: 1_000_000,
@@ -202,9 +614,14 @@ impl FingerprintProfile {
                 max_idle_timeout: 30_000,
                 client_hello: None,
             },
-           (BrowserProfile::Firefox, OsProfile::Windows) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:127.0) Gecko/20100101 Firefox/127.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xcca9, 0xcca8, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc013, 0xc014],
+            (BrowserProfile::Firefox, OsProfile::Windows) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xcca9, 0xcca8, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.5".to_string(),
                 initial_max_data: 12_582_912,
                 initial_max_stream_data_bidi_local: 1_048_576,
@@ -213,98 +630,143 @@ impl FingerprintProfile {
                 max_idle_timeout: 60_000,
                 client_hello: None,
             },
-           (BrowserProfile::Opera, OsProfile::Windows) => Self {
-               browser, os,               user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 OPR/112.0.0.0".to_string(),
-               tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
-               accept_language: "en-US,en;q=0.9".to_string(),
+            (BrowserProfile::Opera, OsProfile::Windows) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
+                accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 10_000_000,
                 initial_max_stream_data_bidi_local: 1_000_000,
                 initial_max_stream_data_bidi_remote: 1_000_000,
                 initial_max_streams_bidi: 100,
-               max_idle_timeout: 30_000,
+                max_idle_timeout: 30_000,
                 client_hello: None,
-           },
-           (BrowserProfile::Brave, OsProfile::Windows) => Self {
-               browser, os,               user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Brave/1.67.0".to_string(),
-               tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
-               accept_language: "en-US,en;q=0.9".to_string(),
-               initial_max_data: 10_000_000,
-               initial_max_stream_data_bidi_local: 1_000_000,
-               initial_max_stream_data_bidi_remote: 1_000_000,
-               initial_max_streams_bidi: 100,
-               max_idle_timeout: 30_000,
+            },
+            (BrowserProfile::Brave, OsProfile::Windows) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
+                accept_language: "en-US,en;q=0.9".to_string(),
+                initial_max_data: 10_000_000,
+                initial_max_stream_data_bidi_local: 1_000_000,
+                initial_max_stream_data_bidi_remote: 1_000_000,
+                initial_max_streams_bidi: 100,
+                max_idle_timeout: 30_000,
                 client_hello: None,
-           },
-           (BrowserProfile::Edge, OsProfile::Windows) => Self {
-               browser, os,               user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.0.0".to_string(),
-               tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
-               accept_language: "en-US,en;q=0.9".to_string(),
-               initial_max_data: 10_000_000,
-               initial_max_stream_data_bidi_local: 1_000_000,
-               initial_max_stream_data_bidi_remote: 1_000_000,
-               initial_max_streams_bidi: 100,
-               max_idle_timeout: 30_000,
+            },
+            (BrowserProfile::Edge, OsProfile::Windows) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
+                accept_language: "en-US,en;q=0.9".to_string(),
+                initial_max_data: 10_000_000,
+                initial_max_stream_data_bidi_local: 1_000_000,
+                initial_max_stream_data_bidi_remote: 1_000_000,
+                initial_max_streams_bidi: 100,
+                max_idle_timeout: 30_000,
                 client_hello: None,
-           },
-           (BrowserProfile::Edge, OsProfile::MacOS) => Self {
-               browser, os,               user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_6) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.0.0".to_string(),
-               tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
-               accept_language: "en-US,en;q=0.9".to_string(),
-               initial_max_data: 10_000_000,
-               initial_max_stream_data_bidi_local: 1_000_000,
-               initial_max_stream_data_bidi_remote: 1_000_000,
-               initial_max_streams_bidi: 100,
-               max_idle_timeout: 30_000,
+            },
+            (BrowserProfile::Edge, OsProfile::MacOS) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
+                accept_language: "en-US,en;q=0.9".to_string(),
+                initial_max_data: 10_000_000,
+                initial_max_stream_data_bidi_local: 1_000_000,
+                initial_max_stream_data_bidi_remote: 1_000_000,
+                initial_max_streams_bidi: 100,
+                max_idle_timeout: 30_000,
                 client_hello: None,
-           },
-           (BrowserProfile::Edge, OsProfile::Linux) => Self {
-               browser, os,               user_agent: "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.0.0".to_string(),
-               tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
-               accept_language: "en-US,en;q=0.9".to_string(),
-               initial_max_data: 10_000_000,
-               initial_max_stream_data_bidi_local: 1_000_000,
-               initial_max_stream_data_bidi_remote: 1_000_000,
-               initial_max_streams_bidi: 100,
-               max_idle_timeout: 30_000,
+            },
+            (BrowserProfile::Edge, OsProfile::Linux) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
+                accept_language: "en-US,en;q=0.9".to_string(),
+                initial_max_data: 10_000_000,
+                initial_max_stream_data_bidi_local: 1_000_000,
+                initial_max_stream_data_bidi_remote: 1_000_000,
+                initial_max_streams_bidi: 100,
+                max_idle_timeout: 30_000,
                 client_hello: None,
-           },
-           (BrowserProfile::Vivaldi, OsProfile::Windows) => Self {
-               browser, os,               user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Vivaldi/6.7.999.31".to_string(),
-               tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
-               accept_language: "en-US,en;q=0.9".to_string(),
-               initial_max_data: 10_000_000,
-               initial_max_stream_data_bidi_local: 1_000_000,
-               initial_max_stream_data_bidi_remote: 1_000_000,
-               initial_max_streams_bidi: 100,
-               max_idle_timeout: 30_000,
+            },
+            (BrowserProfile::Vivaldi, OsProfile::Windows) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
+                accept_language: "en-US,en;q=0.9".to_string(),
+                initial_max_data: 10_000_000,
+                initial_max_stream_data_bidi_local: 1_000_000,
+                initial_max_stream_data_bidi_remote: 1_000_000,
+                initial_max_streams_bidi: 100,
+                max_idle_timeout: 30_000,
                 client_hello: None,
-           },
-           (BrowserProfile::Vivaldi, OsProfile::MacOS) => Self {
-               browser, os,               user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_6) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Vivaldi/6.7.999.31".to_string(),
-               tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
-               accept_language: "en-US,en;q=0.9".to_string(),
-               initial_max_data: 10_000_000,
-               initial_max_stream_data_bidi_local: 1_000_000,
-               initial_max_stream_data_bidi_remote: 1_000_000,
-               initial_max_streams_bidi: 100,
-               max_idle_timeout: 30_000,
+            },
+            (BrowserProfile::Vivaldi, OsProfile::MacOS) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
+                accept_language: "en-US,en;q=0.9".to_string(),
+                initial_max_data: 10_000_000,
+                initial_max_stream_data_bidi_local: 1_000_000,
+                initial_max_stream_data_bidi_remote: 1_000_000,
+                initial_max_streams_bidi: 100,
+                max_idle_timeout: 30_000,
                 client_hello: None,
-           },
-           (BrowserProfile::Vivaldi, OsProfile::Linux) => Self {
-               browser, os,               user_agent: "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Vivaldi/6.7.999.31".to_string(),
-               tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
-               accept_language: "en-US,en;q=0.9".to_string(),
-               initial_max_data: 10_000_000,
-               initial_max_stream_data_bidi_local: 1_000_000,
-               initial_max_stream_data_bidi_remote: 1_000_000,
-               initial_max_streams_bidi: 100,
-               max_idle_timeout: 30_000,
+            },
+            (BrowserProfile::Vivaldi, OsProfile::Linux) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
+                accept_language: "en-US,en;q=0.9".to_string(),
+                initial_max_data: 10_000_000,
+                initial_max_stream_data_bidi_local: 1_000_000,
+                initial_max_stream_data_bidi_remote: 1_000_000,
+                initial_max_streams_bidi: 100,
+                max_idle_timeout: 30_000,
                 client_hello: None,
-           },
+            },
             // --- macOS Profiles ---
-           (BrowserProfile::Safari, OsProfile::MacOS) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Safari/605.1.15".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc009, 0xc013, 0xc00a, 0xc014],
+            (BrowserProfile::Safari, OsProfile::MacOS) => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc009, 0xc013, 0xc00a,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 15_728_640,
                 initial_max_stream_data_bidi_local: 2_097_152,
@@ -314,8 +776,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Chrome, OsProfile::MacOS) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_6) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 10_000_000,
                 initial_max_stream_data_bidi_local: 1_000_000,
@@ -325,8 +792,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Opera, OsProfile::MacOS) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_6) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 OPR/112.0.0.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 10_000_000,
                 initial_max_stream_data_bidi_local: 1_000_000,
@@ -336,8 +808,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Brave, OsProfile::MacOS) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_6) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Brave/1.67.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 10_000_000,
                 initial_max_stream_data_bidi_local: 1_000_000,
@@ -347,8 +824,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Firefox, OsProfile::MacOS) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 13_6; rv:127.0) Gecko/20100101 Firefox/127.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xcca9, 0xcca8, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xcca9, 0xcca8, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.5".to_string(),
                 initial_max_data: 12_582_912,
                 initial_max_stream_data_bidi_local: 1_048_576,
@@ -358,8 +840,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Chrome, OsProfile::Linux) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 10_000_000,
                 initial_max_stream_data_bidi_local: 1_000_000,
@@ -369,8 +856,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Opera, OsProfile::Linux) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 OPR/112.0.0.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 10_000_000,
                 initial_max_stream_data_bidi_local: 1_000_000,
@@ -380,8 +872,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Brave, OsProfile::Linux) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Brave/1.67.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 10_000_000,
                 initial_max_stream_data_bidi_local: 1_000_000,
@@ -391,8 +888,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Firefox, OsProfile::Linux) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:127.0) Gecko/20100101 Firefox/127.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xcca9, 0xcca8, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xcca9, 0xcca8, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.5".to_string(),
                 initial_max_data: 12_582_912,
                 initial_max_stream_data_bidi_local: 1_048_576,
@@ -402,8 +904,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Chrome, OsProfile::Android) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 5_000_000,
                 initial_max_stream_data_bidi_local: 500_000,
@@ -413,8 +920,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Firefox, OsProfile::Android) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Android 14; Mobile; rv:127.0) Gecko/127.0 Firefox/127.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xcca9, 0xcca8, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xcca9, 0xcca8, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 5_000_000,
                 initial_max_stream_data_bidi_local: 500_000,
@@ -424,8 +936,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Opera, OsProfile::Android) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36 OPR/112.0.0.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 5_000_000,
                 initial_max_stream_data_bidi_local: 500_000,
@@ -435,8 +952,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Brave, OsProfile::Android) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36 Brave/1.67.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 5_000_000,
                 initial_max_stream_data_bidi_local: 500_000,
@@ -446,8 +968,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Edge, OsProfile::Android) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36 EdgA/126.0.0.0".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 5_000_000,
                 initial_max_stream_data_bidi_local: 500_000,
@@ -457,8 +984,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Vivaldi, OsProfile::Android) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36 Vivaldi/6.7.999.31".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 5_000_000,
                 initial_max_stream_data_bidi_local: 500_000,
@@ -468,8 +1000,13 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             (BrowserProfile::Safari, OsProfile::IOS) => Self {
-                browser, os,                user_agent: "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1".to_string(),
-                tls_cipher_suites: vec![0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc009, 0xc013, 0xc00a, 0xc014],
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                tls_cipher_suites: vec![
+                    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xc009, 0xc013, 0xc00a,
+                    0xc014,
+                ],
                 accept_language: "en-US,en;q=0.9".to_string(),
                 initial_max_data: 5_000_000,
                 initial_max_stream_data_bidi_local: 500_000,
@@ -479,15 +1016,32 @@ impl FingerprintProfile {
                 client_hello: None,
             },
             // --- Fallback Profile ---
-            _ => Self::new(BrowserProfile::Chrome, OsProfile::Windows),
+            // Transport parameters of Chrome/Windows, but the UA still
+            // claims the requested browser and OS.
+            _ => Self {
+                browser,
+                os,
+                user_agent: browser.user_agent(os),
+                ..Self::new(BrowserProfile::Chrome, OsProfile::Windows)
+            },
         };
 
         profile.client_hello = TlsClientHelloSpoofer::load_client_hello(browser, os);
         profile.server_hello = None;
         profile.certificate = None;
+        profile.extension_order = Vec::new();
+        profile.alpn = default_alpn();
+        profile.h3_settings = None;
         profile
     }
 
+    /// HTTP/3 SETTINGS announced with this profile.
+    pub fn http3_settings(&self) -> Http3Settings {
+        self.h3_settings
+            .clone()
+            .unwrap_or_else(|| Http3Settings::for_browser(self.browser))
+    }
+
     /// Generates a set of realistic HTTP headers based on the profile.
     pub fn generate_http_headers(&self) -> HashMap<String, String> {
         let mut headers = HashMap::new();
@@ -552,6 +1106,120 @@ impl Http3Masquerade {
         let _ = encoder.encode(&mut out, 0, &headers);
         out
     }
+
+    /// Returns the SETTINGS the profile's browser announces.
+    pub fn settings(&self) -> Http3Settings {
+        self.profile.http3_settings()
+    }
+
+    /// Encodes the profile's SETTINGS frame as sent on the control stream.
+    pub fn settings_frame(&self) -> Vec<u8> {
+        self.settings().encode_frame()
+    }
+}
+
+const H3_FRAME_SETTINGS: u64 = 0x04;
+const SETTINGS_QPACK_MAX_TABLE_CAPACITY: u64 = 0x01;
+const SETTINGS_MAX_FIELD_SECTION_SIZE: u64 = 0x06;
+const SETTINGS_QPACK_BLOCKED_STREAMS: u64 = 0x07;
+const SETTINGS_ENABLE_CONNECT_PROTOCOL: u64 = 0x08;
+const SETTINGS_H3_DATAGRAM: u64 = 0x33;
+
+/// HTTP/3 SETTINGS a browser announces on its control stream. Values follow
+/// published captures of current releases; GREASE settings are omitted.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Http3Settings {
+    pub qpack_max_table_capacity: u64,
+    pub qpack_blocked_streams: u64,
+    pub max_field_section_size: Option<u64>,
+    pub enable_connect_protocol: bool,
+    pub h3_datagram: bool,
+}
+
+impl Http3Settings {
+    pub fn for_browser(browser: BrowserProfile) -> Self {
+        match browser {
+            BrowserProfile::Firefox => Self {
+                qpack_max_table_capacity: 65536,
+                qpack_blocked_streams: 20,
+                max_field_section_size: None,
+                enable_connect_protocol: true,
+                h3_datagram: true,
+            },
+            BrowserProfile::Safari => Self {
+                qpack_max_table_capacity: 16383,
+                qpack_blocked_streams: 100,
+                max_field_section_size: None,
+                enable_connect_protocol: false,
+                h3_datagram: true,
+            },
+            // Chromium based browsers share Chrome's network stack.
+            BrowserProfile::Chrome
+            | BrowserProfile::Opera
+            | BrowserProfile::Brave
+            | BrowserProfile::Edge
+            | BrowserProfile::Vivaldi => Self {
+                qpack_max_table_capacity: 65536,
+                qpack_blocked_streams: 100,
+                max_field_section_size: Some(262144),
+                enable_connect_protocol: false,
+                h3_datagram: true,
+            },
+        }
+    }
+
+    /// Setting identifiers and values in the order the browser sends them.
+    pub fn pairs(&self) -> Vec<(u64, u64)> {
+        let mut out = vec![(
+            SETTINGS_QPACK_MAX_TABLE_CAPACITY,
+            self.qpack_max_table_capacity,
+        )];
+        if let Some(size) = self.max_field_section_size {
+            out.push((SETTINGS_MAX_FIELD_SECTION_SIZE, size));
+        }
+        out.push((SETTINGS_QPACK_BLOCKED_STREAMS, self.qpack_blocked_streams));
+        if self.enable_connect_protocol {
+            out.push((SETTINGS_ENABLE_CONNECT_PROTOCOL, 1));
+        }
+        if self.h3_datagram {
+            out.push((SETTINGS_H3_DATAGRAM, 1));
+        }
+        out
+    }
+
+    /// Encodes a complete SETTINGS frame (RFC 9114, section 7.2.4).
+    pub fn encode_frame(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for (id, value) in self.pairs() {
+            put_varint(&mut payload, id);
+            put_varint(&mut payload, value);
+        }
+        let mut out = Vec::with_capacity(payload.len() + 3);
+        put_varint(&mut out, H3_FRAME_SETTINGS);
+        put_varint(&mut out, payload.len() as u64);
+        out.extend_from_slice(&payload);
+        out
+    }
+
+    /// Applies the settings quiche exposes to an HTTP/3 configuration.
+    pub fn apply(&self, cfg: &mut quiche::h3::Config) {
+        cfg.set_qpack_max_table_capacity(self.qpack_max_table_capacity);
+        cfg.set_qpack_blocked_streams(self.qpack_blocked_streams);
+        if let Some(size) = self.max_field_section_size {
+            cfg.set_max_field_section_size(size);
+        }
+        cfg.enable_extended_connect(self.enable_connect_protocol);
+    }
+}
+
+/// Appends a QUIC variable-length integer (RFC 9000, section 16).
+fn put_varint(out: &mut Vec<u8>, v: u64) {
+    match v {
+        0..=0x3f => out.push(v as u8),
+        0x40..=0x3fff => out.extend_from_slice(&(v as u16 | 0x4000).to_be_bytes()),
+        0x4000..=0x3fff_ffff => out.extend_from_slice(&(v as u32 | 0x8000_0000).to_be_bytes()),
+        _ => out.extend_from_slice(&(v | 0xc000_0000_0000_0000).to_be_bytes()),
+    }
 }
 
 /// Configuration for [`FakeHeaders`].
@@ -588,6 +1256,201 @@ impl FakeHeaders {
     }
 }
 
+/// Origin whose responses a masquerading server imitates, so an active
+/// prober sees headers of a real site behind that CDN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OriginPersona {
+    Cloudflare,
+    Fastly,
+    Akamai,
+    /// Headers taken from a captured response, see
+    /// [`OriginPersona::from_capture`].
+    Custom(Vec<(String, String)>),
+}
+
+/// Edge locations the presets pick their PoP code from.
+const ORIGIN_POPS: &[&str] = &["FRA", "AMS", "LHR", "CDG", "IAD", "SJC", "NRT", "SIN"];
+
+/// Headers a server sets itself or that HTTP/3 forbids; dropped from
+/// captures.
+const CAPTURE_SKIPPED_HEADERS: &[&str] = &[
+    "content-length",
+    "alt-svc",
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+impl std::str::FromStr for OriginPersona {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cloudflare" => Ok(OriginPersona::Cloudflare),
+            "fastly" => Ok(OriginPersona::Fastly),
+            "akamai" => Ok(OriginPersona::Akamai),
+            _ => Err(()),
+        }
+    }
+}
+
+impl OriginPersona {
+    /// Builds a custom persona from a captured HTTP response header block,
+    /// one `name: value` pair per line as printed by `curl -I`. A leading
+    /// status line, pseudo-headers and headers the server sets itself
+    /// (`content-length`, `alt-svc`) or HTTP/3 forbids are skipped. A
+    /// captured `date` is replaced by the current time on every response.
+    pub fn from_capture(capture: &str) -> Result<Self, String> {
+        let mut headers = Vec::new();
+        for line in capture.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if line.starts_with("HTTP/") || line.starts_with(':') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("malformed header line '{}'", line))?;
+            let name = name.trim().to_ascii_lowercase();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(format!("malformed header name in '{}'", line));
+            }
+            if CAPTURE_SKIPPED_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+            headers.push((name, value.trim().to_string()));
+        }
+        if headers.is_empty() {
+            return Err("capture contains no headers".into());
+        }
+        Ok(OriginPersona::Custom(headers))
+    }
+
+    /// Reads a capture from `path`, see [`OriginPersona::from_capture`].
+    pub fn from_capture_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Self::from_capture(&contents)?)
+    }
+
+    /// Response headers of this persona, in the order the CDN sends them.
+    /// `pop` is the three letter edge location code embedded in request ids.
+    pub fn response_headers<R: rand::Rng>(
+        &self,
+        pop: &str,
+        rng: &mut R,
+        now: std::time::SystemTime,
+    ) -> Vec<quiche::h3::Header> {
+        let date = http_date(now);
+        let h =
+            |name: &str, value: &str| quiche::h3::Header::new(name.as_bytes(), value.as_bytes());
+        match self {
+            OriginPersona::Cloudflare => vec![
+                h("date", &date),
+                h("server", "cloudflare"),
+                h("cf-ray", &format!("{:016x}-{}", rng.gen::<u64>(), pop)),
+                h("cf-cache-status", "DYNAMIC"),
+                h("vary", "accept-encoding"),
+            ],
+            OriginPersona::Fastly => {
+                let secs = now
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default();
+                vec![
+                    h("date", &date),
+                    h("via", "1.1 varnish"),
+                    h("accept-ranges", "bytes"),
+                    h("age", "0"),
+                    h(
+                        "x-served-by",
+                        &format!(
+                            "cache-{}-k{}-{}",
+                            pop.to_ascii_lowercase(),
+                            rng.gen_range(1_000_000..10_000_000u32),
+                            pop
+                        ),
+                    ),
+                    h("x-cache", "MISS"),
+                    h("x-cache-hits", "0"),
+                    h(
+                        "x-timer",
+                        &format!(
+                            "S{}.{:06},VS0,VE{}",
+                            secs.as_secs(),
+                            secs.subsec_micros(),
+                            rng.gen_range(1..40u32)
+                        ),
+                    ),
+                ]
+            }
+            OriginPersona::Akamai => {
+                let secs = now
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                vec![
+                    h("server", "AkamaiGHost"),
+                    h("cache-control", "max-age=0, no-cache, no-store"),
+                    h("expires", &date),
+                    h("date", &date),
+                    h(
+                        "akamai-grn",
+                        &format!(
+                            "0.{:08x}.{}.{:07x}",
+                            rng.gen::<u32>(),
+                            secs,
+                            rng.gen::<u32>() >> 4
+                        ),
+                    ),
+                ]
+            }
+            OriginPersona::Custom(headers) => headers
+                .iter()
+                .map(|(name, value)| {
+                    if name == "date" {
+                        h(name, &date)
+                    } else {
+                        h(name, value)
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Formats `t` as an IMF-fixdate (RFC 9110, section 5.6.7).
+fn http_date(t: std::time::SystemTime) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let secs = t
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let days = secs / 86_400;
+    let rem = secs % 86_400;
+    // Civil date from days since the epoch (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
 // --- 4. Domain Fronting ---
 
 /// Represents a CDN provider that can be used for domain fronting.
@@ -653,6 +1516,11 @@ impl DomainFrontingManager {
             .unwrap_or_else(|| "cdn.example.com".to_string())
     }
 
+    /// Domains rotated through, in rotation order.
+    pub fn domains(&self) -> &[String] {
+        &self.domains
+    }
+
     /// Replaces the current domain list.
     pub fn set_domains(&mut self, domains: Vec<String>) {
         self.domains = domains;
@@ -662,30 +1530,222 @@ impl DomainFrontingManager {
 
 // --- 5. XOR-based Traffic Obfuscation ---
 
-/// A simple XOR obfuscator for packet payloads.
-pub struct XorObfuscator {
-    key: Mutex<Vec<u8>>,
-    position: AtomicUsize,
-}
+/// A reversible payload transform applied by the stealth pipeline.
+///
+/// [`XorObfuscator`] is the built-in implementation; others can be passed to
+/// [`StealthManager::with_obfuscator`].
+pub trait Obfuscator: Send + Sync {
+    fn obfuscate(&self, payload: &mut [u8]);
 
-impl XorObfuscator {
-    /// Creates a new obfuscator, ideally using a key from the CryptoManager.
-    pub fn new(crypto_manager: &CryptoManager) -> Self {
-        // Generate a session specific key so that each connection uses a
-        // different obfuscation key.
-        let key = crypto_manager.generate_session_key(32);
-        Self {
-            key: Mutex::new(key),
-            position: AtomicUsize::new(0),
-        }
+    fn deobfuscate(&self, payload: &mut [u8]);
+
+    /// Short name of the transform, as listed in a [`StealthSnapshot`].
+    fn name(&self) -> &'static str {
+        "custom"
     }
 
-    /// Applies XOR obfuscation to a mutable payload using the best available SIMD implementation.
-    pub fn obfuscate(&self, payload: &mut [u8]) {
-        let mut key = self.key.lock().unwrap();
-        if key.is_empty() {
-            return;
-        }
+    /// Length of the current key in bytes, `None` for keyless transforms.
+    fn key_len(&self) -> Option<usize> {
+        None
+    }
+
+    /// Replaces the key and restarts both key streams. Keyless transforms
+    /// ignore it.
+    fn set_key(&self, _key: &[u8]) {}
+
+    /// Replaces the key of the send direction only and restarts its key
+    /// stream. Keyless transforms ignore it.
+    fn set_send_key(&self, _key: &[u8]) {}
+
+    /// Replaces the key of the receive direction only and restarts its key
+    /// stream. Keyless transforms ignore it.
+    fn set_receive_key(&self, _key: &[u8]) {}
+}
+
+/// TLS exporter label (RFC 5705) the XOR key is derived from.
+pub const XOR_EXPORTER_LABEL: &str = "EXPORTER-quicfuscate-xor";
+
+/// Where the XOR obfuscation key of a [`StealthManager`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum XorKeySource {
+    /// Random per manager; the peer must be given the key out of band.
+    Session,
+    /// Fixed key known to every endpoint, used until the handshake
+    /// completes or when no exporter secret is available.
+    Static,
+    /// Derived from the TLS exporter secret of the established session.
+    Exporter,
+    /// Pre-shared key from [`StealthConfig::xor_key`].
+    Configured,
+    /// Drawn by [`StealthManager::rotate_obfuscation`].
+    Rotated,
+}
+
+/// Exporter derived XOR key waiting for the peer to switch to it, see
+/// [`StealthManager::coordinate_xor_key`].
+struct StagedXorKey {
+    key: Vec<u8>,
+    // The send direction already uses the key.
+    sending: bool,
+    // The receive direction already uses the key.
+    receiving: bool,
+}
+
+/// How the XOR key stream moves on from one packet to the next.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum XorPattern {
+    /// The key is replaced by its SHA-256 digest after every packet, so no
+    /// two packets share a key stream. Each side advances on its own, so a
+    /// single lost or reordered datagram desynchronises the receiver for
+    /// good; only suitable for loss-free, in-order transports.
+    Rolling,
+    /// Every packet is XORed with the same repeating key. Tolerates loss
+    /// and reordering, at the price of a key stream that repeats.
+    #[default]
+    Repeating,
+}
+
+impl std::str::FromStr for XorPattern {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rolling" => Ok(XorPattern::Rolling),
+            "repeating" => Ok(XorPattern::Repeating),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Parses a hex encoded XOR key as given on the command line or in
+/// `[stealth] xor_key`, rejecting keys shorter than
+/// [`StealthManager::MIN_XOR_KEY_LEN`] bytes.
+pub fn parse_xor_key(hex_key: &str) -> Result<Vec<u8>, String> {
+    let key =
+        hex::decode(hex_key.trim()).map_err(|e| format!("xor_key is not valid hex: {}", e))?;
+    if key.len() < StealthManager::MIN_XOR_KEY_LEN {
+        return Err(format!(
+            "xor_key is {} bytes, at least {} required",
+            key.len(),
+            StealthManager::MIN_XOR_KEY_LEN
+        ));
+    }
+    Ok(key)
+}
+
+/// A simple XOR obfuscator for packet payloads.
+///
+/// Sending and receiving use independent keys derived from the same session
+/// key, so packets obfuscated by one side are restored by the peer's
+/// `deobfuscate`. With [`XorPattern::Rolling`] this only holds as long as
+/// both process the same packets in the same order.
+pub struct XorObfuscator {
+    key: Mutex<Vec<u8>>,
+    rx_key: Mutex<Vec<u8>>,
+    position: AtomicUsize,
+    pattern: XorPattern,
+}
+
+impl XorObfuscator {
+    /// Creates a new obfuscator, ideally using a key from the CryptoManager.
+    pub fn new(crypto_manager: &CryptoManager) -> Self {
+        // Generate a session specific key so that each connection uses a
+        // different obfuscation key.
+        let key = crypto_manager.generate_session_key(32);
+        Self {
+            rx_key: Mutex::new(key.clone()),
+            key: Mutex::new(key),
+            position: AtomicUsize::new(0),
+            pattern: XorPattern::default(),
+        }
+    }
+
+    /// Creates an obfuscator with a fixed `key`.
+    pub fn with_key(key: &[u8]) -> Self {
+        Self {
+            key: Mutex::new(key.to_vec()),
+            rx_key: Mutex::new(key.to_vec()),
+            position: AtomicUsize::new(0),
+            pattern: XorPattern::default(),
+        }
+    }
+
+    /// Sets how the key stream evolves between packets. Both endpoints must
+    /// use the same pattern.
+    pub fn with_pattern(mut self, pattern: XorPattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    pub fn pattern(&self) -> XorPattern {
+        self.pattern
+    }
+
+    /// Key both endpoints start from when the key is coordinated with the
+    /// handshake. It only hides the handshake from passive matching, so it
+    /// is not secret.
+    pub fn static_key() -> Vec<u8> {
+        Sha256::digest(b"quicfuscate static xor key").to_vec()
+    }
+
+    /// Derives the 32 byte key from a TLS exporter secret exported with
+    /// [`XOR_EXPORTER_LABEL`]. Both endpoints of a session export the same
+    /// secret and therefore arrive at the same key.
+    pub fn key_from_exporter(secret: &[u8]) -> Vec<u8> {
+        let mut h = Sha256::new();
+        h.update(XOR_EXPORTER_LABEL.as_bytes());
+        h.update([0u8]);
+        h.update(secret);
+        h.finalize().to_vec()
+    }
+
+    /// Replaces the send and receive keys with `key` and restarts both
+    /// key streams.
+    pub fn set_key(&self, key: &[u8]) {
+        *self.rx_key.lock().unwrap() = key.to_vec();
+        *self.key.lock().unwrap() = key.to_vec();
+        self.position.store(0, Ordering::Relaxed);
+    }
+
+    /// Replaces the send key with `key` and restarts the send key stream.
+    pub fn set_send_key(&self, key: &[u8]) {
+        *self.key.lock().unwrap() = key.to_vec();
+    }
+
+    /// Replaces the receive key with `key` and restarts the receive key
+    /// stream.
+    pub fn set_receive_key(&self, key: &[u8]) {
+        *self.rx_key.lock().unwrap() = key.to_vec();
+    }
+
+    /// Applies XOR obfuscation to a mutable payload using the best available SIMD implementation.
+    pub fn obfuscate(&self, payload: &mut [u8]) {
+        let mut key = self.key.lock().unwrap();
+        self.apply(&mut key, payload);
+    }
+
+    /// Reverses XOR obfuscation using the receive key stream.
+    pub fn deobfuscate(&self, payload: &mut [u8]) {
+        let mut key = self.rx_key.lock().unwrap();
+        self.apply(&mut key, payload);
+    }
+
+    fn apply(&self, key: &mut Vec<u8>, payload: &mut [u8]) {
+        if key.is_empty() {
+            return;
+        }
 
         let key_len = key.len();
         let start = self.position.load(Ordering::Relaxed);
@@ -768,25 +1828,58 @@ impl XorObfuscator {
             }
         });
         // Rolling key update using SHA-256 after each packet
-        let digest = Sha256::digest(&key[..]);
-        key.clear();
-        key.extend_from_slice(&digest);
+        if self.pattern == XorPattern::Rolling {
+            let digest = Sha256::digest(&key[..]);
+            key.clear();
+            key.extend_from_slice(&digest);
+        }
         self.position.store(0, Ordering::Relaxed);
     }
 
-    /// Reverses XOR obfuscation. The operation is symmetrical.
-    pub fn deobfuscate(&self, payload: &mut [u8]) {
-        self.obfuscate(payload);
+    /// Length of the current send key in bytes.
+    pub fn key_len(&self) -> usize {
+        self.key.lock().unwrap().len()
     }
 
     /// Generates a fresh obfuscation key using the provided CryptoManager.
     pub fn rekey(&self, crypto_manager: &CryptoManager) {
-        let mut key = self.key.lock().unwrap();
-        *key = crypto_manager.generate_session_key(32);
+        let new_key = crypto_manager.generate_session_key(32);
+        *self.rx_key.lock().unwrap() = new_key.clone();
+        *self.key.lock().unwrap() = new_key;
         self.position.store(0, Ordering::Relaxed);
     }
 }
 
+impl Obfuscator for XorObfuscator {
+    fn name(&self) -> &'static str {
+        "xor"
+    }
+
+    fn obfuscate(&self, payload: &mut [u8]) {
+        XorObfuscator::obfuscate(self, payload)
+    }
+
+    fn deobfuscate(&self, payload: &mut [u8]) {
+        XorObfuscator::deobfuscate(self, payload)
+    }
+
+    fn key_len(&self) -> Option<usize> {
+        Some(XorObfuscator::key_len(self))
+    }
+
+    fn set_key(&self, key: &[u8]) {
+        XorObfuscator::set_key(self, key)
+    }
+
+    fn set_send_key(&self, key: &[u8]) {
+        XorObfuscator::set_send_key(self, key)
+    }
+
+    fn set_receive_key(&self, key: &[u8]) {
+        XorObfuscator::set_receive_key(self, key)
+    }
+}
+
 // --- 6. TLS Client Hello Spoofing ---
 
 /// Allows manipulation of the TLS ClientHello to mimic real browser behaviour.
@@ -872,7 +1965,496 @@ impl TlsClientHelloSpoofer {
     }
 }
 
-// --- 7. Stealth Manager and Configuration ---
+// --- 7. Send Pacing ---
+
+/// Token bucket that bounds how fast shaped datagrams are released.
+///
+/// The rate is driven by the connection's congestion controller so that
+/// padding and cover traffic never exceed what a real client on the same
+/// path could plausibly send. A rate of `0` disables pacing.
+#[derive(Debug, Clone)]
+pub struct SendPacer {
+    rate: u64,
+    tokens: f64,
+    last: Option<Instant>,
+}
+
+impl SendPacer {
+    /// Smallest burst that is always allowed, roughly two full-sized packets.
+    pub const MIN_BURST: u64 = 2 * 1350;
+
+    pub fn new() -> Self {
+        Self {
+            rate: 0,
+            tokens: 0.0,
+            last: None,
+        }
+    }
+
+    /// Updates the send rate in bytes per second.
+    pub fn set_rate(&mut self, bytes_per_sec: u64) {
+        self.rate = bytes_per_sec;
+        self.tokens = self.tokens.min(self.burst() as f64);
+    }
+
+    /// Returns the current send rate in bytes per second.
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Bucket size: 100ms worth of data, but never less than [`Self::MIN_BURST`].
+    fn burst(&self) -> u64 {
+        (self.rate / 10).max(Self::MIN_BURST)
+    }
+
+    /// Returns `true` if a datagram of `len` bytes may be released at `now`
+    /// and consumes the corresponding budget.
+    pub fn try_release(&mut self, len: usize, now: Instant) -> bool {
+        if self.rate == 0 {
+            return true;
+        }
+        let burst = self.burst() as f64;
+        match self.last {
+            Some(last) => {
+                let elapsed = now.saturating_duration_since(last).as_secs_f64();
+                self.tokens = (self.tokens + elapsed * self.rate as f64).min(burst);
+            }
+            None => self.tokens = burst,
+        }
+        self.last = Some(now);
+        if self.tokens >= len as f64 {
+            self.tokens -= len as f64;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Default for SendPacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// --- 8. Profile Rotation Scheduling ---
+
+/// Controls when the active fingerprint profile is rotated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationSchedule {
+    /// Rotate after a constant interval.
+    Fixed(Duration),
+    /// Rotate after an interval drawn uniformly from `[min, max]`.
+    Uniform { min: Duration, max: Duration },
+    /// Pick a random profile for every generated request.
+    PerRequest,
+}
+
+impl RotationSchedule {
+    /// Shortest delay between two timer driven rotations, so a schedule
+    /// whose jitter reaches down to zero cannot make the rotation loop spin.
+    pub const MIN_DELAY: Duration = Duration::from_millis(100);
+
+    /// Builds a schedule from a base interval and a symmetric jitter.
+    /// A zero jitter yields [`RotationSchedule::Fixed`]. The lower bound is
+    /// raised to [`MIN_DELAY`](Self::MIN_DELAY) if `jitter` would take it
+    /// below.
+    pub fn jittered(interval: Duration, jitter: Duration) -> Self {
+        if jitter.is_zero() {
+            RotationSchedule::Fixed(interval)
+        } else {
+            RotationSchedule::Uniform {
+                min: interval.saturating_sub(jitter).max(Self::MIN_DELAY),
+                max: (interval + jitter).max(Self::MIN_DELAY),
+            }
+        }
+    }
+
+    /// Returns the delay until the next rotation, never shorter than
+    /// [`MIN_DELAY`](Self::MIN_DELAY), or `None` for
+    /// [`RotationSchedule::PerRequest`] which is not timer driven.
+    pub fn next_interval(&self) -> Option<Duration> {
+        let delay = match *self {
+            RotationSchedule::Fixed(d) => d,
+            RotationSchedule::Uniform { min, max } if min < max => {
+                use rand::Rng;
+                rand::thread_rng().gen_range(min..=max)
+            }
+            RotationSchedule::Uniform { min, .. } => min,
+            RotationSchedule::PerRequest => return None,
+        };
+        Some(delay.max(Self::MIN_DELAY))
+    }
+}
+
+impl From<Duration> for RotationSchedule {
+    fn from(d: Duration) -> Self {
+        RotationSchedule::Fixed(d)
+    }
+}
+
+// --- 9. Spin Bit Scheduling ---
+
+/// How the QUIC latency spin bit is driven on short-header packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpinBitMode {
+    /// Always send `0`, as stacks without spin support do.
+    Disabled,
+    /// Draw a fresh random value for every packet.
+    Random,
+    /// Toggle once per measured RTT so passive observers see a plausible
+    /// latency signal that matches the real path.
+    Accurate,
+}
+
+/// Produces the spin bit value for outgoing short-header packets.
+///
+/// The spin bit is covered by packet protection, so the value returned by
+/// [`SpinBitRandomizer::spin_for`] has to be written while the header is
+/// built and cannot be patched into an already sealed packet. quiche only
+/// takes it through the Rust API added by `spin_bit.patch`, so a
+/// [`QuicFuscateConnection`](crate::core::QuicFuscateConnection) built
+/// against the crates.io quiche does not apply it and every packet carries
+/// `0`.
+#[derive(Debug, Clone)]
+pub struct SpinBitRandomizer {
+    mode: SpinBitMode,
+    rtt: Option<Duration>,
+    spin: bool,
+    last_pn: Option<u64>,
+    last_toggle: Option<Instant>,
+    rng: rand::rngs::StdRng,
+}
+
+impl SpinBitRandomizer {
+    pub fn new(mode: SpinBitMode) -> Self {
+        use rand::SeedableRng;
+        Self {
+            mode,
+            rtt: None,
+            spin: false,
+            last_pn: None,
+            last_toggle: None,
+            rng: rand::rngs::StdRng::from_entropy(),
+        }
+    }
+
+    /// Restarts the random sequence of [`SpinBitMode::Random`] from `seed`
+    /// and derives the current spin value from it. The RTT estimate and
+    /// packet number history are kept.
+    pub fn reseed(&mut self, seed: u64) {
+        use rand::SeedableRng;
+        self.rng = rand::rngs::StdRng::seed_from_u64(seed);
+        self.spin = seed & 1 == 1;
+        self.last_toggle = None;
+    }
+
+    pub fn mode(&self) -> SpinBitMode {
+        self.mode
+    }
+
+    /// Updates the RTT estimate used as toggle period in
+    /// [`SpinBitMode::Accurate`]. Zero samples are ignored.
+    pub fn on_rtt_sample(&mut self, rtt: Duration) {
+        if !rtt.is_zero() {
+            self.rtt = Some(rtt);
+        }
+    }
+
+    /// Returns the current RTT estimate, if any sample was seen.
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt
+    }
+
+    /// Returns the spin bit for the packet with number `pn` sent at `now`.
+    ///
+    /// In accurate mode the bit only flips on a packet number larger than
+    /// any seen before and once at least one RTT has passed since the last
+    /// flip, so retransmissions and reordered sends never toggle it. Until
+    /// an RTT sample is available the bit stays constant.
+    pub fn spin_for(&mut self, pn: u64, now: Instant) -> bool {
+        match self.mode {
+            SpinBitMode::Disabled => false,
+            SpinBitMode::Random => {
+                self.spin = rand::Rng::gen(&mut self.rng);
+                self.spin
+            }
+            SpinBitMode::Accurate => {
+                if self.last_pn.map_or(false, |last| pn <= last) {
+                    return self.spin;
+                }
+                self.last_pn = Some(pn);
+                let started = *self.last_toggle.get_or_insert(now);
+                if let Some(rtt) = self.rtt {
+                    if now.saturating_duration_since(started) >= rtt {
+                        self.spin = !self.spin;
+                        self.last_toggle = Some(now);
+                    }
+                }
+                self.spin
+            }
+        }
+    }
+}
+
+impl Default for SpinBitRandomizer {
+    fn default() -> Self {
+        Self::new(SpinBitMode::Accurate)
+    }
+}
+
+/// Number of padding buckets drawn by [`StealthManager::rotate_obfuscation`].
+pub const PADDING_BUCKET_COUNT: usize = 3;
+/// Range the padding bucket sizes are drawn from, in bytes.
+pub const PADDING_BUCKET_RANGE: Range<usize> = 1200..1400;
+
+/// Parameters chosen by [`StealthManager::rotate_obfuscation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObfuscationRotation {
+    /// First 8 bytes of the SHA-256 of the new XOR key, `None` if XOR
+    /// obfuscation is disabled.
+    pub xor_key_fingerprint: Option<[u8; 8]>,
+    /// Seed of the spin bit scheduler.
+    pub spin_seed: u64,
+    /// Padding bucket sizes, ascending.
+    pub padding_buckets: Vec<usize>,
+}
+
+/// Latency spin bit in the first byte of a short-header packet.
+pub const SPIN_BIT: u8 = 0x20;
+
+/// Clears the spin bit of a serialized short-header (1-RTT) packet and
+/// returns whether it was set. Long-header packets carry no spin bit and are
+/// left untouched.
+///
+/// The bit is authenticated as part of the header, so this is a guard rather
+/// than a way to rewrite it: while the spin bit is disabled the scheduler
+/// hands `0` to quiche, which makes clearing a no-op on every packet it
+/// builds. Should a build ever set the bit anyway, the packet is dropped by
+/// the peer instead of leaking a latency signal.
+pub fn clear_spin_bit(packet: &mut [u8]) -> bool {
+    match packet.first_mut() {
+        Some(first) if *first & 0x80 == 0 && *first & SPIN_BIT != 0 => {
+            *first &= !SPIN_BIT;
+            true
+        }
+        _ => false,
+    }
+}
+
+// --- 10. Handshake Timing ---
+
+/// Inter-packet gaps a browser leaves between its handshake datagrams.
+///
+/// Browsers pace their handshake flights, so a client that writes every
+/// datagram back to back stands out even when the ClientHello is a perfect
+/// copy. Each gap is drawn uniformly from `gap ± jitter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandshakeTiming {
+    pub gap: Duration,
+    pub jitter: Duration,
+    /// Number of datagrams after the Initial that are delayed.
+    pub packets: usize,
+}
+
+impl HandshakeTiming {
+    /// Typical pacing of the given browser's QUIC stack: Chromium based
+    /// browsers send their flights almost back to back, Firefox and Safari
+    /// leave noticeably larger gaps.
+    pub fn for_browser(browser: BrowserProfile) -> Self {
+        let (gap_us, jitter_us) = match browser {
+            BrowserProfile::Firefox => (2_000, 700),
+            BrowserProfile::Safari => (1_500, 500),
+            BrowserProfile::Chrome
+            | BrowserProfile::Opera
+            | BrowserProfile::Brave
+            | BrowserProfile::Edge
+            | BrowserProfile::Vivaldi => (800, 300),
+        };
+        Self {
+            gap: Duration::from_micros(gap_us),
+            jitter: Duration::from_micros(jitter_us),
+            packets: 4,
+        }
+    }
+}
+
+/// Produces the delays to insert between handshake datagrams so their
+/// timing follows a [`HandshakeTiming`]. The Initial goes out immediately,
+/// the next [`HandshakeTiming::packets`] datagrams are held back by a
+/// sampled gap and everything after that is sent without delay.
+#[derive(Debug, Clone)]
+pub struct HandshakeTimingModel {
+    timing: HandshakeTiming,
+    rng: rand::rngs::StdRng,
+    sent: usize,
+}
+
+impl HandshakeTimingModel {
+    pub fn new(timing: HandshakeTiming) -> Self {
+        use rand::SeedableRng;
+        Self::with_rng(timing, rand::rngs::StdRng::from_entropy())
+    }
+
+    /// Creates a model whose gaps are reproducible for a given `seed`.
+    pub fn with_seed(timing: HandshakeTiming, seed: u64) -> Self {
+        use rand::SeedableRng;
+        Self::with_rng(timing, rand::rngs::StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(timing: HandshakeTiming, rng: rand::rngs::StdRng) -> Self {
+        Self {
+            timing,
+            rng,
+            sent: 0,
+        }
+    }
+
+    pub fn timing(&self) -> HandshakeTiming {
+        self.timing
+    }
+
+    /// Switches to another timing, e.g. after a profile change. Datagrams
+    /// already sent still count against [`HandshakeTiming::packets`].
+    pub fn set_timing(&mut self, timing: HandshakeTiming) {
+        self.timing = timing;
+    }
+
+    /// Returns how long to wait before sending the next handshake datagram.
+    pub fn next_delay(&mut self) -> Duration {
+        let idx = self.sent;
+        self.sent += 1;
+        if idx == 0 || idx > self.timing.packets {
+            return Duration::ZERO;
+        }
+        let HandshakeTiming { gap, jitter, .. } = self.timing;
+        if jitter.is_zero() {
+            return gap;
+        }
+        use rand::Rng;
+        self.rng
+            .gen_range(gap.saturating_sub(jitter)..=gap + jitter)
+    }
+
+    /// Starts over for a new handshake.
+    pub fn reset(&mut self) {
+        self.sent = 0;
+    }
+}
+
+// --- 11. Cover Traffic ---
+
+/// Leading byte of the obfuscation header on a datagram carrying data.
+pub const COVER_FLAG_DATA: u8 = 0x00;
+/// Leading byte of the obfuscation header on a cover datagram.
+pub const COVER_FLAG_COVER: u8 = 0x01;
+
+/// Sizes of cover datagrams on the wire, header included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SizeDistribution {
+    Fixed(usize),
+    /// Uniform between `min` and `max`, both inclusive.
+    Uniform {
+        min: usize,
+        max: usize,
+    },
+    /// Picks one of the listed sizes, e.g. taken from a capture of real
+    /// traffic.
+    Empirical(Vec<usize>),
+}
+
+impl SizeDistribution {
+    /// Draws a size; never less than the one byte header.
+    pub fn sample<R: rand::Rng>(&self, rng: &mut R) -> usize {
+        use rand::seq::SliceRandom;
+        let size = match self {
+            SizeDistribution::Fixed(size) => *size,
+            SizeDistribution::Uniform { min, max } => rng.gen_range(*min..=(*max).max(*min)),
+            SizeDistribution::Empirical(sizes) => sizes.choose(rng).copied().unwrap_or(1),
+        };
+        size.max(1)
+    }
+}
+
+/// Generator of decoy datagrams at a mean `rate` per second.
+///
+/// Gaps between cover datagrams are exponentially distributed so they do
+/// not form a regular pattern of their own.
+pub struct CoverTraffic {
+    rate: f64,
+    sizes: SizeDistribution,
+    rng: rand::rngs::StdRng,
+    next_at: Option<Instant>,
+}
+
+impl CoverTraffic {
+    pub fn new(rate: f64, sizes: SizeDistribution) -> Self {
+        use rand::SeedableRng;
+        Self::with_rng(rate, sizes, rand::rngs::StdRng::from_entropy())
+    }
+
+    /// Creates a generator whose gaps and sizes are reproducible for a
+    /// given `seed`.
+    pub fn with_seed(rate: f64, sizes: SizeDistribution, seed: u64) -> Self {
+        use rand::SeedableRng;
+        Self::with_rng(rate, sizes, rand::rngs::StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(rate: f64, sizes: SizeDistribution, rng: rand::rngs::StdRng) -> Self {
+        Self {
+            rate: rate.max(0.0),
+            sizes,
+            rng,
+            next_at: None,
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn sizes(&self) -> &SizeDistribution {
+        &self.sizes
+    }
+
+    fn gap(&mut self) -> Duration {
+        use rand::Rng;
+        let u: f64 = self.rng.gen();
+        Duration::from_secs_f64(-(1.0 - u).ln() / self.rate)
+    }
+
+    /// Returns the sizes of the cover datagrams due by `now`. The first
+    /// call only schedules the first datagram.
+    pub fn due(&mut self, now: Instant) -> Vec<usize> {
+        let mut out = Vec::new();
+        if self.rate <= 0.0 {
+            return out;
+        }
+        let mut next = match self.next_at {
+            Some(next) => next,
+            None => now + self.gap(),
+        };
+        while next <= now {
+            out.push(self.sizes.sample(&mut self.rng));
+            next += self.gap();
+        }
+        self.next_at = Some(next);
+        out
+    }
+
+    /// Builds the body of a cover datagram of `size` bytes: the cover flag
+    /// followed by random padding.
+    pub fn packet(&mut self, size: usize) -> Vec<u8> {
+        use rand::RngCore;
+        let mut buf = vec![0u8; size.max(1)];
+        self.rng.fill_bytes(&mut buf[1..]);
+        buf[0] = COVER_FLAG_COVER;
+        buf
+    }
+}
+
+// --- 12. Stealth Manager and Configuration ---
 
 /// Configuration for the main StealthManager.
 #[derive(Clone)]
@@ -882,14 +2464,40 @@ pub struct StealthConfig {
     pub use_fake_tls: bool,
     pub enable_doh: bool,
     pub doh_provider: String,
+    pub doh: DohConfig,
     pub enable_http3_masquerading: bool,
+    /// `Alt-Svc` value a masquerading server advertises in its responses.
+    /// Empty disables the header.
+    pub alt_svc: String,
     pub use_qpack_headers: bool,
     pub enable_domain_fronting: bool,
     pub fronting_domains: Vec<String>,
     pub cdn_providers: Vec<CdnProvider>,
     pub enable_xor_obfuscation: bool,
+    /// Start from [`XorObfuscator::static_key`] instead of a random key the
+    /// peer has to be given out of band. A key derived from a TLS exporter
+    /// secret can replace it later, see
+    /// [`StealthManager::coordinate_xor_key`].
+    pub xor_key_from_handshake: bool,
+    /// Pace handshake datagrams like the claimed browser, see
+    /// [`HandshakeTimingModel`].
+    pub mimic_handshake_timing: bool,
+    /// CDN origin whose response headers a masquerading server imitates.
+    pub origin_persona: Option<OriginPersona>,
+    /// Pre-shared XOR obfuscation key of at least
+    /// [`StealthManager::MIN_XOR_KEY_LEN`] bytes, hex encoded in TOML. Takes
+    /// precedence over [`xor_key_from_handshake`](Self::xor_key_from_handshake).
+    pub xor_key: Option<Vec<u8>>,
+    /// How the XOR key stream evolves between packets.
+    pub xor_pattern: XorPattern,
+    /// Keep the latency spin bit at `0` on every short-header packet,
+    /// whatever [`SpinBitMode`] the scheduler runs in.
+    pub disable_spin_bit: bool,
 }
 
+/// `Alt-Svc` advertisement sent by typical HTTP/3 capable web servers.
+pub const DEFAULT_ALT_SVC: &str = "h3=\":443\"; ma=86400";
+
 impl Default for StealthConfig {
     fn default() -> Self {
         Self {
@@ -898,7 +2506,9 @@ impl Default for StealthConfig {
             use_fake_tls: false,
             enable_doh: true,
             doh_provider: "https://cloudflare-dns.com/dns-query".to_string(),
+            doh: DohConfig::default(),
             enable_http3_masquerading: true,
+            alt_svc: DEFAULT_ALT_SVC.to_string(),
             use_qpack_headers: true,
             enable_domain_fronting: true,
             fronting_domains: Vec::new(),
@@ -910,6 +2520,12 @@ impl Default for StealthConfig {
                 CdnProvider::Fastly,
             ],
             enable_xor_obfuscation: true,
+            xor_key_from_handshake: true,
+            mimic_handshake_timing: true,
+            origin_persona: None,
+            xor_key: None,
+            xor_pattern: XorPattern::default(),
+            disable_spin_bit: false,
         }
     }
 }
@@ -923,26 +2539,53 @@ impl StealthConfig {
 
         #[derive(serde::Deserialize)]
         struct Section {
-            browser_profile: Option<BrowserProfile>,
-            os_profile: Option<OsProfile>,
+            browser_profile: Option<String>,
+            os_profile: Option<String>,
             use_fake_tls: Option<bool>,
             enable_doh: Option<bool>,
             doh_provider: Option<String>,
+            doh_keep_alive: Option<bool>,
+            doh_pool_idle_timeout_ms: Option<u64>,
+            doh_cache_ttl_ms: Option<u64>,
+            doh_query_jitter_min_ms: Option<u64>,
+            doh_query_jitter_max_ms: Option<u64>,
+            doh_system_fallback: Option<bool>,
             enable_http3_masquerading: Option<bool>,
+            alt_svc: Option<String>,
             use_qpack_headers: Option<bool>,
             enable_domain_fronting: Option<bool>,
             fronting_domains: Option<Vec<String>>,
             enable_xor_obfuscation: Option<bool>,
+            xor_key_from_handshake: Option<bool>,
+            xor_key: Option<String>,
+            xor_pattern: Option<String>,
+            mimic_handshake_timing: Option<bool>,
+            origin_persona: Option<String>,
+            origin_persona_capture: Option<String>,
+            disable_spin_bit: Option<bool>,
         }
 
         let root: Root = toml::from_str(s)?;
         let mut cfg = StealthConfig::default();
+        let mut errors = Vec::new();
         if let Some(sec) = root.stealth {
             if let Some(v) = sec.browser_profile {
-                cfg.browser_profile = v;
+                match v.parse() {
+                    Ok(p) => cfg.browser_profile = p,
+                    Err(_) => errors.push(ConfigIssue::new(
+                        "stealth.browser_profile",
+                        format!("unknown browser_profile '{}'", v),
+                    )),
+                }
             }
             if let Some(v) = sec.os_profile {
-                cfg.os_profile = v;
+                match v.parse() {
+                    Ok(p) => cfg.os_profile = p,
+                    Err(_) => errors.push(ConfigIssue::new(
+                        "stealth.os_profile",
+                        format!("unknown os_profile '{}'", v),
+                    )),
+                }
             }
             if let Some(v) = sec.use_fake_tls {
                 cfg.use_fake_tls = v;
@@ -953,9 +2596,30 @@ impl StealthConfig {
             if let Some(v) = sec.doh_provider {
                 cfg.doh_provider = v;
             }
+            if let Some(v) = sec.doh_keep_alive {
+                cfg.doh.keep_alive = v;
+            }
+            if let Some(v) = sec.doh_pool_idle_timeout_ms {
+                cfg.doh.pool_idle_timeout = Duration::from_millis(v);
+            }
+            if let Some(v) = sec.doh_cache_ttl_ms {
+                cfg.doh.cache_ttl = Duration::from_millis(v);
+            }
+            if let Some(v) = sec.doh_query_jitter_min_ms {
+                cfg.doh.query_jitter.start = Duration::from_millis(v);
+            }
+            if let Some(v) = sec.doh_query_jitter_max_ms {
+                cfg.doh.query_jitter.end = Duration::from_millis(v);
+            }
+            if let Some(v) = sec.doh_system_fallback {
+                cfg.doh.system_fallback = v;
+            }
             if let Some(v) = sec.enable_http3_masquerading {
                 cfg.enable_http3_masquerading = v;
             }
+            if let Some(v) = sec.alt_svc {
+                cfg.alt_svc = v;
+            }
             if let Some(v) = sec.use_qpack_headers {
                 cfg.use_qpack_headers = v;
             }
@@ -968,6 +2632,51 @@ impl StealthConfig {
             if let Some(v) = sec.enable_xor_obfuscation {
                 cfg.enable_xor_obfuscation = v;
             }
+            if let Some(v) = sec.xor_key_from_handshake {
+                cfg.xor_key_from_handshake = v;
+            }
+            if let Some(v) = sec.xor_key {
+                match parse_xor_key(&v) {
+                    Ok(key) => cfg.xor_key = Some(key),
+                    Err(e) => errors.push(ConfigIssue::new("stealth.xor_key", e)),
+                }
+            }
+            if let Some(v) = sec.xor_pattern {
+                match v.parse() {
+                    Ok(p) => cfg.xor_pattern = p,
+                    Err(_) => errors.push(ConfigIssue::new(
+                        "stealth.xor_pattern",
+                        format!("unknown xor_pattern '{}'", v),
+                    )),
+                }
+            }
+            if let Some(v) = sec.mimic_handshake_timing {
+                cfg.mimic_handshake_timing = v;
+            }
+            if let Some(v) = sec.disable_spin_bit {
+                cfg.disable_spin_bit = v;
+            }
+            if let Some(v) = sec.origin_persona {
+                match v.parse() {
+                    Ok(p) => cfg.origin_persona = Some(p),
+                    Err(_) => errors.push(ConfigIssue::new(
+                        "stealth.origin_persona",
+                        format!("unknown origin_persona '{}'", v),
+                    )),
+                }
+            }
+            if let Some(v) = sec.origin_persona_capture {
+                match OriginPersona::from_capture_file(Path::new(&v)) {
+                    Ok(p) => cfg.origin_persona = Some(p),
+                    Err(e) => errors.push(ConfigIssue::new(
+                        "stealth.origin_persona_capture",
+                        e.to_string(),
+                    )),
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Box::new(ConfigError { issues: errors }));
         }
         Ok(cfg)
     }
@@ -977,28 +2686,148 @@ impl StealthConfig {
         Self::from_toml(&contents)
     }
 
-    /// Validate the configuration values.
+    /// Validate the configuration values, reporting every problem joined
+    /// by `"; "`.
     pub fn validate(&self) -> Result<(), String> {
+        let issues = self.issues();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            let messages: Vec<_> = issues.into_iter().map(|i| i.message).collect();
+            Err(messages.join("; "))
+        }
+    }
+
+    /// Every problem with the configuration values, one per field and keyed
+    /// by its path in the `[stealth]` TOML section. Empty if they are valid.
+    pub fn issues(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
         if self.enable_doh && self.doh_provider.is_empty() {
-            return Err("doh_provider must not be empty when DoH is enabled".into());
+            issues.push(ConfigIssue::new(
+                "stealth.doh_provider",
+                "doh_provider must not be empty when DoH is enabled",
+            ));
+        }
+        if self.doh.query_jitter.start > self.doh.query_jitter.end {
+            issues.push(ConfigIssue::new(
+                "stealth.doh_query_jitter_min_ms",
+                "doh query jitter minimum exceeds maximum",
+            ));
         }
         if self.enable_domain_fronting
             && self.fronting_domains.is_empty()
             && self.cdn_providers.is_empty()
         {
-            return Err("fronting_domains required when domain fronting is enabled".into());
+            issues.push(ConfigIssue::new(
+                "stealth.fronting_domains",
+                "fronting_domains required when domain fronting is enabled",
+            ));
         }
-        Ok(())
+        if let Some(key) = &self.xor_key {
+            if key.len() < StealthManager::MIN_XOR_KEY_LEN {
+                issues.push(ConfigIssue::new(
+                    "stealth.xor_key",
+                    format!(
+                        "xor_key is {} bytes, at least {} required",
+                        key.len(),
+                        StealthManager::MIN_XOR_KEY_LEN
+                    ),
+                ));
+            }
+        }
+        issues
     }
 }
 
+/// Point-in-time view of the stealth configuration as seen by the manager.
+/// Reflects runtime changes such as profile switches, not just the initial
+/// [`StealthConfig`].
+///
+/// Cover traffic is reported as on or off only; its rate and size
+/// distribution are left out, as are key material and key fingerprints.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct StealthSnapshot {
+    pub browser_profile: BrowserProfile,
+    pub os_profile: OsProfile,
+    pub fake_tls: bool,
+    pub doh: bool,
+    pub doh_provider: String,
+    pub doh_cache_ttl_ms: u64,
+    pub http3_masquerading: bool,
+    pub qpack_headers: bool,
+    pub domain_fronting: bool,
+    pub xor_obfuscation: bool,
+    /// [`Obfuscator::name`] of the active transform, `None` when payloads
+    /// are sent as is.
+    pub obfuscator: Option<&'static str>,
+    pub xor_pattern: XorPattern,
+    /// Datagram sizes drawn by the last rotation, see
+    /// [`StealthManager::padding_buckets`].
+    pub padding_buckets: Vec<usize>,
+    pub cover_traffic: bool,
+    pub spin_bit_mode: SpinBitMode,
+    pub send_rate_hint: u64,
+}
+
+/// DNS-over-HTTPS settings as listed in a [`HandshakePlan`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DohPlan {
+    pub provider: String,
+    pub keep_alive: bool,
+    pub cache_ttl_ms: u64,
+    /// Bounds of the random delay before a query, in milliseconds.
+    pub query_jitter_ms: (u64, u64),
+}
+
+/// Everything the active profile puts on the wire while a connection is set
+/// up, meant to be dumped and compared with a real browser capture.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HandshakePlan {
+    pub browser_profile: BrowserProfile,
+    pub os_profile: OsProfile,
+    pub fake_tls: bool,
+    /// ClientHello record: the captured one if present, otherwise the one
+    /// synthesized from the profile.
+    pub client_hello: Vec<u8>,
+    pub ja3: Option<String>,
+    pub ja4: Option<String>,
+    pub cipher_suites: Vec<u16>,
+    pub alpn: Vec<String>,
+    pub split_strategy: fake_tls::SplitStrategy,
+    /// `None` if HTTP/3 masquerading is disabled.
+    pub h3_settings: Option<Http3Settings>,
+    pub spin_bit_mode: SpinBitMode,
+    /// Front domains in rotation order, empty if fronting is disabled.
+    pub fronting_domains: Vec<String>,
+    /// `None` if DoH is disabled.
+    pub doh: Option<DohPlan>,
+}
+
 /// The central orchestrator for all stealth techniques.
 pub struct StealthManager {
     config: StealthConfig,
     fingerprint: Mutex<FingerprintProfile>,
-    doh_client: Client,
+    doh_pool: DohPool,
+    doh_cache: Arc<DohCache>,
     domain_fronter: Option<DomainFrontingManager>,
-    xor_obfuscator: Option<XorObfuscator>,
+    obfuscator: Option<Box<dyn Obfuscator>>,
+    xor_key_source: Mutex<XorKeySource>,
+    staged_xor_key: Mutex<Option<StagedXorKey>>,
+    pacer: Mutex<SendPacer>,
+    // Set by `set_send_rate_hint`; the connection's rate updates are ignored
+    // until `clear_send_rate_hint`. Read and written under the `pacer` lock.
+    send_rate_pinned: AtomicBool,
+    spin_bit: Mutex<SpinBitRandomizer>,
+    handshake_timing: Mutex<Option<HandshakeTimingModel>>,
+    cover: Mutex<Option<CoverTraffic>>,
+    cover_sent: AtomicUsize,
+    cover_dropped: AtomicUsize,
+    origin_persona: Mutex<Option<(OriginPersona, &'static str)>>,
+    sni_split: Mutex<fake_tls::SplitStrategy>,
+    request_profiles: Mutex<Vec<FingerprintProfile>>,
+    resolver_closed: AtomicBool,
+    spin_bit_disabled: AtomicBool,
+    padding_buckets: Mutex<Vec<usize>>,
     // Integration with other modules
     crypto_manager: Arc<CryptoManager>,
     optimization_manager: Arc<OptimizationManager>,
@@ -1029,10 +2858,25 @@ impl StealthManager {
             None
         };
 
-        let xor_obfuscator = if config.enable_xor_obfuscation {
-            Some(XorObfuscator::new(&crypto_manager))
+        let xor_key_source = if config.xor_key.is_some() {
+            XorKeySource::Configured
+        } else if config.xor_key_from_handshake {
+            XorKeySource::Static
         } else {
+            XorKeySource::Session
+        };
+        let obfuscator: Option<Box<dyn Obfuscator>> = if !config.enable_xor_obfuscation {
             None
+        } else {
+            let xor = match (&config.xor_key, config.xor_key_from_handshake) {
+                (Some(key), _) => XorObfuscator::with_key(key),
+                (None, true) => XorObfuscator::with_key(&XorObfuscator::static_key()),
+                (None, false) => XorObfuscator::new(&crypto_manager),
+            };
+            if config.xor_pattern == XorPattern::Rolling {
+                warn!("Rolling XOR pattern loses sync on the first lost or reordered datagram");
+            }
+            Some(Box::new(xor.with_pattern(config.xor_pattern)))
         };
 
         telemetry!(telemetry::STEALTH_DOH.set(if config.enable_doh { 1 } else { 0 }));
@@ -1041,17 +2885,216 @@ impl StealthManager {
         );
         telemetry!(telemetry::STEALTH_XOR.set(if config.enable_xor_obfuscation { 1 } else { 0 }));
 
+        let doh_pool = DohPool::new(config.doh.clone());
+        let doh_cache = Arc::new(DohCache::new(config.doh.cache_ttl));
+        let handshake_timing = config
+            .mimic_handshake_timing
+            .then(|| HandshakeTimingModel::new(HandshakeTiming::for_browser(fingerprint.browser)));
+        let origin_persona = config
+            .origin_persona
+            .clone()
+            .map(|p| (p, Self::pick_origin_pop()));
+
+        let spin_bit_disabled = AtomicBool::new(config.disable_spin_bit);
         Self {
             config,
             fingerprint: Mutex::new(fingerprint),
-            doh_client: Client::new(),
+            doh_pool,
+            doh_cache,
             domain_fronter,
-            xor_obfuscator,
+            obfuscator,
+            xor_key_source: Mutex::new(xor_key_source),
+            staged_xor_key: Mutex::new(None),
+            pacer: Mutex::new(SendPacer::new()),
+            send_rate_pinned: AtomicBool::new(false),
+            spin_bit: Mutex::new(SpinBitRandomizer::default()),
+            handshake_timing: Mutex::new(handshake_timing),
+            cover: Mutex::new(None),
+            cover_sent: AtomicUsize::new(0),
+            cover_dropped: AtomicUsize::new(0),
+            origin_persona: Mutex::new(origin_persona),
+            sni_split: Mutex::new(fake_tls::SplitStrategy::default()),
+            request_profiles: Mutex::new(Vec::new()),
+            resolver_closed: AtomicBool::new(false),
+            spin_bit_disabled,
+            padding_buckets: Mutex::new(Vec::new()),
             crypto_manager,
             optimization_manager,
         }
     }
 
+    /// Creates a manager that uses `obfuscator` instead of the XOR transform,
+    /// regardless of [`StealthConfig::enable_xor_obfuscation`].
+    pub fn with_obfuscator(
+        config: StealthConfig,
+        crypto_manager: Arc<CryptoManager>,
+        optimization_manager: Arc<OptimizationManager>,
+        obfuscator: Box<dyn Obfuscator>,
+    ) -> Self {
+        let mut this = Self::new(config, crypto_manager, optimization_manager);
+        this.obfuscator = Some(obfuscator);
+        this
+    }
+
+    /// Minimum accepted XOR obfuscation key length in bytes.
+    pub const MIN_XOR_KEY_LEN: usize = 16;
+
+    /// Validates the configured sub-components and warms up the DoH runtime.
+    ///
+    /// Checks that the active fingerprint matches the configured browser/OS
+    /// and has a ClientHello dump, that the DoH provider is an `https` URL
+    /// and that the XOR key is long enough.
+    pub fn initialize(&self) -> Result<(), StealthError> {
+        self.config.validate().map_err(StealthError::Config)?;
+
+        {
+            let fp = self.fingerprint.lock().unwrap();
+            if fp.browser != self.config.browser_profile || fp.os != self.config.os_profile {
+                return Err(StealthError::InvalidProfile(format!(
+                    "active profile {:?}/{:?} does not match configured {:?}/{:?}",
+                    fp.browser, fp.os, self.config.browser_profile, self.config.os_profile
+                )));
+            }
+            if fp.client_hello.is_none() {
+                return Err(StealthError::InvalidProfile(format!(
+                    "no ClientHello available for {:?}/{:?}",
+                    fp.browser, fp.os
+                )));
+            }
+        }
+
+        if self.config.enable_doh {
+            let url = &self.config.doh_provider;
+            let invalid = |reason: String| StealthError::InvalidDohProvider {
+                url: url.clone(),
+                reason,
+            };
+            let parsed = Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+            if parsed.scheme() != "https" {
+                return Err(invalid("scheme must be https".into()));
+            }
+            if parsed.host_str().map_or(true, str::is_empty) {
+                return Err(invalid("missing host".into()));
+            }
+            lazy_static::initialize(&DOH_RUNTIME);
+        }
+
+        if let Some(len) = self.obfuscator.as_ref().and_then(|o| o.key_len()) {
+            if len < Self::MIN_XOR_KEY_LEN {
+                return Err(StealthError::XorKeyTooShort {
+                    len,
+                    min: Self::MIN_XOR_KEY_LEN,
+                });
+            }
+        }
+
+        self.resolver_closed.store(false, Ordering::Relaxed);
+        info!("Stealth manager initialized");
+        Ok(())
+    }
+
+    /// Stops the stealth layer: pending profile switches are discarded and
+    /// the DoH resolver is closed, so later lookups use the fallback path.
+    /// Queued datagrams are flushed by the owning connection, see
+    /// [`QuicFuscateConnection::shutdown`](crate::core::QuicFuscateConnection::shutdown).
+    pub fn shutdown(&self) {
+        self.resolver_closed.store(true, Ordering::Relaxed);
+        self.doh_pool.clear();
+        self.doh_cache.clear();
+        self.request_profiles.lock().unwrap().clear();
+        info!("Stealth manager shut down");
+    }
+
+    /// Pins the datagram pacer to `bytes_per_sec`, `0` disabling pacing.
+    /// The rate the connection derives from its congestion controller on
+    /// each state update is ignored until
+    /// [`clear_send_rate_hint`](Self::clear_send_rate_hint).
+    pub fn set_send_rate_hint(&self, bytes_per_sec: u64) {
+        let mut pacer = self.pacer.lock().unwrap();
+        self.send_rate_pinned.store(true, Ordering::Relaxed);
+        pacer.set_rate(bytes_per_sec);
+    }
+
+    /// Drops a rate set by [`set_send_rate_hint`](Self::set_send_rate_hint),
+    /// so the pacer follows the connection's send rate again from its next
+    /// state update.
+    pub fn clear_send_rate_hint(&self) {
+        let _pacer = self.pacer.lock().unwrap();
+        self.send_rate_pinned.store(false, Ordering::Relaxed);
+    }
+
+    /// Feeds the connection's current send rate into the datagram pacer,
+    /// unless a hint is pinned. Shaped output is bounded by this rate to
+    /// avoid implausible bursts.
+    pub(crate) fn update_send_rate(&self, bytes_per_sec: u64) {
+        let mut pacer = self.pacer.lock().unwrap();
+        if !self.send_rate_pinned.load(Ordering::Relaxed) {
+            pacer.set_rate(bytes_per_sec);
+        }
+    }
+
+    /// Returns the last send rate hint in bytes per second (`0` if unpaced).
+    pub fn send_rate_hint(&self) -> u64 {
+        self.pacer.lock().unwrap().rate()
+    }
+
+    /// Returns `true` if a shaped datagram of `len` bytes may be released now.
+    pub fn try_release_datagram(&self, len: usize) -> bool {
+        self.pacer.lock().unwrap().try_release(len, Instant::now())
+    }
+
+    /// Feeds a smoothed RTT sample into the spin bit scheduler.
+    pub fn on_rtt_sample(&self, rtt: Duration) {
+        self.spin_bit.lock().unwrap().on_rtt_sample(rtt);
+    }
+
+    /// Returns the spin bit to place in the short header of packet `pn`.
+    /// Always `false` while the spin bit is disabled.
+    pub fn spin_bit_for(&self, pn: u64) -> bool {
+        let spin = self.spin_bit.lock().unwrap().spin_for(pn, Instant::now());
+        spin && !self.spin_bit_disabled()
+    }
+
+    /// Forces the spin bit to `0` on all outgoing short-header packets,
+    /// overriding the scheduler. Takes effect with the next packet sent.
+    pub fn set_spin_bit_disabled(&self, disabled: bool) {
+        self.spin_bit_disabled.store(disabled, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the spin bit is held at `0`, see
+    /// [`StealthConfig::disable_spin_bit`].
+    pub fn spin_bit_disabled(&self) -> bool {
+        self.spin_bit_disabled.load(Ordering::Relaxed)
+    }
+
+    /// Spin bit strategy in effect, [`SpinBitMode::Disabled`] while the
+    /// spin bit is forced off.
+    pub fn spin_bit_mode(&self) -> SpinBitMode {
+        if self.spin_bit_disabled() {
+            SpinBitMode::Disabled
+        } else {
+            self.spin_bit.lock().unwrap().mode()
+        }
+    }
+
+    /// Returns how long to hold back the next handshake datagram so the
+    /// handshake is paced like the active browser. Zero when
+    /// [`StealthConfig::mimic_handshake_timing`] is off.
+    pub fn next_handshake_delay(&self) -> Duration {
+        match self.handshake_timing.lock().unwrap().as_mut() {
+            Some(model) => model.next_delay(),
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Makes the handshake delays reproducible, for tests.
+    pub fn seed_handshake_timing(&self, seed: u64) {
+        let mut guard = self.handshake_timing.lock().unwrap();
+        if let Some(model) = guard.as_mut() {
+            *model = HandshakeTimingModel::with_seed(model.timing(), seed);
+        }
+    }
+
     /// Returns all fingerprint profiles for which a ClientHello dump exists.
     pub fn available_fingerprints() -> Vec<FingerprintProfile> {
         TlsClientHelloSpoofer::available_profiles()
@@ -1104,9 +3147,10 @@ impl StealthManager {
             }
         }
 
-        config
-            .set_application_protos(quiche::h3::APPLICATION_PROTOCOL)
-            .unwrap();
+        let protos: Vec<&[u8]> = fingerprint.alpn.iter().map(|p| p.as_bytes()).collect();
+        if let Err(e) = config.set_application_protos(&protos) {
+            error!("Failed to set ALPN {:?}: {}", fingerprint.alpn, e);
+        }
 
         // Apply the detailed QUIC transport parameters from the harmonized profile.
         config.set_initial_max_data(fingerprint.initial_max_data);
@@ -1135,6 +3179,10 @@ impl StealthManager {
             TlsClientHelloSpoofer::inject_bytes(c, hello);
         }
 
+        if let Some(model) = self.handshake_timing.lock().unwrap().as_mut() {
+            model.set_timing(HandshakeTiming::for_browser(p.browser));
+        }
+
         let mut fp = self.fingerprint.lock().unwrap();
         *fp = p;
     }
@@ -1144,12 +3192,95 @@ impl StealthManager {
         self.fingerprint.lock().unwrap().clone()
     }
 
+    /// Captures the enabled sub-modules and their key parameters.
+    pub fn snapshot_config(&self) -> StealthSnapshot {
+        let fp = self.current_profile();
+        let obfuscator = self.obfuscator.as_ref().map(|obf| obf.name());
+        StealthSnapshot {
+            browser_profile: fp.browser,
+            os_profile: fp.os,
+            fake_tls: self.config.use_fake_tls,
+            doh: self.config.enable_doh,
+            doh_provider: self.config.doh_provider.clone(),
+            doh_cache_ttl_ms: self.config.doh.cache_ttl.as_millis() as u64,
+            http3_masquerading: self.config.enable_http3_masquerading,
+            qpack_headers: self.config.use_qpack_headers,
+            domain_fronting: self.domain_fronter.is_some(),
+            xor_obfuscation: obfuscator == Some("xor"),
+            obfuscator,
+            xor_pattern: self.config.xor_pattern,
+            padding_buckets: self.padding_buckets(),
+            cover_traffic: self.cover_traffic_enabled(),
+            spin_bit_mode: self.spin_bit_mode(),
+            send_rate_hint: self.send_rate_hint(),
+        }
+    }
+
+    /// Collects the handshake the current profile will perform: ClientHello
+    /// bytes, ALPN, SETTINGS, spin bit strategy, fronting and DoH setup.
+    pub fn handshake_plan(&self) -> HandshakePlan {
+        let fp = self.current_profile();
+        let client_hello = match fp.client_hello {
+            Some(ref ch) => ch.clone(),
+            None if !fp.extension_order.is_empty() => fake_tls::FakeTls::client_hello_ordered(
+                &fp.tls_cipher_suites,
+                &fp.extension_order,
+                &fp.alpn,
+            ),
+            None => fake_tls::FakeTls::client_hello(&fp),
+        };
+        let doh = &self.config.doh;
+        HandshakePlan {
+            browser_profile: fp.browser,
+            os_profile: fp.os,
+            fake_tls: self.config.use_fake_tls,
+            ja3: fake_tls::FakeTls::ja3(&client_hello),
+            ja4: fake_tls::FakeTls::ja4(&client_hello),
+            client_hello,
+            cipher_suites: fp.tls_cipher_suites.clone(),
+            alpn: fp.alpn.clone(),
+            split_strategy: self.split_strategy(),
+            h3_settings: self.http3_settings(),
+            spin_bit_mode: self.spin_bit_mode(),
+            fronting_domains: self
+                .domain_fronter
+                .as_ref()
+                .map(|f| f.domains().to_vec())
+                .unwrap_or_default(),
+            doh: self.config.enable_doh.then(|| DohPlan {
+                provider: self.config.doh_provider.clone(),
+                keep_alive: doh.keep_alive,
+                cache_ttl_ms: doh.cache_ttl.as_millis() as u64,
+                query_jitter_ms: (
+                    doh.query_jitter.start.as_millis() as u64,
+                    doh.query_jitter.end.as_millis() as u64,
+                ),
+            }),
+        }
+    }
+
     /// Generates the FakeTLS handshake bytes for the current profile.
     pub fn fake_tls_handshake(&self) -> Vec<u8> {
         let fp = self.fingerprint.lock().unwrap();
         fake_tls::FakeTls::handshake(&fp)
     }
 
+    /// Selects how ClientHellos are segmented, e.g. splitting inside the SNI
+    /// as an alternative to domain fronting.
+    pub fn set_split_strategy(&self, strategy: fake_tls::SplitStrategy) {
+        *self.sni_split.lock().unwrap() = strategy;
+    }
+
+    pub fn split_strategy(&self) -> fake_tls::SplitStrategy {
+        *self.sni_split.lock().unwrap()
+    }
+
+    /// Cuts a ClientHello into the segments to write, following the
+    /// configured [`SplitStrategy`](fake_tls::SplitStrategy).
+    pub fn segment_client_hello(&self, client_hello: &[u8]) -> Vec<Vec<u8>> {
+        fake_tls::FakeTls::split_client_hello(client_hello, self.split_strategy())
+    }
+
     /// Configures the provided quiche `Config` for the active fingerprint.
     /// Depending on the configuration this either applies an uTLS profile or
     /// generates FakeTLS handshake bytes. The returned vector is only populated
@@ -1180,20 +3311,27 @@ impl StealthManager {
     }
 
     /// Starts automatic rotation through the given browser profiles.
-    /// This spawns a task on the DoH runtime which periodically updates the
-    /// active fingerprint.
+    /// Timer based schedules spawn a task on the DoH runtime which updates
+    /// the active fingerprint whenever the schedule fires. With
+    /// [`RotationSchedule::PerRequest`] a random profile is chosen each time
+    /// request headers are generated instead.
     pub fn start_profile_rotation(
         self: &Arc<Self>,
         profiles: Vec<FingerprintProfile>,
-        interval: std::time::Duration,
+        schedule: impl Into<RotationSchedule>,
     ) {
         if profiles.is_empty() {
             return;
         }
+        let schedule = schedule.into();
+        if schedule == RotationSchedule::PerRequest {
+            *self.request_profiles.lock().unwrap() = profiles;
+            return;
+        }
         let mgr = Arc::clone(self);
         DOH_RUNTIME.spawn(async move {
             let mut idx = 0usize;
-            loop {
+            while let Some(interval) = schedule.next_interval() {
                 tokio::time::sleep(interval).await;
                 idx = (idx + 1) % profiles.len();
                 mgr.set_fingerprint_profile(profiles[idx].clone(), None);
@@ -1201,33 +3339,175 @@ impl StealthManager {
         });
     }
 
-    /// Resolves a domain, using DoH if enabled.
+    /// Switches to a random profile if per-request rotation is active.
+    fn rotate_for_request(&self) {
+        let profile = {
+            use rand::seq::SliceRandom;
+            let profiles = self.request_profiles.lock().unwrap();
+            match profiles.choose(&mut rand::thread_rng()) {
+                Some(p) => p.clone(),
+                None => return,
+            }
+        };
+        self.set_fingerprint_profile(profile, None);
+    }
+
+    /// Resolves a domain, using DoH if enabled. Queries are bounded by
+    /// [`DEFAULT_RESOLVE_TIMEOUT`].
     pub fn resolve_domain(&self, domain: &str) -> IpAddr {
-        if self.config.enable_doh {
-            debug!(
-                "Resolving {} via DoH provider: {}",
-                domain, self.config.doh_provider
-            );
-            match DOH_RUNTIME.block_on(resolve_doh(
-                &self.doh_client,
-                domain,
-                &self.config.doh_provider,
-            )) {
-                Ok(ip) => ip,
-                Err(e) => {
-                    telemetry!(telemetry::DNS_ERRORS.inc());
-                    error!("DoH resolution failed: {}. Falling back.", e);
-                    IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))
-                }
+        match self.resolve_domain_timeout(domain, DEFAULT_RESOLVE_TIMEOUT) {
+            Ok(ip) => ip,
+            Err(ResolveError::Disabled) => {
+                // Fallback to standard DNS resolution (conceptual)
+                info!("DoH disabled, using standard DNS for {}", domain);
+                // In a real app, you would use std::net::ToSocketAddrs here.
+                IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))
+            }
+            Err(e) => {
+                telemetry!(telemetry::DNS_ERRORS.inc());
+                error!("{}. Falling back.", e);
+                IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))
             }
-        } else {
-            // Fallback to standard DNS resolution (conceptual)
-            info!("DoH disabled, using standard DNS for {}", domain);
-            // In a real app, you would use std::net::ToSocketAddrs here.
-            IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))
         }
     }
 
+    /// Resolves `domain` via DoH, giving up after `timeout`. The jitter delay
+    /// from [`DohConfig::query_jitter`] is not counted against the timeout.
+    pub fn resolve_domain_timeout(
+        &self,
+        domain: &str,
+        timeout: Duration,
+    ) -> Result<IpAddr, ResolveError> {
+        self.resolve_domain_detailed(domain, timeout).map(|r| r.ip)
+    }
+
+    /// Like [`resolve_domain_timeout`](Self::resolve_domain_timeout), but
+    /// also reports whether the answer came from DoH, the cache or the
+    /// system resolver fallback.
+    pub fn resolve_domain_detailed(
+        &self,
+        domain: &str,
+        timeout: Duration,
+    ) -> Result<Resolved, ResolveError> {
+        self.resolve_domain_detailed_with(domain, timeout, |domain| {
+            let client = self.doh_pool.checkout(Instant::now());
+            async move { resolve_doh(&client, &domain, &self.config.doh_provider).await }
+        })
+    }
+
+    /// Same as [`resolve_domain_detailed`](Self::resolve_domain_detailed)
+    /// with a custom DoH resolver.
+    pub fn resolve_domain_detailed_with<F, Fut>(
+        &self,
+        domain: &str,
+        timeout: Duration,
+        doh: F,
+    ) -> Result<Resolved, ResolveError>
+    where
+        F: FnOnce(String) -> Fut,
+        Fut: std::future::Future<Output = Result<IpAddr, Box<dyn std::error::Error>>>,
+    {
+        if !self.config.enable_doh || self.resolver_closed.load(Ordering::Relaxed) {
+            return Err(ResolveError::Disabled);
+        }
+        if let Some(ip) = self.doh_cache.get(domain, Instant::now()) {
+            return Ok(Resolved {
+                ip,
+                source: ResolveSource::Cache,
+            });
+        }
+        let delay = self.config.doh.query_delay();
+        debug!(
+            "Resolving {} via DoH provider: {} (after {:?})",
+            domain, self.config.doh_provider, delay
+        );
+        let result = DOH_RUNTIME.block_on(async {
+            tokio::time::sleep(delay).await;
+            resolve_with_timeout(doh(domain.to_string()), timeout).await
+        });
+        match result {
+            Ok(ip) => {
+                self.doh_cache.insert(domain, ip, Instant::now());
+                Ok(Resolved {
+                    ip,
+                    source: ResolveSource::Doh,
+                })
+            }
+            Err(e) if self.config.doh.system_fallback => {
+                warn!(
+                    "DoH lookup of {} failed ({}), using the system resolver; the query is not private",
+                    domain, e
+                );
+                let ip =
+                    DOH_RUNTIME.block_on(resolve_with_timeout(resolve_system(domain), timeout))?;
+                Ok(Resolved {
+                    ip,
+                    source: ResolveSource::System,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Resolves `domains` in the background and caches the results, so later
+    /// lookups are served from the cache. Queries are sent one after another,
+    /// each after its own [`DohConfig::query_jitter`] delay, and domains that
+    /// are already cached are skipped. The handle yields how many domains
+    /// were resolved.
+    pub fn prefetch(&self, domains: &[&str]) -> std::thread::JoinHandle<usize> {
+        let client = self.doh_pool.checkout(Instant::now());
+        let provider = self.config.doh_provider.clone();
+        self.prefetch_with(domains, move |domain| {
+            let client = client.clone();
+            let provider = provider.clone();
+            async move { resolve_doh(&client, &domain, &provider).await }
+        })
+    }
+
+    /// Same as [`prefetch`](Self::prefetch) with a custom resolver.
+    pub fn prefetch_with<F, Fut>(
+        &self,
+        domains: &[&str],
+        resolve: F,
+    ) -> std::thread::JoinHandle<usize>
+    where
+        F: Fn(String) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = Result<IpAddr, Box<dyn std::error::Error>>>,
+    {
+        let enabled = self.config.enable_doh && !self.resolver_closed.load(Ordering::Relaxed);
+        let domains: Vec<String> = if enabled {
+            domains.iter().map(|d| d.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        let cache = Arc::clone(&self.doh_cache);
+        let doh = self.config.doh.clone();
+        std::thread::spawn(move || {
+            DOH_RUNTIME.block_on(async {
+                let mut resolved = 0;
+                for domain in domains {
+                    if cache.get(&domain, Instant::now()).is_some() {
+                        continue;
+                    }
+                    tokio::time::sleep(doh.query_delay()).await;
+                    match resolve_with_timeout(resolve(domain.clone()), DEFAULT_RESOLVE_TIMEOUT)
+                        .await
+                    {
+                        Ok(ip) => {
+                            cache.insert(&domain, ip, Instant::now());
+                            resolved += 1;
+                        }
+                        Err(e) => {
+                            telemetry!(telemetry::DNS_ERRORS.inc());
+                            warn!("Prefetch of {} failed: {}", domain, e);
+                        }
+                    }
+                }
+                resolved
+            })
+        })
+    }
+
     /// Returns the SNI and Host header values for a connection.
     /// Applies domain fronting if enabled.
     pub fn get_connection_headers(&self, real_host: &str) -> (String, String) {
@@ -1276,42 +3556,295 @@ impl StealthManager {
             }
         }
 
-        if self.config.enable_xor_obfuscation && self.xor_obfuscator.is_some() {
+        if let Some(obf) = &self.obfuscator {
             debug!("Applying XOR obfuscation to outgoing packet.");
-            self.xor_obfuscator.as_ref().unwrap().obfuscate(payload);
+            obf.obfuscate(payload);
         }
 
         // HTTP/3 Masquerading is applied at the stream level when sending data,
         // not on raw packets here.
     }
 
+    /// Stages an obfuscation key derived from `exporter_secret`, the TLS
+    /// exporter output for [`XOR_EXPORTER_LABEL`] of the session that just
+    /// completed its handshake. Without a secret the static key stays in
+    /// use. Does nothing unless [`StealthConfig::xor_key_from_handshake`] is
+    /// set, or if a [`StealthConfig::xor_key`] is configured.
+    ///
+    /// The staged key is not used right away, as the endpoints complete the
+    /// handshake one flight apart. Each direction moves over on an explicit
+    /// signal from the caller: [`switch_xor_send_key`](Self::switch_xor_send_key)
+    /// once this side's handshake is complete, and
+    /// [`switch_xor_receive_key`](Self::switch_xor_receive_key) once the
+    /// peer has announced that it sends with the new key, e.g. on its
+    /// handshake-done signal or a key epoch change.
+    pub fn coordinate_xor_key(&self, exporter_secret: Option<&[u8]>) -> XorKeySource {
+        let mut source = self.xor_key_source.lock().unwrap();
+        if !self.config.xor_key_from_handshake
+            || self.config.xor_key.is_some()
+            || *source == XorKeySource::Rotated
+        {
+            return *source;
+        }
+        match (exporter_secret, &self.obfuscator) {
+            (Some(secret), Some(_)) if !secret.is_empty() => {
+                *self.staged_xor_key.lock().unwrap() = Some(StagedXorKey {
+                    key: XorObfuscator::key_from_exporter(secret),
+                    sending: false,
+                    receiving: false,
+                });
+                *source = XorKeySource::Exporter;
+                info!("XOR obfuscation key derived from TLS exporter");
+            }
+            _ => {
+                if *source != XorKeySource::Static {
+                    if let Some(obf) = &self.obfuscator {
+                        obf.set_key(&XorObfuscator::static_key());
+                    }
+                }
+                *source = XorKeySource::Static;
+                debug!("No TLS exporter secret, keeping static XOR key");
+            }
+        }
+        *source
+    }
+
+    /// Returns where the current XOR obfuscation key comes from.
+    pub fn xor_key_source(&self) -> XorKeySource {
+        *self.xor_key_source.lock().unwrap()
+    }
+
+    /// Returns `true` while a key staged by
+    /// [`coordinate_xor_key`](Self::coordinate_xor_key) is not yet in use
+    /// in both directions.
+    pub fn has_staged_xor_key(&self) -> bool {
+        self.staged_xor_key.lock().unwrap().is_some()
+    }
+
+    /// Starts obfuscating outgoing packets with the staged key. The receive
+    /// direction keeps the current key until
+    /// [`switch_xor_receive_key`](Self::switch_xor_receive_key).
+    pub fn switch_xor_send_key(&self) {
+        let mut staged = self.staged_xor_key.lock().unwrap();
+        if let (Some(key), Some(obf)) = (staged.as_mut(), &self.obfuscator) {
+            if !key.sending {
+                obf.set_send_key(&key.key);
+                key.sending = true;
+            }
+        }
+        if staged.as_ref().is_some_and(|k| k.sending && k.receiving) {
+            *staged = None;
+        }
+    }
+
+    /// Starts deobfuscating incoming packets with the staged key, to be
+    /// called once the peer has signalled that it switched its send
+    /// direction. The staged key is dropped once both directions use it.
+    pub fn switch_xor_receive_key(&self) {
+        let mut staged = self.staged_xor_key.lock().unwrap();
+        if let (Some(key), Some(obf)) = (staged.as_mut(), &self.obfuscator) {
+            if !key.receiving {
+                obf.set_receive_key(&key.key);
+                key.receiving = true;
+                debug!("Receiving with the exporter derived XOR key");
+            }
+        }
+        if staged.as_ref().is_some_and(|k| k.sending && k.receiving) {
+            *staged = None;
+        }
+    }
+
+    /// Replaces the XOR key, reseeds the spin bit scheduler and draws new
+    /// datagram padding buckets, all from `rng`.
+    ///
+    /// Rotating everything at once leaves no long-lived parameter to link
+    /// traffic before and after the rotation. The peer has to rotate from an
+    /// RNG in the same state, e.g. one seeded from a shared secret, or it
+    /// can no longer remove the XOR layer. `rng` is consumed the same way
+    /// whether or not XOR obfuscation is enabled.
+    pub fn rotate_obfuscation(&self, rng: &mut impl rand::RngCore) -> ObfuscationRotation {
+        use rand::Rng;
+
+        let mut key = vec![0u8; 32];
+        rng.fill_bytes(&mut key);
+        let xor_key_fingerprint = self.obfuscator.as_ref().map(|obf| {
+            obf.set_key(&key);
+            *self.staged_xor_key.lock().unwrap() = None;
+            *self.xor_key_source.lock().unwrap() = XorKeySource::Rotated;
+            let digest = Sha256::digest(&key);
+            let mut id = [0u8; 8];
+            id.copy_from_slice(&digest[..8]);
+            id
+        });
+
+        let spin_seed = rng.next_u64();
+        self.spin_bit.lock().unwrap().reseed(spin_seed);
+
+        let mut padding_buckets: Vec<usize> = (0..PADDING_BUCKET_COUNT)
+            .map(|_| rng.gen_range(PADDING_BUCKET_RANGE))
+            .collect();
+        padding_buckets.sort_unstable();
+        padding_buckets.dedup();
+        *self.padding_buckets.lock().unwrap() = padding_buckets.clone();
+
+        info!("Obfuscation parameters rotated");
+        ObfuscationRotation {
+            xor_key_fingerprint,
+            spin_seed,
+            padding_buckets,
+        }
+    }
+
+    /// Datagram sizes drawn by the last
+    /// [`rotate_obfuscation`](Self::rotate_obfuscation), ascending. Empty
+    /// before the first rotation.
+    pub fn padding_buckets(&self) -> Vec<usize> {
+        self.padding_buckets.lock().unwrap().clone()
+    }
+
     /// Processes an incoming packet payload, reversing stealth techniques.
     pub fn process_incoming_packet(&self, payload: &mut [u8]) {
-        if self.config.enable_xor_obfuscation && self.xor_obfuscator.is_some() {
+        if let Some(obf) = &self.obfuscator {
             debug!("Reversing XOR obfuscation on incoming packet.");
-            self.xor_obfuscator.as_ref().unwrap().deobfuscate(payload);
+            obf.deobfuscate(payload);
+        }
+    }
+
+    /// Runs the full outgoing obfuscation pipeline on `data` and queues the
+    /// result on the datagram engine. This applies XOR obfuscation when
+    /// enabled and respects the engine's size limit. While cover traffic is
+    /// enabled the data is prefixed with [`COVER_FLAG_DATA`].
+    pub fn obfuscate_and_send(
+        &self,
+        engine: &mut DatagramEngine,
+        data: &[u8],
+    ) -> Result<(), quiche::Error> {
+        let mut buf = if self.cover_traffic_enabled() {
+            let mut framed = Vec::with_capacity(data.len() + 1);
+            framed.push(COVER_FLAG_DATA);
+            framed.extend_from_slice(data);
+            framed
+        } else {
+            data.to_vec()
+        };
+        self.process_outgoing_packet(&mut buf);
+        engine.send(&buf)
+    }
+
+    /// Takes the next received datagram from the engine and reverses the
+    /// stealth transformations applied by [`obfuscate_and_send`]. While
+    /// cover traffic is enabled, cover datagrams are deobfuscated, to keep
+    /// the key stream in step, and dropped.
+    ///
+    /// With the default [`XorPattern::Repeating`] every datagram decodes on
+    /// its own, so loss and reordering do not affect the ones that follow.
+    /// [`XorPattern::Rolling`] has no way to resynchronise: after a lost
+    /// datagram every later one comes out garbled.
+    ///
+    /// [`obfuscate_and_send`]: Self::obfuscate_and_send
+    pub fn recv_and_deobfuscate(&self, engine: &mut DatagramEngine) -> Option<Vec<u8>> {
+        loop {
+            let mut buf = engine.pop_incoming()?;
+            self.process_incoming_packet(&mut buf);
+            if !self.cover_traffic_enabled() {
+                return Some(buf);
+            }
+            match buf.first() {
+                Some(&COVER_FLAG_DATA) => {
+                    buf.remove(0);
+                    return Some(buf);
+                }
+                _ => {
+                    self.cover_dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Starts blending decoy datagrams into the traffic sent with
+    /// [`obfuscate_and_send`](Self::obfuscate_and_send): about `rate` per
+    /// second, sized by `sizes`, sent by
+    /// [`send_cover_traffic`](Self::send_cover_traffic).
+    ///
+    /// Every datagram then carries a one byte header telling cover and data
+    /// apart, so both peers must enable cover traffic; a receive-only peer
+    /// can use a rate of 0.
+    pub fn enable_cover_traffic(&self, rate: f64, sizes: SizeDistribution) {
+        *self.cover.lock().unwrap() = Some(CoverTraffic::new(rate, sizes));
+    }
+
+    /// Makes the cover traffic gaps and sizes reproducible, for tests.
+    pub fn seed_cover_traffic(&self, seed: u64) {
+        let mut guard = self.cover.lock().unwrap();
+        if let Some(cover) = guard.as_mut() {
+            *cover = CoverTraffic::with_seed(cover.rate(), cover.sizes().clone(), seed);
+        }
+    }
+
+    /// Stops cover traffic and removes the header again.
+    pub fn disable_cover_traffic(&self) {
+        *self.cover.lock().unwrap() = None;
+    }
+
+    pub fn cover_traffic_enabled(&self) -> bool {
+        self.cover.lock().unwrap().is_some()
+    }
+
+    /// Queues the cover datagrams due by `now` on `engine`, obfuscated like
+    /// data, and returns how many were queued.
+    /// [`QuicFuscateConnection::send`](crate::core::QuicFuscateConnection::send)
+    /// calls it for the connection's DATAGRAM queue once the handshake is
+    /// done.
+    pub fn send_cover_traffic(&self, engine: &mut DatagramEngine, now: Instant) -> usize {
+        let packets: Vec<Vec<u8>> = match self.cover.lock().unwrap().as_mut() {
+            Some(cover) => cover
+                .due(now)
+                .into_iter()
+                .map(|size| cover.packet(size))
+                .collect(),
+            None => return 0,
+        };
+        let mut sent = 0;
+        for mut buf in packets {
+            self.process_outgoing_packet(&mut buf);
+            if engine.send(&buf).is_ok() {
+                sent += 1;
+            }
         }
+        self.cover_sent.fetch_add(sent, Ordering::Relaxed);
+        sent
+    }
+
+    /// Cover datagrams queued so far.
+    pub fn cover_packets_sent(&self) -> usize {
+        self.cover_sent.load(Ordering::Relaxed)
+    }
+
+    /// Cover datagrams received and dropped so far.
+    pub fn cover_packets_dropped(&self) -> usize {
+        self.cover_dropped.load(Ordering::Relaxed)
     }
 
     /// Processes a TLS ClientHello message before it is sent.
     pub fn process_client_hello(&self, payload: &mut [u8]) {
-        if self.config.enable_xor_obfuscation && self.xor_obfuscator.is_some() {
+        if let Some(obf) = &self.obfuscator {
             debug!("Obfuscating ClientHello payload.");
-            self.xor_obfuscator.as_ref().unwrap().obfuscate(payload);
+            obf.obfuscate(payload);
         }
     }
 
     /// Obfuscates arbitrary payload data within a specific context.
     pub fn obfuscate_payload(&self, payload: &mut [u8], _context_id: u64) {
-        if self.config.enable_xor_obfuscation && self.xor_obfuscator.is_some() {
+        if let Some(obf) = &self.obfuscator {
             debug!("Obfuscating payload for context {}", _context_id);
-            self.xor_obfuscator.as_ref().unwrap().obfuscate(payload);
+            obf.obfuscate(payload);
         }
     }
 
     /// Generates HTTP/3 headers for masquerading a request.
     pub fn get_http3_masquerade_headers(&self, host: &str, path: &str) -> Option<Vec<u8>> {
         if self.config.enable_http3_masquerading {
+            self.rotate_for_request();
             let fp = self.fingerprint.lock().unwrap();
             let fh = FakeHeaders::new(
                 FakeHeadersConfig {
@@ -1335,9 +3868,67 @@ impl StealthManager {
         }
     }
 
+    /// Returns the `Alt-Svc` value to attach to server responses, or `None`
+    /// if masquerading is disabled or no advertisement is configured.
+    pub fn alt_svc_header(&self) -> Option<&str> {
+        if self.config.enable_http3_masquerading && !self.config.alt_svc.is_empty() {
+            Some(&self.config.alt_svc)
+        } else {
+            None
+        }
+    }
+
+    fn pick_origin_pop() -> &'static str {
+        use rand::seq::SliceRandom;
+        ORIGIN_POPS
+            .choose(&mut rand::thread_rng())
+            .copied()
+            .unwrap_or("FRA")
+    }
+
+    /// Makes server responses look like those of `persona`. A new edge
+    /// location is picked and kept for all responses until the next call.
+    pub fn set_origin_persona(&self, persona: OriginPersona) {
+        *self.origin_persona.lock().unwrap() = Some((persona, Self::pick_origin_pop()));
+    }
+
+    /// Returns the active origin persona, if any.
+    pub fn origin_persona(&self) -> Option<OriginPersona> {
+        self.origin_persona
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(p, _)| p.clone())
+    }
+
+    /// Headers of the active origin persona to add to a server response.
+    /// Empty if no persona is set or masquerading is disabled.
+    pub fn origin_response_headers(&self) -> Vec<quiche::h3::Header> {
+        if !self.config.enable_http3_masquerading {
+            return Vec::new();
+        }
+        match self.origin_persona.lock().unwrap().as_ref() {
+            Some((persona, pop)) => {
+                persona.response_headers(pop, &mut rand::thread_rng(), std::time::SystemTime::now())
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the HTTP/3 SETTINGS of the active browser profile, or `None`
+    /// if masquerading is disabled.
+    pub fn http3_settings(&self) -> Option<Http3Settings> {
+        if self.config.enable_http3_masquerading {
+            Some(self.current_profile().http3_settings())
+        } else {
+            None
+        }
+    }
+
     /// Returns a vector of HTTP/3 headers for a request.
     pub fn get_http3_header_list(&self, host: &str, path: &str) -> Option<Vec<quiche::h3::Header>> {
         if self.config.enable_http3_masquerading {
+            self.rotate_for_request();
             let fp = self.fingerprint.lock().unwrap();
             let fh = FakeHeaders::new(
                 FakeHeadersConfig {
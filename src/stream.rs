@@ -0,0 +1,176 @@
+// Copyright (c) 2024, The QuicFuscate Project Authors.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above
+//       copyright notice, this list of conditions and the following disclaimer
+//       in the documentation and/or other materials provided with the
+//       distribution.
+//
+//     * Neither the name of the copyright holder nor the names of its
+//       contributors may be used to endorse or promote products derived from
+//       this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # Stream Engine
+//!
+//! Splits application writes on QUIC streams into chunks sized by the
+//! [`QuicStreamOptimizer`] and releases them in priority order while they
+//! fit into the stream's flow-control window.
+
+use log::debug;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::optimize::QuicStreamOptimizer;
+
+/// A queued piece of stream data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamChunk {
+    pub stream_id: u64,
+    pub data: Vec<u8>,
+    /// Set on the last chunk of a write that finishes the stream.
+    pub fin: bool,
+}
+
+/// Queues stream writes for a single connection.
+pub struct StreamEngine {
+    optimizer: Arc<Mutex<QuicStreamOptimizer>>,
+    queues: BTreeMap<u64, VecDeque<StreamChunk>>,
+}
+
+impl StreamEngine {
+    pub fn new(optimizer: Arc<Mutex<QuicStreamOptimizer>>) -> Self {
+        Self {
+            optimizer,
+            queues: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the optimizer consulted for chunk sizes and windows.
+    pub fn optimizer(&self) -> &Arc<Mutex<QuicStreamOptimizer>> {
+        &self.optimizer
+    }
+
+    /// Splits `data` into chunks of the stream's optimal size and queues
+    /// them. Returns the number of chunks queued.
+    pub fn write(&mut self, stream_id: u64, data: &[u8]) -> usize {
+        self.write_chunks(stream_id, data, false)
+    }
+
+    /// Like [`write`](Self::write) but marks the last chunk with FIN.
+    pub fn write_fin(&mut self, stream_id: u64, data: &[u8]) -> usize {
+        self.write_chunks(stream_id, data, true)
+    }
+
+    fn write_chunks(&mut self, stream_id: u64, data: &[u8], fin: bool) -> usize {
+        let size = self
+            .optimizer
+            .lock()
+            .unwrap()
+            .get_optimal_chunk_size(stream_id);
+        let queue = self.queues.entry(stream_id).or_default();
+        let before = queue.len();
+        let mut chunks = data.chunks(size).peekable();
+        if chunks.peek().is_none() && fin {
+            queue.push_back(StreamChunk {
+                stream_id,
+                data: Vec::new(),
+                fin,
+            });
+        }
+        while let Some(chunk) = chunks.next() {
+            queue.push_back(StreamChunk {
+                stream_id,
+                data: chunk.to_vec(),
+                fin: fin && chunks.peek().is_none(),
+            });
+        }
+        let queued = queue.len() - before;
+        debug!("Stream {stream_id}: queued {queued} chunks of up to {size} bytes");
+        queued
+    }
+
+    /// Returns the queued chunks of a stream without removing them.
+    pub fn queued(&self, stream_id: u64) -> impl Iterator<Item = &StreamChunk> {
+        self.queues.get(&stream_id).into_iter().flatten()
+    }
+
+    /// Total number of queued chunks over all streams.
+    pub fn pending(&self) -> usize {
+        self.queues.values().map(VecDeque::len).sum()
+    }
+
+    /// Removes the next chunk that may be sent: streams are visited in
+    /// urgency order and a chunk is only released if the optimizer reports
+    /// it fits into the stream's window.
+    pub fn pop_sendable(&mut self) -> Option<StreamChunk> {
+        let optimizer = self.optimizer.lock().unwrap();
+        let stream_id = self
+            .queues
+            .iter()
+            .filter_map(|(&id, q)| q.front().map(|c| (id, c.data.len() as u64)))
+            .filter(|&(id, len)| optimizer.can_send_data(id, len))
+            .min_by_key(|&(id, _)| (optimizer.priority(id), id))
+            .map(|(id, _)| id)?;
+        drop(optimizer);
+
+        let queue = self.queues.get_mut(&stream_id)?;
+        let chunk = queue.pop_front();
+        if queue.is_empty() {
+            self.queues.remove(&stream_id);
+        }
+        chunk
+    }
+
+    /// Writes sendable chunks to the QUIC connection until it refuses more.
+    /// Returns the number of bytes handed to `quiche`.
+    pub fn flush_to(&mut self, conn: &mut quiche::Connection) -> usize {
+        let mut written = 0;
+        while let Some(mut chunk) = self.pop_sendable() {
+            match conn.stream_send(chunk.stream_id, &chunk.data, chunk.fin) {
+                Ok(n) => {
+                    written += n;
+                    if n < chunk.data.len() {
+                        // Stream is blocked, requeue the remainder.
+                        chunk.data.drain(..n);
+                        self.requeue(chunk);
+                        break;
+                    }
+                }
+                Err(quiche::Error::Done) => {
+                    self.requeue(chunk);
+                    break;
+                }
+                Err(e) => {
+                    debug!("Dropping chunk for stream {}: {:?}", chunk.stream_id, e);
+                }
+            }
+        }
+        written
+    }
+
+    fn requeue(&mut self, chunk: StreamChunk) {
+        self.queues
+            .entry(chunk.stream_id)
+            .or_default()
+            .push_front(chunk);
+    }
+}
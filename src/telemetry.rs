@@ -8,6 +8,10 @@
 //! - `fec_mode_switch_total`: Number of FEC mode transitions.
 //! - `fec_window_size`: Current FEC window size.
 //! - `decoding_time_ms`: Time spent in the last decode run in milliseconds.
+//! - `fec_blocks_expired_total`: Incomplete FEC blocks dropped by the deadline.
+//! - `fec_recovered_blocks_total`: FEC blocks the decoder completed.
+//! - `fec_decode_failures_total`: FEC blocks given up on, by the deadline or
+//!   because the decoder rejected a packet.
 //! - `fec_overflow_total`: Number of times the FEC memory pool had to allocate
 //!   a new block because the pool was exhausted.
 //! - `dns_errors_total`: Number of DNS resolution errors.
@@ -20,12 +24,14 @@
 //! - `mem_pool_capacity`: Current capacity of the memory pool.
 //! - `mem_pool_in_use`: Number of blocks currently checked out from the pool.
 //! - `cpu_feature_mask`: Bitmask of detected CPU features.
+//! - `crypto_feature_mask`: Bitmask of CPU features used for cipher selection.
 //! - `path_migrations_total`: Successful connection migrations.
 
 use prometheus::{
     register_int_counter, register_int_gauge, Encoder, IntCounter, IntGauge, TextEncoder,
 };
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// Global switch controlling whether telemetry metrics are recorded.
 pub static TELEMETRY_ENABLED: AtomicBool = AtomicBool::new(false);
@@ -52,10 +58,40 @@ lazy_static! {
         register_int_gauge!("fec_mode", "Current FEC mode").unwrap();
     pub static ref FEC_MODE_SWITCHES: IntCounter =
         register_int_counter!("fec_mode_switch_total", "FEC mode transitions").unwrap();
+    pub static ref FEC_OSCILLATION_FREEZES: IntCounter = register_int_counter!(
+        "fec_oscillation_freeze_total",
+        "FEC mode freezes after oscillation was detected"
+    )
+    .unwrap();
     pub static ref FEC_WINDOW: IntGauge =
         register_int_gauge!("fec_window_size", "Current FEC window size").unwrap();
     pub static ref DECODING_TIME_MS: IntGauge =
         register_int_gauge!("decoding_time_ms", "Last decoder runtime in ms").unwrap();
+    pub static ref FEC_ENCODE_TIME_NS: IntCounter = register_int_counter!(
+        "fec_encode_time_ns_total",
+        "Time spent in the FEC encode path"
+    )
+    .unwrap();
+    pub static ref FEC_DECODE_TIME_NS: IntCounter = register_int_counter!(
+        "fec_decode_time_ns_total",
+        "Time spent in the FEC decode path"
+    )
+    .unwrap();
+    pub static ref FEC_BLOCKS_EXPIRED: IntCounter = register_int_counter!(
+        "fec_blocks_expired_total",
+        "Incomplete FEC blocks dropped by the deadline"
+    )
+    .unwrap();
+    pub static ref FEC_RECOVERED: IntCounter = register_int_counter!(
+        "fec_recovered_blocks_total",
+        "FEC blocks completed by the decoder"
+    )
+    .unwrap();
+    pub static ref FEC_DECODE_FAILURES: IntCounter = register_int_counter!(
+        "fec_decode_failures_total",
+        "FEC blocks that could not be recovered"
+    )
+    .unwrap();
     pub static ref FEC_OVERFLOWS: IntCounter =
         register_int_counter!("fec_overflow_total", "FEC memory pool overflows").unwrap();
     pub static ref DNS_ERRORS: IntCounter =
@@ -105,6 +141,11 @@ lazy_static! {
     .unwrap();
     pub static ref CPU_FEATURE_MASK: IntGauge =
         register_int_gauge!("cpu_feature_mask", "Detected CPU features bitmask").unwrap();
+    pub static ref CRYPTO_FEATURE_MASK: IntGauge = register_int_gauge!(
+        "crypto_feature_mask",
+        "CPU features used for cipher suite selection"
+    )
+    .unwrap();
     pub static ref SIMD_ACTIVE: IntGauge =
         register_int_gauge!("simd_active_policy", "Active SIMD policy").unwrap();
     pub static ref MEMORY_USAGE_BYTES: IntGauge =
@@ -141,6 +182,117 @@ lazy_static! {
         register_int_gauge!("stealth_xor", "XOR obfuscation enabled").unwrap();
 }
 
+/// Number of buffered updates after which [`inc_local`] flushes.
+pub const LOCAL_FLUSH_UPDATES: usize = 256;
+/// Longest time buffered deltas stay local before [`inc_local`] flushes.
+pub const LOCAL_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Delta buffered for one counter by one thread. Slots are shared through
+/// [`PENDING`] so any thread can flush them, which matters for async tasks
+/// that hop between worker threads.
+struct PendingDelta {
+    counter: &'static IntCounter,
+    delta: AtomicU64,
+}
+
+impl PendingDelta {
+    fn flush(&self) {
+        let delta = self.delta.swap(0, Ordering::Relaxed);
+        if delta > 0 {
+            self.counter.inc_by(delta);
+        }
+    }
+}
+
+/// Every live thread's buffered deltas.
+static PENDING: Mutex<Vec<Arc<PendingDelta>>> = Mutex::new(Vec::new());
+
+struct LocalCounters {
+    slots: Vec<Arc<PendingDelta>>,
+    updates: usize,
+    last_flush: std::time::Instant,
+}
+
+impl LocalCounters {
+    fn slot(&mut self, counter: &'static IntCounter) -> &PendingDelta {
+        let idx = match self
+            .slots
+            .iter()
+            .position(|s| std::ptr::eq(s.counter, counter))
+        {
+            Some(idx) => idx,
+            None => {
+                let slot = Arc::new(PendingDelta {
+                    counter,
+                    delta: AtomicU64::new(0),
+                });
+                PENDING
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(Arc::clone(&slot));
+                self.slots.push(slot);
+                self.slots.len() - 1
+            }
+        };
+        &self.slots[idx]
+    }
+
+    fn flush(&mut self) {
+        for slot in &self.slots {
+            slot.flush();
+        }
+        self.updates = 0;
+        self.last_flush = std::time::Instant::now();
+    }
+}
+
+impl Drop for LocalCounters {
+    fn drop(&mut self) {
+        self.flush();
+        PENDING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|p| !self.slots.iter().any(|s| Arc::ptr_eq(p, s)));
+    }
+}
+
+thread_local! {
+    static LOCAL: std::cell::RefCell<LocalCounters> = std::cell::RefCell::new(LocalCounters {
+        slots: Vec::new(),
+        updates: 0,
+        last_flush: std::time::Instant::now(),
+    });
+}
+
+/// Adds `v` to `counter` through a per-thread buffer. Hot loops use this
+/// instead of `inc_by` so the shared counter is touched once per
+/// [`LOCAL_FLUSH_UPDATES`] updates or [`LOCAL_FLUSH_INTERVAL`], whichever
+/// comes first. Pending deltas are also flushed when the thread exits.
+pub fn inc_local(counter: &'static IntCounter, v: u64) {
+    LOCAL.with(|l| {
+        let mut l = l.borrow_mut();
+        l.slot(counter).delta.fetch_add(v, Ordering::Relaxed);
+        l.updates += 1;
+        if l.updates >= LOCAL_FLUSH_UPDATES || l.last_flush.elapsed() >= LOCAL_FLUSH_INTERVAL {
+            l.flush();
+        }
+    });
+}
+
+/// Pushes the buffered [`inc_local`] deltas of every thread to the shared
+/// counters, so a task that moved to another worker thread does not leave
+/// its earlier updates behind.
+pub fn flush_local() {
+    for slot in PENDING.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        slot.flush();
+    }
+    LOCAL.with(|l| {
+        let mut l = l.borrow_mut();
+        l.updates = 0;
+        l.last_flush = std::time::Instant::now();
+    });
+}
+
 pub fn update_memory_usage() {
     let mut sys = sysinfo::System::new();
     sys.refresh_process(sysinfo::get_current_pid().unwrap());
@@ -149,24 +301,145 @@ pub fn update_memory_usage() {
     }
 }
 
-pub fn serve(addr: &str) {
-    use std::io::Write;
+/// Liveness and readiness reported on `/healthz` and `/readyz`.
+///
+/// The server marks itself listening once its socket is bound and draining
+/// when graceful shutdown begins; it is ready only in between.
+#[derive(Debug, Default)]
+pub struct HealthState {
+    listening: AtomicBool,
+    draining: AtomicBool,
+}
+
+impl HealthState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_listening(&self, listening: bool) {
+        self.listening.store(listening, Ordering::SeqCst);
+    }
+
+    pub fn set_draining(&self, draining: bool) {
+        self.draining.store(draining, Ordering::SeqCst);
+    }
+
+    pub fn is_listening(&self) -> bool {
+        self.listening.load(Ordering::SeqCst)
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Returns `true` while the listener accepts new peers.
+    pub fn is_ready(&self) -> bool {
+        self.is_listening() && !self.is_draining()
+    }
+}
+
+/// Handle of a running metrics endpoint started by [`serve`].
+///
+/// Dropping the handle leaves the server running; call
+/// [`shutdown`](Self::shutdown) to stop it and release the port.
+pub struct MetricsServer {
+    addr: std::net::SocketAddr,
+    health: std::sync::Arc<HealthState>,
+    stop: std::sync::Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// Address the server is bound to. Reports the actual port when bound
+    /// to port `0`.
+    pub fn local_addr(&self) -> std::net::SocketAddr {
+        self.addr
+    }
+
+    /// State answered on the health endpoints.
+    pub fn health(&self) -> std::sync::Arc<HealthState> {
+        self.health.clone()
+    }
+
+    /// Stops accepting scrapes and waits for the server thread to exit.
+    pub fn shutdown(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the blocking accept so the loop sees the flag.
+        let _ = std::net::TcpStream::connect(self.addr);
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+/// Serves the Prometheus text format over HTTP on `addr`, e.g.
+/// `"127.0.0.1:0"` for an ephemeral port.
+pub fn serve(addr: &str) -> MetricsServer {
+    serve_with_health(addr, std::sync::Arc::new(HealthState::new()))
+}
+
+/// Like [`serve`], additionally answering `GET /healthz` with 200 while the
+/// process runs and `GET /readyz` with 200 or 503 depending on
+/// [`HealthState::is_ready`]. Every other path returns the metrics.
+pub fn serve_with_health(addr: &str, health: std::sync::Arc<HealthState>) -> MetricsServer {
+    use std::io::{Read, Write};
     use std::net::TcpListener;
     let listener = TcpListener::bind(addr).expect("bind metrics");
-    std::thread::spawn(move || {
+    let local = listener.local_addr().expect("metrics address");
+    let stop = std::sync::Arc::new(AtomicBool::new(false));
+    let flag = stop.clone();
+    let state = health.clone();
+    let thread = std::thread::spawn(move || {
         let encoder = TextEncoder::new();
         for stream in listener.incoming() {
+            if flag.load(Ordering::SeqCst) {
+                break;
+            }
             if let Ok(mut s) = stream {
-                let metrics = prometheus::gather();
-                let mut buf = Vec::new();
-                encoder.encode(&metrics, &mut buf).unwrap();
-                let _ = s.write_all(&buf);
+                let mut req = [0u8; 1024];
+                let n = s.read(&mut req).unwrap_or(0);
+                let path = std::str::from_utf8(&req[..n])
+                    .ok()
+                    .and_then(|r| r.split_whitespace().nth(1))
+                    .map(|p| p.split('?').next().unwrap_or(p));
+                let (status, content_type, body) = match path {
+                    Some("/healthz") => ("200 OK", "text/plain", b"ok\n".to_vec()),
+                    Some("/readyz") if state.is_ready() => {
+                        ("200 OK", "text/plain", b"ready\n".to_vec())
+                    }
+                    Some("/readyz") => (
+                        "503 Service Unavailable",
+                        "text/plain",
+                        b"not ready\n".to_vec(),
+                    ),
+                    _ => {
+                        let metrics = prometheus::gather();
+                        let mut buf = Vec::new();
+                        encoder.encode(&metrics, &mut buf).unwrap();
+                        ("200 OK", encoder.format_type(), buf)
+                    }
+                };
+                let header = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    content_type,
+                    body.len()
+                );
+                let _ = s.write_all(header.as_bytes());
+                let _ = s.write_all(&body);
             }
         }
     });
+    MetricsServer {
+        addr: local,
+        health,
+        stop,
+        thread: Some(thread),
+    }
 }
 
 pub fn flush() {
+    flush_local();
     let encoder = TextEncoder::new();
     let metrics = prometheus::gather();
     let mut buf = Vec::new();
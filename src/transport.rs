@@ -0,0 +1,264 @@
+// Copyright (c) 2024, The QuicFuscate Project Authors.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above
+//       copyright notice, this list of conditions and the following disclaimer
+//       in the documentation and/or other materials provided with the
+//       distribution.
+//
+//     * Neither the name of the copyright holder nor the names of its
+//       contributors may be used to endorse or promote products derived from
+//       this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # Datagram Transport
+//!
+//! The send/recv interface connections use to move UDP datagrams, with an
+//! in-memory implementation for tests. [`MemoryTransport`] connects two
+//! endpoints through a pair of channels and can drop, reorder and delay
+//! datagrams under a seeded RNG and a [`Clock`], so full flows can be driven
+//! deterministically without real sockets.
+
+use crate::optimize::{Clock, SystemClock};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Unconnected datagram socket operations, as offered by [`UdpSocket`].
+pub trait DatagramTransport: Send {
+    /// Sends `buf` to `to` and returns the number of bytes sent.
+    fn send_to(&self, buf: &[u8], to: SocketAddr) -> io::Result<usize>;
+
+    /// Receives one datagram into `buf`, truncating it if `buf` is too
+    /// small. Fails with [`io::ErrorKind::WouldBlock`] when nothing is
+    /// pending on a non-blocking transport.
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)>;
+
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+impl DatagramTransport for UdpSocket {
+    fn send_to(&self, buf: &[u8], to: SocketAddr) -> io::Result<usize> {
+        UdpSocket::send_to(self, buf, to)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        UdpSocket::recv_from(self, buf)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        UdpSocket::local_addr(self)
+    }
+}
+
+/// Impairments applied to every datagram a [`MemoryTransport`] sends.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LinkConditions {
+    /// Probability in `[0, 1]` that a datagram is dropped.
+    pub loss: f64,
+    /// Probability in `[0, 1]` that a datagram is held back and delivered
+    /// after the next one.
+    pub reorder: f64,
+    /// One-way delay before a datagram can be received.
+    pub delay: Duration,
+}
+
+impl LinkConditions {
+    /// A perfect link.
+    pub fn lossless() -> Self {
+        Self::default()
+    }
+
+    pub fn with_loss(mut self, loss: f64) -> Self {
+        self.loss = loss.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_reorder(mut self, reorder: f64) -> Self {
+        self.reorder = reorder.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+struct Datagram {
+    from: SocketAddr,
+    deliver_at: Instant,
+    data: Vec<u8>,
+}
+
+/// One end of an in-memory datagram link, see [`MemoryTransport::pair`].
+///
+/// Sends are always non-blocking and succeed unless the peer end was
+/// dropped; lost datagrams are counted in [`dropped`](Self::dropped). A
+/// datagram held back for reordering goes out right after the next send, or
+/// on [`flush`](Self::flush). Receives never block.
+pub struct MemoryTransport {
+    local: SocketAddr,
+    peer: SocketAddr,
+    conditions: LinkConditions,
+    tx: Sender<Datagram>,
+    rx: Mutex<Receiver<Datagram>>,
+    // Datagrams taken off the channel that are not due yet.
+    inbox: Mutex<VecDeque<Datagram>>,
+    held: Mutex<Option<Datagram>>,
+    rng: Mutex<StdRng>,
+    clock: Arc<dyn Clock>,
+    sent: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl MemoryTransport {
+    /// Connects endpoints `a` and `b`. Both directions use `conditions`.
+    pub fn pair(a: SocketAddr, b: SocketAddr, conditions: LinkConditions) -> (Self, Self) {
+        Self::pair_with(a, b, conditions, rand::random(), Arc::new(SystemClock))
+    }
+
+    /// Like [`pair`](Self::pair) but with a fixed RNG seed and clock, so loss
+    /// and reordering are reproducible and delays can be stepped through with
+    /// a [`ManualClock`](crate::optimize::ManualClock).
+    pub fn pair_with(
+        a: SocketAddr,
+        b: SocketAddr,
+        conditions: LinkConditions,
+        seed: u64,
+        clock: Arc<dyn Clock>,
+    ) -> (Self, Self) {
+        let (a_tx, b_rx) = channel();
+        let (b_tx, a_rx) = channel();
+        let end = |local, peer, tx, rx, seed| Self {
+            local,
+            peer,
+            conditions,
+            tx,
+            rx: Mutex::new(rx),
+            inbox: Mutex::new(VecDeque::new()),
+            held: Mutex::new(None),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+            clock: Arc::clone(&clock),
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        };
+        (
+            end(a, b, a_tx, a_rx, seed),
+            end(b, a, b_tx, b_rx, seed.wrapping_add(1)),
+        )
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    pub fn conditions(&self) -> LinkConditions {
+        self.conditions
+    }
+
+    /// Datagrams handed to the link, including lost ones.
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    /// Datagrams lost on the way to the peer.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Releases a datagram held back for reordering.
+    pub fn flush(&self) -> io::Result<()> {
+        match self.held.lock().unwrap().take() {
+            Some(d) => self.deliver(d),
+            None => Ok(()),
+        }
+    }
+
+    fn deliver(&self, d: Datagram) -> io::Result<()> {
+        self.tx
+            .send(d)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "peer transport dropped"))
+    }
+}
+
+impl DatagramTransport for MemoryTransport {
+    fn send_to(&self, buf: &[u8], to: SocketAddr) -> io::Result<usize> {
+        if to != self.peer {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("no route to {to}"),
+            ));
+        }
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        let (lost, reorder) = {
+            let mut rng = self.rng.lock().unwrap();
+            (
+                rng.gen_bool(self.conditions.loss.clamp(0.0, 1.0)),
+                rng.gen_bool(self.conditions.reorder.clamp(0.0, 1.0)),
+            )
+        };
+        if lost {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return Ok(buf.len());
+        }
+        let d = Datagram {
+            from: self.local,
+            deliver_at: self.clock.now() + self.conditions.delay,
+            data: buf.to_vec(),
+        };
+        let mut held = self.held.lock().unwrap();
+        match held.take() {
+            Some(mut earlier) => {
+                // Keep delivery times monotonic so the swap survives delay.
+                earlier.deliver_at = d.deliver_at;
+                self.deliver(d)?;
+                self.deliver(earlier)?;
+            }
+            None if reorder => *held = Some(d),
+            None => self.deliver(d)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let mut inbox = self.inbox.lock().unwrap();
+        inbox.extend(self.rx.lock().unwrap().try_iter());
+        let now = self.clock.now();
+        if inbox.front().map_or(true, |d| d.deliver_at > now) {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let d = inbox.pop_front().unwrap();
+        let len = d.data.len().min(buf.len());
+        buf[..len].copy_from_slice(&d.data[..len]);
+        Ok((len, d.from))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local)
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright (c) 2024, The QuicFuscate Project Authors.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+//       notice, this list of conditions and the following disclaimer.
+//
+//     * Redistributions in binary form must reproduce the above
+//       copyright notice, this list of conditions and the following disclaimer
+//       in the documentation and/or other materials provided with the
+//       distribution.
+//
+//     * Neither the name of the copyright holder nor the names of its
+//       contributors may be used to endorse or promote products derived from
+//       this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # 0-RTT Engine
+//!
+//! Queues application data that a resumed client sends as TLS early data,
+//! before the handshake completes. The amount of early data is capped, both
+//! because the server limits it and because 0-RTT data can be replayed; an
+//! [`EarlyDataOverflow`] policy decides what happens to data beyond the cap.
+
+use log::debug;
+use std::collections::VecDeque;
+
+/// Early data cap used until [`ZeroRttEngine::set_max_early_data`] is called.
+pub const DEFAULT_MAX_EARLY_DATA: usize = 16 * 1024;
+
+/// How [`ZeroRttEngine::push_early_data`] treats data beyond the cap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EarlyDataOverflow {
+    /// Reject the whole write with `quiche::Error::BufferTooShort`.
+    #[default]
+    Reject,
+    /// Accept as much as fits and drop the rest.
+    Truncate,
+    /// Accept as much as fits as early data and hold the rest back until
+    /// the handshake has completed.
+    BufferUntilHandshake,
+}
+
+/// Early data waiting for a single connection.
+pub struct ZeroRttEngine {
+    max_early_data: usize,
+    accepted: usize,
+    early: VecDeque<(u64, Vec<u8>)>,
+    deferred: VecDeque<(u64, Vec<u8>)>,
+    policy: EarlyDataOverflow,
+}
+
+impl ZeroRttEngine {
+    pub fn new() -> Self {
+        Self {
+            max_early_data: DEFAULT_MAX_EARLY_DATA,
+            accepted: 0,
+            early: VecDeque::new(),
+            deferred: VecDeque::new(),
+            policy: EarlyDataOverflow::Reject,
+        }
+    }
+
+    /// Sets the most bytes that may be sent as early data, usually the
+    /// server's `max_early_data_size`. Data already accepted counts against
+    /// the new cap.
+    pub fn set_max_early_data(&mut self, max: usize) {
+        self.max_early_data = max;
+    }
+
+    /// Returns the early data cap in bytes.
+    pub fn max_early_data(&self) -> usize {
+        self.max_early_data
+    }
+
+    /// Selects how writes beyond the cap are handled.
+    pub fn set_overflow_policy(&mut self, policy: EarlyDataOverflow) {
+        self.policy = policy;
+    }
+
+    pub fn overflow_policy(&self) -> EarlyDataOverflow {
+        self.policy
+    }
+
+    /// Bytes that can still be queued as early data.
+    pub fn early_data_remaining(&self) -> usize {
+        self.max_early_data.saturating_sub(self.accepted)
+    }
+
+    /// Queues `data` for `stream_id` and returns the number of bytes taken.
+    ///
+    /// Writes that fit are always accepted. Beyond the cap the
+    /// [`EarlyDataOverflow`] policy applies; `Reject` and `Truncate` with no
+    /// room left fail with `quiche::Error::BufferTooShort`.
+    pub fn push_early_data(&mut self, stream_id: u64, data: &[u8]) -> Result<usize, quiche::Error> {
+        let room = self.early_data_remaining();
+        if data.len() <= room {
+            self.queue_early(stream_id, data);
+            return Ok(data.len());
+        }
+        match self.policy {
+            EarlyDataOverflow::Reject => Err(quiche::Error::BufferTooShort),
+            EarlyDataOverflow::Truncate if room == 0 => Err(quiche::Error::BufferTooShort),
+            EarlyDataOverflow::Truncate => {
+                self.queue_early(stream_id, &data[..room]);
+                Ok(room)
+            }
+            EarlyDataOverflow::BufferUntilHandshake => {
+                if room > 0 {
+                    self.queue_early(stream_id, &data[..room]);
+                }
+                self.deferred.push_back((stream_id, data[room..].to_vec()));
+                Ok(data.len())
+            }
+        }
+    }
+
+    fn queue_early(&mut self, stream_id: u64, data: &[u8]) {
+        self.accepted += data.len();
+        self.early.push_back((stream_id, data.to_vec()));
+    }
+
+    /// Bytes queued as early data and not yet handed to `quiche`.
+    pub fn pending_early(&self) -> usize {
+        self.early.iter().map(|(_, d)| d.len()).sum()
+    }
+
+    /// Bytes held back until the handshake completes.
+    pub fn pending_deferred(&self) -> usize {
+        self.deferred.iter().map(|(_, d)| d.len()).sum()
+    }
+
+    /// Writes queued data into the connection's streams: early data once
+    /// the connection accepts 0-RTT or is established, deferred data only
+    /// after the handshake. Returns the number of bytes written.
+    pub fn flush_to(&mut self, conn: &mut quiche::Connection) -> usize {
+        let mut written = 0;
+        if conn.is_in_early_data() || conn.is_established() {
+            written += Self::write_queue(&mut self.early, conn);
+        }
+        if conn.is_established() && self.early.is_empty() {
+            written += Self::write_queue(&mut self.deferred, conn);
+        }
+        written
+    }
+
+    fn write_queue(queue: &mut VecDeque<(u64, Vec<u8>)>, conn: &mut quiche::Connection) -> usize {
+        let mut written = 0;
+        while let Some((stream_id, data)) = queue.front_mut() {
+            match conn.stream_send(*stream_id, data, false) {
+                Ok(n) => {
+                    written += n;
+                    if n < data.len() {
+                        data.drain(..n);
+                        break;
+                    }
+                    queue.pop_front();
+                }
+                Err(quiche::Error::Done) => break,
+                Err(e) => {
+                    debug!("early data stream_send failed: {}", e);
+                    break;
+                }
+            }
+        }
+        written
+    }
+}
+
+impl Default for ZeroRttEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
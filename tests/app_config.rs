@@ -0,0 +1,190 @@
+use quicfuscate::app_config::{AppConfig, ConfigError};
+
+#[test]
+fn example_config_is_valid() {
+    let cfg = AppConfig::from_toml(include_str!("../docs/example_config.toml")).unwrap();
+    assert!(cfg.validate().is_ok());
+}
+
+#[test]
+fn unknown_browser_profile_is_reported() {
+    let err = AppConfig::from_toml("[stealth]\nbrowser_profile = \"netscape\"\n").unwrap_err();
+    let err = err.downcast_ref::<ConfigError>().expect("structured error");
+    assert!(err.has_field("stealth.browser_profile"));
+    assert!(err.to_string().contains("unknown browser_profile 'netscape'"));
+}
+
+#[test]
+fn every_invalid_field_is_listed() {
+    let toml = r#"
+[adaptive_fec]
+lambda = 2.0
+
+[stealth]
+enable_doh = true
+doh_provider = "ftp://dns.example"
+
+[optimize]
+pool_capacity = 0
+"#;
+    let cfg = AppConfig::from_toml(toml).unwrap();
+    let err = cfg.validate().unwrap_err();
+    assert!(err.has_field("adaptive_fec.lambda"));
+    assert!(err.has_field("stealth.doh_provider"));
+    assert!(err.has_field("optimize.pool_capacity"));
+    assert_eq!(err.issues.len(), 3);
+    let msg = err.to_string();
+    assert!(msg.contains("lambda must be in (0, 1]"));
+    assert!(msg.contains("must be an https:// URL"));
+    assert!(msg.contains("pool_capacity must be > 0"));
+}
+
+#[test]
+fn malformed_doh_url_is_reported() {
+    let cfg = AppConfig::from_toml("[stealth]\ndoh_provider = \"not a url\"\n").unwrap();
+    let err = cfg.validate().unwrap_err();
+    assert!(err.has_field("stealth.doh_provider"));
+    assert!(err.to_string().contains("is not a valid URL"));
+}
+
+#[test]
+fn conflicting_stealth_flags_are_reported() {
+    let toml = r#"
+[stealth]
+enable_domain_fronting = false
+fronting_domains = ["cdn.example.com"]
+enable_http3_masquerading = false
+use_qpack_headers = true
+"#;
+    let cfg = AppConfig::from_toml(toml).unwrap();
+    let err = cfg.validate().unwrap_err();
+    assert!(err.has_field("stealth.fronting_domains"));
+    assert!(err.has_field("stealth.use_qpack_headers"));
+}
+
+#[test]
+fn dry_run_prints_plan_for_valid_config() {
+    let cfg = AppConfig::from_toml(include_str!("../docs/example_config.toml")).unwrap();
+    let plan = cfg.dry_run().expect("example config passes dry run");
+    assert_eq!(plan.browser_profile, cfg.stealth.browser_profile);
+    assert_eq!(plan.pool_capacity, cfg.optimize.pool_capacity);
+    let text = plan.to_string();
+    assert!(text.contains("cipher suite:"));
+    assert!(text.contains("fec:"));
+}
+
+#[test]
+fn dry_run_lists_every_problem() {
+    let toml = r#"
+[stealth]
+browser_profile = "safari"
+os_profile = "windows"
+doh_provider = "ftp://dns.example"
+
+[optimize]
+pool_capacity = 0
+"#;
+    let cfg = AppConfig::from_toml(toml).unwrap();
+    let err = cfg.dry_run().unwrap_err();
+    assert!(err.has_field("stealth.os_profile"));
+    assert!(err.has_field("stealth.doh_provider"));
+    assert!(err.has_field("optimize.pool_capacity"));
+    let msg = err.to_string();
+    assert!(msg.contains("no ClientHello capture for Safari@Windows"));
+}
+
+#[test]
+fn xor_key_and_pattern_are_parsed_and_validated() {
+    use quicfuscate::stealth::XorPattern;
+
+    let toml = r#"
+[stealth]
+xor_key = "000102030405060708090a0b0c0d0e0f"
+xor_pattern = "rolling"
+"#;
+    let cfg = AppConfig::from_toml(toml).unwrap();
+    assert_eq!(cfg.stealth.xor_key, Some((0u8..16).collect::<Vec<_>>()));
+    assert_eq!(cfg.stealth.xor_pattern, XorPattern::Rolling);
+    assert!(cfg.validate().is_ok());
+
+    let err = AppConfig::from_toml("[stealth]\nxor_key = \"00112233\"\n").unwrap_err();
+    let err = err.downcast_ref::<ConfigError>().expect("structured error");
+    assert!(err.has_field("stealth.xor_key"));
+    assert!(err.to_string().contains("xor_key is 4 bytes, at least 16 required"));
+    let err = AppConfig::from_toml("[stealth]\nxor_pattern = \"spiral\"\n").unwrap_err();
+    let err = err.downcast_ref::<ConfigError>().expect("structured error");
+    assert!(err.has_field("stealth.xor_pattern"));
+    assert!(err.to_string().contains("unknown xor_pattern 'spiral'"));
+
+    let mut cfg = AppConfig::from_toml("").unwrap();
+    cfg.stealth.xor_key = Some(vec![7; 8]);
+    assert!(cfg.validate().unwrap_err().has_field("stealth.xor_key"));
+}
+
+#[test]
+fn every_problem_within_a_section_is_listed() {
+    let toml = r#"
+[adaptive_fec]
+lambda = 2.0
+burst_window = 0
+
+[optimize]
+pool_capacity = 0
+block_size = 0
+"#;
+    let cfg = AppConfig::from_toml(toml).unwrap();
+    let err = cfg.validate().unwrap_err();
+    assert_eq!(err.issues.len(), 4);
+    assert!(err.has_field("adaptive_fec.lambda"));
+    assert!(err.has_field("adaptive_fec.burst_window"));
+    assert!(err.has_field("optimize.pool_capacity"));
+    assert!(err.has_field("optimize.block_size"));
+    let msg = err.to_string();
+    assert!(msg.contains("lambda must be in (0, 1]"));
+    assert!(msg.contains("burst_window must be > 0"));
+    assert!(msg.contains("pool_capacity must be > 0"));
+    assert!(msg.contains("block_size must be > 0"));
+}
+
+#[test]
+fn every_unparsable_stealth_value_is_listed() {
+    let toml = r#"
+[stealth]
+browser_profile = "netscape"
+os_profile = "amiga"
+xor_pattern = "spiral"
+"#;
+    let err = AppConfig::from_toml(toml).unwrap_err();
+    let err = err.downcast_ref::<ConfigError>().expect("structured error");
+    assert_eq!(err.issues.len(), 3);
+    assert!(err.has_field("stealth.browser_profile"));
+    assert!(err.has_field("stealth.os_profile"));
+    assert!(err.has_field("stealth.xor_pattern"));
+    let msg = err.to_string();
+    assert!(msg.contains("unknown browser_profile 'netscape'"));
+    assert!(msg.contains("unknown os_profile 'amiga'"));
+    assert!(msg.contains("unknown xor_pattern 'spiral'"));
+}
+
+#[test]
+fn kalman_and_congestion_issues_name_their_field() {
+    let toml = r#"
+[adaptive_fec]
+kalman_enabled = true
+kalman_q = 0.0
+kalman_r = -1.0
+
+[optimize]
+congestion_control = "vegas"
+"#;
+    let err = AppConfig::from_toml(toml).unwrap_err();
+    let err = err.downcast_ref::<ConfigError>().expect("structured error");
+    assert_eq!(err.issues.len(), 1);
+    assert!(err.has_field("optimize.congestion_control"));
+
+    let cfg = AppConfig::from_toml(&toml.replace("vegas", "cubic")).unwrap();
+    let err = cfg.validate().unwrap_err();
+    assert_eq!(err.issues.len(), 2);
+    assert!(err.has_field("adaptive_fec.kalman_q"));
+    assert!(err.has_field("adaptive_fec.kalman_r"));
+}
@@ -0,0 +1,71 @@
+use quicfuscate::congestion::{CcAlgorithm, DEFAULT_MSS};
+use std::time::{Duration, Instant};
+
+const RTT: Duration = Duration::from_millis(40);
+
+#[test]
+fn each_algorithm_responds_to_loss_differently() {
+    let t0 = Instant::now();
+    let mut windows = Vec::new();
+    for algo in [CcAlgorithm::Reno, CcAlgorithm::Cubic, CcAlgorithm::Bbr] {
+        let mut cc = algo.controller(DEFAULT_MSS);
+        assert_eq!(cc.algorithm(), algo);
+        // Two round trips of acknowledgements, then a single loss.
+        for i in 1..=20u32 {
+            cc.on_packet_acknowledged(DEFAULT_MSS, RTT, t0 + RTT * i / 10);
+        }
+        let before = cc.congestion_window();
+        cc.on_packet_lost(DEFAULT_MSS, t0 + RTT * 3);
+        windows.push((algo, before, cc.congestion_window()));
+    }
+
+    for (algo, before, after) in &windows {
+        match algo {
+            CcAlgorithm::Reno => assert_eq!(*after, before / 2),
+            CcAlgorithm::Cubic => assert_eq!(*after, (*before as f64 * 0.7) as usize),
+            CcAlgorithm::Bbr => assert_eq!(after, before, "BBR ignores isolated loss"),
+        }
+    }
+}
+
+#[test]
+fn algorithm_names_round_trip() {
+    for algo in [CcAlgorithm::Reno, CcAlgorithm::Cubic, CcAlgorithm::Bbr] {
+        assert_eq!(algo.as_str().parse::<CcAlgorithm>(), Ok(algo));
+    }
+    assert!("vegas".parse::<CcAlgorithm>().is_err());
+}
+
+#[test]
+fn seeded_rtt_paces_until_first_sample() {
+    let t0 = Instant::now();
+    let seed = Duration::from_millis(600);
+    for algo in [CcAlgorithm::Reno, CcAlgorithm::Cubic, CcAlgorithm::Bbr] {
+        let mut cc = algo.controller(DEFAULT_MSS);
+        assert_eq!(cc.pacing_rate(), None);
+
+        cc.set_initial_rtt(seed);
+        assert_eq!(cc.rtt(), Some(seed));
+        let expected = (cc.congestion_window() as f64 / seed.as_secs_f64()) as u64;
+        assert_eq!(cc.pacing_rate(), Some(expected));
+
+        // A genuine sample replaces the seed, even for BBR's min filter.
+        cc.on_packet_acknowledged(DEFAULT_MSS, Duration::from_millis(700), t0);
+        assert_eq!(cc.rtt(), Some(Duration::from_millis(700)), "{:?}", algo);
+        cc.set_initial_rtt(seed);
+        assert_eq!(cc.rtt(), Some(Duration::from_millis(700)), "{:?}", algo);
+    }
+}
+
+#[test]
+fn optimize_config_selects_congestion_control() {
+    use quicfuscate::optimize::OptimizeConfig;
+
+    let cfg = OptimizeConfig::from_toml("[optimize]\ncongestion_control = \"cubic\"\n").unwrap();
+    assert_eq!(cfg.congestion_control, CcAlgorithm::Cubic);
+    assert_eq!(
+        OptimizeConfig::from_toml("").unwrap().congestion_control,
+        CcAlgorithm::Bbr
+    );
+    assert!(OptimizeConfig::from_toml("[optimize]\ncongestion_control = \"vegas\"\n").is_err());
+}
@@ -31,6 +31,8 @@ fn cross_fade_packet_recovery() {
         kalman_q: 0.001,
         kalman_r: 0.01,
         window_sizes: FecConfig::default_windows(),
+        systematic: true,
+        max_recovery_delay: None,
     };
     let mut sender = AdaptiveFec::new(cfg.clone(), Arc::clone(&pool));
     let mut receiver = AdaptiveFec::new(cfg, Arc::clone(&pool));
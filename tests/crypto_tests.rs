@@ -1,5 +1,6 @@
 use hex;
 use quicfuscate::crypto::{CipherSuite, CipherSuiteSelector};
+use quicfuscate::error::CryptoError;
 
 fn run_test(suite: CipherSuite) {
     let selector = CipherSuiteSelector::with_suite(suite);
@@ -47,11 +48,28 @@ fn test_morus256() {
     run_test(CipherSuite::Morus1280_256);
 }
 
+#[cfg(feature = "allow_insecure_stub")]
 #[test]
 fn test_fallback() {
     run_test(CipherSuite::SoftwareFallback);
 }
 
+#[cfg(not(feature = "allow_insecure_stub"))]
+#[test]
+fn fallback_stub_refuses_by_default() {
+    let selector = CipherSuiteSelector::with_suite(CipherSuite::SoftwareFallback);
+    let key = [0u8; 16];
+    let nonce = [0u8; 16];
+    assert_eq!(
+        selector.encrypt(&key, &nonce, b"ad", b"secret"),
+        Err(CryptoError::InsecureStub)
+    );
+    assert_eq!(
+        selector.decrypt(&key, &nonce, b"ad", b"secret"),
+        Err(CryptoError::InsecureStub)
+    );
+}
+
 #[test]
 fn test_vectors() {
     let selector = CipherSuiteSelector::with_suite(CipherSuite::Aegis128L);
@@ -91,3 +109,77 @@ fn test_vectors() {
     let pt = selector.decrypt(key, &nonce, ad, &ct).expect("decrypt");
     assert_eq!(pt, msg);
 }
+
+fn flipped_tag_fails(suite: CipherSuite, key_len: usize, nonce_len: usize) {
+    let selector = CipherSuiteSelector::with_suite(suite);
+    let key = vec![7u8; key_len];
+    let nonce = vec![3u8; nonce_len];
+    let mut ct = selector
+        .encrypt(&key, &nonce, b"ad", b"hello world")
+        .expect("encrypt");
+    let last = ct.len() - 1;
+    ct[last] ^= 0x01;
+    assert_eq!(
+        selector.decrypt(&key, &nonce, b"ad", &ct),
+        Err(CryptoError::TagMismatch),
+        "{:?} accepted a modified tag",
+        suite
+    );
+}
+
+#[test]
+fn bit_flipped_tag_is_rejected() {
+    flipped_tag_fails(CipherSuite::Aegis128X, 16, 16);
+    flipped_tag_fails(CipherSuite::Aegis128L, 16, 16);
+    flipped_tag_fails(CipherSuite::Aegis256, 32, 32);
+    flipped_tag_fails(CipherSuite::Morus1280_128, 16, 16);
+    flipped_tag_fails(CipherSuite::Morus1280_256, 32, 16);
+    #[cfg(feature = "allow_insecure_stub")]
+    flipped_tag_fails(CipherSuite::SoftwareFallback, 16, 16);
+}
+
+#[test]
+fn detected_features_match_selector() {
+    use quicfuscate::crypto::detected_features;
+
+    let features = detected_features();
+    let selector = CipherSuiteSelector::new();
+    assert_eq!(selector.is_hardware_accelerated(), features.hardware_aes());
+    if features.vaes {
+        assert_eq!(selector.selected_suite(), CipherSuite::Aegis256);
+    }
+    assert_eq!(features, detected_features());
+    assert_eq!(features.mask() & 1 != 0, features.aesni);
+}
+
+#[test]
+fn cipher_listing_and_self_test_cover_all_suites() {
+    use quicfuscate::crypto::{cipher_listing, self_test_all};
+
+    let listing = cipher_listing();
+    for name in [
+        "Aegis256",
+        "Aegis128X",
+        "Aegis128L",
+        "Morus1280_256",
+        "Morus1280_128",
+        "SoftwareFallback",
+    ] {
+        assert!(listing.contains(name), "{name} missing from {listing}");
+    }
+    let selected = format!("{:?}", CipherSuiteSelector::new().selected_suite());
+    let marked: Vec<&str> = listing.lines().filter(|l| l.contains("(selected)")).collect();
+    assert_eq!(marked.len(), 1);
+    assert!(marked[0].contains(&selected));
+
+    let results = self_test_all();
+    assert_eq!(results.len(), CipherSuite::ALL.len());
+    for (suite, res) in results {
+        match suite {
+            CipherSuite::SoftwareFallback if !cfg!(feature = "allow_insecure_stub") => {
+                assert_eq!(res, Err(CryptoError::InsecureStub))
+            }
+            _ => assert!(res.is_ok(), "{suite:?} failed: {res:?}"),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use quicfuscate::datagram::{read_capture, DatagramEngine, Direction, OversizePolicy};
+
+fn capture_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("qf_{}_{}.cap", name, std::process::id()))
+}
+
+#[test]
+fn capture_then_replay_reproduces_inbound() {
+    let path = capture_path("replay");
+    let mut engine = DatagramEngine::new();
+    engine.set_capture(&path).unwrap();
+    engine.send(b"out-1").unwrap();
+    engine.recv(b"in-1");
+    engine.recv(b"in-2");
+    engine.send(b"out-2").unwrap();
+    engine.recv(b"in-3");
+    engine.stop_capture().unwrap();
+
+    let mut original = Vec::new();
+    while let Some(d) = engine.pop_incoming() {
+        original.push(d);
+    }
+
+    let records = read_capture(&path).unwrap();
+    assert_eq!(records.len(), 5);
+    assert_eq!(records[0].direction, Direction::Outbound);
+    assert_eq!(records[1].direction, Direction::Inbound);
+
+    let mut replayed = DatagramEngine::new();
+    assert_eq!(replayed.replay(&path).unwrap(), 3);
+    let mut again = Vec::new();
+    while let Some(d) = replayed.pop_incoming() {
+        again.push(d);
+    }
+    assert_eq!(original, again);
+    assert_eq!(replayed.pending_outgoing(), 0);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn oversized_send_is_rejected() {
+    let mut engine = DatagramEngine::new();
+    engine.set_max_size(1200);
+    assert_eq!(
+        engine.send(&[0u8; 1201]),
+        Err(quiche::Error::BufferTooShort)
+    );
+    assert_eq!(engine.pending_outgoing(), 0);
+}
+
+#[test]
+fn boundary_sized_send_succeeds() {
+    let mut engine = DatagramEngine::new();
+    engine.set_max_size(1200);
+    assert!(engine.send(&[0u8; 1200]).is_ok());
+    assert_eq!(engine.pop_outgoing().unwrap().len(), 1200);
+}
+
+#[test]
+fn oversized_send_is_split_when_enabled() {
+    let mut engine = DatagramEngine::new();
+    engine.set_max_size(500);
+    engine.set_oversize_policy(OversizePolicy::Split);
+    engine.send(&[7u8; 1200]).unwrap();
+    let sizes: Vec<usize> = std::iter::from_fn(|| engine.pop_outgoing())
+        .map(|d| d.len())
+        .collect();
+    assert_eq!(sizes, vec![500, 500, 200]);
+}
+
+#[test]
+fn flush_drops_datagrams_that_no_longer_fit() {
+    let mut engine = DatagramEngine::new();
+    engine.send(&[1u8; 100]).unwrap();
+    engine.send(&[2u8; 1300]).unwrap();
+    engine.send(&[3u8; 100]).unwrap();
+
+    // The writable size shrank below the second payload after it was queued.
+    let mut delivered = Vec::new();
+    let sent = engine.flush_with(|payload| {
+        if payload.len() > 1200 {
+            return Err(quiche::Error::BufferTooShort);
+        }
+        delivered.push(payload[0]);
+        Ok(())
+    });
+    assert_eq!(sent, 2);
+    assert_eq!(delivered, vec![1, 3]);
+    assert_eq!(engine.oversized_dropped(), 1);
+    assert_eq!(engine.pending_outgoing(), 0);
+
+    // A full queue keeps the payload for the next flush.
+    engine.send(&[4u8; 100]).unwrap();
+    assert_eq!(engine.flush_with(|_| Err(quiche::Error::Done)), 0);
+    assert_eq!(engine.pending_outgoing(), 1);
+    assert_eq!(engine.oversized_dropped(), 1);
+}
+
+#[test]
+fn batch_is_received_in_order() {
+    let batch: Vec<Vec<u8>> = (0..16u8).map(|i| vec![i; i as usize + 1]).collect();
+    let refs: Vec<&[u8]> = batch.iter().map(|d| d.as_slice()).collect();
+
+    let mut tx = DatagramEngine::new();
+    assert_eq!(tx.send_batch(&refs), Ok(batch.len()));
+
+    let mut rx = DatagramEngine::new();
+    while let Some(d) = tx.pop_outgoing() {
+        rx.recv(&d);
+    }
+    let received: Vec<Vec<u8>> = std::iter::from_fn(|| rx.pop_incoming()).collect();
+    assert_eq!(received, batch);
+}
+
+#[test]
+fn batch_stops_at_first_oversized_datagram() {
+    let mut engine = DatagramEngine::new();
+    engine.set_max_size(4);
+    let batch: [&[u8]; 3] = [b"ok", b"too long", b"ok2"];
+    assert_eq!(engine.send_batch(&batch), Ok(1));
+    assert_eq!(engine.pending_outgoing(), 1);
+    assert_eq!(
+        engine.send_batch(&batch[1..]),
+        Err(quiche::Error::BufferTooShort)
+    );
+}
+
+#[test]
+fn dedup_drops_replays_within_window() {
+    let mut tx = DatagramEngine::new();
+    let mut rx = DatagramEngine::new();
+    tx.set_dedup_window(4);
+    rx.set_dedup_window(4);
+    assert_eq!(rx.dedup_window(), Some(4));
+
+    let mut wire = Vec::new();
+    for i in 0..8u8 {
+        tx.send(&[i; 3]).unwrap();
+        wire.push(tx.pop_outgoing().unwrap());
+    }
+    assert_eq!(wire[0].len(), quicfuscate::datagram::DEDUP_HEADER_LEN + 3);
+
+    assert!(rx.recv(&wire[0]));
+    assert!(!rx.recv(&wire[0]), "duplicate inside the window");
+    assert!(rx.recv(&wire[2]));
+    assert!(rx.recv(&wire[1]), "reordered but fresh");
+    assert!(!rx.recv(&wire[2]));
+    assert!(rx.recv(&wire[7]));
+    assert!(!rx.recv(&wire[3]), "older than the window");
+    assert!(!rx.recv(b"short"));
+    assert_eq!(rx.duplicates_dropped(), 4);
+
+    let delivered: Vec<Vec<u8>> = std::iter::from_fn(|| rx.pop_incoming()).collect();
+    assert_eq!(
+        delivered,
+        vec![vec![0; 3], vec![2; 3], vec![1; 3], vec![7; 3]]
+    );
+}
+
+#[test]
+fn dedup_off_passes_everything() {
+    let mut engine = DatagramEngine::new();
+    assert_eq!(engine.dedup_window(), None);
+    assert!(engine.recv(b"same"));
+    assert!(engine.recv(b"same"));
+    assert_eq!(engine.pending_incoming(), 2);
+
+    // The header counts against the maximum size.
+    engine.set_dedup_window(16);
+    engine.set_max_size(10);
+    assert!(engine.send(&[0; 2]).is_ok());
+    assert_eq!(engine.send(&[0; 3]), Err(quiche::Error::BufferTooShort));
+}
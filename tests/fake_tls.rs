@@ -1,6 +1,6 @@
 use quicfuscate::fake_tls::{
-    ClientHelloParams, FakeTls, ServerHelloParams, DEFAULT_CERTIFICATE, DEFAULT_CLIENT_HELLO,
-    DEFAULT_SERVER_HELLO,
+    ClientHelloParams, FakeTls, ServerHelloParams, SplitStrategy, DEFAULT_CERTIFICATE,
+    DEFAULT_CLIENT_HELLO, DEFAULT_SERVER_HELLO,
 };
 use quicfuscate::stealth::{BrowserProfile, FingerprintProfile, OsProfile};
 
@@ -61,3 +61,98 @@ fn custom_certificate_support() {
     exp.extend_from_slice(&expected);
     assert_eq!(full, exp);
 }
+
+fn ext(ty: u16, body: &[u8]) -> Vec<u8> {
+    let mut out = ty.to_be_bytes().to_vec();
+    out.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+/// Extension block modelled on Chrome's ClientHello, including GREASE.
+fn chrome_extensions() -> Vec<u8> {
+    let mut sni = vec![0x00, 0x0e, 0x00, 0x00, 0x0b];
+    sni.extend_from_slice(b"example.com");
+    let groups = [0x00, 0x08, 0x1a, 0x1a, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18];
+    let sigalgs = [
+        0x00, 0x10, 0x04, 0x03, 0x08, 0x04, 0x04, 0x01, 0x05, 0x03, 0x08, 0x05, 0x05, 0x01, 0x08,
+        0x06, 0x06, 0x01,
+    ];
+    let mut alpn = vec![0x00, 0x0c, 0x02];
+    alpn.extend_from_slice(b"h2");
+    alpn.push(0x08);
+    alpn.extend_from_slice(b"http/1.1");
+    let versions = [0x06, 0x2a, 0x2a, 0x03, 0x04, 0x03, 0x03];
+
+    [
+        ext(0x0a0a, &[]),
+        ext(0x0000, &sni),
+        ext(0x000a, &groups),
+        ext(0x000b, &[0x01, 0x00]),
+        ext(0x000d, &sigalgs),
+        ext(0x0010, &alpn),
+        ext(0x002b, &versions),
+    ]
+    .concat()
+}
+
+#[test]
+fn chrome_profile_fingerprints_are_stable() {
+    let fp = FingerprintProfile::new(BrowserProfile::Chrome, OsProfile::Windows);
+    let extensions = chrome_extensions();
+    let hello = FakeTls::client_hello_custom(ClientHelloParams {
+        tls_version: 0x0303,
+        cipher_suites: &fp.tls_cipher_suites,
+        extensions: &extensions,
+    });
+
+    assert_eq!(
+        FakeTls::ja3(&hello).unwrap(),
+        "771,4865-4866-4867-49195-49199-49196-49200-52393-52392-49171-49172,\
+         0-10-11-13-16-43,29-23-24,0"
+    );
+    let ja4 = "t13d1106h2_5e2a75874763_724796723175";
+    assert_eq!(FakeTls::ja4(&hello).unwrap(), ja4);
+    assert!(FakeTls::assert_ja4(&hello, ja4).is_ok());
+    assert!(FakeTls::assert_ja4(&hello, "t13d1106h2_000000000000_000000000000").is_err());
+
+    // Without the record layer the hello is treated as QUIC CRYPTO data.
+    assert!(FakeTls::ja4(&hello[5..]).unwrap().starts_with("q13d"));
+    assert!(FakeTls::ja4(DEFAULT_SERVER_HELLO).is_none());
+}
+
+#[test]
+fn sni_split_spreads_server_name_over_two_segments() {
+    let fp = FingerprintProfile::new(BrowserProfile::Chrome, OsProfile::Windows);
+    let extensions = chrome_extensions();
+    let hello = FakeTls::client_hello_custom(ClientHelloParams {
+        tls_version: 0x0303,
+        cipher_suites: &fp.tls_cipher_suites,
+        extensions: &extensions,
+    });
+    let contains = |seg: &[u8]| seg.windows(11).any(|w| w == b"example.com");
+    assert!(contains(&hello));
+    assert_eq!(
+        FakeTls::split_client_hello(&hello, SplitStrategy::None),
+        vec![hello.clone()]
+    );
+
+    // Record framing: two records whose payloads rebuild the handshake.
+    let segments = FakeTls::split_client_hello(&hello, SplitStrategy::SegmentAtSni);
+    assert_eq!(segments.len(), 2);
+    let mut body = Vec::new();
+    for seg in &segments {
+        assert!(!contains(seg));
+        assert_eq!(&seg[..3], &hello[..3]);
+        let len = u16::from_be_bytes([seg[3], seg[4]]) as usize;
+        assert_eq!(seg.len(), len + 5);
+        body.extend_from_slice(&seg[5..]);
+    }
+    assert_eq!(body, &hello[5..]);
+
+    // Bare handshake as carried in QUIC CRYPTO frames.
+    let segments = FakeTls::split_client_hello(&hello[5..], SplitStrategy::SegmentAtSni);
+    assert_eq!(segments.len(), 2);
+    assert!(segments.iter().all(|seg| !contains(seg)));
+    assert_eq!(segments.concat(), &hello[5..]);
+}
@@ -1,4 +1,7 @@
-use quicfuscate::fec::{AdaptiveFec, Decoder, Decoder16, Encoder, Encoder16, FecConfig, FecMode};
+use quicfuscate::fec::{
+    AdaptiveFec, Cm256Backend, Cm256Scheme, Decoder, Decoder16, Encoder, Encoder16, FecConfig,
+    FecMode,
+};
 use quicfuscate::optimize::MemoryPool;
 use std::sync::Arc;
 
@@ -274,7 +277,8 @@ fn bitsliced_mul_matches_table() {
 #[cfg(target_arch = "x86_64")]
 #[test]
 fn avx2_kernel_matches_table() {
-    if !std::is_x86_feature_detected!("avx2") || !std::is_x86_feature_detected!("pclmulqdq") {
+    let hw = quicfuscate::fec::kernel_support();
+    if !(hw.avx2 && hw.clmul) {
         return;
     }
     quicfuscate::fec::init_gf_tables();
@@ -290,10 +294,8 @@ fn avx2_kernel_matches_table() {
 #[cfg(target_arch = "x86_64")]
 #[test]
 fn avx512_kernel_matches_table() {
-    if !(std::is_x86_feature_detected!("avx512f")
-        && std::is_x86_feature_detected!("avx512vbmi")
-        && std::is_x86_feature_detected!("pclmulqdq"))
-    {
+    let hw = quicfuscate::fec::kernel_support();
+    if !(hw.avx512 && hw.clmul) {
         return;
     }
     quicfuscate::fec::init_gf_tables();
@@ -309,7 +311,8 @@ fn avx512_kernel_matches_table() {
 #[cfg(target_arch = "aarch64")]
 #[test]
 fn neon_kernel_matches_table() {
-    if !std::arch::is_aarch64_feature_detected!("pmull") {
+    let hw = quicfuscate::fec::kernel_support();
+    if !(hw.neon && hw.clmul) {
         return;
     }
     quicfuscate::fec::init_gf_tables();
@@ -321,3 +324,1429 @@ fn neon_kernel_matches_table() {
         }
     }
 }
+
+#[test]
+fn write_payload_into_caller_buffer() {
+    let mgr = quicfuscate::optimize::OptimizationManager::new();
+    let raw = [1u8, 10, 20, 30, 40];
+    let pkt = quicfuscate::fec::Packet::from_raw(0, &raw, &mgr).unwrap();
+
+    let mut out = [0u8; 8];
+    assert_eq!(pkt.write_payload(&mut out), Ok(4));
+    assert_eq!(&out[..4], &[10, 20, 30, 40]);
+
+    let mut small = [0u8; 2];
+    assert_eq!(pkt.write_payload(&mut small), Err(4));
+    assert_eq!(small, [0, 0]);
+}
+
+#[test]
+fn proportional_repairs_scale_with_loss_and_block() {
+    use quicfuscate::fec::{optimal_repair_count, RepairPolicy};
+
+    let fixed = RepairPolicy::Fixed.repairs(FecMode::Normal, 128, 0.15);
+    let prop = RepairPolicy::Proportional.repairs(FecMode::Normal, 128, 0.15);
+    assert_eq!(fixed, 20);
+    assert!(prop > fixed, "proportional {} <= fixed {}", prop, fixed);
+
+    // Fixed ignores the loss rate, proportional does not.
+    assert_eq!(
+        RepairPolicy::Fixed.repairs(FecMode::Normal, 128, 0.0),
+        fixed
+    );
+    assert_eq!(optimal_repair_count(128, 0.0), 0);
+
+    // Larger blocks at the same loss need more repairs.
+    assert!(optimal_repair_count(512, 0.15) > optimal_repair_count(128, 0.15));
+    // Enough repairs to cover the expected loss.
+    let r = optimal_repair_count(512, 0.15);
+    assert!((512 + r) as f32 * 0.85 >= 512.0);
+}
+
+#[test]
+fn adaptive_fec_repair_policy_switch() {
+    use quicfuscate::fec::RepairPolicy;
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    let k = FecConfig::default_windows()[&FecMode::Normal];
+    let fixed = RepairPolicy::Fixed.repairs(FecMode::Normal, k, 0.15);
+    let proportional = RepairPolicy::Proportional.repairs(FecMode::Normal, k, 0.15);
+    assert_ne!(fixed, proportional);
+
+    for (policy, expected) in [
+        (RepairPolicy::Fixed, fixed),
+        (RepairPolicy::Proportional, proportional),
+    ] {
+        let pool = Arc::new(MemoryPool::new(512, 64));
+        let mut fec = AdaptiveFec::new(
+            FecConfig {
+                lambda: 1.0,
+                initial_mode: FecMode::Normal,
+                // Keeps mode and window in place across the loss report.
+                min_dwell_time: Duration::from_secs(3600),
+                autotune: false,
+                ..FecConfig::default()
+            },
+            Arc::clone(&pool),
+        );
+        fec.set_repair_policy(policy);
+        // The policy applies to the coder built for the next loss report.
+        fec.report_loss(15, 100);
+        assert_eq!(fec.current_mode(), FecMode::Normal);
+        assert_eq!(fec.current_window(), k);
+
+        // Repairs for a block go out with its last source.
+        let mut queue = VecDeque::new();
+        for id in 0..k as u64 {
+            fec.on_send(make_packet(id, id as u8, &pool), &mut queue);
+            let repairs = queue.iter().filter(|p| !p.is_systematic).count();
+            let want = if id + 1 < k as u64 { 0 } else { expected };
+            assert_eq!(repairs, want, "{:?} after source {}", policy, id);
+        }
+        assert_eq!(queue.len(), k + expected);
+    }
+}
+
+#[test]
+fn expired_block_dropped_fresh_block_retained() {
+    use quicfuscate::fec::FecEvent;
+    use std::time::{Duration, Instant};
+
+    let mgr = quicfuscate::optimize::OptimizationManager::new();
+    let mut cfg = FecConfig::default();
+    cfg.initial_mode = FecMode::Normal;
+    let mut fec = AdaptiveFec::new(cfg, mgr.memory_pool());
+    fec.set_deadline_policy(Duration::from_millis(50));
+
+    let t0 = Instant::now();
+    let pkt = quicfuscate::fec::Packet::from_raw(0, &[1, 1, 2, 3], &mgr).unwrap();
+    assert!(fec.on_receive_at(pkt, t0).unwrap().is_empty());
+    assert!(!fec.flush_expired(t0 + Duration::from_millis(20)));
+    assert_eq!(fec.poll_event(), None);
+
+    // The old incomplete block is dropped once its deadline has passed.
+    let t1 = t0 + Duration::from_millis(80);
+    assert!(fec.flush_expired(t1));
+    assert_eq!(
+        fec.poll_event(),
+        Some(FecEvent::BlockExpired {
+            age: Duration::from_millis(80),
+            received: 1,
+        })
+    );
+    assert_eq!(fec.pending_block_age(t1), None);
+
+    // A fresh block started afterwards is kept.
+    let pkt = quicfuscate::fec::Packet::from_raw(1, &[1, 4, 5, 6], &mgr).unwrap();
+    fec.on_receive_at(pkt, t1).unwrap();
+    let t2 = t1 + Duration::from_millis(30);
+    assert!(!fec.flush_expired(t2));
+    assert_eq!(fec.pending_block_age(t2), Some(Duration::from_millis(30)));
+    assert_eq!(fec.poll_event(), None);
+}
+
+#[test]
+fn feature_detection_agrees_across_modules() {
+    use quicfuscate::{cpu_features, CpuFeature};
+
+    let detector = cpu_features();
+    let fec = quicfuscate::fec::kernel_support();
+    let crypto = quicfuscate::crypto::detected_features();
+
+    let avx2 = detector.has_feature(CpuFeature::AVX2);
+    let neon = detector.has_feature(CpuFeature::NEON);
+    assert_eq!(fec.avx2, avx2);
+    assert_eq!(crypto.avx2, avx2);
+    assert_eq!(fec.neon, neon);
+    assert_eq!(crypto.neon, neon);
+    assert_eq!(fec.clmul, crypto.pclmulqdq);
+}
+
+#[test]
+fn scalar_hw_path_disables_simd() {
+    use quicfuscate::fec::gf_tables::{gf_mul, gf_mul_table, gf_mul_with};
+    use quicfuscate::fec::{detected_hw_path, with_hw_path, HwPath};
+
+    quicfuscate::fec::init_gf_tables();
+    let pool = Arc::new(MemoryPool::new(32, 64));
+    let fec = AdaptiveFec::new(FecConfig::default(), pool);
+    assert_eq!(fec.hw_path(), detected_hw_path().as_str());
+
+    // Scoped to this thread, so tests running in parallel keep their path.
+    with_hw_path(HwPath::Scalar, || {
+        assert_eq!(fec.hw_path(), "scalar");
+        for a in 0u8..=255 {
+            let b = a.wrapping_mul(7).wrapping_add(3);
+            assert_eq!(gf_mul(a, b), gf_mul_table(a, b));
+        }
+    });
+    assert_eq!(fec.hw_path(), detected_hw_path().as_str());
+
+    // Other threads, like the coders' rayon workers, take the path explicitly.
+    std::thread::spawn(|| {
+        for a in 0u8..=255 {
+            let b = a.wrapping_mul(7).wrapping_add(3);
+            assert_eq!(gf_mul_with(HwPath::Scalar, a, b), gf_mul_table(a, b));
+        }
+    })
+    .join()
+    .unwrap();
+
+    if !HwPath::Avx512.is_supported() {
+        assert!(fec.force_hw_path(HwPath::Avx512).is_err());
+    }
+}
+
+#[test]
+fn fec_config_builder_validates_inputs() {
+    assert!(FecConfig::builder().lambda(0.0).is_err());
+    assert!(FecConfig::builder().lambda(1.5).is_err());
+    assert!(FecConfig::builder().burst_window(0).is_err());
+    assert!(FecConfig::builder().hysteresis(1.0).is_err());
+    assert!(FecConfig::builder().pid(1.0, -0.1, 0.0).is_err());
+    assert!(FecConfig::builder().kalman(0.0, 0.01).is_err());
+    assert!(FecConfig::builder()
+        .window_size(FecMode::Normal, 0)
+        .is_err());
+
+    let cfg = FecConfig::builder()
+        .lambda(0.2)
+        .and_then(|b| b.burst_window(32))
+        .and_then(|b| b.hysteresis(0.05))
+        .and_then(|b| b.kalman(0.002, 0.02))
+        .and_then(|b| b.window_size(FecMode::Light, 24))
+        .map(|b| b.initial_mode(FecMode::Light))
+        .and_then(|b| b.build())
+        .unwrap();
+    assert_eq!(cfg.lambda, 0.2);
+    assert_eq!(cfg.burst_window, 32);
+    assert!(cfg.kalman_enabled);
+    assert_eq!(cfg.window_sizes[&FecMode::Light], 24);
+    assert_eq!(cfg.initial_mode, FecMode::Light);
+    assert!(cfg.validate().is_ok());
+
+    // The setters and `validate` apply the same rules.
+    for lambda in [0.0, 0.5, 1.0, 1.5] {
+        let direct = FecConfig {
+            lambda,
+            ..FecConfig::default()
+        };
+        assert_eq!(
+            FecConfig::builder().lambda(lambda).is_ok(),
+            direct.validate().is_ok(),
+            "lambda {}",
+            lambda
+        );
+    }
+}
+
+#[test]
+fn zero_mode_sends_bypass_encoder() {
+    use std::collections::VecDeque;
+
+    let pool = Arc::new(MemoryPool::new(256, 64));
+    let config = FecConfig {
+        initial_mode: FecMode::Zero,
+        ..FecConfig::default()
+    };
+    let mut fec = AdaptiveFec::new(config, Arc::clone(&pool));
+    assert!(fec.is_passthrough());
+    let baseline = pool.in_use();
+
+    let mut queue = VecDeque::new();
+    for i in 0..100u64 {
+        fec.on_send(make_packet(i, i as u8, &pool), &mut queue);
+    }
+    // No repair packets and no pool blocks beyond the packets themselves.
+    assert_eq!(queue.len(), 100);
+    assert!(queue.iter().all(|p| p.is_systematic));
+    assert_eq!(pool.in_use(), baseline + 100);
+    assert_eq!(fec.passthrough_packets(), 100);
+    for (i, p) in queue.iter().enumerate() {
+        assert_eq!(p.id, i as u64);
+    }
+}
+
+#[test]
+fn exported_test_vectors_decode_to_sources() {
+    use quicfuscate::fec::{export_test_vectors, Packet, TEST_VECTOR_LENGTHS};
+    use quicfuscate::optimize::OptimizationManager;
+
+    let vectors = export_test_vectors(42, FecMode::Light);
+    assert_eq!(vectors.len(), TEST_VECTOR_LENGTHS.len());
+    assert_eq!(vectors, export_test_vectors(42, FecMode::Light));
+    assert_ne!(
+        vectors[0].sources,
+        export_test_vectors(43, FecMode::Light)[0].sources
+    );
+    assert!(export_test_vectors(42, FecMode::Zero).is_empty());
+
+    let opt = OptimizationManager::new();
+    for v in &vectors {
+        assert_eq!(v.packets.len(), v.n);
+        assert!(v.n > v.k);
+        assert_eq!(v.packets[0][0], 1);
+        assert_eq!(&v.packets[0][1..], v.sources[0].as_slice());
+
+        // Lose the first source and recover it from the repair packet.
+        let config = FecConfig {
+            initial_mode: v.mode,
+            ..FecConfig::default()
+        };
+        let mut fec = AdaptiveFec::new(config, opt.memory_pool());
+        let mut recovered = Vec::new();
+        for (id, raw) in v.packets.iter().enumerate().skip(1) {
+            let pkt = Packet::from_raw(id as u64, raw, &opt).unwrap();
+            recovered.extend(fec.on_receive(pkt).unwrap());
+        }
+        assert_eq!(recovered.len(), v.k);
+        for (i, pkt) in recovered.iter().enumerate() {
+            let len = v.sources[i].len();
+            assert_eq!(&pkt.data.as_ref().unwrap()[..len], v.sources[i].as_slice());
+        }
+    }
+}
+
+#[test]
+fn non_systematic_block_decodes_from_coded_packets() {
+    use std::collections::VecDeque;
+
+    let pool = Arc::new(MemoryPool::new(512, 64));
+    let config = FecConfig::builder()
+        .initial_mode(FecMode::Light)
+        .systematic(false)
+        .build()
+        .unwrap();
+    let mut sender = AdaptiveFec::new(config.clone(), Arc::clone(&pool));
+    let mut receiver = AdaptiveFec::new(config, Arc::clone(&pool));
+    let k = FecConfig::default_windows()[&FecMode::Light];
+
+    let mut queue = VecDeque::new();
+    for i in 0..k as u64 - 1 {
+        sender.on_send(make_packet(i, i as u8 + 1, &pool), &mut queue);
+    }
+    assert!(queue.is_empty(), "nothing is sent before the block is full");
+    sender.on_send(make_packet(k as u64 - 1, k as u8, &pool), &mut queue);
+    assert!(queue.len() >= k);
+    assert!(queue.iter().all(|p| !p.is_systematic));
+
+    let mut recovered = Vec::new();
+    for pkt in queue.drain(..).take(k) {
+        recovered.extend(receiver.on_receive(pkt).unwrap());
+    }
+    assert_eq!(recovered.len(), k);
+    for (i, pkt) in recovered.iter().enumerate() {
+        assert_eq!(pkt.data.as_ref().unwrap()[..8], [i as u8 + 1; 8]);
+    }
+}
+
+#[test]
+fn partial_coded_block_is_sent_on_flush() {
+    use std::collections::VecDeque;
+
+    let pool = Arc::new(MemoryPool::new(512, 64));
+    let config = FecConfig::builder()
+        .initial_mode(FecMode::Light)
+        .systematic(false)
+        .build()
+        .unwrap();
+    let mut sender = AdaptiveFec::new(config.clone(), Arc::clone(&pool));
+    let mut receiver = AdaptiveFec::new(config, Arc::clone(&pool));
+    let k = FecConfig::default_windows()[&FecMode::Light];
+    assert!(k > 2);
+
+    let mut queue = VecDeque::new();
+    for i in 0..2u64 {
+        sender.on_send(make_packet(i, i as u8 + 1, &pool), &mut queue);
+    }
+    assert!(queue.is_empty());
+    assert!(sender.flush(&mut queue) >= k);
+    assert_eq!(sender.flush(&mut queue), 0, "the block was flushed once");
+
+    let mut recovered = Vec::new();
+    for pkt in queue.drain(..).take(k) {
+        recovered.extend(receiver.on_receive(pkt).unwrap());
+    }
+    assert_eq!(recovered.len(), 2, "the filler sources are dropped");
+    for (i, pkt) in recovered.iter().enumerate() {
+        assert_eq!(pkt.payload(), &[i as u8 + 1; 8]);
+    }
+}
+
+#[test]
+fn repeated_erasure_pattern_hits_recovery_cache() {
+    quicfuscate::fec::init_gf_tables();
+    let pool = Arc::new(MemoryPool::new(64, 64));
+    let k = 5;
+    let n = 7;
+    let cache = quicfuscate::fec::new_recovery_cache();
+    let decode = |seed: u8| {
+        let mut enc = Encoder::new(k, n);
+        let mut packets = Vec::new();
+        for i in 0..k {
+            let p = make_packet(i as u64, seed.wrapping_add(i as u8), &pool);
+            enc.add_source_packet(p.clone());
+            packets.push(p);
+        }
+        let mut dec = Decoder::with_recovery_cache(k, Arc::clone(&pool), Arc::clone(&cache));
+        for i in [0, 2, 4] {
+            dec.add_packet(packets[i].clone()).unwrap();
+        }
+        for i in 0..(n - k) {
+            dec.add_packet(enc.generate_repair_packet(i, &pool).unwrap())
+                .unwrap();
+        }
+        assert!(dec.is_decoded);
+        let hit = dec.used_cached_inverse();
+        let out: Vec<Vec<u8>> = dec
+            .get_decoded_packets()
+            .iter()
+            .map(|p| p.payload()[..8].to_vec())
+            .collect();
+        (hit, out)
+    };
+
+    let (hit, first) = decode(7);
+    assert!(!hit);
+    assert_eq!(cache.lock().unwrap().stats(), (0, 1));
+    // Same erasure pattern, different payloads: the cached inverse must
+    // still recover the new block.
+    let (hit, second) = decode(40);
+    assert!(hit);
+    assert_eq!(cache.lock().unwrap().stats(), (1, 1));
+    for (i, payload) in first.iter().enumerate() {
+        assert_eq!(payload, &vec![7 + i as u8; 8]);
+    }
+    for (i, payload) in second.iter().enumerate() {
+        assert_eq!(payload, &vec![40 + i as u8; 8]);
+    }
+
+    // Another decoder with its own cache does not see those entries.
+    let mut other = Decoder::new(k, Arc::clone(&pool));
+    let mut enc = Encoder::new(k, n);
+    let packets: Vec<_> = (0..k)
+        .map(|i| make_packet(i as u64, 90 + i as u8, &pool))
+        .collect();
+    for p in &packets {
+        enc.add_source_packet(p.clone());
+    }
+    for i in [0, 2, 4] {
+        other.add_packet(packets[i].clone()).unwrap();
+    }
+    for i in 0..(n - k) {
+        other
+            .add_packet(enc.generate_repair_packet(i, &pool).unwrap())
+            .unwrap();
+    }
+    assert!(other.is_decoded);
+    assert!(!other.used_cached_inverse());
+    assert_eq!(cache.lock().unwrap().stats(), (1, 1));
+}
+
+#[test]
+fn identical_windows_produce_identical_repairs() {
+    quicfuscate::fec::init_gf_tables();
+    let pool = Arc::new(MemoryPool::new(64, 64));
+    let k = 6;
+    let n = 9;
+    let encode = || {
+        let mut enc = Encoder::new(k, n);
+        let mut enc16 = Encoder16::new(k, n);
+        for i in 0..k {
+            let p = make_packet(i as u64, 0x30 + i as u8, &pool);
+            enc16.add_source_packet(p.clone());
+            enc.add_source_packet(p);
+        }
+        let mut wire = Vec::new();
+        for i in 0..(n - k) {
+            for repair in [
+                enc.generate_repair_packet(i, &pool).unwrap(),
+                enc16.generate_repair_packet(i, &pool).unwrap(),
+            ] {
+                let mut buf = vec![0u8; 64];
+                let len = repair.to_raw(&mut buf).unwrap();
+                buf.truncate(len);
+                wire.push(buf);
+            }
+        }
+        wire
+    };
+    assert_eq!(encode(), encode());
+}
+
+#[test]
+fn reset_returns_to_initial_state() {
+    quicfuscate::fec::init_gf_tables();
+    let pool = Arc::new(MemoryPool::new(32, 64));
+    let cfg = FecConfig::default();
+    let initial = cfg.initial_mode;
+    let mut fec = AdaptiveFec::new(cfg, Arc::clone(&pool));
+    fec.set_repair_policy(quicfuscate::fec::RepairPolicy::Proportional);
+    fec.report_loss(40, 50);
+    assert_eq!(fec.current_mode(), FecMode::Extreme);
+
+    fec.reset();
+    assert_eq!(fec.current_mode(), initial);
+    assert!(!fec.is_transitioning());
+    assert_eq!(fec.passthrough_packets(), 0);
+    assert!(fec.poll_event().is_none());
+    assert_eq!(
+        fec.repair_policy(),
+        quicfuscate::fec::RepairPolicy::Proportional
+    );
+
+    // The loss history is gone: a clean report keeps the initial mode.
+    fec.report_loss(0, 50);
+    assert_eq!(fec.current_mode(), initial);
+}
+
+#[test]
+fn block_past_max_recovery_delay_is_released() {
+    use quicfuscate::fec::FecEvent;
+    use std::time::{Duration, Instant};
+
+    let mgr = quicfuscate::optimize::OptimizationManager::new();
+    let pool = mgr.memory_pool();
+    let cfg = FecConfig::builder()
+        .initial_mode(FecMode::Normal)
+        .max_recovery_delay(Duration::from_millis(50))
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut fec = AdaptiveFec::new(cfg, Arc::clone(&pool));
+    assert_eq!(fec.deadline_policy(), Some(Duration::from_millis(50)));
+
+    let base = pool.in_use();
+    let t0 = Instant::now();
+    let pkt = quicfuscate::fec::Packet::from_raw(0, &[1, 1, 2, 3], &mgr).unwrap();
+    assert!(fec.on_receive_at(pkt, t0).unwrap().is_empty());
+    assert_eq!(pool.in_use(), base + 1);
+
+    // The next packet arrives after the deadline: the stale block is failed
+    // and its buffer returned before the new packet starts a fresh block.
+    let t1 = t0 + Duration::from_millis(70);
+    let pkt = quicfuscate::fec::Packet::from_raw(1, &[1, 4, 5, 6], &mgr).unwrap();
+    fec.on_receive_at(pkt, t1).unwrap();
+    assert_eq!(
+        fec.poll_event(),
+        Some(FecEvent::BlockExpired {
+            age: Duration::from_millis(70),
+            received: 1,
+        })
+    );
+    assert_eq!(pool.in_use(), base + 1);
+}
+
+#[test]
+fn zero_mode_receive_with_deadline_passes_packets_through() {
+    use std::time::{Duration, Instant};
+
+    let mgr = quicfuscate::optimize::OptimizationManager::new();
+    let cfg = FecConfig::builder()
+        .initial_mode(FecMode::Zero)
+        .max_recovery_delay(Duration::from_millis(250))
+        .unwrap()
+        .autotune(false)
+        .build()
+        .unwrap();
+    let mut fec = AdaptiveFec::new(cfg, mgr.memory_pool());
+    assert!(fec.is_passthrough());
+
+    // Zero mode has no blocks: sources are delivered as they arrive and
+    // nothing is left to expire, however late the next packet is.
+    let t0 = Instant::now();
+    for (i, at) in [t0, t0 + Duration::from_secs(1)].into_iter().enumerate() {
+        let pkt = quicfuscate::fec::Packet::from_raw(i as u64, &[1, i as u8, 7], &mgr).unwrap();
+        let out = fec.on_receive_at(pkt, at).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].id, i as u64);
+        assert_eq!(fec.pending_block_age(at), None);
+    }
+    assert!(!fec.flush_expired(t0 + Duration::from_secs(2)));
+    assert_eq!(fec.poll_event(), None);
+    assert!(fec.take_missing_sources().is_empty());
+}
+
+#[test]
+fn block_keeps_its_window_across_mode_switch() {
+    use std::time::{Duration, Instant};
+
+    let pool = Arc::new(MemoryPool::new(32, 64));
+    let mut fec = AdaptiveFec::new(
+        FecConfig {
+            lambda: 1.0,
+            pid: quicfuscate::fec::PidConfig {
+                kp: 1.0,
+                ki: 0.0,
+                kd: 0.0,
+            },
+            initial_mode: FecMode::Normal,
+            min_dwell_time: Duration::ZERO,
+            max_recovery_delay: Some(Duration::from_millis(50)),
+            autotune: false,
+            ..FecConfig::default()
+        },
+        Arc::clone(&pool),
+    );
+    let k = fec.current_window() as u64;
+
+    let t0 = Instant::now();
+    for id in 0..2 {
+        assert!(fec
+            .on_receive_at(make_packet(id, 1, &pool), t0)
+            .unwrap()
+            .is_empty());
+    }
+    fec.report_loss(45, 100);
+    assert_ne!(fec.current_window() as u64, k);
+
+    // The block opened before the switch still expires with the window it
+    // was sent with.
+    assert!(fec.flush_expired(t0 + Duration::from_millis(80)));
+    assert_eq!(
+        fec.poll_event(),
+        Some(quicfuscate::fec::FecEvent::BlockExpired {
+            age: Duration::from_millis(80),
+            received: 2,
+        })
+    );
+    assert_eq!(fec.take_missing_sources(), (2..k).collect::<Vec<_>>());
+    assert_eq!(fec.poll_event(), None);
+}
+
+#[test]
+fn cross_fade_end_expires_block_left_by_mode_switch() {
+    use quicfuscate::fec::FecEvent;
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    let pool = Arc::new(MemoryPool::new(64, 64));
+    let mut fec = AdaptiveFec::new(
+        FecConfig {
+            lambda: 1.0,
+            pid: quicfuscate::fec::PidConfig {
+                kp: 1.0,
+                ki: 0.0,
+                kd: 0.0,
+            },
+            initial_mode: FecMode::Normal,
+            min_dwell_time: Duration::ZERO,
+            autotune: false,
+            ..FecConfig::default()
+        },
+        Arc::clone(&pool),
+    );
+    let k = fec.current_window() as u64;
+    for id in 0..2 {
+        assert!(fec
+            .on_receive(make_packet(id, 1, &pool))
+            .unwrap()
+            .is_empty());
+    }
+    fec.report_loss(45, 100);
+    assert!(fec.is_transitioning());
+    assert_eq!(fec.poll_event(), None);
+
+    // The old decoder is retired halfway through the cross-fade, giving up
+    // on its block once, with the window the block was sent with.
+    let mut queue = VecDeque::new();
+    for id in 0..16 {
+        fec.on_send(make_packet(id, 2, &pool), &mut queue);
+    }
+    assert!(matches!(
+        fec.poll_event(),
+        Some(FecEvent::BlockExpired { received: 2, .. })
+    ));
+    assert_eq!(fec.poll_event(), None);
+    assert_eq!(fec.take_missing_sources(), (2..k).collect::<Vec<_>>());
+}
+
+#[test]
+fn from_raw_rejects_empty_and_oversized_frames() {
+    use quicfuscate::fec::Packet;
+    use quicfuscate::optimize::{OptimizationManager, OptimizeConfig};
+
+    let opt = OptimizationManager::from_cfg(OptimizeConfig {
+        pool_capacity: 4,
+        block_size: 64,
+        enable_xdp: false,
+        strict_pool_capacity: false,
+        congestion_control: quicfuscate::congestion::CcAlgorithm::Bbr,
+    });
+
+    assert!(Packet::from_raw(0, &[], &opt).is_err());
+
+    let mut payload = vec![1u8];
+    payload.extend(std::iter::repeat(0xAA).take(65));
+    assert!(Packet::from_raw(1, &payload, &opt).is_err());
+
+    let mut repair = vec![0u8];
+    repair.extend_from_slice(&100u16.to_be_bytes());
+    repair.extend(std::iter::repeat(0x55).take(100));
+    assert!(Packet::from_raw(2, &repair, &opt).is_err());
+
+    let mut ok = vec![1u8];
+    ok.extend(std::iter::repeat(0x11).take(64));
+    let pkt = Packet::from_raw(3, &ok, &opt).unwrap();
+    assert_eq!(pkt.payload().len(), 64);
+}
+
+#[test]
+fn reorder_buffer_restores_sequence_order() {
+    use quicfuscate::fec::ReorderBuffer;
+
+    let pool = Arc::new(MemoryPool::new(64, 64));
+    let mut buf = ReorderBuffer::new(4, 8);
+
+    // Two blocks interleaved and shuffled within the window.
+    let order = [5u64, 1, 3, 0, 7, 2, 6, 4];
+    let mut blocks = Vec::new();
+    for id in order {
+        blocks.extend(buf.push(make_packet(id, id as u8, &pool)));
+    }
+    assert_eq!(blocks.len(), 2);
+    let ids: Vec<u64> = blocks.iter().flatten().map(|p| p.id).collect();
+    assert_eq!(ids, (0..8).collect::<Vec<_>>());
+    for p in blocks.iter().flatten() {
+        assert_eq!(p.payload()[0], p.id as u8);
+    }
+
+    // A packet for an already released block is dropped.
+    assert!(buf.push(make_packet(3, 3, &pool)).is_empty());
+    assert_eq!(buf.late_packets(), 1);
+
+    // A gap is held back until the window moves past it.
+    assert!(buf.push(make_packet(9, 9, &pool)).is_empty());
+    assert!(buf.push(make_packet(8, 8, &pool)).is_empty());
+    let released = buf.push(make_packet(16, 16, &pool));
+    assert_eq!(released.len(), 1);
+    let ids: Vec<u64> = released[0].iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec![8, 9]);
+
+    let rest = buf.flush();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0][0].id, 16);
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn coding_latency_is_tracked_per_mode() {
+    use std::collections::VecDeque;
+
+    let pool = Arc::new(MemoryPool::new(64, 64));
+    let cfg = FecConfig {
+        initial_mode: FecMode::Light,
+        ..FecConfig::default()
+    };
+    let mut fec = AdaptiveFec::new(cfg, Arc::clone(&pool));
+    assert_eq!(fec.latency(FecMode::Light).avg_encode(), None);
+
+    let mut out = VecDeque::new();
+    for i in 0..4 {
+        fec.on_send(make_packet(i, i as u8, &pool), &mut out);
+    }
+    let light = fec.latency(FecMode::Light);
+    assert_eq!(light.encode_calls, 4);
+    assert!(light.encode_time_ns > 0);
+    assert!(light.avg_encode().is_some());
+
+    for pkt in out.drain(..) {
+        fec.on_receive(pkt).unwrap();
+    }
+    let light = fec.latency(FecMode::Light);
+    assert!(light.decode_calls > 0);
+    assert!(light.decode_time_ns > 0);
+    assert_eq!(fec.latency(FecMode::Extreme), Default::default());
+}
+
+#[test]
+fn region_kernels_agree_with_scalar() {
+    use quicfuscate::fec::gf_tables::{gf_mul_add_region_with, gf_mul_table, HwPath};
+
+    quicfuscate::fec::init_gf_tables();
+    // Odd length so every kernel also runs its scalar tail.
+    let src: Vec<u8> = (0..1027u32).map(|i| (i * 7 + 3) as u8).collect();
+    let base: Vec<u8> = (0..1027u32).map(|i| (i * 13) as u8).collect();
+    for c in [0u8, 1, 2, 0x1d, 0x80, 0xff] {
+        let mut expected = base.clone();
+        gf_mul_add_region_with(HwPath::Scalar, c, &src, &mut expected);
+        for (e, (&b, &s)) in expected.iter().zip(base.iter().zip(&src)) {
+            assert_eq!(*e, b ^ gf_mul_table(c, s));
+        }
+        for path in [HwPath::Sse2, HwPath::Avx2, HwPath::Avx512, HwPath::Neon] {
+            if !path.is_supported() {
+                continue;
+            }
+            let mut out = base.clone();
+            gf_mul_add_region_with(path, c, &src, &mut out);
+            assert_eq!(out, expected, "{} differs for c={}", path.as_str(), c);
+        }
+    }
+}
+
+#[test]
+fn slice_and_scalar_mul_agree_across_paths() {
+    use quicfuscate::fec::gf_tables::{gf_mul, gf_mul_slice, gf_mul_table, with_hw_path, HwPath};
+
+    quicfuscate::fec::init_gf_tables();
+    let a: Vec<u8> = (0..1027u32).map(|i| (i * 7 + 3) as u8).collect();
+    let b: Vec<u8> = (0..1027u32).map(|i| (i * 31 + 5) as u8).collect();
+    let expected: Vec<u8> = a
+        .iter()
+        .zip(&b)
+        .map(|(&x, &y)| gf_mul_table(x, y))
+        .collect();
+    for path in [
+        HwPath::Scalar,
+        HwPath::Sse2,
+        HwPath::Avx2,
+        HwPath::Avx512,
+        HwPath::Neon,
+    ] {
+        if !path.is_supported() {
+            continue;
+        }
+        let mut out = vec![0u8; a.len()];
+        with_hw_path(path, || gf_mul_slice(&a, &b, &mut out));
+        assert_eq!(out, expected, "gf_mul_slice on {}", path.as_str());
+        let per_byte: Vec<u8> = with_hw_path(path, || {
+            a.iter().zip(&b).map(|(&x, &y)| gf_mul(x, y)).collect()
+        });
+        assert_eq!(per_byte, expected, "gf_mul on {}", path.as_str());
+    }
+}
+
+#[test]
+fn table_sources_agree() {
+    use quicfuscate::fec::gf_tables::{gf_mul_precomputed, gf_mul_table};
+
+    quicfuscate::fec::init_gf_tables();
+    for a in 0..=255u8 {
+        for b in 0..=255u8 {
+            assert_eq!(
+                gf_mul_table(a, b),
+                gf_mul_precomputed(a, b),
+                "{} * {}",
+                a,
+                b
+            );
+        }
+    }
+}
+
+#[test]
+fn gf16_tables_match_bitserial() {
+    use quicfuscate::fec::gf_tables::{gf16_inv, gf16_mul, gf16_mul_bitserial};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0x16);
+    for _ in 0..100_000 {
+        let (a, b): (u16, u16) = (rng.gen(), rng.gen());
+        assert_eq!(gf16_mul(a, b), gf16_mul_bitserial(a, b), "{} * {}", a, b);
+    }
+    for a in [0u16, 1, 2, 0x8000, 0xffff] {
+        for b in [0u16, 1, 2, 0x8000, 0xffff] {
+            assert_eq!(gf16_mul(a, b), gf16_mul_bitserial(a, b), "{} * {}", a, b);
+        }
+    }
+    for _ in 0..1000 {
+        let a = rng.gen_range(1..=u16::MAX);
+        assert_eq!(gf16_mul_bitserial(a, gf16_inv(a)), 1, "inverse of {}", a);
+    }
+}
+
+#[test]
+fn matrix_multiply_matches_per_byte_product() {
+    use quicfuscate::fec::gf_tables::{gf_matrix_multiply, gf_mul_table};
+
+    quicfuscate::fec::init_gf_tables();
+    let matrix = vec![vec![1u8, 2, 3], vec![0x53, 0, 0xca]];
+    let a: Vec<u8> = (0..100).collect();
+    let b: Vec<u8> = (0..100).map(|i| 255 - i).collect();
+    let c: Vec<u8> = (0..100).map(|i: u8| i.wrapping_mul(3)).collect();
+    let inputs = [&a[..], &b[..], &c[..]];
+    let mut outputs = vec![vec![0xAA; 100]; 2];
+    gf_matrix_multiply(&matrix, &inputs, &mut outputs);
+    for (row, out) in matrix.iter().zip(&outputs) {
+        for i in 0..100 {
+            let want = row
+                .iter()
+                .zip(&inputs)
+                .fold(0, |acc, (&m, input)| acc ^ gf_mul_table(m, input[i]));
+            assert_eq!(out[i], want);
+        }
+    }
+}
+
+#[test]
+fn autotune_decision_applies_in_high_loss_modes() {
+    use quicfuscate::fec::hw_path;
+
+    let pool = Arc::new(MemoryPool::new(256, 64));
+    let config = FecConfig::builder()
+        .initial_mode(FecMode::Strong)
+        .autotune(false)
+        .build()
+        .unwrap();
+    let mut fec = AdaptiveFec::new(config, Arc::clone(&pool));
+    assert_eq!(fec.autotuned_path(), None);
+    assert_eq!(fec.kernel_path(), hw_path());
+
+    let path = fec.autotune();
+    assert!(path.is_supported());
+    assert_eq!(fec.autotuned_path(), Some(path));
+    assert_eq!(fec.kernel_path(), path);
+
+    let light = FecConfig::builder()
+        .initial_mode(FecMode::Light)
+        .autotune(true)
+        .build()
+        .unwrap();
+    let fec = AdaptiveFec::new(light, pool);
+    assert_eq!(fec.autotuned_path(), Some(path));
+    assert_eq!(fec.kernel_path(), hw_path());
+}
+
+#[test]
+fn redundancy_follows_mode_after_loss_reports() {
+    use quicfuscate::fec::{optimal_repair_count, ModeManager, RepairPolicy};
+
+    let pool = Arc::new(MemoryPool::new(32, 64));
+    let config = FecConfig::builder().autotune(false).build().unwrap();
+    let mut fec = AdaptiveFec::new(config, pool);
+    assert_eq!(fec.current_mode(), FecMode::Zero);
+    assert_eq!(fec.current_repair_count(), 0);
+    assert_eq!(fec.redundancy_ratio(), 0.0);
+
+    fec.report_loss(18, 20);
+    let mode = fec.current_mode();
+    assert_ne!(mode, FecMode::Zero);
+    let window = FecConfig::default_windows()[&mode];
+    let (k, n) = ModeManager::params_for(mode, window);
+    assert_eq!(fec.current_repair_count(), n - k);
+    assert!((fec.redundancy_ratio() - (n - k) as f64 / k as f64).abs() < 1e-9);
+
+    fec.set_repair_policy(RepairPolicy::Proportional);
+    fec.report_loss(18, 20);
+    let mode = fec.current_mode();
+    let window = FecConfig::default_windows()[&mode];
+    let repairs = fec.current_repair_count();
+    assert!(repairs >= optimal_repair_count(window, 0.0));
+    assert!((fec.redundancy_ratio() - repairs as f64 / window as f64).abs() < 1e-9);
+}
+
+#[test]
+fn max_window_caps_extreme_mode() {
+    use quicfuscate::fec::{AdaptiveFec, FecConfig, FecMode};
+    use quicfuscate::optimize::MemoryPool;
+    use std::sync::Arc;
+
+    let cfg = FecConfig::builder()
+        .autotune(false)
+        .max_window(256)
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut fec = AdaptiveFec::new(cfg, Arc::new(MemoryPool::new(64, 64)));
+    for _ in 0..20 {
+        fec.report_loss(18, 20);
+        assert_eq!(fec.current_mode(), FecMode::Extreme);
+        assert!(
+            fec.current_window() <= 256,
+            "window {}",
+            fec.current_window()
+        );
+    }
+
+    assert!(FecConfig::builder().max_window(0).is_err());
+    let toml = "[adaptive_fec]\nmax_window = 128\n";
+    assert_eq!(FecConfig::from_toml(toml).unwrap().max_window, Some(128));
+}
+
+#[test]
+fn interleaved_blocks_decode_independently() {
+    use quicfuscate::fec::{block_of, decode_blocks};
+
+    quicfuscate::fec::init_gf_tables();
+    let pool = Arc::new(MemoryPool::new(128, 64));
+    let k = 4;
+    let n = 6;
+
+    // Two consecutive blocks, each losing two sources.
+    let mut blocks = Vec::new();
+    for b in 0..2u64 {
+        let mut enc = Encoder::new(k, n);
+        let mut sources = Vec::new();
+        for i in 0..k as u64 {
+            let id = b * k as u64 + i;
+            let p = make_packet(id, 10 * b as u8 + i as u8 + 1, &pool);
+            enc.add_source_packet(p.clone());
+            sources.push(p);
+        }
+        let repairs: Vec<_> = (0..n - k)
+            .map(|i| enc.generate_repair_packet(i, &pool).unwrap())
+            .collect();
+        for r in &repairs {
+            assert_eq!(block_of(k, r), Some(b));
+        }
+        blocks.push((sources, repairs));
+    }
+
+    // Interleave what survived of both blocks in a single buffer.
+    let (src0, rep0) = &blocks[0];
+    let (src1, rep1) = &blocks[1];
+    let mixed = vec![
+        src1[0].clone(),
+        src0[1].clone(),
+        rep1[1].clone(),
+        src0[3].clone(),
+        rep0[0].clone(),
+        src1[3].clone(),
+        rep1[0].clone(),
+        rep0[1].clone(),
+    ];
+
+    let decoded = decode_blocks(k, mixed, &pool);
+    assert_eq!(decoded.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+    for (b, packets) in &decoded {
+        assert_eq!(packets.len(), k);
+        for (i, p) in packets.iter().enumerate() {
+            assert_eq!(p.id, b * k as u64 + i as u64);
+            assert_eq!(p.payload()[..8], [10 * *b as u8 + i as u8 + 1; 8]);
+        }
+    }
+}
+
+#[test]
+fn reactive_mode_sends_repairs_only_after_loss_report() {
+    use std::collections::VecDeque;
+    use std::time::{Duration, Instant};
+
+    let pool = Arc::new(MemoryPool::new(512, 64));
+    let config = FecConfig::builder()
+        .initial_mode(FecMode::Normal)
+        .reactive(true)
+        .max_recovery_delay(Duration::from_millis(250))
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut sender = AdaptiveFec::new(config.clone(), Arc::clone(&pool));
+    let mut receiver = AdaptiveFec::new(config, Arc::clone(&pool));
+    let k = sender.current_window();
+
+    // No repairs can be built before a full window was sent.
+    let mut partial = AdaptiveFec::new(config.clone(), Arc::clone(&pool));
+    let mut queue = VecDeque::new();
+    for i in 0..k as u64 - 1 {
+        partial.on_send(make_packet(i, i as u8 + 1, &pool), &mut queue);
+    }
+    queue.clear();
+    assert_eq!(
+        partial.report_receiver_loss(1, Instant::now(), &mut queue),
+        0
+    );
+    assert!(queue.is_empty());
+
+    // Lossless run: only sources leave the sender.
+    for i in 0..k as u64 {
+        sender.on_send(make_packet(i, i as u8 + 1, &pool), &mut queue);
+    }
+    assert_eq!(queue.len(), k);
+    assert!(queue.iter().all(|p| p.is_systematic));
+
+    // The receiver misses two sources and reports them.
+    let mut recovered = Vec::new();
+    for pkt in queue.drain(..) {
+        if pkt.id == 3 || pkt.id == 7 {
+            continue;
+        }
+        recovered.extend(receiver.on_receive(pkt).unwrap());
+    }
+    assert!(recovered.is_empty());
+
+    let now = Instant::now();
+    assert_eq!(sender.report_receiver_loss(2, now, &mut queue), 2);
+    assert_eq!(queue.len(), 2);
+    assert!(queue.iter().all(|p| !p.is_systematic));
+    for pkt in queue.drain(..) {
+        recovered.extend(receiver.on_receive(pkt).unwrap());
+    }
+    assert_eq!(recovered.len(), k);
+    assert_eq!(recovered[3].payload()[..8], [4; 8]);
+    assert_eq!(recovered[7].payload()[..8], [8; 8]);
+
+    // Past the recovery deadline the receiver has given up on the block.
+    let late = now + Duration::from_secs(1);
+    assert_eq!(sender.report_receiver_loss(2, late, &mut queue), 0);
+    assert!(queue.is_empty());
+
+    let invalid = FecConfig {
+        reactive: true,
+        systematic: false,
+        ..FecConfig::default()
+    };
+    assert!(invalid.validate().is_err());
+}
+
+#[test]
+fn min_dwell_time_bounds_mode_switch_rate() {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    let pool = Arc::new(MemoryPool::new(32, 64));
+    let config = |dwell: Duration| FecConfig {
+        lambda: 1.0,
+        pid: quicfuscate::fec::PidConfig {
+            kp: 1.0,
+            ki: 0.0,
+            kd: 0.0,
+        },
+        initial_mode: FecMode::Normal,
+        min_dwell_time: dwell,
+        autotune: false,
+        ..FecConfig::default()
+    };
+    let default_dwell = quicfuscate::fec::DEFAULT_MIN_DWELL;
+    let mut fast = AdaptiveFec::new(config(Duration::from_millis(20)), Arc::clone(&pool));
+    let mut slow = AdaptiveFec::new(config(default_dwell), Arc::clone(&pool));
+
+    // 45 % loss is far from every mode's target but below the emergency
+    // switch to Extreme, which ignores the dwell time.
+    sleep(Duration::from_millis(40));
+    fast.report_loss(45, 100);
+    slow.report_loss(45, 100);
+    let first = fast.current_mode();
+    assert_ne!(first, FecMode::Normal);
+    assert_eq!(slow.current_mode(), FecMode::Normal);
+
+    // Within the dwell window the new mode is kept despite the loss.
+    fast.report_loss(45, 100);
+    assert_eq!(fast.current_mode(), first);
+
+    sleep(Duration::from_millis(40));
+    fast.report_loss(45, 100);
+    assert_ne!(fast.current_mode(), first);
+    assert_eq!(slow.current_mode(), FecMode::Normal);
+
+    let parsed = FecConfig::from_toml("[adaptive_fec]\nmin_dwell_ms = 50\n").unwrap();
+    assert_eq!(parsed.min_dwell_time, Duration::from_millis(50));
+}
+
+#[test]
+fn gf8_decode_from_repairs_only() {
+    quicfuscate::fec::init_gf_tables();
+    let pool = Arc::new(MemoryPool::new(32, 64));
+    let k = 4;
+    let n = 8;
+    let mut enc = Encoder::new(k, n);
+    for i in 0..k {
+        enc.add_source_packet(make_packet(i as u64, i as u8 + 1, &pool));
+    }
+    let mut repairs = Vec::new();
+    for i in 0..(n - k) {
+        repairs.push(enc.generate_repair_packet(i, &pool).unwrap());
+    }
+    let mut dec = Decoder::new(k, Arc::clone(&pool));
+    // A retransmitted repair must not count towards the k rows.
+    dec.add_packet(repairs[0].clone()).unwrap();
+    dec.add_packet(repairs[0].clone()).unwrap();
+    assert!(!dec.is_decoded);
+    for r in repairs.into_iter().skip(1) {
+        dec.add_packet(r).unwrap();
+    }
+    assert!(dec.is_decoded);
+    let out = dec.get_decoded_packets();
+    assert_eq!(out.len(), k);
+    for i in 0..k {
+        assert_eq!(out[i].data.as_ref().unwrap()[..8], [i as u8 + 1; 8]);
+    }
+}
+
+#[test]
+fn gf16_decode_from_repairs_only() {
+    quicfuscate::fec::init_gf_tables();
+    let pool = Arc::new(MemoryPool::new(128, 64));
+    let k = 4;
+    let n = 2 * k;
+    let mut enc = Encoder16::new(k, n);
+    for i in 0..k {
+        enc.add_source_packet(make_packet(i as u64, 0x10 + i as u8, &pool));
+    }
+    let mut repairs = Vec::new();
+    for i in 0..(n - k) {
+        repairs.push(enc.generate_repair_packet(i, &pool).unwrap());
+    }
+    let mut dec = Decoder16::new(k, Arc::clone(&pool));
+    dec.add_packet(repairs[1].clone()).unwrap();
+    dec.add_packet(repairs[1].clone()).unwrap();
+    assert!(!dec.is_decoded);
+    for r in repairs {
+        dec.add_packet(r).unwrap();
+    }
+    assert!(dec.is_decoded);
+    let out = dec.get_decoded_packets();
+    assert_eq!(out.len(), k);
+    for i in 0..k {
+        assert_eq!(out[i].id, i as u64);
+        assert_eq!(out[i].data.as_ref().unwrap()[..8], [0x10 + i as u8; 8]);
+    }
+}
+
+#[test]
+fn gf16_decode_mixed_sources_and_repairs() {
+    quicfuscate::fec::init_gf_tables();
+    let pool = Arc::new(MemoryPool::new(128, 64));
+    let k = 4;
+    let n = k + 2;
+    let mut enc = Encoder16::new(k, n);
+    let mut packets = Vec::new();
+    for i in 0..k {
+        let p = make_packet(i as u64, 0xa0 + i as u8, &pool);
+        enc.add_source_packet(p.clone());
+        packets.push(p);
+    }
+    let mut dec = Decoder16::new(k, Arc::clone(&pool));
+    dec.add_packet(packets[1].clone()).unwrap();
+    dec.add_packet(packets[3].clone()).unwrap();
+    for i in 0..(n - k) {
+        dec.add_packet(enc.generate_repair_packet(i, &pool).unwrap())
+            .unwrap();
+    }
+    assert!(dec.is_decoded);
+    let out = dec.get_decoded_packets();
+    assert_eq!(out.len(), k);
+    for i in 0..k {
+        assert_eq!(out[i].data.as_ref().unwrap()[..8], [0xa0 + i as u8; 8]);
+    }
+}
+
+#[test]
+fn can_decode_checks_packet_count_without_decoding() {
+    use std::collections::VecDeque;
+
+    let pool = Arc::new(MemoryPool::new(512, 64));
+    let config = FecConfig::builder()
+        .initial_mode(FecMode::Normal)
+        .build()
+        .unwrap();
+    let mut sender = AdaptiveFec::new(config.clone(), Arc::clone(&pool));
+    let receiver = AdaptiveFec::new(config.clone(), Arc::clone(&pool));
+    let k = sender.current_window();
+    let r = sender.current_repair_count();
+    assert!(r >= 1);
+
+    let mut queue = VecDeque::new();
+    for i in 0..k as u64 {
+        sender.on_send(make_packet(i, i as u8 + 1, &pool), &mut queue);
+    }
+    let (sources, repairs): (Vec<_>, Vec<_>) = queue.into_iter().partition(|p| p.is_systematic);
+    assert_eq!(repairs.len(), r);
+
+    // Lose as many sources as there are repairs: exactly k packets remain.
+    let mut enough: Vec<_> = sources.into_iter().skip(r).collect();
+    enough.extend(repairs);
+    assert!(receiver.can_decode(&enough, k));
+
+    // One short, also when the gap is filled with a duplicate.
+    let mut short: Vec<_> = enough.iter().skip(1).map(|p| p.clone()).collect();
+    assert!(!receiver.can_decode(&short, k));
+    short.push(short.last().unwrap().clone());
+    assert!(!receiver.can_decode(&short, k));
+
+    // The verdict matches what the decoder actually does.
+    let mut decoder = AdaptiveFec::new(config, Arc::clone(&pool));
+    let mut recovered = Vec::new();
+    for pkt in enough {
+        recovered.extend(decoder.on_receive(pkt).unwrap());
+    }
+    assert_eq!(recovered.len(), k);
+}
+
+#[test]
+fn extreme_mode_recovers_odd_length_payloads() {
+    use std::collections::VecDeque;
+
+    let pool = Arc::new(MemoryPool::new(512, 64));
+    let config = FecConfig::builder()
+        .initial_mode(FecMode::Extreme)
+        .window_size(FecMode::Extreme, 4)
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut sender = AdaptiveFec::new(config.clone(), Arc::clone(&pool));
+    let mut receiver = AdaptiveFec::new(config, Arc::clone(&pool));
+    let k = sender.current_window();
+    assert_eq!(k, 4);
+
+    let payload = |i: u64| -> Vec<u8> { (0..9u8).map(|b| b * 16 + i as u8 + 1).collect() };
+    let mut queue = VecDeque::new();
+    for i in 0..k as u64 {
+        let mut pkt = make_packet(i, 0, &pool);
+        pkt.data.as_mut().unwrap()[..9].copy_from_slice(&payload(i));
+        pkt.data.as_mut().unwrap()[9..].fill(0xee);
+        pkt.len = 9;
+        sender.on_send(pkt, &mut queue);
+    }
+    assert!(queue.iter().any(|p| !p.is_systematic));
+
+    // Lose two sources so their last byte has to come out of the repairs.
+    let mut recovered = Vec::new();
+    for pkt in queue.drain(..) {
+        if pkt.is_systematic && (pkt.id == 1 || pkt.id == 2) {
+            continue;
+        }
+        recovered.extend(receiver.on_receive(pkt).unwrap());
+    }
+    assert_eq!(recovered.len(), k);
+    for (i, pkt) in recovered.iter().enumerate() {
+        assert!(pkt.len >= 9);
+        assert_eq!(pkt.payload()[..9], payload(i as u64)[..]);
+        assert_eq!(pkt.payload()[8], 8 * 16 + i as u8 + 1);
+    }
+}
+
+#[test]
+fn negotiation_converges_from_different_defaults() {
+    use quicfuscate::fec::{negotiate, FecAgreement, FecAlgorithm, FecOffer};
+
+    let client_cfg = FecConfig::builder()
+        .initial_mode(FecMode::Light)
+        .build()
+        .unwrap();
+    let server_cfg = FecConfig::builder()
+        .initial_mode(FecMode::Strong)
+        .systematic(false)
+        .max_window(256)
+        .unwrap()
+        .build()
+        .unwrap();
+    let client = FecOffer::from_config(&client_cfg);
+    let server = FecOffer::decode(&FecOffer::from_config(&server_cfg).encode()).unwrap();
+    assert_eq!(server, FecOffer::from_config(&server_cfg));
+
+    let on_client = negotiate(&client, &server);
+    let on_server = negotiate(&server, &client);
+    assert_eq!(on_client, on_server);
+    assert_eq!(
+        on_client,
+        FecAgreement::Enabled {
+            algorithm: FecAlgorithm::CauchyGf8,
+            mode: FecMode::Strong,
+            window: 16,
+            systematic: false,
+        }
+    );
+
+    let (mut a, mut b) = (client_cfg.clone(), server_cfg.clone());
+    on_client.apply(&mut a);
+    on_server.apply(&mut b);
+    assert_eq!(FecOffer::from_config(&a), FecOffer::from_config(&b));
+
+    // Without a shared GF(2^16) code Extreme falls back to Strong.
+    let mut gf8_only = server;
+    gf8_only.mode = FecMode::Extreme;
+    gf8_only.algorithms = FecAlgorithm::CauchyGf8.bit();
+    match negotiate(&client, &gf8_only) {
+        FecAgreement::Enabled {
+            mode, algorithm, ..
+        } => {
+            assert_eq!(mode, FecMode::Strong);
+            assert_eq!(algorithm, FecAlgorithm::CauchyGf8);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Irreconcilable offers disable FEC on both sides.
+    let mut other_version = server;
+    other_version.version += 1;
+    assert!(!negotiate(&client, &other_version).is_enabled());
+    let mut no_common = server;
+    no_common.algorithms = 0;
+    let disabled = negotiate(&no_common, &client);
+    assert!(!disabled.is_enabled());
+    disabled.apply(&mut a);
+    assert_eq!(a.initial_mode, FecMode::Zero);
+}
+
+#[test]
+fn malformed_frames_return_errors_instead_of_panicking() {
+    use quicfuscate::fec::Packet;
+    use quicfuscate::optimize::{OptimizationManager, OptimizeConfig};
+
+    let opt = OptimizationManager::from_cfg(OptimizeConfig {
+        pool_capacity: 4,
+        block_size: 64,
+        enable_xdp: false,
+        strict_pool_capacity: false,
+        congestion_control: quicfuscate::congestion::CcAlgorithm::Bbr,
+    });
+    let reject = |raw: &[u8]| {
+        let err = Packet::from_raw(0, raw, &opt).unwrap_err();
+        let mut block = opt.alloc_block();
+        block[..raw.len()].copy_from_slice(raw);
+        assert!(Packet::from_block(0, block, raw.len(), &opt).is_err());
+        err
+    };
+
+    // Truncated repair headers.
+    assert!(reject(&[0]).contains("coefficient length"));
+    assert!(reject(&[0, 0]).contains("coefficient length"));
+    // Coefficient length pointing past the end of the buffer.
+    assert!(reject(&[0, 0xff, 0xff, 1, 2, 3]).contains("coefficients"));
+    assert!(reject(&[0, 0, 4, 1, 2, 3]).contains("coefficients"));
+    // Zero-length payloads.
+    assert!(reject(&[1]).contains("empty payload"));
+    assert!(reject(&[0, 0, 2, 7, 9]).contains("empty payload"));
+    // Unknown packet type.
+    assert!(reject(&[7, 1, 2]).contains("unknown packet type"));
+
+    let pkt = Packet::from_raw(0, &[0, 0, 2, 7, 9, 0xab], &opt).unwrap();
+    assert_eq!(pkt.coeff_len, 2);
+    assert_eq!(pkt.payload(), &[0xab]);
+    assert_eq!(opt.memory_pool().in_use(), 2);
+}
+
+fn cm256_round_trip(scheme: &Cm256Scheme, shard_len: usize) {
+    let data: Vec<Vec<u8>> = (0..scheme.data_shards())
+        .map(|i| (0..shard_len).map(|b| (i * 31 + b * 7) as u8).collect())
+        .collect();
+    let refs: Vec<&[u8]> = data.iter().map(Vec::as_slice).collect();
+    let parity = scheme.encode(&refs).unwrap();
+    assert_eq!(parity.len(), scheme.parity_shards());
+
+    let mut shards: Vec<Option<Vec<u8>>> = data.iter().cloned().chain(parity).map(Some).collect();
+    // Lose as many data shards as there are parity shards.
+    for lost in shards.iter_mut().take(scheme.parity_shards()) {
+        *lost = None;
+    }
+    scheme.decode(&mut shards).unwrap();
+    for (restored, original) in shards.iter().zip(&data) {
+        assert_eq!(restored.as_ref(), Some(original));
+    }
+
+    let mut too_few: Vec<Option<Vec<u8>>> = vec![None; scheme.data_shards() + 1];
+    too_few.resize(
+        scheme.data_shards() + scheme.parity_shards(),
+        Some(vec![0; shard_len]),
+    );
+    assert!(scheme.decode(&mut too_few).is_err());
+}
+
+#[test]
+fn cm256_scalar_recovers_lost_shards() {
+    assert_eq!(
+        Cm256Backend::for_path(quicfuscate::fec::HwPath::Scalar),
+        Cm256Backend::Scalar
+    );
+    let scheme = Cm256Scheme::with_backend(4, 2, Cm256Backend::Scalar).unwrap();
+    cm256_round_trip(&scheme, 100);
+    assert!(Cm256Scheme::with_backend(200, 57, Cm256Backend::Scalar).is_err());
+}
+
+#[cfg(feature = "leopard")]
+#[test]
+fn cm256_leopard_recovers_lost_shards() {
+    let scheme = Cm256Scheme::with_backend(4, 2, Cm256Backend::Leopard).unwrap();
+    cm256_round_trip(&scheme, 128);
+}
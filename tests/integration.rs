@@ -1,4 +1,7 @@
-use quicfuscate::core::QuicFuscateConnection;
+use quicfuscate::core::{
+    bind_addr_for, parse_peer_addr, parse_peer_addrs, path_peer_addr, reconnect_with_backoff,
+    CoalescedDatagramBuilder, PaddingPolicy, QuicFuscateConnection, ReconnectPolicy,
+};
 use quicfuscate::fec::{FecConfig, FecMode};
 use quicfuscate::optimize::OptimizeConfig;
 use quicfuscate::stealth::StealthConfig;
@@ -8,6 +11,7 @@ use std::fs::File;
 use std::io::Write;
 use std::net::UdpSocket;
 use std::os::raw::c_void;
+use std::sync::{Arc, Mutex};
 
 #[tokio::test]
 async fn client_server_end_to_end() {
@@ -45,13 +49,7 @@ async fn client_server_end_to_end() {
         true,
     )
     .unwrap();
-    let mut server_config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
-    server_config
-        .load_cert_chain_from_pem_file("libs/patched_quiche/quiche/examples/cert.crt")
-        .unwrap();
-    server_config
-        .load_priv_key_from_pem_file("libs/patched_quiche/quiche/examples/cert.key")
-        .unwrap();
+    let mut server_config = server_quiche_config();
     server_config
         .set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
         .unwrap();
@@ -175,13 +173,7 @@ async fn tls_custom_clienthello() {
     )
     .unwrap();
 
-    let mut server_config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
-    server_config
-        .load_cert_chain_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.crt")
-        .unwrap();
-    server_config
-        .load_priv_key_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.key")
-        .unwrap();
+    let mut server_config = server_quiche_config();
     server_config.verify_peer(false);
 
     let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
@@ -246,13 +238,7 @@ async fn profile_rotation_changes_profile() {
     )
     .unwrap();
 
-    let mut server_config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
-    server_config
-        .load_cert_chain_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.crt")
-        .unwrap();
-    server_config
-        .load_priv_key_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.key")
-        .unwrap();
+    let mut server_config = server_quiche_config();
     server_config.verify_peer(false);
     let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
     let client_addr = client_socket.local_addr().unwrap();
@@ -335,13 +321,7 @@ async fn connection_migration_events() {
     .unwrap();
 
     let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
-    let mut srv_cfg = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
-    srv_cfg
-        .load_cert_chain_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.crt")
-        .unwrap();
-    srv_cfg
-        .load_priv_key_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.key")
-        .unwrap();
+    let srv_cfg = server_quiche_config();
 
     let mut server_conn = QuicFuscateConnection::new_server(
         &scid,
@@ -429,13 +409,7 @@ async fn real_quiche_end_to_end() {
     )
     .unwrap();
 
-    let mut server_config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
-    server_config
-        .load_cert_chain_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.crt")
-        .unwrap();
-    server_config
-        .load_priv_key_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.key")
-        .unwrap();
+    let mut server_config = server_quiche_config();
     server_config.verify_peer(false);
 
     let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
@@ -499,3 +473,1287 @@ async fn real_quiche_end_to_end() {
 
     assert!(telemetry::ENCODED_PACKETS.get() > 0);
 }
+
+fn memory_server(local: std::net::SocketAddr, peer: std::net::SocketAddr) -> QuicFuscateConnection {
+    memory_server_with_bidi_streams(local, peer, 100)
+}
+
+fn memory_server_with_bidi_streams(
+    local: std::net::SocketAddr,
+    peer: std::net::SocketAddr,
+    max_streams_bidi: u64,
+) -> QuicFuscateConnection {
+    let mut server_config = server_quiche_config();
+    server_config
+        .set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
+        .unwrap();
+    server_config.set_initial_max_data(1_000_000);
+    server_config.set_initial_max_stream_data_bidi_local(1_000_000);
+    server_config.set_initial_max_stream_data_bidi_remote(1_000_000);
+    server_config.set_initial_max_streams_bidi(max_streams_bidi);
+    server_config.set_initial_max_streams_uni(100);
+    let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
+    QuicFuscateConnection::new_server(
+        &scid,
+        None,
+        local,
+        peer,
+        server_config,
+        StealthConfig::default(),
+        FecConfig::default(),
+        OptimizeConfig::default(),
+    )
+    .unwrap()
+}
+
+fn memory_client(
+    local: std::net::SocketAddr,
+    server: std::net::SocketAddr,
+) -> QuicFuscateConnection {
+    let mut cfg = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    cfg.set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
+        .unwrap();
+    cfg.set_initial_max_data(1_000_000);
+    cfg.set_initial_max_stream_data_bidi_local(1_000_000);
+    cfg.set_initial_max_stream_data_bidi_remote(1_000_000);
+    cfg.set_initial_max_streams_bidi(100);
+    cfg.set_initial_max_streams_uni(100);
+    cfg.verify_peer(false);
+    QuicFuscateConnection::new_client(
+        "example.com",
+        local,
+        server,
+        cfg,
+        StealthConfig::default(),
+        FecConfig::default(),
+        OptimizeConfig::default(),
+        true,
+    )
+    .unwrap()
+}
+
+/// quiche config with the example certificate and key loaded, for the
+/// server side of a test connection.
+fn server_quiche_config() -> quiche::Config {
+    let mut config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    config
+        .load_cert_chain_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.crt")
+        .unwrap();
+    config
+        .load_priv_key_from_pem_file("libs/vanilla_quiche/quiche/examples/cert.key")
+        .unwrap();
+    config
+}
+
+/// Hands everything `client` and `server` have queued to the other side,
+/// once in each direction. Returns `false` if neither had anything to send.
+fn pump(client: &mut QuicFuscateConnection, server: &mut QuicFuscateConnection) -> bool {
+    let (client_addr, server_addr) = (server.peer_addr, client.peer_addr);
+    pump_on_path(client, server, client_addr, server_addr)
+}
+
+/// Like [`pump`], with the datagrams arriving on the path between
+/// `client_addr` and `server_addr` instead of the active one.
+fn pump_on_path(
+    client: &mut QuicFuscateConnection,
+    server: &mut QuicFuscateConnection,
+    client_addr: std::net::SocketAddr,
+    server_addr: std::net::SocketAddr,
+) -> bool {
+    let mut out = [0u8; 65535];
+    let mut moved = false;
+    while let Ok(len) = client.send(&mut out) {
+        if len == 0 {
+            break;
+        }
+        moved = true;
+        server
+            .recv_on_path(&out[..len], client_addr, server_addr)
+            .ok();
+    }
+    while let Ok(len) = server.send(&mut out) {
+        if len == 0 {
+            break;
+        }
+        moved = true;
+        client
+            .recv_on_path(&out[..len], server_addr, client_addr)
+            .ok();
+    }
+    moved
+}
+
+/// Pumps until neither side has anything left to send.
+fn pump_until_idle(client: &mut QuicFuscateConnection, server: &mut QuicFuscateConnection) {
+    for _ in 0..50 {
+        if !pump(client, server) {
+            break;
+        }
+    }
+}
+
+/// Pumps until both sides have completed the handshake.
+fn complete_handshake(client: &mut QuicFuscateConnection, server: &mut QuicFuscateConnection) {
+    for _ in 0..50 {
+        pump(client, server);
+        if client.conn.is_established() && server.conn.is_established() {
+            return;
+        }
+    }
+    panic!("handshake did not complete");
+}
+
+#[test]
+fn invalid_stealth_config_fails_connection_setup() {
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5013".parse().unwrap();
+    let stealth = StealthConfig {
+        doh_provider: "http://dns.example/dns-query".into(),
+        ..StealthConfig::default()
+    };
+
+    let mut cfg = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    cfg.verify_peer(false);
+    let err = QuicFuscateConnection::new_client(
+        "example.com",
+        client_addr,
+        server_addr,
+        cfg,
+        stealth.clone(),
+        FecConfig::default(),
+        OptimizeConfig::default(),
+        true,
+    )
+    .err()
+    .expect("client accepted an invalid DoH provider");
+    assert!(err.contains("Stealth initialization failed"), "{}", err);
+
+    let cfg = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
+    assert!(QuicFuscateConnection::new_server(
+        &scid,
+        None,
+        server_addr,
+        client_addr,
+        cfg,
+        stealth,
+        FecConfig::default(),
+        OptimizeConfig::default(),
+    )
+    .is_err());
+}
+
+#[test]
+fn drain_refuses_new_peers_and_serves_existing() {
+    use quicfuscate::core::ServerDrain;
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    // Packets are handed over in memory instead of via sockets.
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let old_addr: std::net::SocketAddr = "127.0.0.1:5000".parse().unwrap();
+    let new_addr: std::net::SocketAddr = "127.0.0.1:5001".parse().unwrap();
+
+    let mut clients: HashMap<std::net::SocketAddr, QuicFuscateConnection> = HashMap::new();
+    let mut old_client = memory_client(old_addr, server_addr);
+    let mut new_client = memory_client(new_addr, server_addr);
+    let mut drain = ServerDrain::new();
+    let mut out = [0u8; 65535];
+
+    // Datagrams go through the same admission the server loop uses.
+    let deliver = |clients: &mut HashMap<_, QuicFuscateConnection>,
+                   drain: &ServerDrain,
+                   from: std::net::SocketAddr,
+                   pkt: &[u8]| {
+        match drain.admit(clients, from, || memory_server(server_addr, from)) {
+            Some(conn) => {
+                conn.recv(pkt).ok();
+                true
+            }
+            None => false,
+        }
+    };
+
+    // The existing peer sends its first flight before the drain starts.
+    let len = old_client.send(&mut out).unwrap();
+    assert!(deliver(&mut clients, &drain, old_addr, &out[..len]));
+
+    drain.start(Duration::from_secs(30), Instant::now());
+    assert!(drain.is_draining());
+
+    // A new peer is refused while draining.
+    let len = new_client.send(&mut out).unwrap();
+    assert!(!deliver(&mut clients, &drain, new_addr, &out[..len]));
+    assert_eq!(clients.len(), 1);
+
+    // The existing peer can still complete the handshake and a request.
+    let mut request_sent = false;
+    for _ in 0..200 {
+        while let Ok(len) = old_client.send(&mut out) {
+            if len == 0 {
+                break;
+            }
+            assert!(deliver(&mut clients, &drain, old_addr, &out[..len]));
+        }
+        let server = clients.get_mut(&old_addr).unwrap();
+        server.poll_http3().ok();
+        while let Ok(len) = server.send(&mut out) {
+            if len == 0 {
+                break;
+            }
+            old_client.recv(&out[..len]).ok();
+        }
+        if old_client.conn.is_established() && !request_sent {
+            old_client.send_http3_request("/").unwrap();
+            request_sent = true;
+        }
+        old_client.poll_http3().ok();
+        if request_sent && server.conn.is_established() {
+            break;
+        }
+    }
+    assert!(request_sent);
+    assert!(clients[&old_addr].conn.is_established());
+    assert!(!drain.finished(Instant::now(), clients.len()));
+    assert!(drain.finished(Instant::now() + Duration::from_secs(31), clients.len()));
+    assert!(drain.finished(Instant::now(), 0));
+}
+
+#[test]
+fn close_reason_reports_code_and_phrase() {
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5002".parse().unwrap();
+    let mut client = memory_client(client_addr, server_addr);
+    let mut server = memory_server(server_addr, client_addr);
+
+    complete_handshake(&mut client, &mut server);
+    assert_eq!(server.close_reason(), None);
+
+    client.conn.close(true, 0x42, b"going away").unwrap();
+    pump(&mut client, &mut server);
+
+    let expected = Some((0x42, "going away".to_string()));
+    assert_eq!(client.close_reason(), expected);
+    assert_eq!(server.close_reason(), expected);
+}
+
+#[test]
+fn concurrent_http3_requests_use_distinct_streams() {
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5003".parse().unwrap();
+    let mut client = memory_client(client_addr, server_addr);
+    let mut server = memory_server(server_addr, client_addr);
+
+    complete_handshake(&mut client, &mut server);
+    server.init_http3().unwrap();
+
+    let ids = client.send_http3_requests(&["/a.css", "/b.js"]).unwrap();
+    assert_eq!(ids.len(), 2);
+    assert_ne!(ids[0], ids[1]);
+
+    let mut responses = std::collections::HashMap::new();
+    for _ in 0..50 {
+        pump(&mut client, &mut server);
+        server.poll_http3().ok();
+        for (id, _) in server.take_completed_http3_streams() {
+            server
+                .send_http3_response(id, 200, format!("body-{}", id).as_bytes())
+                .unwrap();
+        }
+        pump(&mut client, &mut server);
+        client.poll_http3().ok();
+        responses.extend(client.take_completed_http3_streams());
+        if responses.len() == 2 {
+            break;
+        }
+    }
+
+    assert_eq!(responses.len(), 2);
+    for id in &ids {
+        assert_eq!(responses[id].body, format!("body-{}", id).into_bytes());
+    }
+    assert_eq!(responses[&ids[0]].path.as_deref(), Some("/a.css"));
+    assert_eq!(responses[&ids[1]].path.as_deref(), Some("/b.js"));
+}
+
+#[test]
+fn http3_requests_beyond_stream_limit_wait_for_credit() {
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5014".parse().unwrap();
+    let mut client = memory_client(client_addr, server_addr);
+    let mut server = memory_server_with_bidi_streams(server_addr, client_addr, 2);
+
+    complete_handshake(&mut client, &mut server);
+    server.init_http3().unwrap();
+
+    let ids = client
+        .send_http3_requests(&["/a.css", "/b.js", "/c.png"])
+        .unwrap();
+    assert_eq!(ids.len(), 2);
+    assert_eq!(client.pending_http3_requests(), 1);
+    let path = |c: &QuicFuscateConnection, id| c.http3_stream(id).and_then(|s| s.path.clone());
+    assert_eq!(path(&client, ids[0]).as_deref(), Some("/a.css"));
+    assert_eq!(path(&client, ids[1]).as_deref(), Some("/b.js"));
+
+    let mut responses = std::collections::HashMap::new();
+    for _ in 0..100 {
+        pump(&mut client, &mut server);
+        server.poll_http3().ok();
+        for (id, _) in server.take_completed_http3_streams() {
+            server
+                .send_http3_response(id, 200, format!("body-{}", id).as_bytes())
+                .unwrap();
+        }
+        pump(&mut client, &mut server);
+        client.poll_http3().ok();
+        responses.extend(client.take_completed_http3_streams());
+        if responses.len() == 3 {
+            break;
+        }
+    }
+
+    assert_eq!(client.pending_http3_requests(), 0);
+    assert_eq!(responses.len(), 3);
+    for (id, stream) in &responses {
+        assert_eq!(stream.body, format!("body-{}", id).into_bytes());
+    }
+    let mut paths: Vec<_> = responses.values().filter_map(|s| s.path.clone()).collect();
+    paths.sort();
+    assert_eq!(paths, ["/a.css", "/b.js", "/c.png"]);
+}
+
+#[test]
+fn fec_offer_cut_short_by_flow_control_is_completed() {
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5015".parse().unwrap();
+    // Less stream credit than one offer, so the first write is partial.
+    let configure = |config: &mut quiche::Config| {
+        config
+            .set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
+            .unwrap();
+        config.set_initial_max_data(1_000_000);
+        config.set_initial_max_streams_uni(100);
+        config.set_initial_max_stream_data_uni(4);
+    };
+    let mut server_config = server_quiche_config();
+    configure(&mut server_config);
+    let mut client_config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    configure(&mut client_config);
+    client_config.verify_peer(false);
+    let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
+    let mut server = QuicFuscateConnection::new_server(
+        &scid,
+        None,
+        server_addr,
+        client_addr,
+        server_config,
+        StealthConfig::default(),
+        FecConfig::default(),
+        OptimizeConfig::default(),
+    )
+    .unwrap();
+    let mut client = QuicFuscateConnection::new_client(
+        "example.com",
+        client_addr,
+        server_addr,
+        client_config,
+        StealthConfig::default(),
+        FecConfig::default(),
+        OptimizeConfig::default(),
+        true,
+    )
+    .unwrap();
+
+    for _ in 0..50 {
+        pump(&mut client, &mut server);
+        if client.fec_agreement().is_some() && server.fec_agreement().is_some() {
+            break;
+        }
+    }
+
+    let agreement = client
+        .fec_agreement()
+        .expect("client never saw the full offer");
+    assert!(agreement.is_enabled());
+    assert_eq!(server.fec_agreement(), Some(agreement));
+}
+
+#[test]
+fn switching_congestion_control_swaps_controller() {
+    use quicfuscate::congestion::CcAlgorithm;
+    use std::time::{Duration, Instant};
+
+    let server: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let local: std::net::SocketAddr = "127.0.0.1:5000".parse().unwrap();
+    let mut client = memory_client(local, server);
+    assert_eq!(client.congestion_control(), CcAlgorithm::Bbr);
+
+    let now = Instant::now();
+    client.set_congestion_control(CcAlgorithm::Reno);
+    assert_eq!(client.congestion_control(), CcAlgorithm::Reno);
+    let before = client.congestion_window();
+    client.on_packet_acknowledged(1350, Duration::from_millis(20), now);
+    client.on_packet_lost(1350, now);
+    assert!(client.congestion_window() < before);
+
+    // Switching installs a fresh controller with the initial window.
+    client.set_congestion_control(CcAlgorithm::Cubic);
+    assert_eq!(client.congestion_control(), CcAlgorithm::Cubic);
+    assert_eq!(client.congestion_window(), before);
+}
+
+#[test]
+fn configured_congestion_control_reaches_new_connections() {
+    use quicfuscate::congestion::CcAlgorithm;
+
+    let mut cfg = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    cfg.set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
+        .unwrap();
+    cfg.verify_peer(false);
+    let client = QuicFuscateConnection::new_client(
+        "example.com",
+        "127.0.0.1:5000".parse().unwrap(),
+        "127.0.0.1:4433".parse().unwrap(),
+        cfg,
+        StealthConfig::default(),
+        FecConfig::default(),
+        OptimizeConfig {
+            congestion_control: CcAlgorithm::Cubic,
+            ..OptimizeConfig::default()
+        },
+        true,
+    )
+    .unwrap();
+    assert_eq!(client.congestion_control(), CcAlgorithm::Cubic);
+}
+
+#[test]
+fn oversized_fec_repair_routes_to_stream_under_auto() {
+    use quicfuscate::core::{FecTransport, FecTransportPolicy};
+
+    let server: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let local: std::net::SocketAddr = "127.0.0.1:5000".parse().unwrap();
+    let mut client = memory_client(local, server);
+    assert_eq!(client.fec_transport_policy(), FecTransportPolicy::Auto);
+
+    let auto = FecTransportPolicy::Auto;
+    assert_eq!(auto.route(1400, Some(1200)), FecTransport::Stream);
+    assert_eq!(auto.route(900, Some(1200)), FecTransport::Datagram);
+    assert_eq!(auto.route(900, None), FecTransport::Stream);
+    assert_eq!(
+        FecTransportPolicy::DatagramOnly.route(1400, Some(1200)),
+        FecTransport::Datagram
+    );
+    assert_eq!(
+        FecTransportPolicy::StreamOnly.route(100, Some(1200)),
+        FecTransport::Stream
+    );
+
+    client.set_fec_transport_policy(FecTransportPolicy::StreamOnly);
+    assert_eq!(
+        client.fec_transport_policy(),
+        FecTransportPolicy::StreamOnly
+    );
+}
+
+#[test]
+fn stream_repairs_survive_partial_writes() {
+    use quicfuscate::core::{FecTransport, FecTransportPolicy};
+    use quicfuscate::fec::Packet;
+
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5050".parse().unwrap();
+    let mut server_config = server_quiche_config();
+    server_config
+        .set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
+        .unwrap();
+    server_config.set_initial_max_data(1_000_000);
+    server_config.set_initial_max_streams_uni(100);
+    // Less credit than one burst of repairs, so writes are cut short.
+    server_config.set_initial_max_stream_data_uni(1000);
+    let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
+    let mut server = QuicFuscateConnection::new_server(
+        &scid,
+        None,
+        server_addr,
+        client_addr,
+        server_config,
+        StealthConfig::default(),
+        FecConfig::default(),
+        OptimizeConfig::default(),
+    )
+    .unwrap();
+    let mut client = memory_client(client_addr, server_addr);
+
+    complete_handshake(&mut client, &mut server);
+
+    let mgr = quicfuscate::optimize::OptimizationManager::new();
+    let pool = mgr.memory_pool();
+    client.set_fec_transport_policy(FecTransportPolicy::StreamOnly);
+    for id in 0..3 {
+        let mut data = pool.alloc();
+        data[..600].fill(id as u8);
+        let mut coefficients = pool.alloc();
+        coefficients[..4].copy_from_slice(&[1, 2, 3, 4]);
+        let repair = Packet {
+            id,
+            data: Some(data),
+            len: 600,
+            is_systematic: false,
+            coefficients: Some(coefficients),
+            coeff_len: 4,
+            mem_pool: Arc::clone(&pool),
+        };
+        assert_eq!(
+            client.send_fec_repair(&repair).unwrap(),
+            FecTransport::Stream
+        );
+    }
+
+    pump_until_idle(&mut client, &mut server);
+    assert_eq!(server.fec_stream_repairs(), 3);
+}
+
+/// Client and server with DATAGRAM support, past the handshake.
+fn established_dgram_pair(
+    client_addr: std::net::SocketAddr,
+) -> (QuicFuscateConnection, QuicFuscateConnection) {
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let mut server_config = server_quiche_config();
+    server_config
+        .set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
+        .unwrap();
+    server_config.set_initial_max_data(1_000_000);
+    server_config.enable_dgram(true, 100, 100);
+    let mut client_config = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    client_config
+        .set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
+        .unwrap();
+    client_config.set_initial_max_data(1_000_000);
+    client_config.enable_dgram(true, 100, 100);
+    client_config.verify_peer(false);
+    let scid = quiche::ConnectionId::from_ref(&[0; quiche::MAX_CONN_ID_LEN]);
+    let mut server = QuicFuscateConnection::new_server(
+        &scid,
+        None,
+        server_addr,
+        client_addr,
+        server_config,
+        StealthConfig::default(),
+        FecConfig::default(),
+        OptimizeConfig::default(),
+    )
+    .unwrap();
+    let mut client = QuicFuscateConnection::new_client(
+        "example.com",
+        client_addr,
+        server_addr,
+        client_config,
+        StealthConfig::default(),
+        FecConfig::default(),
+        OptimizeConfig::default(),
+        true,
+    )
+    .unwrap();
+
+    complete_handshake(&mut client, &mut server);
+    (client, server)
+}
+
+#[test]
+fn send_rate_hint_paces_queued_datagrams() {
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5060".parse().unwrap();
+    let (mut client, _server) = established_dgram_pair(client_addr);
+    let mut out = [0u8; 65535];
+
+    // The lowest rate still allows a burst of `SendPacer::MIN_BURST` bytes.
+    client.stealth_manager().set_send_rate_hint(1);
+    for _ in 0..10 {
+        client.datagrams().send(&[0xd6; 1000]).unwrap();
+    }
+    client.send(&mut out).ok();
+    assert_eq!(client.datagrams().pending_outgoing(), 8);
+
+    // Without a hint the rest goes out.
+    client.stealth_manager().set_send_rate_hint(0);
+    client.send(&mut out).ok();
+    assert_eq!(client.datagrams().pending_outgoing(), 0);
+}
+
+#[test]
+fn send_rate_hint_survives_state_updates() {
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5062".parse().unwrap();
+    let (mut client, _server) = established_dgram_pair(client_addr);
+    let stealth = client.stealth_manager();
+    let pacing = client.pacing_rate().expect("established path has a rate");
+    assert_ne!(pacing, 0);
+
+    // Pinned hints, including `0` for no pacing, outlive state updates.
+    for hint in [0, 1] {
+        stealth.set_send_rate_hint(hint);
+        client.update_state();
+        assert_eq!(stealth.send_rate_hint(), hint);
+    }
+    let mut out = [0u8; 65535];
+    for _ in 0..10 {
+        client.datagrams().send(&[0xd6; 1000]).unwrap();
+    }
+    client.update_state();
+    client.send(&mut out).ok();
+    assert_eq!(client.datagrams().pending_outgoing(), 8);
+
+    // Once cleared, the pacer follows the connection again.
+    stealth.clear_send_rate_hint();
+    client.update_state();
+    assert_eq!(stealth.send_rate_hint(), client.pacing_rate().unwrap());
+}
+
+#[test]
+fn send_loop_emits_cover_traffic_the_peer_drops() {
+    use quicfuscate::stealth::SizeDistribution;
+
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5061".parse().unwrap();
+    let (mut client, mut server) = established_dgram_pair(client_addr);
+    let sender = client.stealth_manager();
+    let receiver = server.stealth_manager();
+    sender.enable_cover_traffic(200.0, SizeDistribution::Fixed(100));
+    sender.seed_cover_traffic(11);
+    receiver.enable_cover_traffic(0.0, SizeDistribution::Fixed(1));
+
+    // The first send only schedules the first decoy.
+    let mut out = [0u8; 65535];
+    client.send(&mut out).ok();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    for _ in 0..5 {
+        while let Ok(len) = client.send(&mut out) {
+            if len == 0 {
+                break;
+            }
+            server.recv(&out[..len]).ok();
+        }
+    }
+
+    assert!(sender.cover_packets_sent() > 0);
+    assert!(receiver.recv_and_deobfuscate(server.datagrams()).is_none());
+    assert_eq!(
+        receiver.cover_packets_dropped(),
+        sender.cover_packets_sent()
+    );
+}
+
+#[test]
+fn initial_rtt_seeds_pacing() {
+    use quicfuscate::congestion::CcAlgorithm;
+    use std::time::{Duration, Instant};
+
+    let server: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let local: std::net::SocketAddr = "127.0.0.1:5000".parse().unwrap();
+    let mut client = memory_client(local, server);
+    assert_eq!(client.rtt_estimate(), None);
+
+    let seed = Duration::from_millis(600);
+    client.set_initial_rtt(seed);
+    let cwnd = client.congestion_window() as f64;
+    assert_eq!(
+        client.pacing_rate(),
+        Some((cwnd / seed.as_secs_f64()) as u64)
+    );
+
+    // Switching controllers keeps the seed.
+    client.set_congestion_control(CcAlgorithm::Cubic);
+    assert_eq!(client.rtt_estimate(), Some(seed));
+
+    client.on_packet_acknowledged(1350, Duration::from_millis(40), Instant::now());
+    assert_eq!(client.rtt_estimate(), Some(Duration::from_millis(40)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn happy_eyeballs_keeps_fastest_candidate() {
+    use quicfuscate::core::{connect_racing, interleave_addrs};
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let v6: SocketAddr = "[::1]:443".parse().unwrap();
+    let v4: SocketAddr = "127.0.0.1:443".parse().unwrap();
+    let v4b: SocketAddr = "127.0.0.2:443".parse().unwrap();
+    assert_eq!(interleave_addrs(&[v4, v4b, v6]), vec![v6, v4, v4b]);
+
+    // The IPv6 candidate starts first but its handshake is slow; the IPv4
+    // candidate started one stagger later completes first.
+    let finished = Arc::new(AtomicUsize::new(0));
+    let counter = finished.clone();
+    let (winner, conn) = connect_racing(&[v4, v6], Duration::from_millis(20), move |addr| {
+        let counter = counter.clone();
+        async move {
+            let rtt = if addr.is_ipv6() { 500 } else { 30 };
+            tokio::time::sleep(Duration::from_millis(rtt)).await;
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, std::io::Error>(format!("conn-{}", addr))
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(winner, v4);
+    assert_eq!(conn, format!("conn-{}", v4));
+
+    // The losing attempt was cancelled rather than completed.
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(finished.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn rotated_connection_id_is_used_for_sends() {
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5010".parse().unwrap();
+    let mut client = memory_client(client_addr, server_addr);
+    let mut server = memory_server(server_addr, client_addr);
+
+    complete_handshake(&mut client, &mut server);
+
+    let old = client.connection_id();
+    let new = client.rotate_connection_id().unwrap();
+    assert_ne!(new, old);
+    assert_eq!(new.len(), quiche::MAX_CONN_ID_LEN);
+
+    // Once the server has handed out a spare ID, the next rotation on the
+    // client switches its sends over to it.
+    let server_new = server.rotate_connection_id().unwrap();
+    pump_until_idle(&mut client, &mut server);
+    assert_ne!(client.peer_connection_id(), server_new);
+    client.rotate_connection_id().unwrap();
+    assert_eq!(client.peer_connection_id(), server_new);
+
+    pump_until_idle(&mut client, &mut server);
+    assert!(client.conn.is_established());
+    assert!(server.conn.is_established());
+}
+
+#[test]
+fn io_observer_sees_sent_and_received_bytes() {
+    use quicfuscate::datagram::Direction;
+    use std::sync::{Arc, Mutex};
+
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5011".parse().unwrap();
+    let mut client = memory_client(client_addr, server_addr);
+    let mut server = memory_server(server_addr, client_addr);
+
+    let seen: Arc<Mutex<Vec<(Direction, Vec<u8>)>>> = Arc::default();
+    let log = Arc::clone(&seen);
+    client.set_io_observer(move |dir, bytes| log.lock().unwrap().push((dir, bytes.to_vec())));
+
+    let mut out = [0u8; 65535];
+    let len = client.send(&mut out).unwrap();
+    assert!(len > 0);
+    server.recv(&out[..len]).unwrap();
+
+    let mut reply = [0u8; 65535];
+    let reply_len = server.send(&mut reply).unwrap();
+    assert!(reply_len > 0);
+    client.recv(&reply[..reply_len]).unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    assert_eq!(seen[0], (Direction::Outbound, out[..len].to_vec()));
+    assert_eq!(seen[1], (Direction::Inbound, reply[..reply_len].to_vec()));
+}
+
+#[test]
+fn send_reports_path_of_datagram() {
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5012".parse().unwrap();
+    let mut client = memory_client(client_addr, server_addr);
+    let mut server = memory_server(server_addr, client_addr);
+
+    let mut out = [0u8; 65535];
+    let (len, info) = client.send_with_info(&mut out).unwrap();
+    assert!(len > 0);
+    assert_eq!((info.from, info.to), (client_addr, server_addr));
+    server.recv(&out[..len]).unwrap();
+
+    let (len, info) = server.send_with_info(&mut out).unwrap();
+    assert!(len > 0);
+    assert_eq!((info.from, info.to), (server_addr, client_addr));
+}
+
+/// Records the `peer` field of every span and the span each event ran in.
+#[derive(Default, Clone)]
+struct SpanCapture {
+    spans: Arc<Mutex<Vec<(String, Option<String>)>>>,
+    current: Arc<Mutex<Option<u64>>>,
+    events: Arc<Mutex<Vec<Option<u64>>>>,
+}
+
+struct PeerField(Option<String>);
+
+impl tracing::field::Visit for PeerField {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "peer" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+impl tracing::Subscriber for SpanCapture {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        let mut peer = PeerField(None);
+        attrs.record(&mut peer);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((attrs.metadata().name().to_string(), peer.0));
+        tracing::span::Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, _: &tracing::Event<'_>) {
+        let current = *self.current.lock().unwrap();
+        self.events.lock().unwrap().push(current);
+    }
+
+    fn enter(&self, span: &tracing::span::Id) {
+        *self.current.lock().unwrap() = Some(span.into_u64());
+    }
+
+    fn exit(&self, _: &tracing::span::Id) {
+        *self.current.lock().unwrap() = None;
+    }
+}
+
+#[test]
+fn connection_span_carries_peer_address() {
+    let capture = SpanCapture::default();
+    let peer: std::net::SocketAddr = "127.0.0.1:50123".parse().unwrap();
+    let local: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+
+    tracing::subscriber::with_default(capture.clone(), || {
+        let server = memory_server(local, peer);
+        server.span().in_scope(|| tracing::info!("inside"));
+    });
+
+    let spans = capture.spans.lock().unwrap();
+    let id = spans
+        .iter()
+        .position(|(name, _)| name == "connection")
+        .expect("connection span created");
+    assert_eq!(spans[id].1.as_deref(), Some("127.0.0.1:50123"));
+    assert!(capture
+        .events
+        .lock()
+        .unwrap()
+        .contains(&Some(id as u64 + 1)));
+}
+
+/// Long-header Handshake packet with a `payload_len` byte body.
+fn handshake_packet(payload_len: usize) -> Vec<u8> {
+    let mut pkt = vec![0xe0, 0x00, 0x00, 0x00, 0x01, 8];
+    pkt.extend_from_slice(&[0xaa; 8]);
+    pkt.push(8);
+    pkt.extend_from_slice(&[0xbb; 8]);
+    pkt.extend_from_slice(&(0x4000 | payload_len as u16).to_be_bytes());
+    pkt.extend(std::iter::repeat(0x5a).take(payload_len));
+    pkt
+}
+
+#[test]
+fn small_packets_are_coalesced_under_mtu() {
+    let first = handshake_packet(40);
+    let second = handshake_packet(60);
+    let mut buf = vec![0u8; 1500];
+    let mut datagram = CoalescedDatagramBuilder::new(&mut buf, 1200);
+    assert!(datagram.try_push(&first));
+    assert!(datagram.try_push(&second));
+    assert_eq!(datagram.packet_count(), 2);
+    assert_eq!(datagram.len(), first.len() + second.len());
+    assert_eq!(&datagram.as_slice()[..first.len()], &first[..]);
+    assert!(datagram.can_extend());
+}
+
+#[test]
+fn packets_exceeding_mtu_are_split() {
+    let first = handshake_packet(40);
+    let second = handshake_packet(60);
+    let mtu = first.len() + second.len() - 1;
+    let mut buf = vec![0u8; 1500];
+
+    let mut datagram = CoalescedDatagramBuilder::new(&mut buf, mtu);
+    assert!(datagram.try_push(&first));
+    assert!(!datagram.try_push(&second));
+    assert_eq!(datagram.packet_count(), 1);
+    assert_eq!(datagram.len(), first.len());
+
+    let mut next = CoalescedDatagramBuilder::new(&mut buf, mtu);
+    assert!(next.try_push(&second));
+    assert_eq!(next.packet_count(), 1);
+}
+
+#[test]
+fn short_header_packet_ends_datagram() {
+    let mut buf = vec![0u8; 1500];
+    let mut datagram = CoalescedDatagramBuilder::new(&mut buf, 1200);
+    assert!(datagram.try_push(&handshake_packet(40)));
+    assert!(datagram.try_push(&[0x40, 1, 2, 3, 4]));
+    assert!(!datagram.can_extend());
+    assert!(!datagram.try_push(&handshake_packet(10)));
+    assert_eq!(datagram.packet_count(), 2);
+}
+
+#[test]
+fn pad_to_mtu_fills_datagram() {
+    let mut buf = vec![0u8; 1500];
+    let mut datagram = CoalescedDatagramBuilder::new(&mut buf, 1200);
+    assert!(datagram.try_push(&handshake_packet(40)));
+    let target = PaddingPolicy::PadToMtu.target_len(datagram.len(), 1200);
+    assert_eq!(target, 1200);
+    datagram.pad_to(target);
+    assert_eq!(datagram.len(), 1200);
+    assert!(!datagram.can_extend());
+}
+
+#[test]
+fn pad_to_bucket_rounds_up_to_nearest_size() {
+    let policy = PaddingPolicy::PadToBucket(vec![1252, 300, 600]);
+    assert_eq!(policy.target_len(100, 1350), 300);
+    assert_eq!(policy.target_len(300, 1350), 300);
+    assert_eq!(policy.target_len(301, 1350), 600);
+    assert_eq!(policy.target_len(1000, 1200), 1200, "capped at the MTU");
+    assert_eq!(
+        policy.target_len(1300, 1350),
+        1300,
+        "larger than every bucket"
+    );
+    assert_eq!(PaddingPolicy::None.target_len(100, 1200), 100);
+}
+
+#[test]
+fn padding_skipped_after_short_header_packet() {
+    let mut buf = vec![0u8; 1500];
+    let mut datagram = CoalescedDatagramBuilder::new(&mut buf, 1200);
+    assert!(datagram.try_push(&[0x40, 1, 2, 3, 4]));
+    assert_eq!(datagram.pad_to(1200), 0);
+    assert_eq!(datagram.len(), 5);
+}
+
+#[test]
+fn connection_padding_policy_is_configurable() {
+    let peer: std::net::SocketAddr = "127.0.0.1:50124".parse().unwrap();
+    let local: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let mut conn = memory_server(local, peer);
+    assert_eq!(conn.padding_policy(), &PaddingPolicy::None);
+    conn.set_padding_policy(PaddingPolicy::PadToBucket(vec![600, 1200]));
+    assert_eq!(
+        conn.padding_policy(),
+        &PaddingPolicy::PadToBucket(vec![600, 1200])
+    );
+}
+
+/// Transport double whose handshake fails a fixed number of times.
+struct FlakyTransport {
+    failures_left: Mutex<u32>,
+    handshakes: Mutex<Vec<u32>>,
+}
+
+impl FlakyTransport {
+    fn new(failures: u32) -> Self {
+        Self {
+            failures_left: Mutex::new(failures),
+            handshakes: Mutex::new(Vec::new()),
+        }
+    }
+
+    async fn handshake(&self, attempt: u32) -> Result<&'static str, String> {
+        self.handshakes.lock().unwrap().push(attempt);
+        let mut left = self.failures_left.lock().unwrap();
+        if *left > 0 {
+            *left -= 1;
+            return Err("handshake timed out".into());
+        }
+        Ok("established")
+    }
+}
+
+#[tokio::test]
+async fn reconnect_retries_failed_handshake_with_backoff() {
+    let transport = FlakyTransport::new(2);
+    let slept = Mutex::new(Vec::new());
+    let policy = ReconnectPolicy {
+        jitter: 0.0,
+        ..ReconnectPolicy::new(5, std::time::Duration::from_millis(100))
+    };
+
+    let res = reconnect_with_backoff(
+        &policy,
+        |n| transport.handshake(n),
+        |d| {
+            slept.lock().unwrap().push(d);
+            async {}
+        },
+    )
+    .await;
+
+    assert_eq!(res, Ok("established"));
+    assert_eq!(*transport.handshakes.lock().unwrap(), vec![0, 1, 2]);
+    assert_eq!(
+        *slept.lock().unwrap(),
+        vec![
+            std::time::Duration::from_millis(100),
+            std::time::Duration::from_millis(200)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reconnect_gives_up_after_max_retries() {
+    let transport = FlakyTransport::new(u32::MAX);
+    let slept = Mutex::new(Vec::new());
+    let policy = ReconnectPolicy::new(3, std::time::Duration::from_millis(100));
+
+    let res = reconnect_with_backoff(
+        &policy,
+        |n| transport.handshake(n),
+        |d| {
+            slept.lock().unwrap().push(d);
+            async {}
+        },
+    )
+    .await;
+
+    assert!(res.is_err());
+    assert_eq!(transport.handshakes.lock().unwrap().len(), 4);
+    let slept = slept.lock().unwrap();
+    assert_eq!(slept.len(), 3);
+    for (i, d) in slept.iter().enumerate() {
+        let base = policy.base_delay_for(i as u32 + 1);
+        assert!(*d >= base.mul_f64(1.0 - policy.jitter) && *d <= base.mul_f64(1.0 + policy.jitter));
+    }
+}
+
+#[test]
+fn reconnect_backoff_is_capped() {
+    let policy = ReconnectPolicy {
+        max_delay: std::time::Duration::from_secs(2),
+        ..ReconnectPolicy::new(10, std::time::Duration::from_millis(500))
+    };
+    assert_eq!(
+        policy.base_delay_for(1),
+        std::time::Duration::from_millis(500)
+    );
+    assert_eq!(policy.base_delay_for(3), std::time::Duration::from_secs(2));
+    assert_eq!(policy.base_delay_for(40), std::time::Duration::from_secs(2));
+}
+
+#[test]
+fn peer_addresses_accept_ipv6_literals() {
+    let v6: std::net::SocketAddr = "[::1]:4433".parse().unwrap();
+    assert_eq!(parse_peer_addr("[::1]:4433", 443).unwrap(), v6);
+    assert_eq!(parse_peer_addr("[::1]", 4433).unwrap(), v6);
+    assert_eq!(parse_peer_addr("::1", 4433).unwrap(), v6);
+    assert_eq!(
+        parse_peer_addr("127.0.0.1:4433", 443).unwrap(),
+        "127.0.0.1:4433".parse().unwrap()
+    );
+    assert!(parse_peer_addr("[::1", 443).is_err());
+    assert_eq!(parse_peer_addrs("[::1]", 4433).unwrap(), vec![v6]);
+    // Every answer of the resolver is a racing candidate.
+    let local = parse_peer_addrs("localhost", 4433).unwrap();
+    assert!(!local.is_empty() && local.iter().all(|a| a.port() == 4433));
+
+    let any_v4: std::net::SocketAddr = "0.0.0.0:0".parse().unwrap();
+    assert_eq!(bind_addr_for(any_v4, v6), "[::]:0".parse().unwrap());
+    let fixed: std::net::SocketAddr = "127.0.0.1:5000".parse().unwrap();
+    assert_eq!(bind_addr_for(fixed, v6), fixed);
+
+    // Dual-stack sockets see IPv4 peers v4-mapped, IPv4 sockets cannot
+    // reach IPv6 peers.
+    let any_v6: std::net::SocketAddr = "[::]:4433".parse().unwrap();
+    assert_eq!(
+        path_peer_addr(any_v6, "127.0.0.1:5000".parse().unwrap()),
+        Some("[::ffff:127.0.0.1]:5000".parse().unwrap())
+    );
+    assert_eq!(path_peer_addr(fixed, v6), None);
+}
+
+#[test]
+fn mtu_floor_accounts_for_ipv6_header() {
+    use quicfuscate::optimize::MtuConfig;
+    let v4 = MtuConfig::for_peer(&"127.0.0.1:4433".parse().unwrap());
+    let v6 = MtuConfig::for_peer(&"[::1]:4433".parse().unwrap());
+    assert_eq!(v4.max_mtu, 1472);
+    assert_eq!(v6.max_mtu, 1452);
+    assert!(v6.base_mtu <= v6.max_mtu);
+}
+
+#[test]
+fn handshake_over_ipv6_loopback() {
+    let server_addr = parse_peer_addr("[::1]:4433", 443).unwrap();
+    let client_addr: std::net::SocketAddr = "[::1]:5020".parse().unwrap();
+    let mut client = memory_client(client_addr, server_addr);
+    let mut server = memory_server(server_addr, client_addr);
+
+    complete_handshake(&mut client, &mut server);
+    assert_eq!(client.peer_addr, server_addr);
+}
+
+#[test]
+fn migration_moves_from_ipv4_to_ipv6_path() {
+    let server_v4: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_v4: std::net::SocketAddr = "127.0.0.1:5021".parse().unwrap();
+    let server_v6: std::net::SocketAddr = "[::1]:4433".parse().unwrap();
+    let client_v6: std::net::SocketAddr = "[::1]:5021".parse().unwrap();
+    let mut client = memory_client(client_v4, server_v4);
+    let mut server = memory_server(server_v4, client_v4);
+
+    complete_handshake(&mut client, &mut server);
+
+    // The new path needs a spare connection ID from the server.
+    server.rotate_connection_id().unwrap();
+    pump_until_idle(&mut client, &mut server);
+
+    // An IPv6 peer cannot be reached from the bound IPv4 socket.
+    assert!(client.migrate_connection(server_v6).is_err());
+    client.migrate_path(client_v6, server_v6).unwrap();
+
+    for _ in 0..50 {
+        if !pump_on_path(&mut client, &mut server, client_v6, server_v6) {
+            break;
+        }
+    }
+    client.update_state();
+    assert!(client.peer_addr.is_ipv6());
+    assert_eq!(client.peer_addr, server_v6);
+    assert!(client.conn.is_established());
+    assert!(server.conn.is_established());
+}
+
+#[test]
+fn expired_block_sources_feed_retransmit_tracker() {
+    use quicfuscate::core::RetransmitTracker;
+    use quicfuscate::fec::AdaptiveFec;
+    use std::time::{Duration, Instant};
+
+    let mgr = quicfuscate::optimize::OptimizationManager::new();
+    let cfg = FecConfig::builder()
+        .initial_mode(FecMode::Normal)
+        .max_recovery_delay(Duration::from_millis(50))
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut fec = AdaptiveFec::new(cfg, mgr.memory_pool());
+    let k = fec.current_window();
+
+    let t0 = Instant::now();
+    for id in [0, 2] {
+        let pkt = quicfuscate::fec::Packet::from_raw(id, &[1, 1, 2, 3], &mgr).unwrap();
+        assert!(fec.on_receive_at(pkt, t0).unwrap().is_empty());
+    }
+    assert!(fec.take_missing_sources().is_empty());
+    assert!(fec.flush_expired(t0 + Duration::from_millis(80)));
+
+    let missing = fec.take_missing_sources();
+    let expected: Vec<u64> = (0..k as u64).filter(|id| *id != 0 && *id != 2).collect();
+    assert_eq!(missing, expected);
+    assert!(fec.take_missing_sources().is_empty());
+
+    let mut tracker = RetransmitTracker::default();
+    assert_eq!(tracker.record(missing.clone()), missing.len());
+    // Reports of the same loss are not requested twice.
+    assert_eq!(tracker.record(missing.clone()), 0);
+    assert_eq!(tracker.pending_retransmits(), expected);
+
+    tracker.mark_serviced(&expected[..1]);
+    assert_eq!(tracker.pending_retransmits(), expected[1..]);
+    tracker.mark_serviced(&expected);
+    assert!(tracker.is_empty());
+}
+
+#[test]
+fn retransmit_tracker_is_bounded() {
+    use quicfuscate::core::RetransmitTracker;
+
+    let mut tracker = RetransmitTracker::new(4);
+    assert_eq!(tracker.record(0..10), 10);
+    assert_eq!(tracker.len(), 4);
+    assert_eq!(tracker.dropped(), 6);
+    assert_eq!(tracker.pending_retransmits(), vec![6, 7, 8, 9]);
+}
+
+#[test]
+fn connection_stats_reflect_quiche_path_state() {
+    use quicfuscate::core::ConnectionStats;
+
+    let empty = ConnectionStats::from_quiche(&quiche::Stats::default(), None);
+    assert_eq!(empty, ConnectionStats::default());
+
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5030".parse().unwrap();
+    let mut client = memory_client(client_addr, server_addr);
+    let mut server = memory_server(server_addr, client_addr);
+
+    complete_handshake(&mut client, &mut server);
+    client.update_state();
+    let stats = client.stats().clone();
+    let quiche_stats = client.conn.stats();
+    let path = client.conn.path_stats().find(|p| p.active).unwrap();
+
+    assert_eq!(stats.packets_sent, quiche_stats.sent as u64);
+    assert_eq!(stats.packets_lost, quiche_stats.lost as u64);
+    assert_eq!(stats.cwnd, path.cwnd as u64);
+    assert!(stats.cwnd > 0);
+    assert_eq!(stats.delivery_rate, path.delivery_rate);
+    assert!(stats.rtt > 0.0);
+    assert!(stats.min_rtt > 0.0 && stats.min_rtt <= stats.rtt);
+    assert_eq!(stats.loss_rate, 0.0);
+    assert_eq!(
+        stats.bytes_in_flight,
+        quiche_stats.sent_bytes - quiche_stats.acked_bytes - quiche_stats.lost_bytes
+    );
+}
+
+#[test]
+fn connection_reports_probed_mtu() {
+    use quicfuscate::optimize::MtuStatus;
+
+    let server_addr: std::net::SocketAddr = "127.0.0.1:4433".parse().unwrap();
+    let client_addr: std::net::SocketAddr = "127.0.0.1:5040".parse().unwrap();
+    let mut cfg = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
+    cfg.set_application_protos(b"\x0ahq-interop\x05h3-29\x05h3-28\x05h3-27\x08http/0.9")
+        .unwrap();
+    cfg.set_initial_max_data(1_000_000);
+    cfg.set_initial_max_streams_uni(100);
+    cfg.set_max_send_udp_payload_size(1400);
+    cfg.discover_pmtu(true);
+    cfg.verify_peer(false);
+    let mut client = QuicFuscateConnection::new_client(
+        "example.com",
+        client_addr,
+        server_addr,
+        cfg,
+        StealthConfig::default(),
+        FecConfig::default(),
+        OptimizeConfig::default(),
+        true,
+    )
+    .unwrap();
+    let mut server = memory_server(server_addr, client_addr);
+    assert_eq!(client.current_mtu(), 1200);
+    assert_eq!(client.mtu_status(), MtuStatus::Idle);
+
+    for _ in 0..100 {
+        pump(&mut client, &mut server);
+        client.update_state();
+        if client.current_mtu() > 1200 {
+            break;
+        }
+    }
+    assert!(client.conn.is_established());
+    assert!(client.current_mtu() > 1200);
+    assert!(client.current_mtu() <= 1400);
+    assert_ne!(client.mtu_status(), MtuStatus::Idle);
+}
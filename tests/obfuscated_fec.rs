@@ -0,0 +1,233 @@
+//! End-to-end pipeline: FEC encode, obfuscate, datagram send, loss, receive,
+//! deobfuscate, FEC decode.
+
+use quicfuscate::crypto::CryptoManager;
+use quicfuscate::datagram::DatagramEngine;
+use quicfuscate::fec::{export_test_vectors, AdaptiveFec, FecConfig, FecMode, Packet};
+use quicfuscate::optimize::OptimizationManager;
+use quicfuscate::stealth::{StealthConfig, StealthManager, XorPattern};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::sync::Arc;
+
+const MODES: [FecMode; 5] = [
+    FecMode::Light,
+    FecMode::Normal,
+    FecMode::Medium,
+    FecMode::Strong,
+    FecMode::Extreme,
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Obfuscation {
+    Off,
+    /// The built-in XOR obfuscator with the given key stream pattern.
+    Xor(XorPattern),
+}
+
+const OBFUSCATIONS: [Obfuscation; 3] = [
+    Obfuscation::Off,
+    Obfuscation::Xor(XorPattern::Repeating),
+    Obfuscation::Xor(XorPattern::Rolling),
+];
+
+fn stealth(obfuscation: Obfuscation) -> StealthManager {
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mut config = StealthConfig::default();
+    config.enable_xor_obfuscation = false;
+    if let Obfuscation::Xor(pattern) = obfuscation {
+        config.enable_xor_obfuscation = true;
+        config.xor_pattern = pattern;
+    }
+    StealthManager::new(config, crypto, optimize)
+}
+
+/// Sends every block of `mode`'s test vectors through the full pipeline,
+/// dropping `loss` of the datagrams of each block. Returns the recovered
+/// source payloads per block, or an error naming the block that could not
+/// be decoded.
+fn transfer(
+    mode: FecMode,
+    obfuscation: Obfuscation,
+    loss: f32,
+    seed: u64,
+) -> Result<Vec<Vec<Vec<u8>>>, String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let opt = OptimizationManager::new();
+    let mgr = stealth(obfuscation);
+    let mut blocks = Vec::new();
+
+    for (b, v) in export_test_vectors(seed, mode).iter().enumerate() {
+        let mut engine = DatagramEngine::new();
+        let mut ids: Vec<usize> = (0..v.packets.len()).collect();
+        ids.shuffle(&mut rng);
+        let lost = &ids[..(loss * v.packets.len() as f32).round() as usize];
+
+        let mut delivered = Vec::new();
+        for (id, raw) in v.packets.iter().enumerate() {
+            mgr.obfuscate_and_send(&mut engine, raw)
+                .map_err(|e| e.to_string())?;
+            let wire = engine.pop_outgoing().expect("queued datagram");
+            if !matches!(obfuscation, Obfuscation::Off) {
+                assert_ne!(&wire[..], &raw[..]);
+            }
+            if lost.contains(&id) {
+                continue;
+            }
+            engine.recv(&wire);
+            delivered.push(id as u64);
+        }
+
+        let config = FecConfig {
+            initial_mode: mode,
+            ..FecConfig::default()
+        };
+        let mut fec = AdaptiveFec::new(config, opt.memory_pool());
+        let mut recovered = Vec::new();
+        for id in delivered {
+            let raw = mgr
+                .recv_and_deobfuscate(&mut engine)
+                .expect("delivered datagram");
+            let pkt = Packet::from_raw(id, &raw, &opt)?;
+            recovered.extend(fec.on_receive(pkt)?);
+        }
+        if recovered.len() < v.k {
+            return Err(format!(
+                "block {} undecodable: {} of {} datagrams lost, {} repairs",
+                b,
+                lost.len(),
+                v.n,
+                v.n - v.k
+            ));
+        }
+        blocks.push(
+            recovered
+                .iter()
+                .zip(&v.sources)
+                .map(|(p, s)| p.payload()[..s.len()].to_vec())
+                .collect(),
+        );
+    }
+    Ok(blocks)
+}
+
+fn sources(mode: FecMode, seed: u64) -> Vec<Vec<Vec<u8>>> {
+    export_test_vectors(seed, mode)
+        .into_iter()
+        .map(|v| v.sources)
+        .collect()
+}
+
+/// Loss fraction that drops exactly `lost` of the `n` datagrams of a block.
+fn loss_for(lost: usize, n: usize) -> f32 {
+    lost as f32 / n as f32
+}
+
+#[test]
+fn lossless_transfer_for_every_mode_and_obfuscation() {
+    for mode in MODES {
+        for obfuscation in OBFUSCATIONS {
+            let out = transfer(mode, obfuscation, 0.0, 7)
+                .unwrap_or_else(|e| panic!("{:?}/{:?}: {}", mode, obfuscation, e));
+            assert_eq!(out, sources(mode, 7), "{:?}/{:?}", mode, obfuscation);
+        }
+    }
+}
+
+#[test]
+fn loss_within_redundancy_is_recovered() {
+    for mode in MODES {
+        let v = &export_test_vectors(11, mode)[0];
+        let loss = loss_for(v.n - v.k, v.n);
+        for obfuscation in [Obfuscation::Off, Obfuscation::Xor(XorPattern::Repeating)] {
+            let out = transfer(mode, obfuscation, loss, 11)
+                .unwrap_or_else(|e| panic!("{:?}/{:?}: {}", mode, obfuscation, e));
+            assert_eq!(out, sources(mode, 11), "{:?}/{:?}", mode, obfuscation);
+        }
+    }
+}
+
+// The rolling key advances once per datagram on each side, so the receiver
+// falls out of step with the first lost datagram and FEC cannot help.
+#[test]
+fn rolling_xor_does_not_survive_loss() {
+    for mode in MODES {
+        let v = &export_test_vectors(11, mode)[0];
+        let loss = loss_for(1, v.n);
+        let out = transfer(mode, Obfuscation::Xor(XorPattern::Rolling), loss, 11);
+        assert!(
+            out.map_or(true, |out| out != sources(mode, 11)),
+            "{:?}: rolling XOR recovered from loss",
+            mode
+        );
+    }
+}
+
+#[test]
+fn loss_beyond_redundancy_fails_cleanly() {
+    for mode in MODES {
+        let v = &export_test_vectors(13, mode)[0];
+        let loss = loss_for(v.n - v.k + 1, v.n);
+        for obfuscation in [Obfuscation::Off, Obfuscation::Xor(XorPattern::Repeating)] {
+            let err = transfer(mode, obfuscation, loss, 13).unwrap_err();
+            assert!(err.contains("undecodable"), "{:?}: {}", mode, err);
+        }
+    }
+}
+
+#[test]
+fn cover_packets_do_not_disturb_fec_stream() {
+    use quicfuscate::stealth::SizeDistribution;
+    use std::time::{Duration, Instant};
+
+    let opt = OptimizationManager::new();
+    let sender = stealth(Obfuscation::Xor(XorPattern::Rolling));
+    let receiver = stealth(Obfuscation::Xor(XorPattern::Rolling));
+    sender.enable_cover_traffic(1000.0, SizeDistribution::Empirical(vec![60, 1200]));
+    sender.seed_cover_traffic(5);
+    receiver.enable_cover_traffic(0.0, SizeDistribution::Fixed(1));
+
+    let mode = FecMode::Normal;
+    let v = &export_test_vectors(17, mode)[0];
+    let mut engine = DatagramEngine::new();
+    let start = Instant::now();
+    for (i, raw) in v.packets.iter().enumerate() {
+        sender.send_cover_traffic(&mut engine, start + Duration::from_millis(5 * i as u64));
+        sender.obfuscate_and_send(&mut engine, raw).unwrap();
+    }
+    let mut wire = 0;
+    while let Some(d) = engine.pop_outgoing() {
+        engine.recv(&d);
+        wire += 1;
+    }
+    assert!(sender.cover_packets_sent() > 0);
+    assert_eq!(wire, v.packets.len() + sender.cover_packets_sent());
+
+    let config = FecConfig {
+        initial_mode: mode,
+        ..FecConfig::default()
+    };
+    let mut fec = AdaptiveFec::new(config, opt.memory_pool());
+    let mut recovered = Vec::new();
+    for id in 0..v.packets.len() {
+        let raw = receiver
+            .recv_and_deobfuscate(&mut engine)
+            .expect("data datagram");
+        assert_eq!(raw, v.packets[id]);
+        let pkt = Packet::from_raw(id as u64, &raw, &opt).unwrap();
+        recovered.extend(fec.on_receive(pkt).unwrap());
+    }
+    assert!(receiver.recv_and_deobfuscate(&mut engine).is_none());
+    assert_eq!(
+        receiver.cover_packets_dropped(),
+        sender.cover_packets_sent()
+    );
+    let out: Vec<Vec<u8>> = recovered
+        .iter()
+        .zip(&v.sources)
+        .map(|(p, s)| p.payload()[..s.len()].to_vec())
+        .collect();
+    assert_eq!(out, v.sources);
+}
@@ -1,6 +1,7 @@
-use quicfuscate::optimize::{MemoryPool, OptimizationManager};
+use quicfuscate::optimize::{MemoryPool, OptimizationManager, QuicStreamOptimizer};
 use quicfuscate::xdp_socket::XdpSocket;
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 
 #[test]
 fn memory_pool_alloc_free() {
@@ -22,6 +23,40 @@ fn memory_pool_reuse() {
     pool.free(block2);
 }
 
+#[test]
+fn strict_pool_rejects_allocation_past_capacity() {
+    use quicfuscate::error::PoolError;
+
+    let pool = MemoryPool::new(3, 64);
+    pool.set_strict_capacity(true);
+    let blocks: Vec<_> = (0..3).map(|_| pool.try_alloc().unwrap()).collect();
+    assert_eq!(pool.in_use(), 3);
+    assert_eq!(
+        pool.try_alloc().unwrap_err(),
+        PoolError::Exhausted { capacity: 3 }
+    );
+    assert_eq!(pool.in_use(), 3);
+
+    let mut blocks = blocks.into_iter();
+    pool.free(blocks.next().unwrap());
+    assert_eq!(pool.in_use(), 2);
+    let again = pool.try_alloc().unwrap();
+    assert_eq!(pool.in_use(), 3);
+    pool.free(again);
+    blocks.for_each(|b| pool.free(b));
+    assert_eq!(pool.in_use(), 0);
+}
+
+#[test]
+fn non_strict_pool_grows_past_capacity() {
+    let pool = MemoryPool::new(2, 64);
+    assert!(!pool.is_strict_capacity());
+    let blocks: Vec<_> = (0..5).map(|_| pool.try_alloc().unwrap()).collect();
+    assert_eq!(pool.in_use(), 5);
+    blocks.into_iter().for_each(|b| pool.free(b));
+    assert_eq!(pool.in_use(), 0);
+}
+
 #[cfg(windows)]
 #[test]
 fn zerocopybuffer_basic() {
@@ -47,3 +82,126 @@ fn xdp_socket_creation() {
         assert!(sock.is_none());
     }
 }
+
+fn simulate_high_bdp(autotune: bool) -> Vec<u64> {
+    let mut opt = QuicStreamOptimizer::new(64 * 1024);
+    opt.set_autotune(autotune);
+    opt.set_max_window(4 * 1024 * 1024);
+    let rtt = Duration::from_millis(50);
+    let start = Instant::now();
+    let mut windows = Vec::new();
+    // 1 MiB delivered per round trip in 10 chunks.
+    for round in 0..8u32 {
+        for chunk in 0..10u32 {
+            let now = start + rtt * round + rtt * chunk / 10;
+            opt.on_data_delivered(4, 100 * 1024, rtt, now);
+        }
+        windows.push(opt.window(4));
+    }
+    windows
+}
+
+#[test]
+fn stream_window_autotune_grows() {
+    let windows = simulate_high_bdp(true);
+    assert!(windows.windows(2).all(|w| w[1] >= w[0]));
+    assert!(*windows.last().unwrap() > 1024 * 1024);
+    assert!(*windows.last().unwrap() <= 4 * 1024 * 1024);
+}
+
+#[test]
+fn stream_window_static_without_autotune() {
+    let windows = simulate_high_bdp(false);
+    assert!(windows.iter().all(|&w| w == 64 * 1024));
+}
+
+fn mtu_manager() -> (
+    quicfuscate::optimize::PathMtuManager,
+    quicfuscate::optimize::ManualClock,
+) {
+    use quicfuscate::optimize::{ManualClock, MtuConfig, PathMtuManager};
+
+    let clock = ManualClock::new();
+    let cfg = MtuConfig {
+        base_mtu: 1200,
+        max_mtu: 1500,
+        probe_timeout_ms: 500,
+        reprobe_interval_ms: 10_000,
+    };
+    (
+        PathMtuManager::with_clock(cfg, std::sync::Arc::new(clock.clone())),
+        clock,
+    )
+}
+
+#[test]
+fn mtu_probe_times_out_exactly_at_timeout() {
+    use std::time::Duration;
+
+    let (mut mtu, clock) = mtu_manager();
+    let size = mtu.maybe_send_probe().unwrap();
+    assert_eq!(size, 1350);
+
+    clock.advance(Duration::from_millis(499));
+    assert!(!mtu.check_probe_timeouts());
+    assert_eq!(mtu.probe_in_flight(), Some(size));
+
+    clock.advance(Duration::from_millis(1));
+    assert!(mtu.check_probe_timeouts());
+    assert_eq!(mtu.probe_in_flight(), None);
+    assert_eq!(mtu.current_mtu(), 1200);
+}
+
+#[test]
+fn mtu_reprobes_after_interval() {
+    use std::time::Duration;
+
+    // Simulated path that drops everything above 1300 bytes.
+    let (mut mtu, clock) = mtu_manager();
+    while !mtu.is_search_complete() {
+        match mtu.maybe_send_probe() {
+            Some(size) if size <= 1300 => assert!(mtu.handle_probe_response(size)),
+            Some(_) => {
+                clock.advance(Duration::from_millis(500));
+                assert!(mtu.check_probe_timeouts());
+            }
+            None => break,
+        }
+    }
+    assert!(mtu.is_search_complete());
+    assert_eq!(mtu.current_mtu(), 1300);
+
+    clock.advance(Duration::from_millis(9_999));
+    assert_eq!(mtu.maybe_send_probe(), None);
+
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(mtu.maybe_send_probe(), Some(1400));
+    assert!(!mtu.is_search_complete());
+}
+
+#[test]
+fn mtu_update_follows_confirmed_size() {
+    use quicfuscate::optimize::MtuStatus;
+    use std::time::Duration;
+
+    let (mut mtu, clock) = mtu_manager();
+    assert_eq!(mtu.status(), MtuStatus::Idle);
+    assert_eq!(mtu.update(1200), MtuStatus::Probing(1350));
+
+    // The transport confirmed more than the probe asked for.
+    assert_eq!(mtu.update(1400), MtuStatus::Probing(1450));
+    assert_eq!(mtu.current_mtu(), 1400);
+
+    clock.advance(Duration::from_millis(500));
+    assert_eq!(mtu.update(1400), MtuStatus::Probing(1425));
+    clock.advance(Duration::from_millis(500));
+    mtu.update(1400);
+    clock.advance(Duration::from_millis(500));
+    mtu.update(1400);
+    assert_eq!(mtu.current_mtu(), 1400);
+
+    // A smaller confirmed size restarts the search below.
+    mtu.update(1250);
+    assert_eq!(mtu.current_mtu(), 1250);
+    assert!(!mtu.is_search_complete());
+}
@@ -1,6 +1,6 @@
 use quicfuscate::crypto::CryptoManager;
 use quicfuscate::optimize::OptimizationManager;
-use quicfuscate::stealth::{StealthConfig, StealthManager};
+use quicfuscate::stealth::{RotationSchedule, SendPacer, StealthConfig, StealthManager};
 use quicfuscate::stealth::{BrowserProfile, FingerprintProfile, OsProfile};
 use std::time::Duration;
 use std::sync::Arc;
@@ -98,3 +98,1053 @@ fn apply_utls_profile_runs() {
     let mut cfg = quiche::Config::new(quiche::PROTOCOL_VERSION).unwrap();
     mgr.apply_utls_profile(&mut cfg, None);
 }
+
+fn released_in_one_second(rate: u64) -> usize {
+    let mut pacer = SendPacer::new();
+    pacer.set_rate(rate);
+    let start = std::time::Instant::now();
+    (0..1000)
+        .filter(|i| pacer.try_release(1200, start + Duration::from_millis(*i)))
+        .count()
+}
+
+#[test]
+fn low_rate_hint_reduces_release_rate() {
+    let fast = released_in_one_second(10_000_000);
+    let slow = released_in_one_second(60_000);
+    assert_eq!(fast, 1000);
+    assert!(slow < 60, "released {} datagrams at 60 kB/s", slow);
+    assert!(slow > 0);
+}
+
+#[test]
+fn send_rate_hint_updates_manager() {
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mgr = StealthManager::new(StealthConfig::default(), crypto, optimize);
+    assert_eq!(mgr.send_rate_hint(), 0);
+    assert!(mgr.try_release_datagram(1200));
+    mgr.set_send_rate_hint(50_000);
+    assert_eq!(mgr.send_rate_hint(), 50_000);
+}
+
+#[test]
+fn obfuscate_and_send_roundtrip() {
+    use quicfuscate::datagram::DatagramEngine;
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mgr = StealthManager::new(StealthConfig::default(), crypto, optimize);
+    let mut engine = DatagramEngine::new();
+
+    for msg in [&b"first datagram"[..], &b"second one"[..]] {
+        mgr.obfuscate_and_send(&mut engine, msg).unwrap();
+        let wire = engine.pop_outgoing().expect("queued datagram");
+        assert_ne!(&wire[..], msg);
+        engine.recv(&wire);
+        assert_eq!(mgr.recv_and_deobfuscate(&mut engine).unwrap(), msg);
+    }
+    assert!(mgr.recv_and_deobfuscate(&mut engine).is_none());
+}
+
+#[test]
+fn datagrams_after_a_lost_one_still_deobfuscate() {
+    use quicfuscate::datagram::DatagramEngine;
+    use quicfuscate::stealth::XorPattern;
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let transfer = |pattern: XorPattern| {
+        let mut config = StealthConfig::default();
+        config.xor_pattern = pattern;
+        let sender = StealthManager::new(config.clone(), crypto.clone(), optimize.clone());
+        let receiver = StealthManager::new(config, crypto.clone(), optimize.clone());
+        let mut engine = DatagramEngine::new();
+        let msgs: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i + 1; 24]).collect();
+        for (i, msg) in msgs.iter().enumerate() {
+            sender.obfuscate_and_send(&mut engine, msg).unwrap();
+            let wire = engine.pop_outgoing().unwrap();
+            // The datagram in the middle is lost.
+            if i != 2 {
+                engine.recv(&wire);
+            }
+        }
+        let mut out = Vec::new();
+        while let Some(d) = receiver.recv_and_deobfuscate(&mut engine) {
+            out.push(d);
+        }
+        let mut expected = msgs;
+        expected.remove(2);
+        (out, expected)
+    };
+
+    let (out, expected) = transfer(XorPattern::default());
+    assert_eq!(out, expected);
+
+    // The rolling pattern has no resync path: everything after the loss is
+    // garbled.
+    let (out, expected) = transfer(XorPattern::Rolling);
+    assert_eq!(out[..2], expected[..2]);
+    assert!(out[2..].iter().zip(&expected[2..]).all(|(o, e)| o != e));
+}
+
+#[test]
+fn jittered_rotation_intervals_within_bounds() {
+    let schedule = RotationSchedule::jittered(Duration::from_secs(60), Duration::from_secs(15));
+    let intervals: Vec<Duration> = (0..32).map(|_| schedule.next_interval().unwrap()).collect();
+    for d in &intervals {
+        assert!(*d >= Duration::from_secs(45) && *d <= Duration::from_secs(75));
+    }
+    assert!(intervals.iter().any(|d| *d != intervals[0]));
+}
+
+#[test]
+fn jitter_beyond_interval_keeps_a_minimum_delay() {
+    let floor = RotationSchedule::MIN_DELAY;
+    for jitter in [Duration::from_secs(10), Duration::from_secs(25)] {
+        let schedule = RotationSchedule::jittered(Duration::from_secs(10), jitter);
+        assert_eq!(
+            schedule,
+            RotationSchedule::Uniform {
+                min: floor,
+                max: Duration::from_secs(10) + jitter,
+            }
+        );
+        for _ in 0..32 {
+            assert!(schedule.next_interval().unwrap() >= floor);
+        }
+    }
+    let zero: RotationSchedule = Duration::ZERO.into();
+    assert_eq!(zero.next_interval(), Some(floor));
+}
+
+#[test]
+fn fixed_and_per_request_schedules() {
+    let fixed: RotationSchedule = Duration::from_secs(10).into();
+    assert_eq!(fixed.next_interval(), Some(Duration::from_secs(10)));
+    assert_eq!(
+        RotationSchedule::jittered(Duration::from_secs(10), Duration::ZERO),
+        fixed
+    );
+    assert_eq!(RotationSchedule::PerRequest.next_interval(), None);
+}
+
+#[test]
+fn per_request_rotation_changes_profile() {
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mgr = Arc::new(StealthManager::new(StealthConfig::default(), crypto, optimize));
+    let profiles = vec![
+        FingerprintProfile::new(BrowserProfile::Chrome, OsProfile::Windows),
+        FingerprintProfile::new(BrowserProfile::Firefox, OsProfile::Linux),
+    ];
+    mgr.start_profile_rotation(profiles, RotationSchedule::PerRequest);
+    let mut seen = std::collections::HashSet::new();
+    for _ in 0..32 {
+        mgr.get_http3_header_list("example.com", "/");
+        seen.insert(format!("{:?}", mgr.current_profile().browser));
+    }
+    assert_eq!(seen.len(), 2);
+}
+
+#[test]
+fn initialize_accepts_default_config() {
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mgr = StealthManager::new(StealthConfig::default(), crypto, optimize);
+    assert_eq!(mgr.initialize(), Ok(()));
+    mgr.shutdown();
+}
+
+#[test]
+fn initialize_rejects_invalid_config() {
+    use quicfuscate::error::StealthError;
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+
+    let mut config = StealthConfig::default();
+    config.doh_provider = "http://dns.example/dns-query".into();
+    let mgr = StealthManager::new(config, crypto.clone(), optimize.clone());
+    assert!(matches!(
+        mgr.initialize(),
+        Err(StealthError::InvalidDohProvider { .. })
+    ));
+
+    let mut config = StealthConfig::default();
+    config.browser_profile = BrowserProfile::Safari;
+    config.os_profile = OsProfile::Windows;
+    let mgr = StealthManager::new(config, crypto, optimize);
+    assert!(matches!(
+        mgr.initialize(),
+        Err(StealthError::InvalidProfile(_))
+    ));
+}
+
+#[test]
+fn doh_pool_reuses_connection_within_idle_window() {
+    use quicfuscate::stealth::{DohConfig, DohPool};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    let next_id = Arc::new(AtomicUsize::new(0));
+    let ids = next_id.clone();
+    let config = DohConfig {
+        keep_alive: true,
+        pool_idle_timeout: Duration::from_secs(10),
+        ..DohConfig::default()
+    };
+    // Each "connection" of the mock transport is identified by a number.
+    let pool = DohPool::with_connector(config.clone(), move |_| ids.fetch_add(1, Ordering::SeqCst));
+
+    let t0 = Instant::now();
+    let first = pool.checkout(t0);
+    let second = pool.checkout(t0 + Duration::from_secs(5));
+    assert_eq!(first, second);
+    assert_eq!(pool.connections_opened(), 1);
+
+    // Idle for longer than the timeout: a new connection is opened.
+    let third = pool.checkout(t0 + Duration::from_secs(20));
+    assert_ne!(third, second);
+    assert_eq!(pool.connections_opened(), 2);
+
+    let no_keep_alive = DohPool::with_connector(
+        DohConfig {
+            keep_alive: false,
+            ..config
+        },
+        |_| 0u8,
+    );
+    no_keep_alive.checkout(t0);
+    no_keep_alive.checkout(t0);
+    assert_eq!(no_keep_alive.connections_opened(), 2);
+}
+
+#[test]
+fn accurate_spin_bit_toggles_once_per_rtt() {
+    use quicfuscate::stealth::{SpinBitMode, SpinBitRandomizer};
+    use std::time::Instant;
+
+    let mut spin = SpinBitRandomizer::new(SpinBitMode::Accurate);
+    spin.on_rtt_sample(Duration::from_millis(50));
+
+    // One packet every 10ms for 500ms: the bit flips every 5th packet.
+    let t0 = Instant::now();
+    let mut last = spin.spin_for(0, t0);
+    let mut toggles = Vec::new();
+    for pn in 1..=50u64 {
+        let now = t0 + Duration::from_millis(pn * 10);
+        let bit = spin.spin_for(pn, now);
+        if bit != last {
+            toggles.push(pn);
+        }
+        last = bit;
+    }
+    assert_eq!(toggles, (1..=10).map(|i| i * 5).collect::<Vec<_>>());
+
+    // A reordered (older) packet number never flips the bit.
+    let late = t0 + Duration::from_millis(1000);
+    assert_eq!(spin.spin_for(10, late), last);
+
+    // A larger RTT slows the cadence down.
+    spin.on_rtt_sample(Duration::from_millis(100));
+    let t1 = t0 + Duration::from_millis(500);
+    let before = spin.spin_for(51, t1 + Duration::from_millis(60));
+    assert_eq!(before, last);
+    assert_ne!(spin.spin_for(52, t1 + Duration::from_millis(100)), last);
+
+    let mut off = SpinBitRandomizer::new(SpinBitMode::Disabled);
+    off.on_rtt_sample(Duration::from_millis(1));
+    assert!((0..10u64).all(|pn| !off.spin_for(pn, t0 + Duration::from_millis(pn * 5))));
+}
+
+#[test]
+fn doh_query_delay_stays_within_jitter_range() {
+    use quicfuscate::stealth::DohConfig;
+
+    let config = DohConfig {
+        query_jitter: Duration::from_millis(20)..Duration::from_millis(80),
+        ..DohConfig::default()
+    };
+    for _ in 0..200 {
+        let delay = config.query_delay();
+        assert!(delay >= Duration::from_millis(20) && delay < Duration::from_millis(80));
+    }
+    assert_eq!(DohConfig::default().query_delay(), Duration::ZERO);
+}
+
+#[test]
+fn doh_cache_refreshes_are_staggered() {
+    use quicfuscate::stealth::DohCache;
+    use std::collections::HashSet;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Instant;
+
+    let ttl = Duration::from_secs(300);
+    let cache = DohCache::new(ttl);
+    let now = Instant::now();
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let expiries: HashSet<Instant> = (0..20)
+        .map(|i| cache.insert(&format!("host{i}.example"), ip, now))
+        .collect();
+
+    assert!(expiries.len() > 1, "entries must not expire together");
+    for e in &expiries {
+        assert!(*e <= now + ttl && *e >= now + ttl - ttl / 10);
+    }
+    assert_eq!(cache.get("host0.example", now), Some(ip));
+    assert_eq!(cache.get("host0.example", now + ttl), None);
+}
+
+#[test]
+fn snapshot_reflects_runtime_settings() {
+    use quicfuscate::stealth::{SizeDistribution, XorPattern};
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mut config = StealthConfig::default();
+    config.use_fake_tls = true;
+    config.enable_domain_fronting = false;
+    config.enable_xor_obfuscation = false;
+    let mgr = StealthManager::new(config, crypto, optimize);
+
+    mgr.set_fingerprint_profile(
+        FingerprintProfile::new(BrowserProfile::Firefox, OsProfile::Linux),
+        None,
+    );
+    mgr.set_send_rate_hint(250_000);
+
+    let snap = mgr.snapshot_config();
+    assert_eq!(snap.browser_profile, BrowserProfile::Firefox);
+    assert_eq!(snap.os_profile, OsProfile::Linux);
+    assert!(snap.fake_tls);
+    assert!(snap.doh);
+    assert!(!snap.domain_fronting);
+    assert!(!snap.xor_obfuscation);
+    assert_eq!(snap.obfuscator, None);
+    assert_eq!(snap.xor_pattern, XorPattern::Repeating);
+    assert!(snap.padding_buckets.is_empty());
+    assert!(!snap.cover_traffic);
+    assert_eq!(snap.send_rate_hint, 250_000);
+
+    let json = serde_json::to_value(&snap).unwrap();
+    assert_eq!(json["browser_profile"], "firefox");
+    assert_eq!(json["os_profile"], "linux");
+    assert_eq!(json["xor_pattern"], "repeating");
+
+    mgr.enable_cover_traffic(0.0, SizeDistribution::Fixed(1));
+    assert!(mgr.snapshot_config().cover_traffic);
+}
+
+#[test]
+fn hung_resolver_times_out() {
+    use quicfuscate::error::ResolveError;
+    use quicfuscate::stealth::resolve_with_timeout;
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let timeout = Duration::from_millis(50);
+    let started = std::time::Instant::now();
+    let res = rt.block_on(resolve_with_timeout(std::future::pending(), timeout));
+    assert_eq!(res, Err(ResolveError::Timeout(timeout)));
+    assert!(started.elapsed() >= timeout);
+}
+
+#[test]
+fn alt_svc_follows_http3_masquerading() {
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mgr = StealthManager::new(StealthConfig::default(), crypto.clone(), optimize.clone());
+    assert_eq!(mgr.alt_svc_header(), Some("h3=\":443\"; ma=86400"));
+
+    let mut config = StealthConfig::default();
+    config.enable_http3_masquerading = false;
+    let mgr = StealthManager::new(config, crypto, optimize);
+    assert_eq!(mgr.alt_svc_header(), None);
+
+    let cfg = StealthConfig::from_toml("[stealth]\nalt_svc = 'h3=\":8443\"'\n").unwrap();
+    assert_eq!(cfg.alt_svc, "h3=\":8443\"");
+}
+
+#[test]
+fn http3_settings_frames_match_browser_captures() {
+    use quicfuscate::stealth::Http3Masquerade;
+
+    let chrome = Http3Masquerade::new(FingerprintProfile::new(
+        BrowserProfile::Chrome,
+        OsProfile::Windows,
+    ));
+    let firefox = Http3Masquerade::new(FingerprintProfile::new(
+        BrowserProfile::Firefox,
+        OsProfile::Linux,
+    ));
+
+    // QPACK capacity 65536, max field section 262144, blocked streams 100,
+    // H3_DATAGRAM 1.
+    assert_eq!(
+        chrome.settings_frame(),
+        vec![
+            0x04, 0x0f, 0x01, 0x80, 0x01, 0x00, 0x00, 0x06, 0x80, 0x04, 0x00, 0x00, 0x07, 0x40,
+            0x64, 0x33, 0x01,
+        ]
+    );
+    // QPACK capacity 65536, blocked streams 20, extended CONNECT,
+    // H3_DATAGRAM 1.
+    assert_eq!(
+        firefox.settings_frame(),
+        vec![0x04, 0x0b, 0x01, 0x80, 0x01, 0x00, 0x00, 0x07, 0x14, 0x08, 0x01, 0x33, 0x01]
+    );
+    assert_ne!(chrome.settings(), firefox.settings());
+}
+
+#[test]
+fn custom_and_xor_obfuscators_share_pipeline() {
+    use quicfuscate::datagram::DatagramEngine;
+    use quicfuscate::stealth::Obfuscator;
+
+    struct Identity;
+    impl Obfuscator for Identity {
+        fn obfuscate(&self, _payload: &mut [u8]) {}
+        fn deobfuscate(&self, _payload: &mut [u8]) {}
+    }
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mut config = StealthConfig::default();
+    config.enable_xor_obfuscation = false;
+    let managers = [
+        (
+            StealthManager::new(StealthConfig::default(), crypto.clone(), optimize.clone()),
+            true,
+        ),
+        (
+            StealthManager::with_obfuscator(config, crypto, optimize, Box::new(Identity)),
+            false,
+        ),
+    ];
+
+    for (mgr, transforms) in &managers {
+        let snap = mgr.snapshot_config();
+        let name = if *transforms { "xor" } else { "custom" };
+        assert_eq!(snap.obfuscator, Some(name));
+        assert_eq!(snap.xor_obfuscation, *transforms);
+        let mut engine = DatagramEngine::new();
+        let msg = b"pluggable transform";
+        mgr.obfuscate_and_send(&mut engine, msg).unwrap();
+        let wire = engine.pop_outgoing().unwrap();
+        assert_eq!(&wire[..] != msg, *transforms);
+        engine.recv(&wire);
+        assert_eq!(mgr.recv_and_deobfuscate(&mut engine).unwrap(), msg);
+    }
+}
+
+#[test]
+fn custom_definitions_drive_client_hello_and_settings() {
+    use quicfuscate::fake_tls::{ClientHelloInfo, FakeTls};
+    use quicfuscate::stealth::{Http3Masquerade, Http3Settings};
+
+    let path = std::env::temp_dir().join(format!("qf_defs_{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+[[profile]]
+browser = "firefox"
+os = "linux"
+user_agent = "Mozilla/5.0 (X11; Linux x86_64; rv:130.0) Gecko/20100101 Firefox/130.0"
+cipher_suites = [0x1301, 0x1303, 0x1302]
+extension_order = [0x0000, 0x002b, 0x0010, 0x000a, 0x000d]
+alpn = ["h3", "h3-29"]
+
+[profile.h3_settings]
+qpack_max_table_capacity = 4096
+qpack_blocked_streams = 16
+enable_connect_protocol = false
+h3_datagram = false
+"#,
+    )
+    .unwrap();
+    let profiles = BrowserProfile::from_definitions(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(profiles.len(), 1);
+    let profile = &profiles[0];
+    assert_eq!((profile.browser, profile.os), (BrowserProfile::Firefox, OsProfile::Linux));
+    assert!(profile.user_agent.contains("Firefox/130.0"));
+
+    let info = ClientHelloInfo::parse(&FakeTls::client_hello(profile)).unwrap();
+    assert_eq!(info.cipher_suites, vec![0x1301, 0x1303, 0x1302]);
+    assert_eq!(info.extensions, vec![0x0000, 0x002b, 0x0010, 0x000a, 0x000d]);
+    assert_eq!(info.alpn, vec!["h3", "h3-29"]);
+
+    let settings = Http3Masquerade::new(profile.clone()).settings();
+    assert_eq!(
+        settings,
+        Http3Settings {
+            qpack_max_table_capacity: 4096,
+            qpack_blocked_streams: 16,
+            max_field_section_size: None,
+            enable_connect_protocol: false,
+            h3_datagram: false,
+        }
+    );
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mgr = StealthManager::new(StealthConfig::default(), crypto, optimize);
+    mgr.set_fingerprint_profile(profile.clone(), None);
+    assert_eq!(mgr.current_profile().user_agent, profile.user_agent);
+    assert_eq!(mgr.http3_settings(), Some(settings));
+}
+
+#[test]
+fn builtin_definitions_match_compiled_profiles() {
+    let defs = BrowserProfile::builtin_definitions();
+    assert!(!defs.is_empty());
+    for def in defs {
+        let compiled = FingerprintProfile::new(def.browser, def.os);
+        assert_eq!(def.user_agent, compiled.user_agent, "{:?}/{:?}", def.browser, def.os);
+        assert_eq!(def.cipher_suites, compiled.tls_cipher_suites);
+        assert_eq!(def.accept_language, compiled.accept_language);
+        assert!(def.extension_order.is_empty());
+    }
+}
+
+#[test]
+fn handshake_plan_for_chrome() {
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mut config = StealthConfig::default();
+    config.browser_profile = BrowserProfile::Chrome;
+    config.os_profile = OsProfile::Windows;
+    let mgr = StealthManager::new(config, crypto, optimize);
+
+    let plan = mgr.handshake_plan();
+    assert_eq!(plan.browser_profile, BrowserProfile::Chrome);
+    assert!(!plan.client_hello.is_empty());
+    assert_eq!(plan.alpn, vec!["h3".to_string()]);
+    assert!(plan.h3_settings.is_some());
+    assert!(!plan.fronting_domains.is_empty());
+    assert!(plan.doh.is_some());
+
+    let json = serde_json::to_value(&plan).unwrap();
+    assert_eq!(json["browser_profile"], "chrome");
+    assert_eq!(json["alpn"][0], "h3");
+}
+
+#[test]
+fn prefetch_warms_doh_cache() {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mut config = StealthConfig::default();
+    // Any real query would fail against this provider.
+    config.doh_provider = "https://invalid.invalid/dns-query".to_string();
+    let mgr = StealthManager::new(config, crypto, optimize);
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let resolver = |calls: Arc<AtomicUsize>| {
+        move |_domain: String| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Ok::<_, Box<dyn std::error::Error>>(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7))) }
+        }
+    };
+    let domains = ["a.example", "b.example"];
+    let resolved = mgr.prefetch_with(&domains, resolver(calls.clone())).join().unwrap();
+    assert_eq!(resolved, 2);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    let ip = mgr
+        .resolve_domain_timeout("a.example", Duration::from_millis(100))
+        .unwrap();
+    assert_eq!(ip, IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7)));
+
+    // Cached domains are not queried again.
+    let resolved = mgr.prefetch_with(&domains, resolver(calls.clone())).join().unwrap();
+    assert_eq!(resolved, 0);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+fn blocked_doh_manager(system_fallback: bool) -> StealthManager {
+    let mut config = StealthConfig::default();
+    config.doh.system_fallback = system_fallback;
+    StealthManager::new(
+        config,
+        Arc::new(CryptoManager::new()),
+        Arc::new(OptimizationManager::new()),
+    )
+}
+
+fn blocked_doh(
+    _domain: String,
+) -> impl std::future::Future<Output = Result<std::net::IpAddr, Box<dyn std::error::Error>>> {
+    async { Err("provider blocked".into()) }
+}
+
+#[test]
+fn system_resolver_answers_when_doh_fails() {
+    use quicfuscate::stealth::ResolveSource;
+
+    let mgr = blocked_doh_manager(true);
+    let resolved = mgr
+        .resolve_domain_detailed_with("localhost", Duration::from_secs(2), blocked_doh)
+        .unwrap();
+    assert_eq!(resolved.source, ResolveSource::System);
+    assert!(!resolved.source.is_private());
+    assert!(resolved.ip.is_loopback());
+}
+
+#[test]
+fn doh_failure_errors_without_system_fallback() {
+    use quicfuscate::error::ResolveError;
+
+    let mgr = blocked_doh_manager(false);
+    let res = mgr.resolve_domain_detailed_with("localhost", Duration::from_secs(2), blocked_doh);
+    assert!(matches!(res, Err(ResolveError::Failed(_))), "{:?}", res);
+}
+
+#[test]
+fn handshake_timing_delays_are_applied() {
+    use quicfuscate::stealth::{HandshakeTiming, HandshakeTimingModel};
+    use std::time::Instant;
+
+    let timing = HandshakeTiming {
+        gap: Duration::from_millis(10),
+        jitter: Duration::from_millis(2),
+        packets: 3,
+    };
+    let mut model = HandshakeTimingModel::with_seed(timing, 7);
+    let mut replay = HandshakeTimingModel::with_seed(timing, 7);
+
+    let mut sent = Vec::new();
+    let mut planned = Vec::new();
+    for _ in 0..5 {
+        let delay = model.next_delay();
+        assert_eq!(delay, replay.next_delay(), "same seed, same gaps");
+        std::thread::sleep(delay);
+        sent.push(Instant::now());
+        planned.push(delay);
+    }
+
+    assert_eq!(planned[0], Duration::ZERO, "Initial is not delayed");
+    assert_eq!(planned[4], Duration::ZERO, "only the handshake is paced");
+    for (i, delay) in planned.iter().enumerate().take(4).skip(1) {
+        assert!(*delay >= timing.gap - timing.jitter && *delay <= timing.gap + timing.jitter);
+        let gap = sent[i] - sent[i - 1];
+        assert!(
+            gap >= *delay && gap < *delay + Duration::from_millis(15),
+            "gap {:?} for planned {:?}",
+            gap,
+            delay
+        );
+    }
+
+    model.reset();
+    assert_eq!(model.next_delay(), Duration::ZERO);
+}
+
+#[test]
+fn handshake_timing_follows_profile() {
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mut config = StealthConfig::default();
+    config.browser_profile = BrowserProfile::Firefox;
+    let mgr = StealthManager::new(config.clone(), crypto.clone(), optimize.clone());
+    mgr.seed_handshake_timing(1);
+
+    assert_eq!(mgr.next_handshake_delay(), Duration::ZERO);
+    let firefox = quicfuscate::stealth::HandshakeTiming::for_browser(BrowserProfile::Firefox);
+    let d = mgr.next_handshake_delay();
+    assert!(d >= firefox.gap - firefox.jitter && d <= firefox.gap + firefox.jitter);
+
+    config.mimic_handshake_timing = false;
+    let off = StealthManager::new(config, crypto, optimize);
+    for _ in 0..3 {
+        assert_eq!(off.next_handshake_delay(), Duration::ZERO);
+    }
+}
+
+fn header_map(headers: &[quiche::h3::Header]) -> std::collections::BTreeMap<String, String> {
+    use quiche::h3::NameValue;
+    headers
+        .iter()
+        .map(|h| {
+            (
+                String::from_utf8_lossy(h.name()).into_owned(),
+                String::from_utf8_lossy(h.value()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn cloudflare_persona_emits_cdn_headers() {
+    use quicfuscate::stealth::OriginPersona;
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    let now = std::time::UNIX_EPOCH + Duration::from_secs(784_111_777);
+    let cf = header_map(&OriginPersona::Cloudflare.response_headers("FRA", &mut rng, now));
+    let names: Vec<&str> = cf.keys().map(String::as_str).collect();
+    assert_eq!(names, ["cf-cache-status", "cf-ray", "date", "server", "vary"]);
+    assert_eq!(cf["server"], "cloudflare");
+    assert_eq!(cf["date"], "Sun, 06 Nov 1994 08:49:37 GMT");
+    let (ray, pop) = cf["cf-ray"].split_once('-').unwrap();
+    assert_eq!(ray.len(), 16);
+    assert!(ray.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(pop, "FRA");
+
+    let fastly = header_map(&OriginPersona::Fastly.response_headers("FRA", &mut rng, now));
+    assert!(!fastly.contains_key("cf-ray"));
+    assert_ne!(fastly.keys().collect::<Vec<_>>(), cf.keys().collect::<Vec<_>>());
+    let akamai = header_map(&OriginPersona::Akamai.response_headers("FRA", &mut rng, now));
+    assert_eq!(akamai["server"], "AkamaiGHost");
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mgr = StealthManager::new(StealthConfig::default(), crypto, optimize);
+    assert!(mgr.origin_response_headers().is_empty());
+    mgr.set_origin_persona(OriginPersona::Cloudflare);
+    assert_eq!(header_map(&mgr.origin_response_headers())["server"], "cloudflare");
+}
+
+#[test]
+fn custom_persona_loads_from_capture() {
+    use quicfuscate::stealth::OriginPersona;
+
+    let capture = "HTTP/2 200\r\nServer: nginx\r\nContent-Length: 12\r\nX-Cache: HIT\r\nDate: Mon, 01 Jan 2024 00:00:00 GMT\r\n";
+    let persona = OriginPersona::from_capture(capture).unwrap();
+    let now = std::time::UNIX_EPOCH + Duration::from_secs(784_111_777);
+    let headers = header_map(&persona.response_headers("AMS", &mut rand::thread_rng(), now));
+    assert_eq!(headers["server"], "nginx");
+    assert_eq!(headers["x-cache"], "HIT");
+    assert_eq!(headers["date"], "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert!(!headers.contains_key("content-length"));
+    assert!(OriginPersona::from_capture("HTTP/2 200\n").is_err());
+
+    let cfg = StealthConfig::from_toml("[stealth]\norigin_persona = \"fastly\"\n").unwrap();
+    assert_eq!(cfg.origin_persona, Some(OriginPersona::Fastly));
+}
+
+#[test]
+fn exporter_secret_yields_matching_xor_keys() {
+    use quicfuscate::stealth::{XorKeySource, XorObfuscator};
+
+    let secret = [0x5au8; 32];
+    let key = XorObfuscator::key_from_exporter(&secret);
+    assert_eq!(key.len(), 32);
+    assert_eq!(key, XorObfuscator::key_from_exporter(&secret));
+    assert_ne!(key, XorObfuscator::key_from_exporter(&[0xa5u8; 32]));
+    assert_ne!(key, XorObfuscator::static_key());
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let client = StealthManager::new(StealthConfig::default(), crypto.clone(), optimize.clone());
+    let server = StealthManager::new(StealthConfig::default(), crypto.clone(), optimize.clone());
+    assert_eq!(client.xor_key_source(), XorKeySource::Static);
+
+    assert_eq!(client.coordinate_xor_key(Some(&secret)), XorKeySource::Exporter);
+    assert_eq!(server.coordinate_xor_key(Some(&secret)), XorKeySource::Exporter);
+    assert!(client.has_staged_xor_key() && server.has_staged_xor_key());
+
+    // The staged key is not used until an endpoint switches, so packets
+    // already in flight still decode.
+    let mut buf = b"first packet".to_vec();
+    client.obfuscate_payload(&mut buf, 0);
+    assert_ne!(&buf[..], b"first packet");
+    server.process_incoming_packet(&mut buf);
+    assert_eq!(&buf[..], b"first packet");
+
+    // Each direction switches on an explicit signal: the server sends with
+    // the new key first and the client receives with it once told so.
+    server.switch_xor_send_key();
+    let mut buf = b"second packet".to_vec();
+    server.obfuscate_payload(&mut buf, 0);
+    let mut early = buf.clone();
+    client.process_incoming_packet(&mut early);
+    assert_ne!(&early[..], b"second packet");
+    client.switch_xor_receive_key();
+    client.process_incoming_packet(&mut buf);
+    assert_eq!(&buf[..], b"second packet");
+    assert!(client.has_staged_xor_key());
+
+    client.switch_xor_send_key();
+    server.switch_xor_receive_key();
+    let mut buf = b"third packet".to_vec();
+    client.obfuscate_payload(&mut buf, 0);
+    server.process_incoming_packet(&mut buf);
+    assert_eq!(&buf[..], b"third packet");
+    assert!(!client.has_staged_xor_key() && !server.has_staged_xor_key());
+    for (from, to, msg) in [
+        (&client, &server, &b"fourth packet"[..]),
+        (&server, &client, &b"fifth packet"[..]),
+    ] {
+        let mut buf = msg.to_vec();
+        from.obfuscate_payload(&mut buf, 0);
+        to.process_incoming_packet(&mut buf);
+        assert_eq!(&buf[..], msg);
+    }
+
+    // A different session secret does not decode.
+    let other = StealthManager::new(StealthConfig::default(), crypto.clone(), optimize.clone());
+    other.coordinate_xor_key(Some(&[0xa5u8; 32]));
+    other.switch_xor_receive_key();
+    let mut buf = b"sixth packet".to_vec();
+    client.obfuscate_payload(&mut buf, 0);
+    other.process_incoming_packet(&mut buf);
+    assert_ne!(&buf[..], b"sixth packet");
+
+    // Without an exporter the static key stays in use.
+    let fallback = StealthManager::new(StealthConfig::default(), crypto.clone(), optimize.clone());
+    assert_eq!(fallback.coordinate_xor_key(None), XorKeySource::Static);
+
+    let mut config = StealthConfig::default();
+    config.xor_key_from_handshake = false;
+    let manual = StealthManager::new(config, crypto, optimize);
+    assert_eq!(manual.coordinate_xor_key(Some(&secret)), XorKeySource::Session);
+}
+
+#[test]
+fn cover_traffic_follows_configured_rate_and_is_dropped() {
+    use quicfuscate::datagram::DatagramEngine;
+    use quicfuscate::stealth::SizeDistribution;
+    use std::time::Instant;
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let sender = StealthManager::new(StealthConfig::default(), crypto.clone(), optimize.clone());
+    let receiver = StealthManager::new(StealthConfig::default(), crypto, optimize);
+    sender.enable_cover_traffic(50.0, SizeDistribution::Uniform { min: 100, max: 300 });
+    sender.seed_cover_traffic(3);
+    receiver.enable_cover_traffic(0.0, SizeDistribution::Fixed(1));
+
+    let mut engine = DatagramEngine::new();
+    let start = Instant::now();
+    let mut wire = Vec::new();
+    for ms in (0..=20_000).step_by(10) {
+        sender.send_cover_traffic(&mut engine, start + Duration::from_millis(ms));
+        while let Some(d) = engine.pop_outgoing() {
+            wire.push(d);
+        }
+    }
+    // 20 s at 50 per second.
+    let sent = sender.cover_packets_sent();
+    assert_eq!(sent, wire.len());
+    assert!((800..=1200).contains(&sent), "{sent} cover packets");
+    assert!(wire.iter().all(|d| (100..=300).contains(&d.len())));
+
+    // Same seed, same schedule.
+    let again = StealthManager::new(
+        StealthConfig::default(),
+        Arc::new(CryptoManager::new()),
+        Arc::new(OptimizationManager::new()),
+    );
+    again.enable_cover_traffic(50.0, SizeDistribution::Uniform { min: 100, max: 300 });
+    again.seed_cover_traffic(3);
+    let mut other = DatagramEngine::new();
+    for ms in (0..=20_000).step_by(10) {
+        again.send_cover_traffic(&mut other, start + Duration::from_millis(ms));
+    }
+    assert_eq!(again.cover_packets_sent(), sent);
+
+    for d in &wire {
+        engine.recv(d);
+    }
+    sender.obfuscate_and_send(&mut engine, b"real data").unwrap();
+    let d = engine.pop_outgoing().unwrap();
+    engine.recv(&d);
+    assert_eq!(receiver.recv_and_deobfuscate(&mut engine).unwrap(), b"real data");
+    assert_eq!(receiver.cover_packets_dropped(), sent);
+    assert!(receiver.recv_and_deobfuscate(&mut engine).is_none());
+}
+
+#[test]
+fn user_agent_matches_claimed_platform() {
+    use quicfuscate::stealth::Http3Masquerade;
+    use quiche::h3::NameValue;
+
+    let browsers = [
+        BrowserProfile::Chrome,
+        BrowserProfile::Firefox,
+        BrowserProfile::Safari,
+        BrowserProfile::Opera,
+        BrowserProfile::Brave,
+        BrowserProfile::Edge,
+        BrowserProfile::Vivaldi,
+    ];
+    let platforms = [
+        (OsProfile::Windows, "Windows NT 10.0"),
+        (OsProfile::MacOS, "Macintosh; Intel Mac OS X"),
+        (OsProfile::Linux, "Linux x86_64"),
+        (OsProfile::Android, "Android 14"),
+        (OsProfile::IOS, "iPhone; CPU iPhone OS"),
+    ];
+    for browser in browsers {
+        for (os, token) in platforms {
+            let ua = BrowserProfile::user_agent(browser, os);
+            assert!(ua.contains(token), "{browser:?}/{os:?}: {ua}");
+            for (other, other_token) in platforms {
+                if other != os {
+                    assert!(!ua.contains(other_token), "{browser:?}/{os:?}: {ua}");
+                }
+            }
+            assert_eq!(FingerprintProfile::new(browser, os).user_agent, ua);
+        }
+    }
+
+    assert!(BrowserProfile::Firefox.user_agent(OsProfile::Linux).contains("Firefox/"));
+    assert!(BrowserProfile::Edge.user_agent(OsProfile::Windows).contains("Edg/"));
+    assert!(BrowserProfile::Chrome.user_agent(OsProfile::IOS).contains("CriOS/"));
+
+    // Pairs without a compiled-in profile still send a matching UA.
+    let profile = FingerprintProfile::new(BrowserProfile::Firefox, OsProfile::IOS);
+    let masq = Http3Masquerade::new(profile);
+    let headers = masq.generate_headers("example.com", "/");
+    let ua = headers
+        .iter()
+        .find(|h| h.name() == b"user-agent")
+        .map(|h| String::from_utf8_lossy(h.value()).into_owned())
+        .unwrap();
+    assert!(ua.contains("iPhone") && ua.contains("FxiOS/"), "{ua}");
+}
+
+#[test]
+fn disabled_spin_bit_stays_zero() {
+    use quicfuscate::stealth::{clear_spin_bit, SpinBitMode, SPIN_BIT};
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mut config = StealthConfig::default();
+    config.disable_spin_bit = true;
+    let mgr = StealthManager::new(config, crypto, optimize);
+    // With a tiny RTT the accurate scheduler would flip on every packet.
+    mgr.on_rtt_sample(Duration::from_nanos(1));
+    assert_eq!(mgr.snapshot_config().spin_bit_mode, SpinBitMode::Disabled);
+    for pn in 0..100u64 {
+        assert!(!mgr.spin_bit_for(pn));
+        std::thread::sleep(Duration::from_micros(10));
+    }
+
+    // Short headers written with the bit set lose it, long headers are
+    // left alone.
+    let dcid = [0xab; 8];
+    for first in [0x40u8, 0x41, 0x43, 0x44] {
+        let mut pkt = vec![first | SPIN_BIT];
+        pkt.extend_from_slice(&dcid);
+        pkt.extend_from_slice(&[0u8; 24]);
+        assert!(clear_spin_bit(&mut pkt));
+        assert_eq!(pkt[0] & SPIN_BIT, 0);
+        assert!(!clear_spin_bit(&mut pkt));
+    }
+    let mut long = [0xc0u8 | SPIN_BIT, 0, 0, 0, 1];
+    assert!(!clear_spin_bit(&mut long));
+    assert_eq!(long[0] & SPIN_BIT, SPIN_BIT);
+
+    // The receive side never looks at the peer's spin bit: both values
+    // parse to the same header.
+    let mut spun = vec![0x40 | SPIN_BIT];
+    spun.extend_from_slice(&dcid);
+    let mut plain = spun.clone();
+    plain[0] &= !SPIN_BIT;
+    let a = quiche::Header::from_slice(&mut spun, dcid.len()).unwrap();
+    let b = quiche::Header::from_slice(&mut plain, dcid.len()).unwrap();
+    assert_eq!(a.ty, b.ty);
+    assert_eq!(a.dcid, b.dcid);
+
+    mgr.set_spin_bit_disabled(false);
+    assert_eq!(mgr.snapshot_config().spin_bit_mode, SpinBitMode::Accurate);
+}
+
+#[test]
+fn configured_xor_key_and_pattern_round_trip() {
+    use quicfuscate::stealth::{parse_xor_key, XorKeySource, XorPattern};
+
+    let key = parse_xor_key("a0a1a2a3a4a5a6a7a8a9aaabacadaeaf").unwrap();
+    assert!(parse_xor_key("a0a1").is_err());
+    assert!(parse_xor_key("not hex").is_err());
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let mut config = StealthConfig::default();
+    config.xor_key = Some(key.clone());
+    config.xor_pattern = XorPattern::Repeating;
+    let client = StealthManager::new(config.clone(), crypto.clone(), optimize.clone());
+    let server = StealthManager::new(config.clone(), crypto.clone(), optimize.clone());
+    assert_eq!(client.xor_key_source(), XorKeySource::Configured);
+    // The pre-shared key is not replaced by the handshake.
+    assert_eq!(
+        client.coordinate_xor_key(Some(&[0x5a; 32])),
+        XorKeySource::Configured
+    );
+
+    let msg = b"configured key and pattern".to_vec();
+    for _ in 0..3 {
+        let mut buf = msg.clone();
+        client.process_outgoing_packet(&mut buf);
+        // Repeating pattern: every packet is XORed with the same key.
+        let expected: Vec<u8> = msg
+            .iter()
+            .enumerate()
+            .map(|(i, b)| b ^ key[i % key.len()])
+            .collect();
+        assert_eq!(buf, expected);
+        server.process_incoming_packet(&mut buf);
+        assert_eq!(buf, msg);
+    }
+
+    // With the rolling pattern the key stream moves on.
+    config.xor_pattern = XorPattern::Rolling;
+    let rolling = StealthManager::new(config, crypto, optimize);
+    let (mut a, mut b) = (msg.clone(), msg.clone());
+    rolling.process_outgoing_packet(&mut a);
+    rolling.process_outgoing_packet(&mut b);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn rotating_obfuscation_changes_all_parameters_and_round_trips() {
+    use quicfuscate::stealth::{XorKeySource, PADDING_BUCKET_RANGE};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let crypto = Arc::new(CryptoManager::new());
+    let optimize = Arc::new(OptimizationManager::new());
+    let config = StealthConfig::default();
+    let client = StealthManager::new(config.clone(), crypto.clone(), optimize.clone());
+    let server = StealthManager::new(config, crypto, optimize);
+    assert!(client.padding_buckets().is_empty());
+
+    let msg = b"rotated obfuscation parameters".to_vec();
+    let mut rotations = Vec::new();
+    for seed in [1u64, 2] {
+        // Both endpoints rotate from the same RNG state.
+        let first = client.rotate_obfuscation(&mut StdRng::seed_from_u64(seed));
+        let second = server.rotate_obfuscation(&mut StdRng::seed_from_u64(seed));
+        assert_eq!(first, second);
+        assert!(first.xor_key_fingerprint.is_some());
+        assert!(!first.padding_buckets.is_empty());
+        assert!(first
+            .padding_buckets
+            .iter()
+            .all(|b| PADDING_BUCKET_RANGE.contains(b)));
+        assert_eq!(client.padding_buckets(), first.padding_buckets);
+        assert_eq!(client.xor_key_source(), XorKeySource::Rotated);
+
+        for _ in 0..3 {
+            let mut buf = msg.clone();
+            client.process_outgoing_packet(&mut buf);
+            assert_ne!(buf, msg);
+            server.process_incoming_packet(&mut buf);
+            assert_eq!(buf, msg);
+        }
+        rotations.push(first);
+    }
+
+    assert_ne!(rotations[0], rotations[1]);
+    assert_ne!(
+        rotations[0].xor_key_fingerprint,
+        rotations[1].xor_key_fingerprint
+    );
+    assert_ne!(rotations[0].spin_seed, rotations[1].spin_seed);
+}
@@ -0,0 +1,55 @@
+use quicfuscate::optimize::QuicStreamOptimizer;
+use quicfuscate::stream::StreamEngine;
+use std::sync::{Arc, Mutex};
+
+const HIGH: u64 = 0;
+const LOW: u64 = 4;
+
+fn engine() -> StreamEngine {
+    let mut opt = QuicStreamOptimizer::new(1024 * 1024);
+    opt.set_priority(HIGH, 0);
+    opt.set_priority(LOW, 6);
+    StreamEngine::new(Arc::new(Mutex::new(opt)))
+}
+
+#[test]
+fn high_priority_writes_use_larger_chunks() {
+    let mut engine = engine();
+    let data = vec![0xabu8; 200 * 1024];
+    let high = engine.write(HIGH, &data);
+    let low = engine.write_fin(LOW, &data);
+    assert!(high < low);
+
+    let max_len = |id| engine.queued(id).map(|c| c.data.len()).max().unwrap();
+    assert_eq!(max_len(HIGH), QuicStreamOptimizer::MAX_CHUNK_SIZE);
+    assert!(max_len(LOW) < max_len(HIGH));
+    assert!(engine.queued(LOW).last().unwrap().fin);
+
+    // Chunks reassemble to the original data.
+    let joined: Vec<u8> = engine.queued(LOW).flat_map(|c| c.data.clone()).collect();
+    assert_eq!(joined, data);
+
+    // The urgent stream is drained first.
+    assert_eq!(engine.pop_sendable().unwrap().stream_id, HIGH);
+}
+
+#[test]
+fn chunks_never_exceed_window() {
+    let mut engine = engine();
+    engine
+        .optimizer()
+        .lock()
+        .unwrap()
+        .update_flow_control_window(HIGH, 4000);
+    engine.write(HIGH, &[1u8; 10_000]);
+    assert!(engine.queued(HIGH).all(|c| c.data.len() <= 4000));
+
+    // Shrinking the window below the queued chunk size holds the data back.
+    engine
+        .optimizer()
+        .lock()
+        .unwrap()
+        .update_flow_control_window(HIGH, 1000);
+    assert!(engine.pop_sendable().is_none());
+    assert_eq!(engine.pending(), 3);
+}
@@ -0,0 +1,225 @@
+use quicfuscate::telemetry;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+#[test]
+fn metrics_server_binds_ephemeral_port_and_shuts_down() {
+    telemetry::ENCODED_PACKETS.inc();
+    let server = telemetry::serve("127.0.0.1:0");
+    let addr = server.local_addr();
+    assert_ne!(addr.port(), 0);
+
+    let mut conn = TcpStream::connect(addr).unwrap();
+    conn.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut resp = String::new();
+    conn.read_to_string(&mut resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(resp.contains("encoded_packets_total"));
+
+    server.shutdown();
+    assert!(TcpListener::bind(addr).is_ok());
+}
+
+#[test]
+fn batched_increments_reach_shared_counter() {
+    let counter: &'static prometheus::IntCounter = Box::leak(Box::new(
+        prometheus::IntCounter::new("batched_test_total", "test").unwrap(),
+    ));
+
+    for _ in 0..10 {
+        telemetry::inc_local(counter, 3);
+    }
+    telemetry::flush_local();
+    assert_eq!(counter.get(), 30);
+
+    // Enough updates trigger a flush on their own.
+    for _ in 0..telemetry::LOCAL_FLUSH_UPDATES {
+        telemetry::inc_local(counter, 1);
+    }
+    assert_eq!(counter.get(), 30 + telemetry::LOCAL_FLUSH_UPDATES as u64);
+
+    // Deltas left on a thread are flushed when it exits.
+    std::thread::spawn(move || telemetry::inc_local(counter, 5))
+        .join()
+        .unwrap();
+    assert_eq!(counter.get(), 35 + telemetry::LOCAL_FLUSH_UPDATES as u64);
+}
+
+#[test]
+fn flush_local_collects_deltas_of_other_threads() {
+    let counter: &'static prometheus::IntCounter = Box::leak(Box::new(
+        prometheus::IntCounter::new("batched_cross_thread_total", "test").unwrap(),
+    ));
+
+    // The worker stays alive, like a runtime thread a task migrated away from.
+    let (buffered_tx, buffered_rx) = std::sync::mpsc::channel();
+    let (exit_tx, exit_rx) = std::sync::mpsc::channel::<()>();
+    let worker = std::thread::spawn(move || {
+        telemetry::inc_local(counter, 7);
+        buffered_tx.send(()).unwrap();
+        exit_rx.recv().unwrap();
+    });
+    buffered_rx.recv().unwrap();
+
+    telemetry::flush_local();
+    assert_eq!(counter.get(), 7);
+
+    exit_tx.send(()).unwrap();
+    worker.join().unwrap();
+    assert_eq!(counter.get(), 7);
+}
+
+fn get(addr: std::net::SocketAddr, path: &str) -> String {
+    let mut conn = TcpStream::connect(addr).unwrap();
+    write!(conn, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut resp = String::new();
+    conn.read_to_string(&mut resp).unwrap();
+    resp
+}
+
+#[test]
+fn health_endpoints_follow_listener_and_drain_state() {
+    let health = std::sync::Arc::new(telemetry::HealthState::new());
+    let server = telemetry::serve_with_health("127.0.0.1:0", health.clone());
+    let addr = server.local_addr();
+
+    assert!(get(addr, "/healthz").starts_with("HTTP/1.1 200 OK\r\n"));
+    // Not bound yet.
+    assert!(get(addr, "/readyz").starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+
+    health.set_listening(true);
+    assert!(get(addr, "/readyz").starts_with("HTTP/1.1 200 OK\r\n"));
+
+    health.set_draining(true);
+    assert!(get(addr, "/healthz").starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(get(addr, "/readyz").starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+
+    server.shutdown();
+}
+
+/// The FEC counters are process-wide, so tests asserting on their deltas
+/// take this lock to keep each other's blocks out of the count.
+static FEC_COUNTERS: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn lock_fec_counters() -> std::sync::MutexGuard<'static, ()> {
+    telemetry::TELEMETRY_ENABLED.store(true, std::sync::atomic::Ordering::Relaxed);
+    FEC_COUNTERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[test]
+fn recovered_fec_block_counts_as_recovered() {
+    use quicfuscate::fec::{AdaptiveFec, FecConfig, FecMode, Packet};
+    use quicfuscate::optimize::OptimizationManager;
+    use std::collections::VecDeque;
+
+    let _counters = lock_fec_counters();
+    let mgr = OptimizationManager::new_with_config(2048, 256, false);
+    let mut cfg = FecConfig::default();
+    cfg.initial_mode = FecMode::Normal;
+    let mut sender = AdaptiveFec::new(cfg.clone(), mgr.memory_pool());
+    let k = sender.current_window();
+
+    let mut queue = VecDeque::new();
+    for id in 0..k as u64 {
+        let pkt = Packet::from_raw(id, &[1, id as u8, 0xaa, 0xbb], &mgr).unwrap();
+        sender.on_send(pkt, &mut queue);
+    }
+
+    let recovered_before = telemetry::FEC_RECOVERED.get();
+    let failures_before = telemetry::FEC_DECODE_FAILURES.get();
+    let mut receiver = AdaptiveFec::new(cfg, mgr.memory_pool());
+    for pkt in queue.drain(..).filter(|p| !(p.is_systematic && p.id == 5)) {
+        receiver.on_receive(pkt).unwrap();
+    }
+    assert_eq!(telemetry::FEC_RECOVERED.get(), recovered_before + 1);
+    assert_eq!(telemetry::FEC_DECODE_FAILURES.get(), failures_before);
+}
+
+#[test]
+fn expired_fec_block_counts_as_decode_failure() {
+    use quicfuscate::fec::{AdaptiveFec, FecConfig, FecMode, Packet};
+    use quicfuscate::optimize::OptimizationManager;
+    use std::time::{Duration, Instant};
+
+    let _counters = lock_fec_counters();
+    let mgr = OptimizationManager::new_with_config(2048, 256, false);
+    let mut cfg = FecConfig::default();
+    cfg.initial_mode = FecMode::Normal;
+    let mut receiver = AdaptiveFec::new(cfg, mgr.memory_pool());
+    receiver.set_deadline_policy(Duration::from_millis(50));
+
+    let recovered_before = telemetry::FEC_RECOVERED.get();
+    let failures_before = telemetry::FEC_DECODE_FAILURES.get();
+    let expired_before = telemetry::FEC_BLOCKS_EXPIRED.get();
+    let t0 = Instant::now();
+    let pkt = Packet::from_raw(0, &[1, 1, 2, 3], &mgr).unwrap();
+    receiver.on_receive_at(pkt, t0).unwrap();
+    receiver.flush_expired(t0 + Duration::from_millis(80));
+    assert_eq!(telemetry::FEC_RECOVERED.get(), recovered_before);
+    assert_eq!(telemetry::FEC_DECODE_FAILURES.get(), failures_before + 1);
+    assert_eq!(telemetry::FEC_BLOCKS_EXPIRED.get(), expired_before + 1);
+}
+
+#[test]
+fn zero_mode_gap_is_not_a_decode_failure() {
+    use quicfuscate::fec::{AdaptiveFec, FecConfig, FecMode, Packet};
+    use quicfuscate::optimize::OptimizationManager;
+    use std::time::{Duration, Instant};
+
+    let _counters = lock_fec_counters();
+    let mgr = OptimizationManager::new_with_config(2048, 256, false);
+    let mut cfg = FecConfig::default();
+    cfg.initial_mode = FecMode::Zero;
+    let mut receiver = AdaptiveFec::new(cfg, mgr.memory_pool());
+    receiver.set_deadline_policy(Duration::from_millis(50));
+
+    let failures_before = telemetry::FEC_DECODE_FAILURES.get();
+    let expired_before = telemetry::FEC_BLOCKS_EXPIRED.get();
+    let t0 = Instant::now();
+    for (id, at) in [(0, t0), (1, t0 + Duration::from_millis(80))] {
+        let pkt = Packet::from_raw(id, &[1, id as u8, 2, 3], &mgr).unwrap();
+        receiver.on_receive_at(pkt, at).unwrap();
+    }
+    receiver.flush_expired(t0 + Duration::from_millis(160));
+    assert_eq!(telemetry::FEC_DECODE_FAILURES.get(), failures_before);
+    assert_eq!(telemetry::FEC_BLOCKS_EXPIRED.get(), expired_before);
+}
+
+#[test]
+fn block_retired_by_cross_fade_counts_as_decode_failure() {
+    use quicfuscate::fec::{AdaptiveFec, FecConfig, FecMode, Packet, PidConfig};
+    use quicfuscate::optimize::OptimizationManager;
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    let _counters = lock_fec_counters();
+    let mgr = OptimizationManager::new_with_config(2048, 256, false);
+    let cfg = FecConfig {
+        initial_mode: FecMode::Normal,
+        lambda: 1.0,
+        pid: PidConfig {
+            kp: 1.0,
+            ki: 0.0,
+            kd: 0.0,
+        },
+        min_dwell_time: Duration::ZERO,
+        ..FecConfig::default()
+    };
+    let mut receiver = AdaptiveFec::new(cfg, mgr.memory_pool());
+    for id in 0..2 {
+        let pkt = Packet::from_raw(id, &[1, id as u8, 2, 3], &mgr).unwrap();
+        receiver.on_receive(pkt).unwrap();
+    }
+    receiver.report_loss(45, 100);
+
+    let failures_before = telemetry::FEC_DECODE_FAILURES.get();
+    let expired_before = telemetry::FEC_BLOCKS_EXPIRED.get();
+    let mut queue = VecDeque::new();
+    for id in 0..16 {
+        let pkt = Packet::from_raw(id, &[1, id as u8, 2, 3], &mgr).unwrap();
+        receiver.on_send(pkt, &mut queue);
+    }
+    assert_eq!(telemetry::FEC_BLOCKS_EXPIRED.get(), expired_before + 1);
+    assert_eq!(telemetry::FEC_DECODE_FAILURES.get(), failures_before + 1);
+}
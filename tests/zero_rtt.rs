@@ -0,0 +1,79 @@
+use quicfuscate::zero_rtt::{EarlyDataOverflow, ZeroRttEngine, DEFAULT_MAX_EARLY_DATA};
+
+fn engine(max: usize, policy: EarlyDataOverflow) -> ZeroRttEngine {
+    let mut e = ZeroRttEngine::new();
+    e.set_max_early_data(max);
+    e.set_overflow_policy(policy);
+    e
+}
+
+#[test]
+fn defaults_reject_at_standard_cap() {
+    let e = ZeroRttEngine::new();
+    assert_eq!(e.max_early_data(), DEFAULT_MAX_EARLY_DATA);
+    assert_eq!(e.early_data_remaining(), DEFAULT_MAX_EARLY_DATA);
+    assert_eq!(e.overflow_policy(), EarlyDataOverflow::Reject);
+}
+
+#[test]
+fn reject_accepts_up_to_cap_and_refuses_beyond() {
+    let mut e = engine(10, EarlyDataOverflow::Reject);
+    assert_eq!(e.push_early_data(0, &[1; 6]).unwrap(), 6);
+    assert_eq!(e.push_early_data(0, &[2; 4]).unwrap(), 4);
+    assert_eq!(e.early_data_remaining(), 0);
+    assert_eq!(
+        e.push_early_data(0, &[3; 1]),
+        Err(quiche::Error::BufferTooShort)
+    );
+
+    let mut e = engine(10, EarlyDataOverflow::Reject);
+    e.push_early_data(0, &[1; 6]).unwrap();
+    assert!(e.push_early_data(0, &[2; 5]).is_err());
+    // A rejected write leaves the queue untouched.
+    assert_eq!(e.pending_early(), 6);
+    assert_eq!(e.early_data_remaining(), 4);
+}
+
+#[test]
+fn truncate_keeps_what_fits() {
+    let mut e = engine(10, EarlyDataOverflow::Truncate);
+    assert_eq!(e.push_early_data(4, &[1; 10]).unwrap(), 10);
+    assert_eq!(
+        e.push_early_data(4, &[2; 3]),
+        Err(quiche::Error::BufferTooShort)
+    );
+
+    let mut e = engine(10, EarlyDataOverflow::Truncate);
+    e.push_early_data(4, &[1; 7]).unwrap();
+    assert_eq!(e.push_early_data(4, &[2; 8]).unwrap(), 3);
+    assert_eq!(e.pending_early(), 10);
+    assert_eq!(e.pending_deferred(), 0);
+    assert_eq!(e.early_data_remaining(), 0);
+}
+
+#[test]
+fn buffer_until_handshake_defers_the_excess() {
+    let mut e = engine(10, EarlyDataOverflow::BufferUntilHandshake);
+    assert_eq!(e.push_early_data(0, &[1; 10]).unwrap(), 10);
+    assert_eq!(e.pending_deferred(), 0);
+
+    assert_eq!(e.push_early_data(0, &[2; 8]).unwrap(), 8);
+    assert_eq!(e.pending_early(), 10);
+    assert_eq!(e.pending_deferred(), 8);
+
+    let mut e = engine(10, EarlyDataOverflow::BufferUntilHandshake);
+    e.push_early_data(0, &[1; 6]).unwrap();
+    assert_eq!(e.push_early_data(0, &[2; 9]).unwrap(), 9);
+    assert_eq!(e.pending_early(), 10);
+    assert_eq!(e.pending_deferred(), 5);
+    assert_eq!(e.early_data_remaining(), 0);
+}
+
+#[test]
+fn lowering_cap_counts_accepted_data() {
+    let mut e = engine(10, EarlyDataOverflow::Reject);
+    e.push_early_data(0, &[1; 8]).unwrap();
+    e.set_max_early_data(5);
+    assert_eq!(e.early_data_remaining(), 0);
+    assert!(e.push_early_data(0, &[1]).is_err());
+}